@@ -0,0 +1,595 @@
+//! Language features: diagnostics, go-to-definition, hover, completion and document
+//! symbols.
+
+use std::ops::Range as Span;
+
+use url::Url;
+use wesl::{
+    eval::{ty_eval_ty, Context, EvalTy, Exec, Type},
+    syntax::{
+        Declaration, DiagnosticSeverity, GlobalDeclaration, ModulePath, PathOrigin, Statement,
+        StatementNode, TranslationUnit,
+    },
+    BasicSourceMap, CompileOptions, EscapeMangler, LintConfig, SourceMap,
+};
+use wgsl_parse::lexer::Token;
+
+use crate::{
+    document::{is_name, Document},
+    index::{self, Symbol},
+    lsp::{self, CompletionItem, CompletionItemKind, DocumentSymbol, Location, SymbolKind},
+    workspace::Workspace,
+};
+
+/// What a name refers to.
+#[derive(Clone, Debug)]
+pub enum Target {
+    /// A local declaration, parameter or struct member in the current document.
+    Local(Symbol),
+    /// A module-scope declaration.
+    Decl(ModulePath, String),
+    /// A module.
+    Module(ModulePath),
+}
+
+/// The document being queried, with its location in the workspace.
+pub struct Query<'a> {
+    pub ws: &'a Workspace<'a>,
+    pub uri: &'a Url,
+    pub doc: &'a Document,
+    pub module: ModulePath,
+    symbols: Vec<Symbol>,
+    imports: index::Imports,
+}
+
+impl<'a> Query<'a> {
+    pub fn new(ws: &'a Workspace<'a>, uri: &'a Url, doc: &'a Document) -> Option<Self> {
+        let module = ws.module_path(uri)?;
+        let symbols = index::symbols(doc);
+        let imports = index::imports(doc, &module);
+        Some(Self {
+            ws,
+            uri,
+            doc,
+            module,
+            symbols,
+            imports,
+        })
+    }
+
+    /// Resolve the name under the cursor. Also returns the span of the name.
+    pub fn resolve(&self, offset: usize) -> Option<(Target, Span<usize>)> {
+        let idx = self.doc.ident_at(offset)?;
+        let tokens = &self.doc.tokens;
+        let span = tokens[idx].1.clone();
+
+        if let Some(path) = self.imports.paths.get(&idx) {
+            return Some((self.path_target(path.clone()), span));
+        }
+
+        // qualified name, e.g. `package::foo::bar`
+        let mut start = idx;
+        while start >= 2
+            && tokens[start - 1].0 == Token::SymColonColon
+            && is_name(&tokens[start - 2].0)
+        {
+            start -= 2;
+        }
+        if start != idx {
+            let path = self.inline_path(start, idx)?;
+            let is_module = tokens.get(idx + 1).map(|(tok, _)| tok) == Some(&Token::SymColonColon);
+            let target = if is_module {
+                Target::Module(path)
+            } else {
+                let mut path = path;
+                let name = path.components.pop()?;
+                Target::Decl(path, name)
+            };
+            return Some((target, span));
+        }
+
+        let Token::Ident(name) = &tokens[idx].0 else {
+            return None;
+        };
+        // struct members and swizzles are not resolved
+        if idx > 0 && tokens[idx - 1].0 == Token::SymPeriod {
+            return None;
+        }
+
+        let target = self
+            .local(name, offset)
+            .map(Target::Local)
+            .or_else(|| {
+                self.symbols
+                    .iter()
+                    .any(|sym| sym.name == *name)
+                    .then(|| Target::Decl(self.module.clone(), name.clone()))
+            })
+            .or_else(|| {
+                let import = self.imports.items.iter().find(|imp| imp.name == *name)?;
+                Some(self.path_target(import.path.clone().join([import.item.clone()])))
+            })?;
+        Some((target, span))
+    }
+
+    /// A local declaration, parameter or struct member visible at `offset`.
+    fn local(&self, name: &str, offset: usize) -> Option<Symbol> {
+        let sym = self.symbols.iter().find(|sym| sym.span.contains(&offset))?;
+        match sym.kind {
+            SymbolKind::Function => index::locals(self.doc, sym, offset)
+                .into_iter()
+                .rev()
+                .find(|local| local.name == name),
+            SymbolKind::Struct => sym
+                .children
+                .iter()
+                .find(|member| member.name_span.contains(&offset))
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    /// The module path formed by the name tokens between `start` and `end` (inclusive).
+    fn inline_path(&self, start: usize, end: usize) -> Option<ModulePath> {
+        let mut path = ModulePath::new(PathOrigin::Package, Vec::new());
+        for i in (start..=end).step_by(2) {
+            match &self.doc.tokens[i].0 {
+                Token::KwPackage if i == start => path.origin = PathOrigin::Absolute,
+                Token::KwSelf if i == start => path.origin = PathOrigin::Relative(0),
+                Token::KwSuper if path.components.is_empty() => {
+                    path.origin = match path.origin {
+                        PathOrigin::Relative(n) => PathOrigin::Relative(n + 1),
+                        _ => PathOrigin::Relative(1),
+                    }
+                }
+                Token::Ident(name) => path.push(name),
+                _ => return None,
+            }
+        }
+        let path = match path.origin {
+            // the first component can be an imported item, see `resolve_inline_path`.
+            PathOrigin::Package => {
+                let first = path.first()?;
+                match self.imports.items.iter().find(|imp| imp.name == first) {
+                    Some(imp) => imp
+                        .path
+                        .clone()
+                        .join([imp.item.clone()])
+                        .join(path.components.into_iter().skip(1)),
+                    None => path,
+                }
+            }
+            _ => index::join(&self.module, &path),
+        };
+        Some(path)
+    }
+
+    /// The module or declaration designated by a full path.
+    fn path_target(&self, mut path: ModulePath) -> Target {
+        if self.ws.uri(&path).is_some() || path.components.is_empty() {
+            Target::Module(path)
+        } else {
+            let name = path.components.pop().unwrap_or_default();
+            Target::Decl(path, name)
+        }
+    }
+
+    /// Uri and document of a module.
+    fn module_doc(&self, path: &ModulePath) -> Option<(Url, std::borrow::Cow<'_, Document>)> {
+        if *path == self.module {
+            Some((self.uri.clone(), std::borrow::Cow::Borrowed(self.doc)))
+        } else {
+            let uri = self.ws.uri(path)?;
+            let doc = self.ws.document(&uri)?;
+            Some((uri, doc))
+        }
+    }
+
+    pub fn definition(&self, offset: usize) -> Option<Location> {
+        let (target, _) = self.resolve(offset)?;
+        match target {
+            Target::Local(sym) => Some(Location {
+                uri: self.uri.clone(),
+                range: self.doc.range(sym.name_span),
+            }),
+            Target::Decl(path, name) => {
+                let (uri, doc) = self.module_doc(&path)?;
+                let sym = index::symbols(&doc)
+                    .into_iter()
+                    .find(|sym| sym.name == name)?;
+                Some(Location {
+                    uri,
+                    range: doc.range(sym.name_span),
+                })
+            }
+            Target::Module(path) => Some(Location {
+                uri: self.ws.uri(&path)?,
+                range: Default::default(),
+            }),
+        }
+    }
+
+    pub fn hover(&self, offset: usize) -> Option<lsp::Hover> {
+        let (target, span) = self.resolve(offset)?;
+        let (code, ty) = match &target {
+            Target::Local(sym) => {
+                let code = self.doc.text[sym.span.clone()].trim_end_matches(';');
+                let ty = self.local_type(sym);
+                (code.to_string(), ty)
+            }
+            Target::Decl(path, name) => {
+                let (_, doc) = self.module_doc(path)?;
+                let sym = index::symbols(&doc)
+                    .into_iter()
+                    .find(|sym| sym.name == *name)?;
+                let end = match sym.kind {
+                    // the function body is omitted
+                    SymbolKind::Function => doc.tokens[sym.tokens.clone()]
+                        .iter()
+                        .rev()
+                        .find(|(tok, _)| *tok == Token::SymBraceLeft)
+                        .map(|(_, span)| span.start)
+                        .filter(|_| doc.tokens[sym.tokens.end - 1].0 == Token::SymBraceRight)
+                        .unwrap_or(sym.span.end),
+                    _ => sym.span.end,
+                };
+                let code = doc.text[sym.span.start..end]
+                    .trim_end()
+                    .trim_end_matches(';');
+                let ty = self.decl_type(path, name);
+                (code.to_string(), ty)
+            }
+            Target::Module(path) => (format!("import {path};"), None),
+        };
+
+        let mut value = format!("```wgsl\n{code}\n```");
+        if let Some(ty) = ty {
+            value.push_str(&format!("\n---\n{ty}"));
+        }
+        Some(lsp::Hover {
+            contents: lsp::MarkupContent {
+                kind: "markdown",
+                value,
+            },
+            range: self.doc.range(span),
+        })
+    }
+
+    /// The type of a local declaration, parameter or struct member. Local initializers
+    /// can only be evaluated when they do not refer to other local declarations.
+    fn local_type(&self, sym: &Symbol) -> Option<String> {
+        let parent = self
+            .symbols
+            .iter()
+            .find(|parent| parent.span.contains(&sym.span.start))?;
+        let (wgsl, sourcemap) = self.compile(&self.module)?;
+        let mut ctx = Context::new(&wgsl);
+        let _ = wgsl.exec(&mut ctx);
+        let parent_decl = wgsl
+            .global_declarations
+            .iter()
+            .find(|decl| decl.ident().is_some_and(|id| *id.name() == parent.name))?;
+
+        // parameters start with their name, local declarations with a keyword.
+        let is_param = matches!(self.doc.tokens[sym.tokens.start].0, Token::Ident(_));
+
+        let ty = match parent_decl {
+            GlobalDeclaration::Struct(s) => {
+                let m = s.members.iter().find(|m| *m.ident.name() == sym.name)?;
+                ty_eval_ty(&m.ty, &mut ctx).ok()?
+            }
+            GlobalDeclaration::Function(f) if is_param => {
+                let p = f.parameters.iter().find(|p| *p.ident.name() == sym.name)?;
+                ty_eval_ty(&p.ty, &mut ctx).ok()?
+            }
+            GlobalDeclaration::Function(f) => {
+                let decl = find_declaration(&f.body.statements, sym.name_span.start)?;
+                declaration_type(decl, &mut ctx)?
+            }
+            _ => return None,
+        };
+        Some(format!("type: `{}`", display_type(ty, &sourcemap)))
+    }
+
+    /// The type of a module-scope declaration, evaluated in the context of its module.
+    fn decl_type(&self, path: &ModulePath, name: &str) -> Option<String> {
+        let (wgsl, sourcemap) = self.compile(path)?;
+        let mut ctx = Context::new(&wgsl);
+        let _ = wgsl.exec(&mut ctx);
+        let decl = wgsl
+            .global_declarations
+            .iter()
+            .find(|decl| decl.ident().is_some_and(|id| *id.name() == name))?;
+        let disp = |ty| display_type(ty, &sourcemap);
+
+        match decl {
+            GlobalDeclaration::Declaration(decl) => {
+                let ty = declaration_type(decl, &mut ctx)?;
+                Some(format!("type: `{}`", disp(ty)))
+            }
+            GlobalDeclaration::TypeAlias(alias) => {
+                let ty = ty_eval_ty(&alias.ty, &mut ctx).ok()?;
+                Some(format!("type: `{}`", disp(ty)))
+            }
+            GlobalDeclaration::Struct(_) => {
+                let ty = Type::Struct(name.to_string());
+                let size = ty.size_of(&mut ctx)?;
+                let align = ty.align_of(&mut ctx)?;
+                Some(format!("size: {size}, align: {align}"))
+            }
+            GlobalDeclaration::Function(func) => {
+                let params = func
+                    .parameters
+                    .iter()
+                    .map(|p| {
+                        let ty = ty_eval_ty(&p.ty, &mut ctx).ok()?;
+                        Some(format!("{}: {}", p.ident, disp(ty)))
+                    })
+                    .collect::<Option<Vec<_>>>()?
+                    .join(", ");
+                let ret = match &func.return_type {
+                    Some(ty) => format!(" -> {}", disp(ty_eval_ty(ty, &mut ctx).ok()?)),
+                    None => String::new(),
+                };
+                Some(format!("type: `fn({params}){ret}`"))
+            }
+            GlobalDeclaration::Void | GlobalDeclaration::ConstAssert(_) => None,
+        }
+    }
+
+    /// Compile a module and its dependencies, keeping all declarations.
+    fn compile(&self, path: &ModulePath) -> Option<(TranslationUnit, BasicSourceMap)> {
+        let options = CompileOptions {
+            strip: false,
+            validate: false,
+            features: self.ws.features.clone(),
+            ..Default::default()
+        };
+        let (wgsl, sourcemap) = wesl::compile_sourcemap(path, self.ws, &EscapeMangler, &options);
+        Some((wgsl.ok()?, sourcemap))
+    }
+
+    pub fn completion(&self, offset: usize) -> Vec<CompletionItem> {
+        let tokens = &self.doc.tokens;
+        // index of the last token before the cursor, ignoring the name being typed.
+        let mut prev = tokens.partition_point(|(_, span)| span.end <= offset);
+        if prev > 0 && is_name(&tokens[prev - 1].0) && tokens[prev - 1].1.end == offset {
+            prev -= 1;
+        }
+
+        if prev >= 2 && tokens[prev - 1].0 == Token::SymColonColon {
+            // qualified name: complete the items of a module
+            let path = match self.imports.paths.get(&(prev - 2)) {
+                Some(path) => Some(path.clone()),
+                None => {
+                    let mut start = prev - 2;
+                    while start >= 2
+                        && tokens[start - 1].0 == Token::SymColonColon
+                        && is_name(&tokens[start - 2].0)
+                    {
+                        start -= 2;
+                    }
+                    self.inline_path(start, prev - 2)
+                }
+            };
+            return path
+                .map(|path| self.module_items(&path))
+                .unwrap_or_default();
+        }
+
+        let mut items = Vec::new();
+        if let Some(func) = self
+            .symbols
+            .iter()
+            .find(|sym| sym.kind == SymbolKind::Function && sym.span.contains(&offset))
+        {
+            items.extend(
+                index::locals(self.doc, func, offset)
+                    .into_iter()
+                    .map(|sym| CompletionItem {
+                        label: sym.name,
+                        kind: CompletionItemKind::Variable,
+                        detail: None,
+                    }),
+            );
+        }
+        items.extend(self.symbols.iter().map(|sym| CompletionItem {
+            label: sym.name.clone(),
+            kind: sym.kind.into(),
+            detail: None,
+        }));
+        items.extend(self.imports.items.iter().map(|imp| {
+            let path = imp.path.clone().join([imp.item.clone()]);
+            let kind = match self.path_target(path.clone()) {
+                Target::Module(_) => CompletionItemKind::Module,
+                _ => self
+                    .module_doc(&imp.path)
+                    .and_then(|(_, doc)| {
+                        index::symbols(&doc)
+                            .into_iter()
+                            .find(|sym| sym.name == imp.item)
+                    })
+                    .map(|sym| sym.kind.into())
+                    .unwrap_or(CompletionItemKind::Variable),
+            };
+            CompletionItem {
+                label: imp.name.clone(),
+                kind,
+                detail: Some(path.to_string()),
+            }
+        }));
+        items
+    }
+
+    /// Declarations and sub-modules of a module.
+    fn module_items(&self, path: &ModulePath) -> Vec<CompletionItem> {
+        let mut items = self
+            .ws
+            .submodules(path)
+            .into_iter()
+            .map(|name| CompletionItem {
+                label: name,
+                kind: CompletionItemKind::Module,
+                detail: None,
+            })
+            .collect::<Vec<_>>();
+        if let Some((_, doc)) = self.module_doc(path) {
+            items.extend(index::symbols(&doc).into_iter().map(|sym| CompletionItem {
+                label: sym.name,
+                kind: sym.kind.into(),
+                detail: Some(path.to_string()),
+            }))
+        }
+        items
+    }
+
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        fn convert(doc: &Document, sym: &Symbol) -> DocumentSymbol {
+            DocumentSymbol {
+                name: sym.name.clone(),
+                kind: sym.kind,
+                range: doc.range(sym.span.clone()),
+                selection_range: doc.range(sym.name_span.clone()),
+                children: sym.children.iter().map(|sym| convert(doc, sym)).collect(),
+            }
+        }
+        self.symbols
+            .iter()
+            .map(|sym| convert(self.doc, sym))
+            .collect()
+    }
+
+    /// Compile the document and report the first error, and the warnings of the document.
+    pub fn diagnostics(&self) -> Vec<lsp::Diagnostic> {
+        let mut diagnostics = self.warnings();
+        let options = CompileOptions {
+            strip: false,
+            features: self.ws.features.clone(),
+            ..Default::default()
+        };
+        let (res, _) = wesl::compile_sourcemap(&self.module, self.ws, &EscapeMangler, &options);
+        let Err(err) = res else {
+            return diagnostics;
+        };
+        let diag = wesl::Diagnostic::from(err);
+
+        let mut message = diag.error.to_string();
+        let range = match &diag.module_path {
+            Some(path) if *path != self.module => {
+                // errors in other modules are reported on the import statement.
+                let origin = diag
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| path.to_string());
+                message = format!("{message} (in {origin})");
+                self.imports
+                    .paths
+                    .iter()
+                    .filter(|(_, p)| p.starts_with(path) || path.starts_with(p))
+                    .min_by_key(|(i, _)| **i)
+                    .map(|(i, _)| self.doc.range(self.doc.tokens[*i].1.clone()))
+                    .unwrap_or_default()
+            }
+            _ => diag
+                .span
+                .as_ref()
+                .map(|span| self.doc.range(span.range()))
+                .unwrap_or_default(),
+        };
+        if let Some(decl) = &diag.declaration {
+            message = format!("{message}\nin declaration of `{decl}`");
+        }
+        diagnostics.push(lsp::Diagnostic {
+            range,
+            severity: lsp::DiagnosticSeverity::Error,
+            source: "wesl",
+            message,
+        });
+        diagnostics
+    }
+
+    /// Lints of the document, and feature flags used in `@if` attributes but not declared
+    /// in the document.
+    fn warnings(&self) -> Vec<lsp::Diagnostic> {
+        let Ok(wesl) = self.doc.text.parse::<TranslationUnit>() else {
+            return Vec::new();
+        };
+        let diagnostic = |diag: &wesl::Diagnostic<wesl::Error>, severity| lsp::Diagnostic {
+            range: diag
+                .span
+                .as_ref()
+                .map(|span| self.doc.range(span.range()))
+                .unwrap_or_default(),
+            severity,
+            source: "wesl",
+            message: diag.error.to_string(),
+        };
+        let lints = wesl::lint(&wesl, &LintConfig::new())
+            .into_iter()
+            .map(|lint| {
+                let severity = match lint.severity {
+                    DiagnosticSeverity::Error => lsp::DiagnosticSeverity::Error,
+                    DiagnosticSeverity::Warning => lsp::DiagnosticSeverity::Warning,
+                    _ => lsp::DiagnosticSeverity::Information,
+                };
+                diagnostic(&lint.diagnostic, severity)
+            });
+        wesl::undeclared_features(&wesl)
+            .iter()
+            .map(|diag| diagnostic(diag, lsp::DiagnosticSeverity::Warning))
+            .chain(lints)
+            .collect()
+    }
+}
+
+/// The local declaration statement containing `offset`.
+fn find_declaration(stmts: &[StatementNode], offset: usize) -> Option<&Declaration> {
+    stmts.iter().find_map(|stmt| match stmt.node() {
+        Statement::Declaration(decl) if stmt.span().range().contains(&offset) => Some(decl),
+        Statement::Compound(s) => find_declaration(&s.statements, offset),
+        Statement::If(s) => std::iter::once(&s.if_clause.body)
+            .chain(s.else_if_clauses.iter().map(|clause| &clause.body))
+            .chain(s.else_clause.iter().map(|clause| &clause.body))
+            .find_map(|body| find_declaration(&body.statements, offset)),
+        Statement::Switch(s) => s
+            .clauses
+            .iter()
+            .find_map(|clause| find_declaration(&clause.body.statements, offset)),
+        Statement::Loop(s) => find_declaration(&s.body.statements, offset).or_else(|| {
+            let continuing = s.continuing.as_ref()?;
+            find_declaration(&continuing.body.statements, offset)
+        }),
+        Statement::For(s) => s
+            .initializer
+            .as_ref()
+            .and_then(|init| find_declaration(std::slice::from_ref(init), offset))
+            .or_else(|| find_declaration(&s.body.statements, offset)),
+        Statement::While(s) => find_declaration(&s.body.statements, offset),
+        _ => None,
+    })
+}
+
+fn declaration_type(decl: &Declaration, ctx: &mut Context) -> Option<Type> {
+    match (&decl.ty, &decl.initializer) {
+        (Some(ty), _) => ty_eval_ty(ty, ctx).ok(),
+        (None, Some(init)) => init.eval_ty(ctx).ok(),
+        (None, None) => None,
+    }
+}
+
+/// Display a type with the original names of imported structs.
+fn display_type(ty: Type, sourcemap: &BasicSourceMap) -> Type {
+    let unmangle = |ty| display_type(ty, sourcemap);
+    match ty {
+        Type::Struct(name) => Type::Struct(
+            sourcemap
+                .get_decl(&name)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or(name),
+        ),
+        Type::Array(n, ty) => Type::Array(n, Box::new(unmangle(*ty))),
+        Type::Atomic(ty) => Type::Atomic(Box::new(unmangle(*ty))),
+        Type::Ptr(space, ty) => Type::Ptr(space, Box::new(unmangle(*ty))),
+        ty => ty,
+    }
+}
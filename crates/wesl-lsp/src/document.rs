@@ -0,0 +1,113 @@
+//! Open documents: source text, line index and tokens.
+
+use std::ops::Range as Span;
+
+use wgsl_parse::lexer::{Lexer, Token};
+
+use crate::lsp::{Position, Range};
+
+/// A source file, either opened in the editor or read from disk.
+#[derive(Clone, Debug)]
+pub struct Document {
+    pub text: String,
+    pub version: Option<i32>,
+    pub tokens: Vec<(Token, Span<usize>)>,
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    pub fn new(text: String, version: Option<i32>) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let tokens = tokenize(&text);
+        Self {
+            text,
+            version,
+            tokens,
+            line_starts,
+        }
+    }
+
+    /// Convert a byte offset to a LSP position.
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = self.text[start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position {
+            line: line as u32,
+            character: character as u32,
+        }
+    }
+
+    /// Convert a LSP position to a byte offset. Positions past the end of a line are
+    /// clamped to the end of the line.
+    pub fn offset(&self, pos: Position) -> usize {
+        let Some(start) = self.line_starts.get(pos.line as usize) else {
+            return self.text.len();
+        };
+        let end = self
+            .line_starts
+            .get(pos.line as usize + 1)
+            .copied()
+            .unwrap_or(self.text.len());
+        let mut utf16 = 0;
+        for (i, c) in self.text[*start..end].char_indices() {
+            if utf16 >= pos.character as usize || c == '\n' {
+                return start + i;
+            }
+            utf16 += c.len_utf16();
+        }
+        end
+    }
+
+    pub fn range(&self, span: Span<usize>) -> Range {
+        Range {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+
+    /// Index of the identifier token under the cursor. The cursor is allowed to sit just
+    /// after the identifier.
+    pub fn ident_at(&self, offset: usize) -> Option<usize> {
+        self.tokens
+            .iter()
+            .position(|(tok, span)| is_name(tok) && span.start <= offset && offset <= span.end)
+    }
+}
+
+/// Tokens that can be part of a name or of a module path.
+pub fn is_name(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::Ident(_) | Token::KwPackage | Token::KwSuper | Token::KwSelf
+    )
+}
+
+/// Lex the whole source. The lexer stops at the first invalid token, so lexing resumes
+/// right after it to keep the rest of the document usable.
+fn tokenize(source: &str) -> Vec<(Token, Span<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    'outer: while start < source.len() {
+        for tok in Lexer::new(&source[start..]) {
+            match tok {
+                Ok((l, tok, r)) => tokens.push((tok, start + l..start + r)),
+                Err((_, _, r)) => {
+                    start += r.max(1);
+                    while !source.is_char_boundary(start) {
+                        start += 1;
+                    }
+                    continue 'outer;
+                }
+            }
+        }
+        break;
+    }
+    tokens
+}
@@ -0,0 +1,346 @@
+//! Token-level index of a document: declarations, local variables and imports.
+//!
+//! The syntax tree does not record the position of declarations, and it is not available
+//! at all while the user is typing invalid code. The index is built from the token stream
+//! instead, which is always available.
+
+use std::{collections::HashMap, ops::Range as Span};
+
+use wgsl_parse::{
+    lexer::Token,
+    syntax::{ModulePath, PathOrigin},
+};
+
+use crate::{document::Document, lsp::SymbolKind};
+
+/// A named declaration.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Span of the name.
+    pub name_span: Span<usize>,
+    /// Span of the whole declaration, from the keyword (or the name for parameters and
+    /// struct members) to the end.
+    pub span: Span<usize>,
+    /// Range of token indices covered by `span`.
+    pub tokens: Span<usize>,
+    /// Struct members.
+    pub children: Vec<Symbol>,
+}
+
+/// An item brought into scope by an import statement.
+#[derive(Clone, Debug)]
+pub struct Import {
+    /// Name in the importing module, which differs from `item` when renamed with `as`.
+    pub name: String,
+    /// Path of the module containing `item`.
+    pub path: ModulePath,
+    pub item: String,
+}
+
+/// The import statements of a module.
+#[derive(Clone, Debug, Default)]
+pub struct Imports {
+    pub items: Vec<Import>,
+    /// The full path of each name token inside import statements, by token index.
+    pub paths: HashMap<usize, ModulePath>,
+}
+
+fn is_open(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::SymParenLeft
+            | Token::SymBraceLeft
+            | Token::SymBracketLeft
+            | Token::TemplateArgsStart
+    )
+}
+
+fn is_close(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::SymParenRight
+            | Token::SymBraceRight
+            | Token::SymBracketRight
+            | Token::TemplateArgsEnd
+    )
+}
+
+/// Index of the token closing the bracket opened at `open`, or the last token if it is
+/// never closed.
+fn closing(tokens: &[(Token, Span<usize>)], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, (tok, _)) in tokens.iter().enumerate().skip(open) {
+        if is_open(tok) {
+            depth += 1;
+        } else if is_close(tok) {
+            depth -= 1;
+            if depth == 0 {
+                return i;
+            }
+        }
+    }
+    tokens.len() - 1
+}
+
+/// Index of the first token matching `pred` starting at `from`, skipping over bracketed
+/// groups. Returns `tokens.len()` if none is found, or if a group opened before `from`
+/// is closed first.
+fn find(tokens: &[(Token, Span<usize>)], from: usize, pred: impl Fn(&Token) -> bool) -> usize {
+    let mut i = from;
+    while let Some((tok, _)) = tokens.get(i) {
+        if pred(tok) {
+            return i;
+        } else if is_open(tok) {
+            i = closing(tokens, i);
+        } else if is_close(tok) {
+            break;
+        }
+        i += 1;
+    }
+    tokens.len()
+}
+
+/// Module-scope declarations.
+pub fn symbols(doc: &Document) -> Vec<Symbol> {
+    let tokens = &doc.tokens;
+    let mut syms = Vec::new();
+    let mut i = 0;
+    while let Some((tok, _)) = tokens.get(i) {
+        let kind = match tok {
+            Token::KwFn => SymbolKind::Function,
+            Token::KwStruct => SymbolKind::Struct,
+            Token::KwAlias => SymbolKind::TypeParameter,
+            Token::KwConst | Token::KwOverride => SymbolKind::Constant,
+            Token::KwVar => SymbolKind::Variable,
+            tok if is_open(tok) => {
+                i = closing(tokens, i) + 1;
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        match declaration(doc, i, kind) {
+            Some(sym) => {
+                i = sym.tokens.end;
+                syms.push(sym);
+            }
+            None => i += 1,
+        }
+    }
+    syms
+}
+
+/// The declaration starting with the keyword at `kw`.
+fn declaration(doc: &Document, kw: usize, kind: SymbolKind) -> Option<Symbol> {
+    let tokens = &doc.tokens;
+    let mut i = kw + 1;
+    if matches!(tokens.get(i), Some((Token::TemplateArgsStart, _))) {
+        i = closing(tokens, i) + 1;
+    }
+    let (Token::Ident(name), name_span) = tokens.get(i)? else {
+        return None;
+    };
+
+    let (last, body) = match kind {
+        SymbolKind::Function | SymbolKind::Struct => {
+            let open = find(tokens, i + 1, |tok| *tok == Token::SymBraceLeft);
+            if open < tokens.len() {
+                (closing(tokens, open), Some(open))
+            } else {
+                (tokens.len() - 1, None)
+            }
+        }
+        _ => {
+            let semi = find(tokens, i + 1, |tok| *tok == Token::SymSemicolon);
+            (semi.min(tokens.len() - 1), None)
+        }
+    };
+
+    let children = match (kind, body) {
+        (SymbolKind::Struct, Some(open)) => members(doc, open, last),
+        _ => Vec::new(),
+    };
+
+    Some(Symbol {
+        name: name.clone(),
+        kind,
+        name_span: name_span.clone(),
+        span: tokens[kw].1.start..tokens[last].1.end,
+        tokens: kw..last + 1,
+        children,
+    })
+}
+
+/// Struct members or function parameters: `name: type` pairs between the brackets at
+/// `open` and `close`.
+fn members(doc: &Document, open: usize, close: usize) -> Vec<Symbol> {
+    let tokens = &doc.tokens;
+    let mut res = Vec::new();
+    let mut i = open + 1;
+    while i < close {
+        match (&tokens[i].0, tokens.get(i + 1)) {
+            (Token::Ident(name), Some((Token::SymColon, _))) => {
+                let last = find(tokens, i + 2, |tok| *tok == Token::SymComma).min(close) - 1;
+                res.push(Symbol {
+                    name: name.clone(),
+                    kind: match tokens[open].0 {
+                        Token::SymBraceLeft => SymbolKind::Field,
+                        _ => SymbolKind::Variable,
+                    },
+                    name_span: tokens[i].1.clone(),
+                    span: tokens[i].1.start..tokens[last].1.end,
+                    tokens: i..last + 1,
+                    children: Vec::new(),
+                });
+                i = last + 1;
+            }
+            (tok, _) if is_open(tok) => i = closing(tokens, i) + 1,
+            _ => i += 1,
+        }
+    }
+    res
+}
+
+/// Parameters and local declarations of the function `func` visible at `offset`, in
+/// declaration order.
+pub fn locals(doc: &Document, func: &Symbol, offset: usize) -> Vec<Symbol> {
+    let tokens = &doc.tokens;
+    if !func.span.contains(&offset) {
+        return Vec::new();
+    }
+    let params_open = find(tokens, func.tokens.start + 1, |tok| {
+        *tok == Token::SymParenLeft
+    });
+    if params_open >= func.tokens.end {
+        return Vec::new();
+    }
+    let params_close = closing(tokens, params_open);
+    let mut res = members(doc, params_open, params_close);
+
+    // local declarations are visible after their declaration, in their block and nested
+    // blocks. A block is identified by the index of its opening brace.
+    let mut blocks = Vec::new();
+    let mut cursor_blocks = None;
+    let mut decls = Vec::new();
+    let body = params_close + 1..func.tokens.end;
+    for (i, (tok, span)) in tokens.iter().enumerate().take(body.end).skip(body.start) {
+        if cursor_blocks.is_none() && span.start >= offset {
+            cursor_blocks = Some(blocks.clone());
+        }
+        match tok {
+            Token::SymBraceLeft => blocks.push(i),
+            Token::SymBraceRight => {
+                blocks.pop();
+            }
+            Token::KwLet | Token::KwVar | Token::KwConst => {
+                if let Some(sym) = declaration(doc, i, SymbolKind::Variable) {
+                    decls.push((sym, blocks.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+    let cursor_blocks = cursor_blocks.unwrap_or_default();
+    res.extend(decls.into_iter().filter_map(|(sym, blocks)| {
+        (sym.name_span.start <= offset && cursor_blocks.starts_with(&blocks)).then_some(sym)
+    }));
+    res
+}
+
+/// Resolve a module path relative to the current module, like the compiler does.
+pub fn join(module: &ModulePath, path: &ModulePath) -> ModulePath {
+    module.join_path(path).unwrap_or_else(|| path.clone())
+}
+
+/// Parse the import statements of a module.
+pub fn imports(doc: &Document, module: &ModulePath) -> Imports {
+    let tokens = &doc.tokens;
+    let mut res = Imports::default();
+    let mut i = 0;
+    while let Some((tok, _)) = tokens.get(i) {
+        if *tok == Token::KwImport {
+            i += 1;
+            let path = ModulePath::new(PathOrigin::Package, Vec::new());
+            import_tree(doc, &mut i, path, module, &mut res);
+        } else if is_open(tok) {
+            i = closing(tokens, i) + 1;
+        } else {
+            i += 1;
+        }
+    }
+    res
+}
+
+fn import_tree(
+    doc: &Document,
+    i: &mut usize,
+    mut path: ModulePath,
+    module: &ModulePath,
+    res: &mut Imports,
+) {
+    let tokens = &doc.tokens;
+    let tok = |i: usize| tokens.get(i).map(|(tok, _)| tok);
+    loop {
+        let is_first = path.is_empty();
+        match tok(*i) {
+            Some(Token::KwPackage) if is_first => path.origin = PathOrigin::Absolute,
+            Some(Token::KwSelf) if is_first => path.origin = PathOrigin::Relative(0),
+            Some(Token::KwSuper) if path.components.is_empty() => {
+                path.origin = match path.origin {
+                    PathOrigin::Relative(n) => PathOrigin::Relative(n + 1),
+                    _ => PathOrigin::Relative(1),
+                }
+            }
+            Some(Token::Ident(name)) => path.push(name),
+            _ => return,
+        }
+        res.paths.insert(*i, join(module, &path));
+        *i += 1;
+
+        if tok(*i) == Some(&Token::SymColonColon) {
+            *i += 1;
+            if tok(*i) == Some(&Token::SymBraceLeft) {
+                *i += 1;
+                loop {
+                    if tok(*i) == Some(&Token::SymBraceRight) {
+                        *i += 1;
+                        return;
+                    }
+                    import_tree(doc, i, path.clone(), module, res);
+                    match tok(*i) {
+                        Some(Token::SymComma) => *i += 1,
+                        Some(Token::SymBraceRight) => {
+                            *i += 1;
+                            return;
+                        }
+                        _ => return,
+                    }
+                }
+            }
+            continue;
+        }
+
+        let full_path = join(module, &path);
+        let Some(item) = path.components.pop() else {
+            return;
+        };
+        let name = match (tok(*i), tokens.get(*i + 1)) {
+            (Some(Token::KwAs), Some((Token::Ident(name), _))) => {
+                res.paths.insert(*i + 1, full_path);
+                *i += 2;
+                name.clone()
+            }
+            _ => item.clone(),
+        };
+        res.items.push(Import {
+            name,
+            path: join(module, &path),
+            item,
+        });
+        return;
+    }
+}
@@ -0,0 +1,222 @@
+//! The subset of Language Server Protocol types used by the server.
+//!
+//! Reference: <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/>
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Zero-based line and UTF-16 code unit offset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub uri: Url,
+    pub range: Range,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TextDocumentIdentifier {
+    pub uri: Url,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentItem {
+    pub uri: Url,
+    pub version: i32,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidOpenTextDocumentParams {
+    pub text_document: TextDocumentItem,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionedTextDocumentIdentifier {
+    pub uri: Url,
+    pub version: i32,
+}
+
+/// Only full-document changes are supported (`TextDocumentSyncKind.Full`).
+#[derive(Clone, Debug, Deserialize)]
+pub struct TextDocumentContentChangeEvent {
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeTextDocumentParams {
+    pub text_document: VersionedTextDocumentIdentifier,
+    pub content_changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidCloseTextDocumentParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentPositionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbolParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeParams {
+    #[serde(default)]
+    pub root_uri: Option<Url>,
+    #[serde(default)]
+    pub workspace_folders: Option<Vec<WorkspaceFolder>>,
+    #[serde(default)]
+    pub initialization_options: Option<InitializationOptions>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WorkspaceFolder {
+    pub uri: Url,
+}
+
+/// Server-specific options, passed by the client in `initializationOptions`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct InitializationOptions {
+    /// Root folder for `package::` imports, absolute or relative to the workspace root.
+    /// Defaults to the workspace root.
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Conditional compilation features used when checking documents.
+    #[serde(default)]
+    pub features: std::collections::HashMap<String, bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(into = "u8")]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+}
+
+impl From<DiagnosticSeverity> for u8 {
+    fn from(value: DiagnosticSeverity) -> Self {
+        value as u8
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: DiagnosticSeverity,
+    pub source: &'static str,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PublishDiagnosticsParams {
+    pub uri: Url,
+    pub diagnostics: Vec<Diagnostic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MarkupContent {
+    pub kind: &'static str,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Hover {
+    pub contents: MarkupContent,
+    pub range: Range,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(into = "u8")]
+pub enum SymbolKind {
+    Module = 2,
+    Field = 8,
+    Function = 12,
+    Variable = 13,
+    Constant = 14,
+    Struct = 23,
+    TypeParameter = 26,
+}
+
+impl From<SymbolKind> for u8 {
+    fn from(value: SymbolKind) -> Self {
+        value as u8
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub range: Range,
+    pub selection_range: Range,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(into = "u8")]
+pub enum CompletionItemKind {
+    Variable = 6,
+    Function = 3,
+    Field = 5,
+    Module = 9,
+    Constant = 21,
+    Struct = 22,
+    TypeParameter = 25,
+}
+
+impl From<CompletionItemKind> for u8 {
+    fn from(value: CompletionItemKind) -> Self {
+        value as u8
+    }
+}
+
+impl From<SymbolKind> for CompletionItemKind {
+    fn from(value: SymbolKind) -> Self {
+        match value {
+            SymbolKind::Module => Self::Module,
+            SymbolKind::Field => Self::Field,
+            SymbolKind::Function => Self::Function,
+            SymbolKind::Variable => Self::Variable,
+            SymbolKind::Constant => Self::Constant,
+            SymbolKind::Struct => Self::Struct,
+            SymbolKind::TypeParameter => Self::TypeParameter,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionItemKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
@@ -0,0 +1,30 @@
+//! A Language Server for WESL and WGSL, communicating over stdio.
+//!
+//! Features:
+//! * diagnostics: the first compilation error of each open document,
+//! * go-to-definition, including across `import` statements,
+//! * hover: declaration signatures and their types evaluated by [`wesl::eval`],
+//! * completion of local names, imported items and module items after `::`,
+//! * document symbols.
+//!
+//! Initialization options (all optional):
+//! * `base`: root folder for `package::` imports, relative to the workspace root.
+//! * `features`: conditional compilation features, e.g. `{ "debug": true }`.
+
+mod analysis;
+mod document;
+mod index;
+mod lsp;
+mod rpc;
+mod server;
+mod workspace;
+
+fn main() {
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    let code = server::Server::new(stdout).run(stdin).unwrap_or_else(|e| {
+        eprintln!("wesl-lsp: {e}");
+        1
+    });
+    std::process::exit(code)
+}
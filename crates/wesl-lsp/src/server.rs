@@ -0,0 +1,240 @@
+//! Message dispatch and server state.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{
+    analysis::Query,
+    document::Document,
+    lsp::{
+        DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+        DocumentSymbolParams, InitializationOptions, InitializeParams, PublishDiagnosticsParams,
+        TextDocumentPositionParams,
+    },
+    rpc::{self, code, Message, ResponseError},
+    workspace::Workspace,
+};
+
+pub struct Server<W: Write> {
+    output: W,
+    root: Option<PathBuf>,
+    options: InitializationOptions,
+    docs: HashMap<Url, Document>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            root: None,
+            options: Default::default(),
+            docs: Default::default(),
+            shutdown: false,
+        }
+    }
+
+    /// Serve requests until the `exit` notification or the end of the input. Returns the
+    /// process exit code.
+    pub fn run(mut self, mut input: impl BufRead) -> io::Result<i32> {
+        while let Some(body) = rpc::read_message(&mut input)? {
+            let msg = match serde_json::from_str::<Message>(&body) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    let err = ResponseError::new(code::PARSE_ERROR, e.to_string());
+                    self.send(rpc::response(Value::Null, Err(err)))?;
+                    continue;
+                }
+            };
+            match (msg.id, msg.method) {
+                (Some(id), Some(method)) => {
+                    let res = self.request(&method, msg.params);
+                    self.send(rpc::response(id, res))?;
+                }
+                (None, Some(method)) if method == "exit" => {
+                    return Ok(if self.shutdown { 0 } else { 1 });
+                }
+                (None, Some(method)) => self.notification(&method, msg.params)?,
+                // responses to server requests are ignored, the server sends none.
+                (_, None) => {}
+            }
+        }
+        Ok(1)
+    }
+
+    fn send(&mut self, msg: Value) -> io::Result<()> {
+        rpc::write_message(&mut self.output, &msg)
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        if self.shutdown {
+            return Err(ResponseError::new(
+                code::INVALID_REQUEST,
+                "the server is shutting down",
+            ));
+        }
+        match method {
+            "initialize" => {
+                let params: InitializeParams = parse(params)?;
+                self.initialize(params);
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                        "definitionProvider": true,
+                        "hoverProvider": true,
+                        "completionProvider": { "triggerCharacters": [":"] },
+                        "documentSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "wesl-lsp", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => {
+                let params: TextDocumentPositionParams = parse(params)?;
+                let res = self.query(&params.text_document.uri, |q| {
+                    q.definition(q.doc.offset(params.position))
+                });
+                Ok(json!(res.flatten()))
+            }
+            "textDocument/hover" => {
+                let params: TextDocumentPositionParams = parse(params)?;
+                let res = self.query(&params.text_document.uri, |q| {
+                    q.hover(q.doc.offset(params.position))
+                });
+                Ok(json!(res.flatten()))
+            }
+            "textDocument/completion" => {
+                let params: TextDocumentPositionParams = parse(params)?;
+                let res = self.query(&params.text_document.uri, |q| {
+                    q.completion(q.doc.offset(params.position))
+                });
+                Ok(json!(res.unwrap_or_default()))
+            }
+            "textDocument/documentSymbol" => {
+                let params: DocumentSymbolParams = parse(params)?;
+                let res = self.query(&params.text_document.uri, |q| q.document_symbols());
+                Ok(json!(res.unwrap_or_default()))
+            }
+            _ => Err(ResponseError::new(
+                code::METHOD_NOT_FOUND,
+                format!("unsupported method `{method}`"),
+            )),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: Value) -> io::Result<()> {
+        match method {
+            "textDocument/didOpen" => {
+                if let Ok(params) = parse::<DidOpenTextDocumentParams>(params) {
+                    let item = params.text_document;
+                    let doc = Document::new(item.text, Some(item.version));
+                    self.docs.insert(item.uri, doc);
+                    self.publish_all()?;
+                }
+            }
+            "textDocument/didChange" => {
+                if let Ok(params) = parse::<DidChangeTextDocumentParams>(params) {
+                    let id = params.text_document;
+                    if let Some(change) = params.content_changes.into_iter().last() {
+                        self.docs
+                            .insert(id.uri, Document::new(change.text, Some(id.version)));
+                        self.publish_all()?;
+                    }
+                }
+            }
+            "textDocument/didSave" => self.publish_all()?,
+            "textDocument/didClose" => {
+                if let Ok(params) = parse::<DidCloseTextDocumentParams>(params) {
+                    let uri = params.text_document.uri;
+                    self.docs.remove(&uri);
+                    self.send(rpc::notification(
+                        "textDocument/publishDiagnostics",
+                        PublishDiagnosticsParams {
+                            uri,
+                            diagnostics: Vec::new(),
+                            version: None,
+                        },
+                    ))?;
+                    self.publish_all()?;
+                }
+            }
+            // `initialized`, `$/cancelRequest`, ...
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn initialize(&mut self, params: InitializeParams) {
+        self.root = params
+            .workspace_folders
+            .and_then(|folders| folders.into_iter().next())
+            .map(|folder| folder.uri)
+            .or(params.root_uri)
+            .and_then(|uri| uri.to_file_path().ok());
+        self.options = params.initialization_options.unwrap_or_default();
+    }
+
+    /// The workspace of a document. Documents outside of the base folder are their own
+    /// package, rooted at their parent folder.
+    fn workspace(&self, uri: &Url) -> Option<Workspace<'_>> {
+        let path = uri.to_file_path().ok()?;
+        let base = match (&self.root, &self.options.base) {
+            (Some(root), Some(base)) => root.join(base),
+            (None, Some(base)) => PathBuf::from(base),
+            (Some(root), None) => root.clone(),
+            (None, None) => path.parent()?.to_path_buf(),
+        };
+        let base = if path.starts_with(&base) {
+            base
+        } else {
+            path.parent()?.to_path_buf()
+        };
+        Some(Workspace {
+            base,
+            docs: &self.docs,
+            features: &self.options.features,
+        })
+    }
+
+    fn query<T>(&self, uri: &Url, f: impl FnOnce(&Query) -> T) -> Option<T> {
+        let doc = self.docs.get(uri)?;
+        let ws = self.workspace(uri)?;
+        let query = Query::new(&ws, uri, doc)?;
+        Some(f(&query))
+    }
+
+    /// Check all open documents: a change in one module can affect the modules that
+    /// import it.
+    fn publish_all(&mut self) -> io::Result<()> {
+        let mut uris = self.docs.keys().cloned().collect::<Vec<_>>();
+        uris.sort();
+        for uri in uris {
+            let diagnostics = self.query(&uri, |q| q.diagnostics()).unwrap_or_default();
+            let version = self.docs[&uri].version;
+            self.send(rpc::notification(
+                "textDocument/publishDiagnostics",
+                PublishDiagnosticsParams {
+                    uri,
+                    diagnostics,
+                    version,
+                },
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, ResponseError> {
+    serde_json::from_value(params)
+        .map_err(|e| ResponseError::new(code::INVALID_PARAMS, e.to_string()))
+}
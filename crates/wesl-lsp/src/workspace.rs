@@ -0,0 +1,108 @@
+//! Mapping between module paths and files, with open documents taking precedence over the
+//! files on disk.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use url::Url;
+use wesl::{syntax::ModulePath, ResolveError, Resolver};
+use wgsl_parse::syntax::PathOrigin;
+
+use crate::document::Document;
+
+pub struct Workspace<'a> {
+    /// Root folder for `package::` imports.
+    pub base: PathBuf,
+    pub docs: &'a HashMap<Url, Document>,
+    pub features: &'a HashMap<String, bool>,
+}
+
+impl Workspace<'_> {
+    /// The module path of a file inside the base folder.
+    pub fn module_path(&self, uri: &Url) -> Option<ModulePath> {
+        let path = uri.to_file_path().ok()?;
+        let rel = path.strip_prefix(&self.base).ok()?;
+        let mut path = ModulePath::from_path(rel);
+        path.origin = PathOrigin::Absolute;
+        Some(path)
+    }
+
+    /// The file containing a module, looking for `.wesl` then `.wgsl` files.
+    pub fn uri(&self, path: &ModulePath) -> Option<Url> {
+        let mut fs_path = self.dir(path)?;
+        for ext in ["wesl", "wgsl"] {
+            fs_path.set_extension(ext);
+            let uri = Url::from_file_path(&fs_path).ok()?;
+            if self.docs.contains_key(&uri) || fs_path.is_file() {
+                return Some(uri);
+            }
+        }
+        None
+    }
+
+    /// The folder containing the sub-modules of a module.
+    pub fn dir(&self, path: &ModulePath) -> Option<PathBuf> {
+        if path.origin != PathOrigin::Absolute {
+            return None;
+        }
+        let mut fs_path = self.base.clone();
+        fs_path.extend(&path.components);
+        Some(fs_path)
+    }
+
+    /// Get an open document or read it from disk.
+    pub fn document(&self, uri: &Url) -> Option<Cow<'_, Document>> {
+        if let Some(doc) = self.docs.get(uri) {
+            return Some(Cow::Borrowed(doc));
+        }
+        let text = fs::read_to_string(uri.to_file_path().ok()?).ok()?;
+        Some(Cow::Owned(Document::new(text, None)))
+    }
+
+    /// Names of the sub-modules of a module: `.wesl` and `.wgsl` files and folders.
+    pub fn submodules(&self, path: &ModulePath) -> Vec<String> {
+        let Some(dir) = self.dir(path) else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut names = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let is_module = path.is_dir()
+                    || path
+                        .extension()
+                        .is_some_and(|ext| ext == "wesl" || ext == "wgsl");
+                is_module.then(|| Some(path.file_stem()?.to_str()?.to_string()))?
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+impl Resolver for Workspace<'_> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        let not_found = || ResolveError::ModuleNotFound(path.clone(), "no such file".to_string());
+        let uri = self.uri(path).ok_or_else(not_found)?;
+        match self.document(&uri).ok_or_else(not_found)? {
+            Cow::Borrowed(doc) => Ok(Cow::Borrowed(&doc.text)),
+            Cow::Owned(doc) => Ok(Cow::Owned(doc.text)),
+        }
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        let uri = self.uri(path)?;
+        let path = uri.to_file_path().ok()?;
+        Some(relative(&path, &self.base).display().to_string())
+    }
+}
+
+fn relative<'a>(path: &'a Path, base: &Path) -> &'a Path {
+    path.strip_prefix(base).unwrap_or(path)
+}
@@ -0,0 +1,210 @@
+//! Drive the language server over stdio with a scripted client.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{json, Value};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    /// notifications received while waiting for responses.
+    notifications: Vec<Value>,
+}
+
+impl Client {
+    fn spawn() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_wesl-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to spawn wesl-lsp");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+            notifications: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, msg: Value) {
+        let body = msg.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn recv(&mut self) -> Value {
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(val) = line.strip_prefix("Content-Length: ") {
+                len = val.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; len];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let msg = self.recv();
+            if msg["id"] == id {
+                assert!(msg["error"].is_null(), "error response: {msg}");
+                return msg["result"].clone();
+            }
+            self.notifications.push(msg);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Wait for the next diagnostics published for `uri`.
+    fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        loop {
+            let pos = self.notifications.iter().position(|msg| {
+                msg["method"] == "textDocument/publishDiagnostics" && msg["params"]["uri"] == uri
+            });
+            if let Some(pos) = pos {
+                let msg = self.notifications.remove(pos);
+                return msg["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+            let msg = self.recv();
+            self.notifications.push(msg);
+        }
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn workspace() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wesl-lsp-test-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lighting")).unwrap();
+    std::fs::write(
+        dir.join("lighting/light.wesl"),
+        "struct Light {\n    color: vec3f,\n    intensity: f32,\n}\n\nconst AMBIENT = 0.1;\n\nfn shade(light: Light) -> vec3f {\n    return light.color * light.intensity;\n}\n",
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn stdio_session() {
+    let dir = workspace();
+    let main_path = dir.join("main.wesl");
+    let main_uri = file_uri(&main_path);
+    let main = "import package::lighting::light;\nimport package::lighting::light::{Light, shade};\n\n@fragment\nfn main() -> @location(0) vec4f {\n    let l = Light(vec3f(1.0), 2.0);\n    let c = shade(l) + light::AMBIENT;\n    return vec4f(c, 1.0);\n}\n";
+    let (_, broken) = main.split_once('\n').unwrap();
+
+    let mut client = Client::spawn();
+    let init = client.request(
+        "initialize",
+        json!({ "processId": null, "rootUri": file_uri(&dir), "capabilities": {} }),
+    );
+    assert_eq!(init["capabilities"]["hoverProvider"], true);
+    client.notify("initialized", json!({}));
+
+    // `light` is not imported: one error is reported.
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": main_uri, "languageId": "wesl", "version": 1, "text": broken } }),
+    );
+    let diags = client.diagnostics(&main_uri);
+    assert_eq!(diags.len(), 1, "{diags:?}");
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": main_uri, "version": 2 },
+            "contentChanges": [{ "text": main }],
+        }),
+    );
+    let diags = client.diagnostics(&main_uri);
+    assert!(diags.is_empty(), "{diags:?}");
+
+    // go-to-definition across an import
+    let def = client.request(
+        "textDocument/definition",
+        json!({ "textDocument": { "uri": main_uri }, "position": { "line": 6, "character": 14 } }),
+    );
+    assert_eq!(def["uri"], file_uri(&dir.join("lighting/light.wesl")));
+    assert_eq!(def["range"]["start"], json!({ "line": 7, "character": 3 }));
+
+    // go-to-definition of a local
+    let def = client.request(
+        "textDocument/definition",
+        json!({ "textDocument": { "uri": main_uri }, "position": { "line": 6, "character": 18 } }),
+    );
+    assert_eq!(def["uri"], main_uri);
+    assert_eq!(def["range"]["start"], json!({ "line": 5, "character": 8 }));
+
+    // hover shows the evaluated type
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": main_uri }, "position": { "line": 6, "character": 32 } }),
+    );
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("const AMBIENT = 0.1"), "{text}");
+    assert!(text.contains("AbstractFloat"), "{text}");
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": main_uri }, "position": { "line": 5, "character": 8 } }),
+    );
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("type: `Light`"), "{text}");
+
+    // completion of module items
+    let main = main.replace("light::AMBIENT", "light::");
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": main_uri, "version": 3 },
+            "contentChanges": [{ "text": main }],
+        }),
+    );
+    let items = client.request(
+        "textDocument/completion",
+        json!({ "textDocument": { "uri": main_uri }, "position": { "line": 6, "character": 30 } }),
+    );
+    let labels = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, ["Light", "AMBIENT", "shade"]);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": main_uri } }),
+    );
+    assert_eq!(symbols[0]["name"], "main");
+    assert_eq!(symbols[0]["kind"], 12);
+
+    client.request("shutdown", Value::Null);
+    client.notify("exit", Value::Null);
+    let status = client.child.wait().unwrap();
+    assert!(status.success());
+    std::fs::remove_dir_all(dir).unwrap();
+}
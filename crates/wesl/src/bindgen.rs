@@ -0,0 +1,661 @@
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::{format_ident, quote};
+use thiserror::Error;
+use unicode_ident::{is_xid_continue, is_xid_start};
+use wgsl_parse::syntax::{
+    AccessMode, AddressSpace, DeclarationKind, GlobalDeclaration, TranslationUnit,
+};
+
+use crate::{
+    eval::{
+        round_up, ty_eval_ty, Context, EvalAttrs, SampledType, SamplerType, SyntaxUtil as _,
+        TextureType, Type,
+    },
+    reflect, Binding, Diagnostic, Error, Reflection, ShaderStage,
+};
+
+/// Rust code generation error.
+#[derive(Clone, Debug, Error)]
+pub enum BindgenError {
+    #[error("type `{1}` of `{0}` is not host-shareable")]
+    NotHostShareable(String, Type),
+    #[error("cannot compute the memory layout of `{0}`")]
+    UnknownLayout(String),
+    #[error("type `{1}` of binding `{0}` is not supported by wgpu")]
+    UnsupportedBinding(String, Type),
+    #[error("type `{1}` of vertex input `{0}` is not a vertex format")]
+    NotVertexFormat(String, Type),
+}
+
+type E = BindgenError;
+
+/// Options of the Rust code generation. See [`bindgen`].
+#[derive(Clone, Debug, Default)]
+pub struct BindgenOptions {
+    /// Derive `bytemuck::Pod` and `bytemuck::Zeroable` on the generated structs. The crate
+    /// that includes the generated code must depend on `bytemuck` with the `derive` feature.
+    pub bytemuck: bool,
+    /// Generate `wgpu` helpers: entry point names, bind group layouts and vertex buffer
+    /// layouts. The crate that includes the generated code must depend on `wgpu`.
+    pub wgpu: bool,
+}
+
+/// Generate Rust structs with the memory layout of the WGSL structs used in `uniform` and
+/// `storage` bindings, transitively.
+///
+/// The structs are `#[repr(C)]`, with explicit padding fields and a `new` constructor that
+/// zeroes the padding. The size of each struct is checked with a compile-time assertion.
+///
+/// WGSL types map to Rust types as follows:
+/// * `i32`, `u32` and `f32` map to the same Rust types. `f16` maps to `u16` (the bits).
+/// * `atomic<T>` maps to `T`.
+/// * `vecN<T>` maps to `[T; N]`. In arrays, `vec3<T>` maps to `[T; 4]`.
+/// * `matCxR<T>` maps to `[[T; R]; C]`. `matCx3<T>` maps to `[[T; 4]; C]`.
+/// * `array<T, N>` maps to `[T; N]`.
+/// * Runtime-sized arrays are not included in the struct, which contains the members
+///   that precede it.
+///
+/// The structs have the names of the compiled WGSL. The declarations of the root module
+/// are not mangled. Characters that are not allowed in Rust identifiers, e.g. those of
+/// [`crate::UnicodeMangler`], are replaced with `_`.
+///
+/// With [`BindgenOptions::wgpu`], the following modules are generated as well:
+/// * `entry_points`: a `&str` constant for each entry point name, in upper snake case.
+/// * `bind_groups`: a `wgpu::BindGroupLayoutDescriptor` constant `LAYOUT_{group}` for each
+///   bind group, and a `create_layouts` function that creates all of them. Bindings are
+///   visible to the stages of the entry points that use them.
+/// * `vertex`: for each vertex entry point, the `wgpu::VertexAttribute`s of its
+///   `@location` inputs and a function that returns a `wgpu::VertexBufferLayout`. The
+///   attributes are tightly packed in a single buffer, in location order.
+#[allow(clippy::result_large_err)]
+pub fn bindgen(
+    wgsl: &TranslationUnit,
+    options: &BindgenOptions,
+) -> Result<String, Diagnostic<Error>> {
+    let mut ctx = Context::new(wgsl);
+    let mut structs = Vec::new();
+
+    for decl in &wgsl.global_declarations {
+        if let GlobalDeclaration::Declaration(decl) = decl {
+            let is_buffer = matches!(
+                decl.kind,
+                DeclarationKind::Var(Some(AddressSpace::Uniform | AddressSpace::Storage(_)))
+            );
+            if let (true, Some(ty)) = (is_buffer, &decl.ty) {
+                let name = decl.ident.to_string();
+                ty_eval_ty(ty, &mut ctx)
+                    .map_err(Error::from)
+                    .and_then(|ty| collect_structs(&ty, &mut structs, &mut ctx))
+                    .map_err(|e| Diagnostic::from(e).with_declaration(name))?;
+            }
+        }
+    }
+
+    let tokens = structs
+        .iter()
+        .map(|name| {
+            gen_struct(name, options, &mut ctx)
+                .map_err(|e| Diagnostic::from(e).with_declaration(name.clone()))
+        })
+        .collect::<Result<TokenStream, _>>()?;
+
+    if options.wgpu {
+        let refl = reflect(wgsl)?;
+        let wgpu = gen_wgpu(&refl).map_err(|e| Diagnostic::from(Error::from(e)))?;
+        Ok(quote! { #tokens #wgpu }.to_string())
+    } else {
+        Ok(tokens.to_string())
+    }
+}
+
+/// Collect the names of the structs used by a type, dependencies first.
+#[allow(clippy::result_large_err)]
+fn collect_structs(ty: &Type, structs: &mut Vec<String>, ctx: &mut Context) -> Result<(), Error> {
+    match ty {
+        Type::Struct(name) => {
+            if structs.contains(name) {
+                return Ok(());
+            }
+            let source = ctx.source;
+            let decl = source
+                .decl_struct(name)
+                .ok_or_else(|| E::UnknownLayout(name.clone()))?;
+            for m in &decl.members {
+                let ty = ty_eval_ty(&m.ty, ctx)?;
+                collect_structs(&ty, structs, ctx)?;
+            }
+            structs.push(name.clone());
+        }
+        Type::Array(_, ty) | Type::Atomic(ty) => collect_structs(ty, structs, ctx)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+#[allow(clippy::result_large_err)]
+fn gen_struct(
+    name: &str,
+    options: &BindgenOptions,
+    ctx: &mut Context,
+) -> Result<TokenStream, Error> {
+    let source = ctx.source;
+    let decl = source
+        .decl_struct(name)
+        .ok_or_else(|| E::UnknownLayout(name.to_string()))?;
+    let ident = rust_ident(name);
+
+    let mut fields = Vec::new();
+    let mut params = Vec::new();
+    let mut inits = Vec::new();
+    let pad = |size: u32, fields: &mut Vec<TokenStream>, inits: &mut Vec<TokenStream>| {
+        let ident = format_ident!("__pad{}", fields.len());
+        let size = Literal::u32_unsuffixed(size);
+        fields.push(quote! { #ident: [u8; #size] });
+        inits.push(quote! { #ident: [0; #size] });
+    };
+
+    let mut doc = decl.doc.clone().into_iter().collect::<Vec<_>>();
+    // offset of the next member, and end of the last field.
+    let (mut offset, mut end) = (0, 0);
+    let mut runtime_array = false;
+
+    for m in &decl.members {
+        let member = format!("{name}.{}", m.ident);
+        let ty = ty_eval_ty(&m.ty, ctx)?;
+        let align = m
+            .attr_align(ctx)?
+            .or_else(|| ty.align_of(ctx))
+            .ok_or_else(|| E::UnknownLayout(member.clone()))?;
+        offset = round_up(align, offset);
+
+        if let Type::Array(None, _) = ty {
+            doc.push(format!(
+                "The runtime-sized array `{}` starts at byte offset {offset}.",
+                m.ident
+            ));
+            runtime_array = true;
+            break;
+        }
+
+        let size = ty
+            .size_of(ctx)
+            .ok_or_else(|| E::UnknownLayout(member.clone()))?;
+        if offset > end {
+            pad(offset - end, &mut fields, &mut inits);
+        }
+        let field = rust_ident(&m.ident.name());
+        let rust_ty = rust_ty(&ty, &member)?;
+        let field_doc = m.doc.iter();
+        fields.push(quote! { #(#[doc = #field_doc])* pub #field: #rust_ty });
+        params.push(quote! { #field: #rust_ty });
+        inits.push(quote! { #field });
+        end = offset + size;
+        offset += m.attr_size(ctx)?.unwrap_or(size);
+    }
+
+    let size = if runtime_array {
+        offset
+    } else {
+        let align = Type::Struct(name.to_string())
+            .align_of(ctx)
+            .ok_or_else(|| E::UnknownLayout(name.to_string()))?;
+        round_up(align, offset)
+    };
+    if size > end {
+        pad(size - end, &mut fields, &mut inits);
+    }
+
+    let derives = if options.bytemuck {
+        quote! { Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable }
+    } else {
+        quote! { Clone, Copy, Debug, PartialEq }
+    };
+    let size = Literal::u32_unsuffixed(size);
+
+    Ok(quote! {
+        #(#[doc = #doc])*
+        #[repr(C)]
+        #[derive(#derives)]
+        #[allow(dead_code, non_camel_case_types)]
+        pub struct #ident {
+            #(#fields,)*
+        }
+
+        impl #ident {
+            #[allow(clippy::too_many_arguments)]
+            pub const fn new(#(#params),*) -> Self {
+                Self { #(#inits,)* }
+            }
+        }
+
+        const _: () = assert!(::core::mem::size_of::<#ident>() == #size);
+    })
+}
+
+/// The Rust type with the same size as a host-shareable WGSL type.
+fn rust_ty(ty: &Type, member: &str) -> Result<TokenStream, E> {
+    match ty {
+        Type::I32 => Ok(quote! { i32 }),
+        Type::U32 => Ok(quote! { u32 }),
+        Type::F32 => Ok(quote! { f32 }),
+        Type::F16 => Ok(quote! { u16 }),
+        Type::Struct(name) => {
+            let ident = rust_ident(name);
+            Ok(quote! { #ident })
+        }
+        Type::Atomic(ty) => rust_ty(ty, member),
+        Type::Vec(n, ty) => {
+            let ty = rust_ty(ty, member)?;
+            let n = Literal::u8_unsuffixed(*n);
+            Ok(quote! { [#ty; #n] })
+        }
+        Type::Mat(c, r, ty) => {
+            // columns are aligned like vectors, a 3-component column is padded.
+            let r = if *r == 3 { 4 } else { *r };
+            let ty = rust_ty(&Type::Vec(r, ty.clone()), member)?;
+            let c = Literal::u8_unsuffixed(*c);
+            Ok(quote! { [#ty; #c] })
+        }
+        Type::Array(Some(n), ty) => {
+            // the array stride of a 3-component vector is the size of a 4-component vector.
+            let ty = match &**ty {
+                Type::Vec(3, ty) => rust_ty(&Type::Vec(4, ty.clone()), member)?,
+                ty => rust_ty(ty, member)?,
+            };
+            let n = Literal::usize_unsuffixed(*n);
+            Ok(quote! { [#ty; #n] })
+        }
+        _ => Err(E::NotHostShareable(member.to_string(), ty.clone())),
+    }
+}
+
+fn gen_wgpu(refl: &Reflection) -> Result<TokenStream, E> {
+    let entry_points = refl.entry_points.iter().map(|entry| {
+        let ident = format_ident!("{}", snake_case(&entry.name).to_uppercase());
+        let name = &entry.name;
+        quote! { pub const #ident: &str = #name; }
+    });
+
+    let count = refl.bindings.iter().map(|b| b.group + 1).max().unwrap_or(0);
+    let mut layouts = Vec::new();
+    let mut idents = Vec::new();
+    for group in 0..count {
+        let mut bindings = refl
+            .bindings
+            .iter()
+            .filter(|b| b.group == group)
+            .collect::<Vec<_>>();
+        bindings.sort_by_key(|b| b.binding);
+        let entries = bindings
+            .into_iter()
+            .map(|b| layout_entry(b, refl))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ident = format_ident!("LAYOUT_{group}");
+        let label = format!("@group({group})");
+        layouts.push(quote! {
+            pub const #ident: wgpu::BindGroupLayoutDescriptor<'static> =
+                wgpu::BindGroupLayoutDescriptor {
+                    label: Some(#label),
+                    entries: &[#(#entries),*],
+                };
+        });
+        idents.push(ident);
+    }
+    let count = Literal::usize_unsuffixed(count as usize);
+
+    let mut vertex = Vec::new();
+    for entry in &refl.entry_points {
+        if entry.stage != ShaderStage::Vertex || entry.inputs.is_empty() {
+            continue;
+        }
+        let mut inputs = entry.inputs.iter().collect::<Vec<_>>();
+        inputs.sort_by_key(|input| input.location);
+        let mut attributes = Vec::new();
+        let mut offset = 0;
+        for input in inputs {
+            let (format, size) = vertex_format(&input.ty).ok_or_else(|| {
+                let name = input.name.clone().unwrap_or_default();
+                E::NotVertexFormat(format!("{}.{name}", entry.name), input.ty.clone())
+            })?;
+            let location = Literal::u32_unsuffixed(input.location);
+            let offset_lit = Literal::u64_unsuffixed(offset);
+            attributes.push(quote! {
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::#format,
+                    offset: #offset_lit,
+                    shader_location: #location,
+                }
+            });
+            offset += size;
+        }
+        let name = snake_case(&entry.name);
+        let attrs_ident = format_ident!("{}_ATTRIBUTES", name.to_uppercase());
+        let fn_ident = format_ident!("{name}_buffer_layout");
+        let stride = Literal::u64_unsuffixed(offset);
+        vertex.push(quote! {
+            pub const #attrs_ident: &[wgpu::VertexAttribute] = &[#(#attributes),*];
+
+            pub const fn #fn_ident(
+                step_mode: wgpu::VertexStepMode,
+            ) -> wgpu::VertexBufferLayout<'static> {
+                wgpu::VertexBufferLayout {
+                    array_stride: #stride,
+                    step_mode,
+                    attributes: #attrs_ident,
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        pub mod entry_points {
+            #(#entry_points)*
+        }
+
+        pub mod bind_groups {
+            #(#layouts)*
+
+            pub fn create_layouts(device: &wgpu::Device) -> [wgpu::BindGroupLayout; #count] {
+                [#(device.create_bind_group_layout(&#idents)),*]
+            }
+        }
+
+        pub mod vertex {
+            #(#vertex)*
+        }
+    })
+}
+
+fn layout_entry(b: &Binding, refl: &Reflection) -> Result<TokenStream, E> {
+    let stages = refl
+        .entry_points
+        .iter()
+        .filter(|entry| entry.bindings.contains(&(b.group, b.binding)))
+        .map(|entry| entry.stage)
+        .collect::<Vec<_>>();
+    let visibility = [
+        (ShaderStage::Vertex, quote! { VERTEX }),
+        (ShaderStage::Fragment, quote! { FRAGMENT }),
+        (ShaderStage::Compute, quote! { COMPUTE }),
+    ]
+    .into_iter()
+    .filter(|(stage, _)| stages.contains(stage))
+    .map(|(_, stage)| quote! { wgpu::ShaderStages::#stage })
+    .reduce(|a, b| quote! { #a.union(#b) })
+    .unwrap_or(quote! { wgpu::ShaderStages::NONE });
+
+    let unsupported = || E::UnsupportedBinding(b.name.clone(), b.ty.clone());
+    let ty = match (&b.address_space, &b.ty) {
+        (AddressSpace::Uniform | AddressSpace::Storage(_), _) => {
+            let ty = if b.address_space == AddressSpace::Uniform {
+                quote! { wgpu::BufferBindingType::Uniform }
+            } else {
+                let read_only = b.access_mode == Some(AccessMode::Read);
+                quote! { wgpu::BufferBindingType::Storage { read_only: #read_only } }
+            };
+            let min_size = match b.min_size {
+                Some(size) => {
+                    let size = Literal::u64_unsuffixed(size as u64);
+                    quote! { wgpu::BufferSize::new(#size) }
+                }
+                None => quote! { None },
+            };
+            quote! {
+                wgpu::BindingType::Buffer {
+                    ty: #ty,
+                    has_dynamic_offset: false,
+                    min_binding_size: #min_size,
+                }
+            }
+        }
+        (_, Type::Sampler(SamplerType::Sampler)) => {
+            quote! { wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering) }
+        }
+        (_, Type::Sampler(SamplerType::SamplerComparison)) => {
+            quote! { wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison) }
+        }
+        (_, Type::Texture(tex)) => texture_binding(tex).ok_or_else(unsupported)?,
+        _ => return Err(unsupported()),
+    };
+
+    let binding = Literal::u32_unsuffixed(b.binding);
+    Ok(quote! {
+        wgpu::BindGroupLayoutEntry {
+            binding: #binding,
+            visibility: #visibility,
+            ty: #ty,
+            count: None,
+        }
+    })
+}
+
+fn texture_binding(tex: &TextureType) -> Option<TokenStream> {
+    let sample_type = |ty: &SampledType, multisampled: bool| match ty {
+        SampledType::F32 => {
+            // multisampled textures cannot be filtered.
+            let filterable = !multisampled;
+            quote! { wgpu::TextureSampleType::Float { filterable: #filterable } }
+        }
+        SampledType::I32 => quote! { wgpu::TextureSampleType::Sint },
+        SampledType::U32 => quote! { wgpu::TextureSampleType::Uint },
+    };
+    let depth = quote! { wgpu::TextureSampleType::Depth };
+
+    let (sample_type, dim, multisampled) = match tex {
+        TextureType::Sampled1D(ty) => (sample_type(ty, false), quote! { D1 }, false),
+        TextureType::Sampled2D(ty) => (sample_type(ty, false), quote! { D2 }, false),
+        TextureType::Sampled2DArray(ty) => (sample_type(ty, false), quote! { D2Array }, false),
+        TextureType::Sampled3D(ty) => (sample_type(ty, false), quote! { D3 }, false),
+        TextureType::SampledCube(ty) => (sample_type(ty, false), quote! { Cube }, false),
+        TextureType::SampledCubeArray(ty) => (sample_type(ty, false), quote! { CubeArray }, false),
+        TextureType::Multisampled2D(ty) => (sample_type(ty, true), quote! { D2 }, true),
+        TextureType::DepthMultisampled2D => (depth, quote! { D2 }, true),
+        TextureType::Depth2D => (depth, quote! { D2 }, false),
+        TextureType::Depth2DArray => (depth, quote! { D2Array }, false),
+        TextureType::DepthCube => (depth, quote! { Cube }, false),
+        TextureType::DepthCubeArray => (depth, quote! { CubeArray }, false),
+        TextureType::Storage1D(format, access)
+        | TextureType::Storage2D(format, access)
+        | TextureType::Storage2DArray(format, access)
+        | TextureType::Storage3D(format, access) => {
+            let dim = match tex {
+                TextureType::Storage1D(..) => quote! { D1 },
+                TextureType::Storage2D(..) => quote! { D2 },
+                TextureType::Storage2DArray(..) => quote! { D2Array },
+                _ => quote! { D3 },
+            };
+            let access = match access {
+                AccessMode::Read => quote! { ReadOnly },
+                AccessMode::Write => quote! { WriteOnly },
+                AccessMode::ReadWrite => quote! { ReadWrite },
+            };
+            // the texel formats have the same names in wgpu.
+            let format = format_ident!("{format:?}");
+            return Some(quote! {
+                wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::#access,
+                    format: wgpu::TextureFormat::#format,
+                    view_dimension: wgpu::TextureViewDimension::#dim,
+                }
+            });
+        }
+        TextureType::External => return None,
+    };
+
+    Some(quote! {
+        wgpu::BindingType::Texture {
+            sample_type: #sample_type,
+            view_dimension: wgpu::TextureViewDimension::#dim,
+            multisampled: #multisampled,
+        }
+    })
+}
+
+/// The `wgpu::VertexFormat` of a vertex input type, and its size.
+fn vertex_format(ty: &Type) -> Option<(Ident, u64)> {
+    let (n, ty) = match ty {
+        Type::Vec(n, ty) => (*n, &**ty),
+        ty => (1, ty),
+    };
+    let (name, size) = match ty {
+        Type::F32 => ("Float32", 4),
+        Type::I32 => ("Sint32", 4),
+        Type::U32 => ("Uint32", 4),
+        Type::F16 if n == 2 || n == 4 => ("Float16", 2),
+        _ => return None,
+    };
+    let ident = if n == 1 {
+        format_ident!("{name}")
+    } else {
+        format_ident!("{name}x{n}")
+    };
+    Some((ident, size * n as u64))
+}
+
+/// Convert a `camelCase` or `PascalCase` name to `snake_case`.
+fn snake_case(name: &str) -> String {
+    let mut res = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && prev_lower {
+            res.push('_');
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        res.extend(c.to_lowercase());
+    }
+    res
+}
+
+/// A Rust identifier for a WGSL name, see [`bindgen`].
+fn rust_ident(name: &str) -> Ident {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+    let mut name = name
+        .chars()
+        .map(|c| if is_xid_continue(c) { c } else { '_' })
+        .collect::<String>();
+    if !name.starts_with(|c| is_xid_start(c) || c == '_') {
+        name.insert(0, '_');
+    }
+    match name.as_str() {
+        // these keywords cannot be raw identifiers.
+        "_" | "self" | "Self" | "super" | "crate" => format_ident!("{name}_"),
+        name if KEYWORDS.contains(&name) => Ident::new_raw(name, Span::call_site()),
+        name => Ident::new(name, Span::call_site()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, ManglerKind, VirtualResolver, Wesl};
+
+    #[test]
+    fn test_bindgen() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "struct Light { color: vec3f, intensity: f32, dir: vec3f }
+            struct Scene { count: u32, lights: array<Light> }
+            @group(0) @binding(0) var<storage> scene: Scene;
+            @compute @workgroup_size(1) fn main() { let n = scene.count; }"
+                .into(),
+        );
+        let compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        let code = compiler
+            .compile("main")
+            .unwrap()
+            .bindgen(&BindgenOptions::default())
+            .unwrap()
+            .replace(' ', "");
+
+        // `dir` is followed by 4 bytes of padding, `lights` is aligned to 16 bytes.
+        assert!(code.contains(
+            "pubstructLight{pubcolor:[f32;3],pubintensity:f32,pubdir:[f32;3],__pad3:[u8;4],}"
+        ));
+        assert!(code.contains("size_of::<Light>()==32"));
+        assert!(code.contains("pubstructScene{pubcount:u32,__pad1:[u8;12],}"));
+        assert!(code.contains("size_of::<Scene>()==16"));
+    }
+
+    #[test]
+    fn test_bindgen_names() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::types::Light;
+            @group(0) @binding(0) var<uniform> light: Light;
+            @compute @workgroup_size(1) fn main() { let c = light.color; }"
+                .into(),
+        );
+        resolver.add_module("types", "struct Light { color: vec3f, type_: f32 }".into());
+        let mut compiler = Wesl::new_barebones();
+        compiler
+            .set_options(CompileOptions {
+                lower: false,
+                ..Default::default()
+            })
+            .set_mangler(ManglerKind::Unicode);
+        let compiler = compiler.set_custom_resolver(resolver);
+        let code = compiler
+            .compile("main")
+            .unwrap()
+            .bindgen(&BindgenOptions::default())
+            .unwrap();
+
+        // the unicode mangler separates path components with `ːː`, which Rust rejects.
+        assert!(code.contains("pub struct package__types__Light"));
+
+        assert_eq!(rust_ident("type").to_string(), "r#type");
+        assert_eq!(rust_ident("self").to_string(), "self_");
+        assert_eq!(rust_ident("fooᐸbarᐳ").to_string(), "fooᐸbarᐳ");
+    }
+
+    #[test]
+    fn test_bindgen_wgpu() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "@group(0) @binding(0) var<uniform> tint: vec4f;
+            @group(0) @binding(1) var tex: texture_2d<f32>;
+            @group(0) @binding(2) var samp: sampler;
+            @group(1) @binding(0) var<storage, read_write> counter: atomic<u32>;
+            fn color(uv: vec2f) -> vec4f { return textureSample(tex, samp, uv) * tint; }
+            @vertex fn vsMain(@location(1) uv: vec2f, @location(0) pos: vec3f) -> @builtin(position) vec4f {
+                return vec4f(pos + vec3f(uv, 0.0), 1.0) * tint;
+            }
+            @fragment fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f { return color(uv); }
+            @compute @workgroup_size(1) fn cs_main() { atomicAdd(&counter, 1u); }"
+                .into(),
+        );
+        let compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        let options = BindgenOptions {
+            wgpu: true,
+            ..Default::default()
+        };
+        let code = compiler
+            .compile("main")
+            .unwrap()
+            .bindgen(&options)
+            .unwrap()
+            .replace(' ', "");
+
+        assert!(code.contains(r#"pubconstVS_MAIN:&str="vsMain";"#));
+        assert!(code.contains(
+            "binding:0,visibility:wgpu::ShaderStages::VERTEX.union(wgpu::ShaderStages::FRAGMENT),"
+        ));
+        assert!(code.contains("binding:1,visibility:wgpu::ShaderStages::FRAGMENT,"));
+        assert!(code.contains("Storage{read_only:false}"));
+        assert!(code.contains("[wgpu::BindGroupLayout;2]"));
+        // attributes are in location order.
+        assert!(code.contains("format:wgpu::VertexFormat::Float32x3,offset:0,shader_location:0"));
+        assert!(code.contains("format:wgpu::VertexFormat::Float32x2,offset:12,shader_location:1"));
+        assert!(code.contains("pubconstfnvs_main_buffer_layout("));
+        assert!(code.contains("array_stride:20,"));
+    }
+}
@@ -1,9 +1,17 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
 
-use crate::{visit::Visit, Diagnostic};
+use crate::{visit::Visit, Diagnostic, ResolveError, Resolver};
 use itertools::Itertools;
 use thiserror::Error;
-use wgsl_parse::{syntax::*, Decorated};
+use wgsl_parse::{
+    span::{Span, Spanned},
+    syntax::*,
+    Decorated,
+};
 
 /// Conditional translation error.
 #[derive(Clone, Debug, Error)]
@@ -12,8 +20,12 @@ pub enum CondCompError {
     InvalidFeatureFlag(String),
     #[error("missing feature flag: `{0}`")]
     MissingFeatureFlag(String),
+    #[error("undeclared feature flag: `{0}`")]
+    UndeclaredFeatureFlag(String),
     #[error("invalid if attribute expression: `{0}`")]
     InvalidExpression(Expression),
+    #[error("`@{0}` attribute must directly follow a sibling with an `@if` or `@elif` attribute")]
+    UnmatchedElse(&'static str),
 }
 
 type E = crate::Error;
@@ -94,11 +106,101 @@ pub fn eval_attr(expr: &Expression, features: &Features) -> Result<Expression, C
     }
 }
 
+fn parenthesize(expr: ExpressionNode) -> ExpressionNode {
+    let span = expr.span().clone();
+    let expr = Expression::Parenthesized(ParenthesizedExpression { expression: expr });
+    Spanned::new(expr, span)
+}
+
+fn negate(expr: ExpressionNode) -> ExpressionNode {
+    let span = expr.span().clone();
+    let expr = Expression::Unary(UnaryExpression {
+        operator: UnaryOperator::LogicalNegation,
+        operand: parenthesize(expr),
+    });
+    Spanned::new(expr, span)
+}
+
+fn conjunction(left: Option<ExpressionNode>, right: ExpressionNode) -> ExpressionNode {
+    match left {
+        Some(left) => {
+            let span = right.span().clone();
+            let expr = Expression::Binary(BinaryExpression {
+                operator: BinaryOperator::ShortCircuitAnd,
+                left,
+                right,
+            });
+            Spanned::new(expr, span)
+        }
+        None => right,
+    }
+}
+
+/// Rewrite `@elif` and `@else` chains of consecutive sibling nodes into standalone `@if`
+/// attributes. A branch is kept when its condition holds and all previous conditions of
+/// the chain do not, e.g. `@if(a)`, `@elif(b)`, `@else` become `@if(a)`, `@if(!(a) && (b))`,
+/// `@if(!(a) && !(b))`.
+#[allow(clippy::result_large_err)]
+fn desugar_else_attributes(nodes: &mut [impl Decorated]) -> Result<(), E> {
+    // conditions of the previous branches of the current chain.
+    let mut chain: Option<Vec<ExpressionNode>> = None;
+
+    for node in nodes {
+        let attr = node.attributes_mut().iter_mut().find(|attr| {
+            matches!(
+                attr,
+                Attribute::If(_) | Attribute::Elif(_) | Attribute::Else
+            )
+        });
+        match attr {
+            Some(Attribute::If(expr)) => chain = Some(vec![expr.clone()]),
+            Some(attr) => {
+                let (name, cond) = match attr {
+                    Attribute::Elif(expr) => ("elif", Some(expr.clone())),
+                    _ => ("else", None),
+                };
+                let Some(prev) = &mut chain else {
+                    let err = Diagnostic::from(CondCompError::UnmatchedElse(name));
+                    return Err(match cond {
+                        Some(expr) => err.with_span(expr.span().clone()),
+                        None => err,
+                    }
+                    .into());
+                };
+                let not_prev = prev
+                    .iter()
+                    .map(|expr| negate(expr.clone()))
+                    .reduce(|left, right| conjunction(Some(left), right));
+                let expr = match cond {
+                    Some(cond) => {
+                        prev.push(cond.clone());
+                        conjunction(not_prev, parenthesize(cond))
+                    }
+                    None => {
+                        chain = None;
+                        not_prev.expect("chain has at least one condition")
+                    }
+                };
+                *attr = Attribute::If(expr);
+            }
+            None => chain = None,
+        }
+    }
+    Ok(())
+}
+
 fn eval_if_attr(
     opt_node: &mut Option<impl Decorated>,
     features: &Features,
 ) -> Result<(), CondCompError> {
     if let Some(node) = opt_node {
+        for attr in node.attributes() {
+            match attr {
+                Attribute::Elif(_) => return Err(CondCompError::UnmatchedElse("elif")),
+                Attribute::Else => return Err(CondCompError::UnmatchedElse("else")),
+                _ => (),
+            }
+        }
         let if_attr = node
             .attributes_mut()
             .iter_mut()
@@ -121,6 +223,8 @@ fn eval_if_attr(
 }
 
 fn eval_if_attributes(nodes: &mut Vec<impl Decorated>, features: &Features) -> Result<(), E> {
+    desugar_else_attributes(nodes)?;
+
     let retains = nodes
         .iter()
         .map(|node| {
@@ -219,7 +323,231 @@ fn statement_eval_if_attributes(
     rec(statements, features)
 }
 
+fn feature_directives(wesl: &TranslationUnit) -> impl Iterator<Item = &FeatureDirective> {
+    wesl.global_directives
+        .iter()
+        .filter_map(|directive| match directive {
+            GlobalDirective::Feature(feature) => Some(feature),
+            _ => None,
+        })
+}
+
+/// The feature flags used in an `@if` expression.
+fn feature_flags<'a>(expr: &'a ExpressionNode, flags: &mut Vec<(String, &'a Span)>) {
+    match expr.node() {
+        Expression::Parenthesized(paren) => feature_flags(&paren.expression, flags),
+        Expression::Unary(unary) => feature_flags(&unary.operand, flags),
+        Expression::Binary(binary) => {
+            feature_flags(&binary.left, flags);
+            feature_flags(&binary.right, flags);
+        }
+        Expression::TypeOrIdentifier(ty) => flags.push((ty.ident.name().clone(), expr.span())),
+        _ => (),
+    }
+}
+
+fn used_features(wesl: &TranslationUnit) -> Vec<(String, &Span)> {
+    let mut flags = Vec::new();
+    for attrs in Visit::<Attributes>::visit(wesl) {
+        for attr in attrs {
+            if let Attribute::If(expr) | Attribute::Elif(expr) = attr {
+                feature_flags(expr, &mut flags);
+            }
+        }
+    }
+    flags
+}
+
+/// Check that the feature flags used in `@if` attributes are declared in the module with
+/// a `feature` directive.
+///
+/// Undeclared feature flags are not an error: they must be set by the host. This check is
+/// meant to produce warnings.
+pub fn undeclared_features(wesl: &TranslationUnit) -> Vec<Diagnostic<E>> {
+    let declared = feature_directives(wesl)
+        .map(|feature| feature.name.as_str())
+        .collect_vec();
+    used_features(wesl)
+        .into_iter()
+        .filter(|(name, _)| !declared.contains(&name.as_str()))
+        .map(|(name, span)| {
+            Diagnostic::from(CondCompError::UndeclaredFeatureFlag(name)).with_span(span.clone())
+        })
+        .collect()
+}
+
+/// The feature flags declared and used by the modules of a compilation, recorded before
+/// conditional translation. Unlike [`undeclared_features`], a feature flag can be declared
+/// in any module of the compilation.
+#[derive(Default)]
+pub(crate) struct FeatureUsage {
+    declared: HashSet<String>,
+    used: Vec<ModuleFeatures>,
+}
+
+/// A module that uses feature flags.
+struct ModuleFeatures {
+    path: ModulePath,
+    display_name: Option<String>,
+    source: String,
+    flags: Vec<(String, Span)>,
+}
+
+impl FeatureUsage {
+    /// Record the feature flags of a module, before conditional translation.
+    pub(crate) fn add_module(
+        &mut self,
+        wesl: &TranslationUnit,
+        path: &ModulePath,
+        display_name: Option<String>,
+        source: &str,
+    ) {
+        self.declared
+            .extend(feature_directives(wesl).map(|feature| feature.name.clone()));
+        let flags = used_features(wesl)
+            .into_iter()
+            .map(|(name, span)| (name, span.clone()))
+            .collect_vec();
+        if !flags.is_empty() {
+            self.used.push(ModuleFeatures {
+                path: path.clone(),
+                display_name,
+                source: source.to_string(),
+                flags,
+            });
+        }
+    }
+
+    /// Warnings for the feature flags used in `@if` attributes but declared in none of
+    /// the modules. See [`crate::CompileResult::warnings`].
+    pub(crate) fn warnings(self) -> Vec<Diagnostic<crate::Error>> {
+        let mut res = Vec::new();
+        for module in self.used {
+            for (name, span) in module.flags {
+                if !self.declared.contains(&name) {
+                    res.push(
+                        Diagnostic::from(CondCompError::UndeclaredFeatureFlag(name))
+                            .with_span(span)
+                            .with_module_path(module.path.clone(), module.display_name.clone())
+                            .with_source(module.source.clone()),
+                    );
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Records the feature flags of the modules read through a resolver, see
+/// [`FeatureUsage`].
+///
+/// `FeatureRecorder` is a proxy that implements [`Resolver`], like
+/// [`crate::SourceMapper`]. It must be called before conditional translation, e.g. by a
+/// [`crate::Preprocessor`].
+pub(crate) struct FeatureRecorder<'a> {
+    resolver: &'a dyn Resolver,
+    usage: RefCell<FeatureUsage>,
+}
+
+impl<'a> FeatureRecorder<'a> {
+    pub fn new(resolver: &'a dyn Resolver) -> Self {
+        Self {
+            resolver,
+            usage: Default::default(),
+        }
+    }
+    pub fn finish(self) -> Vec<Diagnostic<crate::Error>> {
+        self.usage.into_inner().warnings()
+    }
+}
+
+impl Resolver for FeatureRecorder<'_> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn source_to_module(
+        &self,
+        source: &str,
+        path: &ModulePath,
+    ) -> Result<TranslationUnit, ResolveError> {
+        let wesl = self.resolver.source_to_module(source, path)?;
+        self.usage
+            .borrow_mut()
+            .add_module(&wesl, path, self.display_name(path), source);
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+}
+
+/// A conditional compilation feature flag of a module tree. See [`crate::Wesl::features`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeatureInfo {
+    pub name: String,
+    /// The default value, if the feature is declared with a `feature` directive.
+    pub default: Option<bool>,
+    /// The doc comment of the `feature` directive.
+    pub description: Option<String>,
+    /// The modules that declare the feature.
+    pub declared_in: Vec<ModulePath>,
+    /// The modules that use the feature in `@if` attributes.
+    pub used_in: Vec<ModulePath>,
+}
+
+/// Collect the features declared or used in a list of modules, sorted by name.
+pub(crate) fn features<'a>(
+    modules: impl IntoIterator<Item = (&'a ModulePath, &'a TranslationUnit)>,
+) -> Vec<FeatureInfo> {
+    fn entry<'r>(res: &'r mut Vec<FeatureInfo>, name: &str) -> &'r mut FeatureInfo {
+        let idx = match res.iter().position(|feature| feature.name == name) {
+            Some(idx) => idx,
+            None => {
+                res.push(FeatureInfo {
+                    name: name.to_string(),
+                    default: None,
+                    description: None,
+                    declared_in: Vec::new(),
+                    used_in: Vec::new(),
+                });
+                res.len() - 1
+            }
+        };
+        &mut res[idx]
+    }
+
+    let mut res = Vec::new();
+    for (path, wesl) in modules {
+        for decl in feature_directives(wesl) {
+            let feature = entry(&mut res, &decl.name);
+            feature.default.get_or_insert(decl.default);
+            if feature.description.is_none() {
+                feature.description = decl.doc.clone();
+            }
+            feature.declared_in.push(path.clone());
+        }
+        for (name, _) in used_features(wesl) {
+            let feature = entry(&mut res, &name);
+            if !feature.used_in.contains(path) {
+                feature.used_in.push(path.clone());
+            }
+        }
+    }
+    res.sort_by(|a, b| a.name.cmp(&b.name));
+    res
+}
+
 pub fn run(wesl: &mut TranslationUnit, features: &Features) -> Result<(), E> {
+    // 0. apply the defaults of declared features, unless set by the host
+
+    let mut defaults = feature_directives(wesl)
+        .map(|feature| (feature.name.clone(), feature.default))
+        .collect::<Features>();
+    defaults.extend(features.iter().map(|(name, val)| (name.clone(), *val)));
+    let features = &defaults;
+    wesl.global_directives
+        .retain(|directive| !directive.is_feature());
+
     // 1. evaluate all if attributes
 
     #[cfg(feature = "imports")]
@@ -264,3 +592,147 @@ pub fn run(wesl: &mut TranslationUnit, features: &Features) -> Result<(), E> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, CompileSession, VirtualResolver, Wesl};
+
+    #[test]
+    fn test_feature_directives() {
+        let source = "feature fast = true;\n@compute @workgroup_size(1) fn main() { @if(fast) let x = 1u; @if(!fast) let x = 2u; @if(debug) let y = 3u; }";
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("main", source.into());
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions {
+            condcomp: true,
+            ..Default::default()
+        });
+        let compiler = compiler.set_custom_resolver(resolver);
+
+        // the declared default applies, unless the feature is set.
+        let wgsl = compiler.compile("main").unwrap().to_string();
+        assert!(wgsl.contains("1u") && !wgsl.contains("2u") && !wgsl.contains("feature"));
+        let mut session = CompileSession::new(compiler);
+        let features = HashMap::from_iter([("fast".to_string(), false)]);
+        let wgsl = session
+            .compile_variant("main", &features)
+            .unwrap()
+            .to_string();
+        assert!(wgsl.contains("2u") && !wgsl.contains("1u"));
+
+        // the directives are stripped when conditional translation is disabled.
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("plain", "feature fast = true;\nfn f() {}".into());
+        let wgsl = Wesl::new_barebones()
+            .set_custom_resolver(resolver)
+            .compile("plain")
+            .unwrap()
+            .to_string();
+        assert!(!wgsl.contains("feature"));
+        assert!(wgsl_parse::recognize_str(&wgsl).is_ok());
+
+        let features = session.compiler().features("main").unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].name, "debug");
+        assert_eq!(features[0].default, None);
+        assert_eq!(features[1].default, Some(true));
+
+        let wesl = source.parse().unwrap();
+        let warnings = undeclared_features(&wesl);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].error.to_string(),
+            "undeclared feature flag: `debug`"
+        );
+    }
+
+    #[cfg(feature = "imports")]
+    #[test]
+    fn test_undeclared_features_warnings() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::util::f;
+            feature fast = true;
+            @compute @workgroup_size(1) fn main() { f(); }"
+                .into(),
+        );
+        resolver.add_module(
+            "util",
+            "fn f() { @if(fast) let x = 1u; @if(debug) let y = 2u; }".into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions {
+            lower: false,
+            ..Default::default()
+        });
+        let compiler = compiler.set_custom_resolver(resolver);
+
+        // feature flags can be declared in any module of the compilation.
+        let comp = compiler.compile("main").unwrap();
+        assert_eq!(comp.warnings.len(), 1);
+        assert_eq!(
+            comp.warnings[0].error.to_string(),
+            "undeclared feature flag: `debug`"
+        );
+        assert!(comp.warnings[0]
+            .display_warning()
+            .to_string()
+            .contains("warning"));
+        let mut session = CompileSession::new(compiler);
+        assert_eq!(session.compile("main").unwrap().warnings.len(), 1);
+        assert_eq!(session.compile("main").unwrap().warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_elif_else_chains() {
+        let source = "struct S { @if(a) x: u32, @else x: f32 }
+            @if(a) const c = 1u; @elif(b) const c = 2u; @else const c = 3u;
+            @compute @workgroup_size(1) fn main() { let s = S(); let y = c; @if(b) {} @else { let z = 4u; } }";
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("main", source.into());
+        resolver.add_module(
+            "dangling",
+            "@compute @workgroup_size(1) fn main() { let x = 1u; @else {} }".into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions {
+            condcomp: true,
+            ..Default::default()
+        });
+        let mut session = CompileSession::new(compiler.set_custom_resolver(resolver));
+        let variant =
+            |a: bool, b: bool| HashMap::from_iter([("a".to_string(), a), ("b".to_string(), b)]);
+
+        let wgsl = session
+            .compile_variant("main", &variant(true, true))
+            .unwrap()
+            .to_string();
+        assert!(wgsl.contains("x: u32") && wgsl.contains("1u"));
+        assert!(!wgsl.contains("f32") && !wgsl.contains("2u") && !wgsl.contains("4u"));
+        let wgsl = session
+            .compile_variant("main", &variant(false, true))
+            .unwrap()
+            .to_string();
+        assert!(wgsl.contains("x: f32") && wgsl.contains("2u") && !wgsl.contains("3u"));
+        let wgsl = session
+            .compile_variant("main", &variant(false, false))
+            .unwrap()
+            .to_string();
+        assert!(wgsl.contains("3u") && wgsl.contains("4u") && !wgsl.contains("1u"));
+
+        // unset features leave standalone `@if` attributes.
+        let mut wesl = source.parse().unwrap();
+        run(&mut wesl, &HashMap::new()).unwrap();
+        let wgsl = wesl.to_string();
+        assert!(wgsl.contains("@if(!(a) && (b))") && wgsl.contains("@if(!(a) && !(b))"));
+
+        let Err(err) = session.compile("dangling") else {
+            panic!("expected an error");
+        };
+        assert!(err
+            .to_string()
+            .contains("`@else` attribute must directly follow"));
+    }
+}
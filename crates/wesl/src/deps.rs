@@ -0,0 +1,194 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{ModulePath, TranslationUnit};
+
+use crate::{ResolveError, Resolver};
+
+/// The modules and files read by a compilation. See [`crate::CompileResult::deps`].
+///
+/// Use it to tell a build system which files to watch, e.g. with
+/// [`Self::to_makefile`] or `cargo:rerun-if-changed` lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    /// The modules loaded by the resolver, in load order.
+    pub modules: Vec<ModulePath>,
+    /// The files the modules were read from, in load order. Modules that are not backed
+    /// by a file (e.g. virtual modules or external packages) are not listed.
+    pub files: Vec<PathBuf>,
+}
+
+impl Dependencies {
+    /// Create an empty dependency list.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Format as a Makefile rule without recipe (the `.d` format understood by Make and
+    /// Ninja), where `target` is the compiled output file.
+    ///
+    /// ```
+    /// # use wesl::Dependencies;
+    /// let deps = Dependencies {
+    ///     modules: Vec::new(),
+    ///     files: vec!["shaders/main.wesl".into(), "shaders/my util.wesl".into()],
+    /// };
+    /// assert_eq!(
+    ///     deps.to_makefile("out.wgsl"),
+    ///     "out.wgsl: shaders/main.wesl shaders/my\\ util.wesl\n"
+    /// );
+    /// ```
+    pub fn to_makefile(&self, target: impl AsRef<Path>) -> String {
+        let mut res = make_escape(target.as_ref());
+        res.push(':');
+        for file in &self.files {
+            res.push(' ');
+            res.push_str(&make_escape(file));
+        }
+        res.push('\n');
+        res
+    }
+
+    /// Format as a JSON object with the `modules` and `files` lists.
+    ///
+    /// ```
+    /// # use wesl::Dependencies;
+    /// let deps = Dependencies {
+    ///     modules: vec!["package::main".into()],
+    ///     files: vec!["shaders/main.wesl".into()],
+    /// };
+    /// assert_eq!(
+    ///     deps.to_json(),
+    ///     r#"{"modules":["package::main"],"files":["shaders/main.wesl"]}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let modules = self
+            .modules
+            .iter()
+            .map(|path| json_string(&path.to_string()))
+            .format(",");
+        let files = self
+            .files
+            .iter()
+            .map(|file| json_string(&file.display().to_string()))
+            .format(",");
+        format!(r#"{{"modules":[{modules}],"files":[{files}]}}"#)
+    }
+
+    /// Print the `cargo:rerun-if-changed` instructions for the files, to be used in a
+    /// `build.rs` script.
+    pub fn emit_rerun_if_changed(&self) {
+        for file in &self.files {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
+    }
+}
+
+fn make_escape(path: &Path) -> String {
+    let mut res = String::new();
+    for c in path.display().to_string().chars() {
+        match c {
+            ' ' | '#' | ':' => res.push('\\'),
+            '$' => res.push('$'),
+            _ => (),
+        }
+        res.push(c);
+    }
+    res
+}
+
+pub(crate) fn json_string(str: &str) -> String {
+    let mut res = String::from('"');
+    for c in str.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => write!(res, "\\u{:04x}", c as u32).unwrap(),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// Records the modules and files read through a resolver.
+///
+/// `DepsRecorder` is a proxy that implements [`Resolver`], like
+/// [`crate::SourceMapper`].
+pub(crate) struct DepsRecorder<'a> {
+    resolver: &'a dyn Resolver,
+    deps: RefCell<Dependencies>,
+}
+
+impl<'a> DepsRecorder<'a> {
+    pub fn new(resolver: &'a dyn Resolver) -> Self {
+        Self {
+            resolver,
+            deps: Default::default(),
+        }
+    }
+    pub fn finish(self) -> Dependencies {
+        self.deps.into_inner()
+    }
+}
+
+impl Resolver for DepsRecorder<'_> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        let res = self.resolver.resolve_source(path)?;
+        let mut deps = self.deps.borrow_mut();
+        if !deps.modules.contains(path) {
+            deps.modules.push(path.clone());
+            // resolvers that read files display modules with their file path.
+            let file = self
+                .resolver
+                .display_name(path)
+                .map(PathBuf::from)
+                .filter(|file| file.is_file());
+            if let Some(file) = file {
+                if !deps.files.contains(&file) {
+                    deps.files.push(file);
+                }
+            }
+        }
+        Ok(res)
+    }
+    fn source_to_module(
+        &self,
+        source: &str,
+        path: &ModulePath,
+    ) -> Result<TranslationUnit, ResolveError> {
+        self.resolver.source_to_module(source, path)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_makefile() {
+        let deps = Dependencies {
+            modules: Vec::new(),
+            files: vec![
+                r"C:\shaders\main.wesl".into(),
+                "shaders/#1 $HOME.wesl".into(),
+            ],
+        };
+        assert_eq!(
+            deps.to_makefile("out dir/out.wgsl"),
+            "out\\ dir/out.wgsl: C\\:\\shaders\\main.wesl shaders/\\#1\\ $$HOME.wesl\n"
+        );
+    }
+}
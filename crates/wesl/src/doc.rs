@@ -0,0 +1,662 @@
+//! Documentation generator for WESL packages.
+//!
+//! One page is rendered per module. It lists the submodules, the imports and the
+//! structs, type aliases, functions, constants and overrides of the module, with their
+//! `///` doc comments. Type names and imports referring to other items of the package
+//! link to their declaration.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{
+    Declaration, DeclarationKind, Expression, Function, GlobalDeclaration, ModulePath, PathOrigin,
+    Struct, TranslationUnit, TypeAlias, TypeExpression,
+};
+
+use crate::import::{flatten_imports, resolve_inline_path};
+
+/// Output format of the documentation pages.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocFormat {
+    #[default]
+    Html,
+    /// CommonMark with inline HTML for the signatures and anchors.
+    Markdown,
+}
+
+impl DocFormat {
+    /// File extension of the pages.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Html => "html",
+            DocFormat::Markdown => "md",
+        }
+    }
+}
+
+/// The documentation page of a module.
+#[derive(Clone, Debug)]
+pub struct DocPage {
+    /// Path of the module in the package. The package root is `package::`.
+    pub module: ModulePath,
+    /// File name of the page. All pages of a package link to each other relative to the
+    /// same directory, and the page of the package root is `index.<ext>`.
+    pub file_name: String,
+    pub content: String,
+}
+
+/// Render the documentation of the modules of a package.
+pub(crate) fn document(
+    name: &str,
+    modules: &[(ModulePath, TranslationUnit)],
+    format: DocFormat,
+) -> Vec<DocPage> {
+    let index = Index {
+        name,
+        format,
+        items: modules
+            .iter()
+            .map(|(path, wesl)| {
+                let items = wesl
+                    .global_declarations
+                    .iter()
+                    .filter_map(|decl| Some((decl_name(decl)?, anchor_kind(decl)?)))
+                    .collect();
+                (path, items)
+            })
+            .collect(),
+    };
+
+    modules
+        .iter()
+        .map(|(path, wesl)| {
+            let page = Page::new(&index, path, wesl);
+            DocPage {
+                module: path.clone(),
+                file_name: index.file_name(path),
+                content: page.render(modules),
+            }
+        })
+        .collect()
+}
+
+fn decl_name(decl: &GlobalDeclaration) -> Option<String> {
+    match decl {
+        GlobalDeclaration::Declaration(decl) => Some(decl.ident.name().to_string()),
+        GlobalDeclaration::TypeAlias(decl) => Some(decl.ident.name().to_string()),
+        GlobalDeclaration::Struct(decl) => Some(decl.ident.name().to_string()),
+        GlobalDeclaration::Function(decl) => Some(decl.ident.name().to_string()),
+        GlobalDeclaration::Void | GlobalDeclaration::ConstAssert(_) => None,
+    }
+}
+
+/// Prefix of the anchor of documented declarations, e.g. `struct.Light`.
+fn anchor_kind(decl: &GlobalDeclaration) -> Option<&'static str> {
+    match decl {
+        GlobalDeclaration::Declaration(decl) => value_kind(decl.kind),
+        GlobalDeclaration::TypeAlias(_) => Some("alias"),
+        GlobalDeclaration::Struct(_) => Some("struct"),
+        GlobalDeclaration::Function(_) => Some("fn"),
+        GlobalDeclaration::Void | GlobalDeclaration::ConstAssert(_) => None,
+    }
+}
+
+fn value_kind(kind: DeclarationKind) -> Option<&'static str> {
+    match kind {
+        DeclarationKind::Const => Some("const"),
+        DeclarationKind::Override => Some("override"),
+        _ => None,
+    }
+}
+
+/// Display a module path as it is written in WESL source code.
+fn fmt_path(path: &ModulePath) -> String {
+    let origin = match path.origin {
+        PathOrigin::Absolute => vec!["package"],
+        PathOrigin::Relative(0) => vec!["self"],
+        PathOrigin::Relative(n) => vec!["super"; n],
+        PathOrigin::Package => vec![],
+    };
+    origin
+        .into_iter()
+        .chain(path.components.iter().map(String::as_str))
+        .join("::")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a doc comment to HTML. Paragraphs are separated by blank lines and inline
+/// code in backquotes is preserved, other markdown syntax is displayed as is.
+fn doc_to_html(doc: &str) -> String {
+    doc.split("\n\n")
+        .filter(|par| !par.trim().is_empty())
+        .map(|par| {
+            let text = escape(par.trim())
+                .split('`')
+                .enumerate()
+                .map(|(i, part)| {
+                    if i % 2 == 1 {
+                        format!("<code>{part}</code>")
+                    } else {
+                        part.to_string()
+                    }
+                })
+                .join("");
+            format!("<p>{text}</p>\n")
+        })
+        .join("")
+}
+
+/// The documented items of each module of the package, to resolve links.
+struct Index<'a> {
+    name: &'a str,
+    format: DocFormat,
+    items: HashMap<&'a ModulePath, HashMap<String, &'static str>>,
+}
+
+impl Index<'_> {
+    fn file_name(&self, path: &ModulePath) -> String {
+        let ext = self.format.extension();
+        if path.components.is_empty() {
+            format!("index.{ext}")
+        } else {
+            format!("{}.{ext}", path.components.join("."))
+        }
+    }
+
+    /// The module name as seen by users of the package.
+    fn display_path(&self, path: &ModulePath) -> String {
+        std::iter::once(self.name)
+            .chain(path.components.iter().map(String::as_str))
+            .join("::")
+    }
+
+    /// Link to an item of a module, or to a module if `name` is a submodule of `path`.
+    fn link(&self, path: &ModulePath, name: &str) -> Option<String> {
+        if let Some(kind) = self.items.get(path).and_then(|items| items.get(name)) {
+            return Some(format!("{}#{kind}.{name}", self.file_name(path)));
+        }
+        let submodule = path.clone().join([name.to_string()]);
+        self.items
+            .contains_key(&submodule)
+            .then(|| self.file_name(&submodule))
+    }
+}
+
+struct Page<'a> {
+    index: &'a Index<'a>,
+    path: &'a ModulePath,
+    wesl: &'a TranslationUnit,
+    /// The imported items: local name, module path and item name, sorted by local name.
+    imports: Vec<(String, ModulePath, String)>,
+    out: String,
+}
+
+impl<'a> Page<'a> {
+    fn new(index: &'a Index<'a>, path: &'a ModulePath, wesl: &'a TranslationUnit) -> Self {
+        let imports = flatten_imports(&wesl.imports, path)
+            .into_iter()
+            .map(|(name, (path, item))| (name.name().to_string(), path, item.name().to_string()))
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+            .collect();
+        Self {
+            index,
+            path,
+            wesl,
+            imports,
+            out: String::new(),
+        }
+    }
+
+    fn is_html(&self) -> bool {
+        self.index.format == DocFormat::Html
+    }
+
+    fn render(mut self, modules: &[(ModulePath, TranslationUnit)]) -> String {
+        let title = self.index.display_path(self.path);
+        if self.is_html() {
+            self.out += &format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n"
+            );
+        }
+        self.title();
+
+        let mut submodules = modules
+            .iter()
+            .map(|(path, _)| path)
+            .filter(|path| {
+                path.components.len() == self.path.components.len() + 1
+                    && path.components.starts_with(&self.path.components)
+            })
+            .peekable();
+        if submodules.peek().is_some() {
+            self.section("Modules");
+            let items = submodules
+                .map(|path| {
+                    let name = path.last().unwrap_or_default();
+                    self.code_link(&self.index.file_name(path), name)
+                })
+                .collect_vec();
+            self.list(items);
+        }
+
+        if !self.imports.is_empty() {
+            self.section("Imports");
+            let items = self
+                .imports
+                .iter()
+                .map(|(name, path, item)| {
+                    let full = format!("{}::{item}", fmt_path(path));
+                    let link = match self.index.link(path, item) {
+                        Some(href) => self.code_link(&self.href(&href), &full),
+                        None => self.code(&full),
+                    };
+                    if name != item {
+                        format!("{link} as {}", self.code(name))
+                    } else {
+                        link
+                    }
+                })
+                .collect_vec();
+            self.list(items);
+        }
+
+        let decls = &self.wesl.global_declarations;
+        let structs = decls
+            .iter()
+            .filter_map(|decl| match decl {
+                GlobalDeclaration::Struct(decl) => Some(decl),
+                _ => None,
+            })
+            .collect_vec();
+        if !structs.is_empty() {
+            self.section("Structs");
+            for decl in structs {
+                self.struct_(decl);
+            }
+        }
+        let aliases = decls
+            .iter()
+            .filter_map(|decl| match decl {
+                GlobalDeclaration::TypeAlias(decl) => Some(decl),
+                _ => None,
+            })
+            .collect_vec();
+        if !aliases.is_empty() {
+            self.section("Type Aliases");
+            for decl in aliases {
+                self.alias(decl);
+            }
+        }
+        let functions = decls
+            .iter()
+            .filter_map(|decl| match decl {
+                GlobalDeclaration::Function(decl) => Some(decl),
+                _ => None,
+            })
+            .collect_vec();
+        if !functions.is_empty() {
+            self.section("Functions");
+            for decl in functions {
+                self.function(decl);
+            }
+        }
+        for (kind, section) in [
+            (DeclarationKind::Const, "Constants"),
+            (DeclarationKind::Override, "Overrides"),
+        ] {
+            let values = decls
+                .iter()
+                .filter_map(|decl| match decl {
+                    GlobalDeclaration::Declaration(decl) => Some(decl),
+                    _ => None,
+                })
+                .filter(|decl| decl.kind == kind)
+                .collect_vec();
+            if !values.is_empty() {
+                self.section(section);
+                for decl in values {
+                    self.value(decl);
+                }
+            }
+        }
+
+        if self.is_html() {
+            self.out += "</body>\n</html>\n";
+        }
+        self.out
+    }
+
+    /// The module path, with a link to each parent module.
+    fn title(&mut self) {
+        let mut parent = ModulePath::new(self.path.origin, Vec::new());
+        let mut crumbs = vec![self.code_link(&self.index.file_name(&parent), self.index.name)];
+        for comp in &self.path.components {
+            parent.push(comp);
+            crumbs.push(self.code_link(&self.index.file_name(&parent), comp));
+        }
+        let title = crumbs.join("::");
+        if self.is_html() {
+            self.out += &format!("<h1>Module {title}</h1>\n");
+        } else {
+            self.out += &format!("# Module {title}\n\n");
+        }
+    }
+
+    fn section(&mut self, title: &str) {
+        if self.is_html() {
+            self.out += &format!("<h2>{title}</h2>\n");
+        } else {
+            self.out += &format!("## {title}\n\n");
+        }
+    }
+
+    fn item(&mut self, kind: &str, name: &str, signature: &str, doc: &Option<String>) {
+        if self.is_html() {
+            self.out += &format!("<h3 id=\"{kind}.{name}\"><code>{name}</code></h3>\n");
+        } else {
+            self.out += &format!("<a id=\"{kind}.{name}\"></a>\n\n### `{name}`\n\n");
+        }
+        self.out += &format!("<pre><code>{signature}</code></pre>\n\n");
+        if let Some(doc) = doc {
+            if self.is_html() {
+                self.out += &doc_to_html(doc);
+            } else {
+                self.out += &format!("{doc}\n\n");
+            }
+        }
+    }
+
+    fn list(&mut self, items: Vec<String>) {
+        if self.is_html() {
+            let items = items
+                .iter()
+                .format_with("", |item, f| f(&format_args!("<li>{item}</li>\n")));
+            self.out += &format!("<ul>\n{items}</ul>\n");
+        } else {
+            for item in items {
+                self.out += &format!("- {item}\n");
+            }
+            self.out += "\n";
+        }
+    }
+
+    fn code(&self, text: &str) -> String {
+        if self.is_html() {
+            format!("<code>{}</code>", escape(text))
+        } else {
+            format!("`{text}`")
+        }
+    }
+
+    fn code_link(&self, href: &str, text: &str) -> String {
+        if self.is_html() {
+            format!("<a href=\"{href}\"><code>{}</code></a>", escape(text))
+        } else {
+            format!("[`{text}`]({href})")
+        }
+    }
+
+    /// Links to the current page are reduced to the anchor.
+    fn href(&self, href: &str) -> String {
+        let page = self.index.file_name(self.path);
+        match href.strip_prefix(&page) {
+            Some(anchor) if anchor.starts_with('#') => anchor.to_string(),
+            _ => href.to_string(),
+        }
+    }
+
+    /// Resolve the declaration referenced by a type expression.
+    fn resolve(&self, ty: &TypeExpression) -> Option<String> {
+        let name = ty.ident.name().to_string();
+        let href = match &ty.path {
+            Some(path) => {
+                let imports = &self.wesl.imports;
+                let path =
+                    resolve_inline_path(path, self.path, &flatten_imports(imports, self.path));
+                self.index.link(&path, &name)
+            }
+            None => self.index.link(self.path, &name).or_else(|| {
+                let (_, path, item) = self.imports.iter().find(|(ident, _, _)| *ident == name)?;
+                self.index.link(path, item)
+            }),
+        }?;
+        Some(self.href(&href))
+    }
+
+    /// Render a type expression in HTML, linking to the declarations of the package.
+    fn ty(&self, ty: &TypeExpression) -> String {
+        let mut res = String::new();
+        if let Some(path) = &ty.path {
+            res += &escape(&format!("{}::", fmt_path(path)));
+        }
+        let name = escape(&ty.ident.name());
+        match self.resolve(ty) {
+            Some(href) => res += &format!("<a href=\"{href}\">{name}</a>"),
+            None => res += &name,
+        }
+        if let Some(args) = &ty.template_args {
+            let args = args
+                .iter()
+                .map(|arg| match arg.expression.node() {
+                    Expression::TypeOrIdentifier(ty) => self.ty(ty),
+                    expr => escape(&expr.to_string()),
+                })
+                .join(", ");
+            res += &format!("&lt;{args}&gt;");
+        }
+        res
+    }
+
+    fn attrs(&self, attrs: &[wgsl_parse::syntax::Attribute], sep: &str) -> String {
+        attrs
+            .iter()
+            .map(|attr| format!("{}{sep}", escape(&attr.to_string())))
+            .join("")
+    }
+
+    fn struct_(&mut self, decl: &Struct) {
+        let name = decl.ident.name().to_string();
+        let members = decl
+            .members
+            .iter()
+            .map(|m| {
+                let attrs = self.attrs(&m.attributes, " ");
+                format!(
+                    "    {attrs}{}: {},\n",
+                    escape(&m.ident.name()),
+                    self.ty(&m.ty)
+                )
+            })
+            .join("");
+        let signature = format!("{}struct {name} {{\n{members}}}", self.struct_attrs(decl));
+        self.item("struct", &name, &signature, &decl.doc);
+
+        let fields = decl
+            .members
+            .iter()
+            .filter_map(|m| {
+                let doc = m.doc.as_ref()?.lines().join(" ");
+                let doc = if self.is_html() { escape(&doc) } else { doc };
+                Some(format!("{}: {doc}", self.code(&m.ident.name())))
+            })
+            .collect_vec();
+        if !fields.is_empty() {
+            self.list(fields);
+        }
+    }
+
+    #[cfg(feature = "attributes")]
+    fn struct_attrs(&self, decl: &Struct) -> String {
+        self.attrs(&decl.attributes, "\n")
+    }
+    #[cfg(not(feature = "attributes"))]
+    fn struct_attrs(&self, _decl: &Struct) -> String {
+        String::new()
+    }
+
+    fn alias(&mut self, decl: &TypeAlias) {
+        let name = decl.ident.name().to_string();
+        let signature = format!("alias {name} = {};", self.ty(&decl.ty));
+        self.item("alias", &name, &signature, &decl.doc);
+    }
+
+    fn function(&mut self, decl: &Function) {
+        let name = decl.ident.name().to_string();
+        let params = decl
+            .parameters
+            .iter()
+            .map(|p| {
+                let attrs = self.attrs(&p.attributes, " ");
+                format!("{attrs}{}: {}", escape(&p.ident.name()), self.ty(&p.ty))
+            })
+            .join(", ");
+        let ret = decl
+            .return_type
+            .as_ref()
+            .map(|ty| {
+                let attrs = self.attrs(&decl.return_attributes, " ");
+                format!(" -&gt; {attrs}{}", self.ty(ty))
+            })
+            .unwrap_or_default();
+        let attrs = self.attrs(&decl.attributes, "\n");
+        let signature = format!("{attrs}fn {name}({params}){ret}");
+        self.item("fn", &name, &signature, &decl.doc);
+    }
+
+    fn value(&mut self, decl: &Declaration) {
+        let name = decl.ident.name().to_string();
+        let attrs = self.attrs(&decl.attributes, " ");
+        let ty = decl
+            .ty
+            .as_ref()
+            .map(|ty| format!(": {}", self.ty(ty)))
+            .unwrap_or_default();
+        let init = decl
+            .initializer
+            .as_ref()
+            .map(|init| format!(" = {}", escape(&init.to_string())))
+            .unwrap_or_default();
+        let kind = value_kind(decl.kind).unwrap_or_default();
+        let signature = format!("{attrs}{kind} {name}{ty}{init};");
+        self.item(kind, &name, &signature, &decl.doc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wgsl_parse::syntax::PathOrigin;
+
+    use super::*;
+
+    fn modules() -> Vec<(ModulePath, TranslationUnit)> {
+        let sources = [
+            (
+                vec![],
+                "import package::util::math::{square, Real as R};
+                 import package::shapes::Circle;
+                 /// The unit circle.
+                 const UNIT: Circle = Circle(1.0);
+                 /// Area of a circle.
+                 fn area(c: Circle) -> R { return square(c.radius); }",
+            ),
+            (vec!["util"], "/// Unused.\nfn noop() {}"),
+            (
+                vec!["util", "math"],
+                "/// A real number.\nalias Real = f32;\n/// `x * x`.\n///\n/// Squares <x>.\nfn square(x: Real) -> Real { return x * x; }",
+            ),
+            (
+                vec!["shapes"],
+                "/// A circle.\nstruct Circle {\n  /// The radius.\n  radius: f32,\n}",
+            ),
+        ];
+        sources
+            .into_iter()
+            .map(|(path, source)| {
+                let path = path.into_iter().map(str::to_string).collect();
+                let path = ModulePath::new(PathOrigin::Absolute, path);
+                (path, source.parse().unwrap())
+            })
+            .collect()
+    }
+
+    fn page(pages: &[DocPage], file_name: &str) -> String {
+        let page = pages.iter().find(|page| page.file_name == file_name);
+        page.unwrap().content.clone()
+    }
+
+    #[test]
+    fn test_markdown() {
+        let pages = document("pkg", &modules(), DocFormat::Markdown);
+        let names = pages
+            .iter()
+            .map(|page| page.file_name.as_str())
+            .collect_vec();
+        assert_eq!(names, ["index.md", "util.md", "util.math.md", "shapes.md"]);
+
+        let index = page(&pages, "index.md");
+        assert!(index.starts_with("# Module [`pkg`](index.md)\n\n## Modules\n\n- [`util`](util.md)\n- [`shapes`](shapes.md)\n\n"));
+        assert!(index.contains(
+            "## Imports\n\n\
+             - [`package::shapes::Circle`](shapes.md#struct.Circle)\n\
+             - [`package::util::math::Real`](util.math.md#alias.Real) as `R`\n\
+             - [`package::util::math::square`](util.math.md#fn.square)\n"
+        ));
+        assert!(index.contains(
+            "<a id=\"fn.area\"></a>\n\n### `area`\n\n\
+             <pre><code>fn area(c: <a href=\"shapes.md#struct.Circle\">Circle</a>) -&gt; \
+             <a href=\"util.math.md#alias.Real\">R</a></code></pre>\n\nArea of a circle.\n\n"
+        ));
+        assert!(index.contains("### `UNIT`\n\n<pre><code>const UNIT: <a href=\"shapes.md#struct.Circle\">Circle</a> = Circle(1.0);</code></pre>\n\nThe unit circle.\n"));
+
+        let util = page(&pages, "util.md");
+        assert!(util.starts_with("# Module [`pkg`](index.md)::[`util`](util.md)\n\n## Modules\n\n- [`math`](util.math.md)\n\n"));
+
+        let math = page(&pages, "util.math.md");
+        assert!(!math.contains("## Modules"));
+        // links to the same page are reduced to the anchor.
+        assert!(math.contains(
+            "fn square(x: <a href=\"#alias.Real\">Real</a>) -&gt; <a href=\"#alias.Real\">Real</a>"
+        ));
+        assert!(math.contains("</code></pre>\n\n`x * x`.\n\nSquares <x>.\n\n"));
+
+        let shapes = page(&pages, "shapes.md");
+        assert!(shapes.contains("A circle.\n\n- `radius`: The radius.\n"));
+    }
+
+    #[test]
+    fn test_html() {
+        let pages = document("pkg", &modules(), DocFormat::Html);
+        let names = pages
+            .iter()
+            .map(|page| page.file_name.as_str())
+            .collect_vec();
+        assert_eq!(
+            names,
+            ["index.html", "util.html", "util.math.html", "shapes.html"]
+        );
+
+        let index = page(&pages, "index.html");
+        assert!(index.starts_with("<!DOCTYPE html>\n"));
+        assert!(index.contains("<title>pkg</title>"));
+        assert!(index.contains("<ul>\n<li><a href=\"util.html\"><code>util</code></a></li>\n<li><a href=\"shapes.html\"><code>shapes</code></a></li>\n</ul>\n"));
+        assert!(index.contains("<li><a href=\"util.math.html#alias.Real\"><code>package::util::math::Real</code></a> as <code>R</code></li>\n"));
+        assert!(index.contains("<h3 id=\"fn.area\"><code>area</code></h3>\n"));
+        assert!(index.contains("<p>Area of a circle.</p>\n"));
+        assert!(index.ends_with("</body>\n</html>\n"));
+
+        let math = page(&pages, "util.math.html");
+        assert!(math.contains("<title>pkg::util::math</title>"));
+        assert!(math.contains("<h1>Module <a href=\"index.html\"><code>pkg</code></a>::<a href=\"util.html\"><code>util</code></a>::<a href=\"util.math.html\"><code>math</code></a></h1>\n"));
+        // doc comments are escaped, with paragraphs and inline code.
+        assert!(math.contains("<p><code>x * x</code>.</p>\n<p>Squares &lt;x&gt;.</p>\n"));
+
+        let shapes = page(&pages, "shapes.html");
+        assert!(shapes.contains("<li><code>radius</code>: The radius.</li>\n"));
+    }
+}
@@ -5,8 +5,10 @@ use wgsl_parse::{
     syntax::{Expression, Ident, ModulePath},
 };
 
-use crate::{Mangler, ResolveError, SourceMap, ValidateError};
+use crate::{LintError, Mangler, ResolveError, SourceMap, SpanMap, ValidateError};
 
+#[cfg(feature = "bindgen")]
+use crate::BindgenError;
 #[cfg(feature = "condcomp")]
 use crate::CondCompError;
 #[cfg(feature = "generics")]
@@ -16,6 +18,8 @@ use crate::ImportError;
 
 #[cfg(feature = "eval")]
 use crate::eval::{Context, EvalError};
+#[cfg(feature = "naga")]
+use crate::NagaError;
 
 /// Any WESL error.
 #[derive(Clone, Debug, thiserror::Error)]
@@ -38,6 +42,14 @@ pub enum Error {
     #[cfg(feature = "eval")]
     #[error("{0}")]
     EvalError(#[from] EvalError),
+    #[cfg(feature = "naga")]
+    #[error("{0}")]
+    NagaError(#[from] NagaError),
+    #[cfg(feature = "bindgen")]
+    #[error("{0}")]
+    BindgenError(#[from] BindgenError),
+    #[error("{0}")]
+    LintError(#[from] LintError),
     #[error("{0}")]
     Error(#[from] Diagnostic<Error>),
 }
@@ -114,6 +126,26 @@ impl From<EvalError> for Diagnostic<Error> {
     }
 }
 
+#[cfg(feature = "naga")]
+impl From<NagaError> for Diagnostic<Error> {
+    fn from(error: NagaError) -> Self {
+        Self::new(error.into())
+    }
+}
+
+#[cfg(feature = "bindgen")]
+impl From<BindgenError> for Diagnostic<Error> {
+    fn from(error: BindgenError) -> Self {
+        Self::new(error.into())
+    }
+}
+
+impl From<LintError> for Diagnostic<Error> {
+    fn from(error: LintError) -> Self {
+        Self::new(error.into())
+    }
+}
+
 impl From<Error> for Diagnostic<Error> {
     fn from(error: Error) -> Self {
         match error {
@@ -128,6 +160,11 @@ impl From<Error> for Diagnostic<Error> {
             Error::GenericsError(e) => e.into(),
             #[cfg(feature = "eval")]
             Error::EvalError(e) => e.into(),
+            #[cfg(feature = "naga")]
+            Error::NagaError(e) => e.into(),
+            #[cfg(feature = "bindgen")]
+            Error::BindgenError(e) => e.into(),
+            Error::LintError(e) => e.into(),
             Error::Error(e) => e,
         }
     }
@@ -213,6 +250,29 @@ impl<E: std::error::Error> Diagnostic<E> {
         self
     }
 
+    /// Locate an error reported on the output WGSL, e.g. by a WebGPU implementation.
+    ///
+    /// The span set with [`Self::with_span`] is a byte range of the output WGSL. It is
+    /// mapped to the source module with the span map. If the span cannot be mapped, the
+    /// diagnostic points to the output WGSL.
+    pub fn with_span_map(mut self, span_map: &SpanMap, sourcemap: &impl SourceMap) -> Self {
+        let mapped = self
+            .span
+            .as_ref()
+            .and_then(|span| span_map.get(span, sourcemap));
+        if let Some((path, span)) = mapped {
+            self.display_name = sourcemap
+                .get_display_name(path)
+                .map(|name| name.to_string());
+            self.source = sourcemap.get_source(path).map(|s| s.to_string());
+            self.module_path = Some(path.clone());
+            self.span = Some(span);
+        } else {
+            self.source = Some(span_map.output().to_string());
+        }
+        self
+    }
+
     pub(crate) fn display_origin(&self) -> String {
         match (&self.module_path, &self.display_name) {
             (Some(res), Some(name)) => {
@@ -324,7 +384,12 @@ impl Diagnostic<Error> {
                 ValidateError::UndefinedSymbol(name)
                 | ValidateError::ParamCount(name, _, _)
                 | ValidateError::NotCallable(name)
-                | ValidateError::Duplicate(name) => unmangle_name(name, sourcemap, mangler),
+                | ValidateError::Duplicate(name)
+                | ValidateError::Overload(name)
+                | ValidateError::NonUniformCall(name)
+                | ValidateError::NonUniformArgument(name, _) => {
+                    unmangle_name(name, sourcemap, mangler)
+                }
             },
             Error::ResolveError(_) => {}
             #[cfg(feature = "imports")]
@@ -332,10 +397,21 @@ impl Diagnostic<Error> {
             #[cfg(feature = "condcomp")]
             Error::CondCompError(e) => match e {
                 CondCompError::InvalidExpression(expr) => unmangle_expr(expr, sourcemap, mangler),
-                CondCompError::InvalidFeatureFlag(_) | CondCompError::MissingFeatureFlag(_) => {}
+                CondCompError::InvalidFeatureFlag(_)
+                | CondCompError::MissingFeatureFlag(_)
+                | CondCompError::UndeclaredFeatureFlag(_)
+                | CondCompError::UnmatchedElse(_) => {}
             },
             #[cfg(feature = "generics")]
-            Error::GenericsError(_) => {}
+            Error::GenericsError(e) => match e {
+                GenericsError::MissingTemplateArgs(name)
+                | GenericsError::TemplateArgCount(name, _, _)
+                | GenericsError::InvalidTemplateArg(name)
+                | GenericsError::UnsatisfiedConstraint(name, _, _, _) => {
+                    unmangle_name(name, sourcemap, mangler)
+                }
+                GenericsError::DisallowedTemplate => {}
+            },
             #[cfg(feature = "eval")]
             Error::EvalError(e) => match e {
                 EvalError::NotScalar(ty) => unmangle_ty(ty, sourcemap, mangler),
@@ -377,6 +453,9 @@ impl Diagnostic<Error> {
                     unmangle_ty(ty2, sourcemap, mangler);
                 }
                 EvalError::UnknownFunction(name) => unmangle_name(name, sourcemap, mangler),
+                EvalError::NoOverload(name, _) | EvalError::AmbiguousOverload(name, _) => {
+                    unmangle_name(name, sourcemap, mangler)
+                }
                 EvalError::NotCallable(name) => unmangle_name(name, sourcemap, mangler),
                 EvalError::Signature(ty, args) => {
                     unmangle_id(&mut ty.ident, sourcemap, mangler);
@@ -394,11 +473,16 @@ impl Diagnostic<Error> {
                     unmangle_name(name, sourcemap, mangler);
                     unmangle_ty(ty2, sourcemap, mangler);
                 }
+                EvalError::UnexpectedReturn(ty, name) => {
+                    unmangle_ty(ty, sourcemap, mangler);
+                    unmangle_name(name, sourcemap, mangler);
+                }
                 EvalError::NoReturn(name, ty) => {
                     unmangle_name(name, sourcemap, mangler);
                     unmangle_ty(ty, sourcemap, mangler);
                 }
                 EvalError::NotConst(name) => unmangle_name(name, sourcemap, mangler),
+                EvalError::NotEntrypoint(name, _) => unmangle_name(name, sourcemap, mangler),
                 EvalError::Void(name) => unmangle_name(name, sourcemap, mangler),
                 EvalError::MustUse(name) => unmangle_name(name, sourcemap, mangler),
                 EvalError::UnknownDecl(name) => unmangle_name(name, sourcemap, mangler),
@@ -412,7 +496,9 @@ impl Diagnostic<Error> {
                 }
                 EvalError::IncrType(ty) => unmangle_ty(ty, sourcemap, mangler),
                 EvalError::DecrType(ty) => unmangle_ty(ty, sourcemap, mangler),
-                EvalError::ConstAssertFailure(expr) => unmangle_expr(expr, sourcemap, mangler),
+                EvalError::ConstAssertFailure(expr) | EvalError::NotAssignable(expr) => {
+                    unmangle_expr(expr, sourcemap, mangler)
+                }
                 EvalError::Todo(_)
                 | EvalError::MissingTemplate(_)
                 | EvalError::NotWrite
@@ -438,6 +524,12 @@ impl Diagnostic<Error> {
                 | EvalError::MissingResource(_, _)
                 | EvalError::AddressSpace(_, _)
                 | EvalError::AccessMode(_, _)
+                | EvalError::MissingIOAttr(_)
+                | EvalError::StageInput(_, _)
+                | EvalError::StageOutput(_, _)
+                | EvalError::MissingInput(_)
+                | EvalError::InvocationAborted
+                | EvalError::InvalidTexture(_, _)
                 | EvalError::MissingBindAttr
                 | EvalError::MissingWorkgroupSize
                 | EvalError::NegativeAttr(_)
@@ -447,9 +539,23 @@ impl Diagnostic<Error> {
                 | EvalError::DecrOverflow
                 | EvalError::FlowInContinuing(_)
                 | EvalError::DiscardInConst
+                | EvalError::Discard
                 | EvalError::FlowInFunction(_)
                 | EvalError::FlowInModule(_) => {}
             },
+            #[cfg(feature = "naga")]
+            Error::NagaError(_) => {}
+            #[cfg(feature = "bindgen")]
+            Error::BindgenError(e) => match e {
+                BindgenError::NotHostShareable(_, ty) => unmangle_ty(ty, sourcemap, mangler),
+                BindgenError::UnknownLayout(name) => unmangle_name(name, sourcemap, mangler),
+                BindgenError::UnsupportedBinding(name, ty)
+                | BindgenError::NotVertexFormat(name, ty) => {
+                    unmangle_name(name, sourcemap, mangler);
+                    unmangle_ty(ty, sourcemap, mangler);
+                }
+            },
+            Error::LintError(_) => {}
             Error::Error(_) => {}
         };
 
@@ -461,9 +567,36 @@ impl<E: std::error::Error> std::error::Error for Diagnostic<E> {}
 
 impl<E: std::error::Error> Display for Diagnostic<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_level(f, annotate_snippets::Level::Error, None)
+    }
+}
+
+/// See [`Diagnostic::display_warning`].
+struct DisplayWarning<'a, E: std::error::Error>(&'a Diagnostic<E>);
+
+impl<E: std::error::Error> Display for DisplayWarning<'_, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0
+            .fmt_with_level(f, annotate_snippets::Level::Warning, None)
+    }
+}
+
+impl<E: std::error::Error> Diagnostic<E> {
+    /// Display the diagnostic as a warning instead of an error.
+    pub fn display_warning(&self) -> impl Display + '_ {
+        DisplayWarning(self)
+    }
+
+    /// Display the diagnostic with a severity level and an extra note.
+    pub(crate) fn fmt_with_level(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        level: annotate_snippets::Level,
+        extra_note: Option<&str>,
+    ) -> std::fmt::Result {
         use annotate_snippets::*;
         let title = format!("{}", self.error);
-        let mut msg = Level::Error.title(&title);
+        let mut msg = level.title(&title);
 
         let orig = self.display_origin();
         let short_orig = self.display_short_origin();
@@ -473,7 +606,7 @@ impl<E: std::error::Error> Display for Diagnostic<E> {
 
             if let Some(source) = source {
                 if span.range().end <= source.len() {
-                    let annot = Level::Error.span(span.range()).label(&title);
+                    let annot = level.span(span.range()).label(&title);
                     let mut snip = Snippet::source(source).fold(true).annotation(annot);
 
                     if let Some(orig) = &short_orig {
@@ -498,6 +631,9 @@ impl<E: std::error::Error> Display for Diagnostic<E> {
             note = format!("in {orig}");
         }
         msg = msg.footer(Level::Note.title(&note));
+        if let Some(note) = extra_note {
+            msg = msg.footer(Level::Note.title(note));
+        }
 
         let renderer = Renderer::styled();
         let rendered = renderer.render(msg);
@@ -32,8 +32,8 @@ pub trait EvalAttrs: Decorated {
 }
 
 impl<T: Decorated> EvalAttrs for T {}
-fn eval_positive_integer(expr: &Expression, ctx: &mut Context) -> Result<u32, E> {
-    let expr = with_stage!(ctx, EvalStage::Const, { expr.eval_value(ctx) })?;
+fn eval_positive_integer(expr: &Expression, stage: EvalStage, ctx: &mut Context) -> Result<u32, E> {
+    let expr = with_stage!(ctx, stage, { expr.eval_value(ctx) })?;
     let expr = match expr {
         Instance::Literal(g) => match g {
             LiteralInstance::AbstractInt(g) => Ok(g),
@@ -62,8 +62,8 @@ fn attr_group_binding(attrs: &[Attribute], ctx: &mut Context) -> Result<(u32, u3
 
     let (group, binding) = match (group, binding) {
         (Some(g), Some(b)) => Ok((
-            eval_positive_integer(g, ctx)?,
-            eval_positive_integer(b, ctx)?,
+            eval_positive_integer(g, EvalStage::Const, ctx)?,
+            eval_positive_integer(b, EvalStage::Const, ctx)?,
         )),
         _ => Err(E::MissingBindAttr),
     }?;
@@ -76,7 +76,7 @@ fn attr_size(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>> {
         _ => None,
     })?;
 
-    Some(eval_positive_integer(expr, ctx))
+    Some(eval_positive_integer(expr, EvalStage::Const, ctx))
 }
 
 fn attr_align(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>> {
@@ -85,7 +85,7 @@ fn attr_align(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>>
         _ => None,
     })?;
 
-    Some(eval_positive_integer(expr, ctx))
+    Some(eval_positive_integer(expr, EvalStage::Const, ctx))
 }
 
 fn attr_id(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>> {
@@ -94,16 +94,16 @@ fn attr_id(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>> {
         _ => None,
     })?;
 
-    Some(eval_positive_integer(expr, ctx))
+    Some(eval_positive_integer(expr, EvalStage::Const, ctx))
 }
 
-fn attr_location(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>> {
+pub(crate) fn attr_location(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<u32, E>> {
     let expr = attrs.iter().find_map(|attr| match attr {
         Attribute::Location(e) => Some(e),
         _ => None,
     })?;
 
-    Some(eval_positive_integer(expr, ctx))
+    Some(eval_positive_integer(expr, EvalStage::Const, ctx))
 }
 
 fn attr_workgroup_size(
@@ -118,28 +118,31 @@ fn attr_workgroup_size(
         })
         .ok_or(E::MissingWorkgroupSize)?;
 
-    let x = eval_positive_integer(&attr.x, ctx)?;
+    // the workgroup size can be an override-expression.
+    let x = eval_positive_integer(&attr.x, EvalStage::Override, ctx)?;
     let y = attr
         .y
         .as_ref()
-        .map(|y| eval_positive_integer(y, ctx))
+        .map(|y| eval_positive_integer(y, EvalStage::Override, ctx))
         .transpose()?;
     let z = attr
         .z
         .as_ref()
-        .map(|z| eval_positive_integer(z, ctx))
+        .map(|z| eval_positive_integer(z, EvalStage::Override, ctx))
         .transpose()?;
     Ok((x, y, z))
 }
 
-fn attr_blend_src(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<bool, E>> {
+pub(crate) fn attr_blend_src(attrs: &[Attribute], ctx: &mut Context) -> Option<Result<bool, E>> {
     let expr = attrs.iter().find_map(|attr| match attr {
         Attribute::BlendSrc(attr) => Some(attr),
         _ => None,
     })?;
-    Some(eval_positive_integer(expr, ctx).and_then(|val| match val {
-        0 => Ok(false),
-        1 => Ok(true),
-        _ => Err(E::InvalidBlendSrc(val)),
-    }))
+    Some(
+        eval_positive_integer(expr, EvalStage::Const, ctx).and_then(|val| match val {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::InvalidBlendSrc(val)),
+        }),
+    )
 }
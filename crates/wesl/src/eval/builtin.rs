@@ -18,9 +18,10 @@ use super::{
     conv::{convert_all, Convert},
     convert, convert_all_inner_to, convert_all_to, convert_all_ty,
     ops::Compwise,
+    texture::zero_texel,
     ty_eval_ty, ArrayInstance, EvalError, EvalStage, Instance, LiteralInstance, MatInstance,
-    RefInstance, SampledType, SamplerType, StructInstance, SyntaxUtil, TexelFormat, TextureType,
-    Ty, Type, VecInstance,
+    RefInstance, SampledType, SamplerInstance, SamplerType, StructInstance, SyntaxUtil, Texel,
+    TexelFormat, TextureDimension, TextureInstance, TextureType, Ty, Type, VecInstance,
 };
 
 type E = EvalError;
@@ -30,6 +31,10 @@ type E = EvalError;
 pub static EXPR_TRUE: Expression = Expression::Literal(LiteralExpression::Bool(true));
 pub static EXPR_FALSE: Expression = Expression::Literal(LiteralExpression::Bool(false));
 
+/// name prefix of the `atomicCompareExchangeWeak` result structs, followed by the
+/// atomic type, e.g. `__atomic_compare_exchange_result_i32`.
+pub const ATOMIC_RESULT: &str = "__atomic_compare_exchange_result_";
+
 pub fn builtin_ident(name: &str) -> Option<&'static Ident> {
     macro_rules! ident {
         ($name:literal) => {
@@ -233,12 +238,10 @@ pub static PRELUDE: LazyLock<TranslationUnit> = LazyLock::new(|| {
     for decl in &mut prelude.global_declarations {
         match decl {
             #[cfg(feature = "attributes")]
-            GlobalDeclaration::Struct(s) => {
-                if s.attributes.contains(&attr_internal) {
-                    s.ident = Ident::new(format!("__{}", s.ident));
-                    for m in &mut s.members {
-                        repl_ty(&mut m.ty);
-                    }
+            GlobalDeclaration::Struct(s) if s.attributes.contains(&attr_internal) => {
+                s.ident = Ident::new(format!("__{}", s.ident));
+                for m in &mut s.members {
+                    repl_ty(&mut m.ty);
                 }
             }
             GlobalDeclaration::Function(f) => {
@@ -270,7 +273,7 @@ fn array_ctor_ty_t(tplt: ArrayTemplate, args: &[Type]) -> Result<Type, E> {
 }
 
 fn array_ctor_ty(args: &[Type]) -> Result<Type, E> {
-    let ty = convert_all_ty(args).ok_or_else(|| E::Builtin("array elements are incompatible"))?;
+    let ty = convert_all_ty(args).ok_or(E::Builtin("array elements are incompatible"))?;
     Ok(Type::Array(Some(args.len()), Box::new(ty.clone())))
 }
 
@@ -285,8 +288,7 @@ fn mat_ctor_ty_t(c: u8, r: u8, tplt: MatTemplate, args: &[Type]) -> Result<Type,
         if args.is_empty() {
             return Err(E::Builtin("matrix constructor expects arguments"));
         }
-        let ty =
-            convert_all_ty(args).ok_or_else(|| E::Builtin("matrix components are incompatible"))?;
+        let ty = convert_all_ty(args).ok_or(E::Builtin("matrix components are incompatible"))?;
         let ty = ty
             .convert_inner_to(tplt.inner_ty())
             .ok_or_else(|| E::Conversion(ty.inner_ty(), tplt.inner_ty().clone()))?;
@@ -322,8 +324,7 @@ fn mat_ctor_ty(c: u8, r: u8, args: &[Type]) -> Result<Type, E> {
         }
         Ok(ty.clone())
     } else {
-        let ty =
-            convert_all_ty(args).ok_or_else(|| E::Builtin("matrix components are incompatible"))?;
+        let ty = convert_all_ty(args).ok_or(E::Builtin("matrix components are incompatible"))?;
         let inner_ty = ty.inner_ty();
 
         if !inner_ty.is_float() && !inner_ty.is_abstract_int() {
@@ -356,7 +357,6 @@ fn mat_ctor_ty(c: u8, r: u8, args: &[Type]) -> Result<Type, E> {
 
 fn vec_ctor_ty_t(n: u8, tplt: VecTemplate, args: &[Type]) -> Result<Type, E> {
     if let [arg] = args {
-        println!("{n} {arg} {}", tplt.ty(n));
         // overload 1: vec init from single scalar value
         if arg.is_scalar() {
             if !arg.is_convertible_to(tplt.inner_ty()) {
@@ -424,8 +424,7 @@ fn vec_ctor_ty(n: u8, args: &[Type]) -> Result<Type, E> {
         }
 
         let tys = args.iter().map(|arg| arg.inner_ty()).collect_vec();
-        let ty =
-            convert_all_ty(&tys).ok_or_else(|| E::Builtin("vector components are incompatible"))?;
+        let ty = convert_all_ty(&tys).ok_or(E::Builtin("vector components are incompatible"))?;
 
         Ok(Type::Vec(n, ty.clone().into()))
     }
@@ -534,7 +533,7 @@ pub fn builtin_fn_type(ty: &TypeExpression, args: &[Type], ctx: &mut Context) ->
             convert_ty(a1, a2).cloned().ok_or_else(err)
         }
         ("degrees", None, [a]) if is_float(a) => Ok(a.clone()),
-        ("determinant", None, [a @ Type::Mat(c, r, _)]) if c == r => Ok(a.clone()),
+        ("determinant", None, [a @ Type::Mat(c, r, _)]) if c == r => Ok(a.inner_ty()),
         ("distance", None, [a1, a2]) if is_float(a1) => {
             convert_ty(a1, a2).map(|ty| ty.inner_ty()).ok_or_else(err)
         }
@@ -640,23 +639,151 @@ pub fn builtin_fn_type(ty: &TypeExpression, args: &[Type], ctx: &mut Context) ->
         ("transpose", None, [Type::Mat(c, r, ty)]) => Ok(Type::Mat(*r, *c, ty.clone())),
         ("trunc", None, [a]) if is_float(a) => Ok(a.clone()),
         // packing
-        // TODO
-        // ("pack4x8snorm", None, [a]) => call_pack4x8snorm(a),
-        // ("pack4x8unorm", None, [a]) => call_pack4x8unorm(a),
-        // ("pack4xI8", None, [a]) => call_pack4xi8(a),
-        // ("pack4xU8", None, [a]) => call_pack4xu8(a),
-        // ("pack4xI8Clamp", None, [a]) => call_pack4xi8clamp(a),
-        // ("pack4xU8Clamp", None, [a]) => call_pack4xu8clamp(a),
-        // ("pack2x16snorm", None, [a]) => call_pack2x16snorm(a),
-        // ("pack2x16unorm", None, [a]) => call_pack2x16unorm(a),
-        // ("pack2x16float", None, [a]) => call_pack2x16float(a),
-        // ("unpack4x8snorm", None, [a]) => call_unpack4x8snorm(a),
-        // ("unpack4x8unorm", None, [a]) => call_unpack4x8unorm(a),
-        // ("unpack4xI8", None, [a]) => call_unpack4xi8(a),
-        // ("unpack4xU8", None, [a]) => call_unpack4xu8(a),
-        // ("unpack2x16snorm", None, [a]) => call_unpack2x16snorm(a),
-        // ("unpack2x16unorm", None, [a]) => call_unpack2x16unorm(a),
-        // ("unpack2x16float", None, [a]) => call_unpack2x16float(a),
+        ("pack4x8snorm" | "pack4x8unorm", None, [Type::Vec(4, t)])
+            if t.is_convertible_to(&Type::F32) =>
+        {
+            Ok(Type::U32)
+        }
+        ("pack4xI8" | "pack4xI8Clamp", None, [Type::Vec(4, t)])
+            if t.is_convertible_to(&Type::I32) =>
+        {
+            Ok(Type::U32)
+        }
+        ("pack4xU8" | "pack4xU8Clamp", None, [Type::Vec(4, t)])
+            if t.is_convertible_to(&Type::U32) =>
+        {
+            Ok(Type::U32)
+        }
+        ("pack2x16snorm" | "pack2x16unorm" | "pack2x16float", None, [Type::Vec(2, t)])
+            if t.is_convertible_to(&Type::F32) =>
+        {
+            Ok(Type::U32)
+        }
+        ("unpack4x8snorm" | "unpack4x8unorm", None, [a]) if a.is_convertible_to(&Type::U32) => {
+            Ok(Type::Vec(4, Type::F32.into()))
+        }
+        ("unpack4xI8", None, [a]) if a.is_convertible_to(&Type::U32) => {
+            Ok(Type::Vec(4, Type::I32.into()))
+        }
+        ("unpack4xU8", None, [a]) if a.is_convertible_to(&Type::U32) => {
+            Ok(Type::Vec(4, Type::U32.into()))
+        }
+        ("unpack2x16snorm" | "unpack2x16unorm" | "unpack2x16float", None, [a])
+            if a.is_convertible_to(&Type::U32) =>
+        {
+            Ok(Type::Vec(2, Type::F32.into()))
+        }
+        // derivative
+        (
+            "dpdx" | "dpdxCoarse" | "dpdxFine" | "dpdy" | "dpdyCoarse" | "dpdyFine" | "fwidth"
+            | "fwidthCoarse" | "fwidthFine",
+            None,
+            [a],
+        ) if is_float(a) => Ok(a.concretize()),
+        // atomic
+        ("atomicLoad", None, [Type::Ptr(_, t)]) if t.is_atomic() => Ok(t.inner_ty()),
+        (
+            "atomicAdd" | "atomicSub" | "atomicMax" | "atomicMin" | "atomicAnd" | "atomicOr"
+            | "atomicXor" | "atomicExchange",
+            None,
+            [Type::Ptr(_, t), _],
+        ) if t.is_atomic() => Ok(t.inner_ty()),
+        ("atomicStore", None, [Type::Ptr(_, t), a]) if t.is_atomic() => {
+            if a.is_convertible_to(&t.inner_ty()) {
+                Err(E::Void("atomicStore".to_string()))
+            } else {
+                Err(err())
+            }
+        }
+        ("atomicCompareExchangeWeak", None, [Type::Ptr(_, t), a2, a3])
+            if t.is_atomic()
+                && a2.is_convertible_to(&t.inner_ty())
+                && a3.is_convertible_to(&t.inner_ty()) =>
+        {
+            Ok(Type::Struct(format!("{ATOMIC_RESULT}{}", t.inner_ty())))
+        }
+        // synchronization
+        (name @ ("storageBarrier" | "textureBarrier" | "workgroupBarrier"), None, []) => {
+            Err(E::Void(name.to_string()))
+        }
+        // NOTE: the address space of pointers is not known here, it is checked at execution.
+        ("workgroupUniformLoad", None, [Type::Ptr(_, t)]) => Ok(*t.clone()),
+        // subgroup
+        (
+            "subgroupAdd"
+            | "subgroupExclusiveAdd"
+            | "subgroupInclusiveAdd"
+            | "subgroupMul"
+            | "subgroupExclusiveMul"
+            | "subgroupInclusiveMul"
+            | "subgroupMax"
+            | "subgroupMin",
+            None,
+            [a],
+        ) if is_numeric(a) => Ok(a.concretize()),
+        ("subgroupAnd" | "subgroupOr" | "subgroupXor", None, [a]) if is_integer(a) => {
+            Ok(a.concretize())
+        }
+        ("subgroupAll" | "subgroupAny", None, [a]) if a.is_bool() => Ok(Type::Bool),
+        ("subgroupBallot", None, [a]) if a.is_bool() => Ok(Type::Vec(4, Type::U32.into())),
+        ("subgroupElect", None, []) => Ok(Type::Bool),
+        ("subgroupBroadcastFirst" | "quadSwapDiagonal" | "quadSwapX" | "quadSwapY", None, [a])
+            if is_numeric(a) =>
+        {
+            Ok(a.concretize())
+        }
+        (
+            "subgroupBroadcast"
+            | "subgroupShuffle"
+            | "subgroupShuffleDown"
+            | "subgroupShuffleUp"
+            | "subgroupShuffleXor"
+            | "quadBroadcast",
+            None,
+            [a1, a2],
+        ) if is_numeric(a1) && a2.is_integer() => Ok(a1.concretize()),
+        // texture
+        ("textureDimensions", None, [Type::Texture(t), ..]) => match t.dimension() {
+            TextureDimension::D1 => Ok(Type::U32),
+            TextureDimension::D2 | TextureDimension::Cube => Ok(Type::Vec(2, Type::U32.into())),
+            TextureDimension::D3 => Ok(Type::Vec(3, Type::U32.into())),
+        },
+        ("textureGather", None, [Type::Texture(t), ..]) if t.is_depth() => {
+            Ok(Type::Vec(4, Type::F32.into()))
+        }
+        ("textureGather", None, [_, Type::Texture(t), ..]) => {
+            Ok(Type::Vec(4, Type::from(t.sampled_type()).into()))
+        }
+        ("textureGatherCompare", None, [Type::Texture(_), ..]) => {
+            Ok(Type::Vec(4, Type::F32.into()))
+        }
+        ("textureLoad", None, [Type::Texture(t), ..]) if t.is_depth() => Ok(Type::F32),
+        ("textureLoad", None, [Type::Texture(t), ..]) => {
+            Ok(Type::Vec(4, Type::from(t.sampled_type()).into()))
+        }
+        ("textureNumLayers", None, [Type::Texture(_)])
+        | ("textureNumLevels", None, [Type::Texture(_)])
+        | ("textureNumSamples", None, [Type::Texture(_)]) => Ok(Type::U32),
+        (
+            "textureSample" | "textureSampleBias" | "textureSampleGrad" | "textureSampleLevel",
+            None,
+            [Type::Texture(t), ..],
+        ) if t.is_depth() => Ok(Type::F32),
+        (
+            "textureSample"
+            | "textureSampleBias"
+            | "textureSampleGrad"
+            | "textureSampleLevel"
+            | "textureSampleBaseClampToEdge",
+            None,
+            [Type::Texture(_), ..],
+        ) => Ok(Type::Vec(4, Type::F32.into())),
+        ("textureSampleCompare" | "textureSampleCompareLevel", None, [Type::Texture(_), ..]) => {
+            Ok(Type::F32)
+        }
+        ("textureStore", None, [Type::Texture(t), ..]) if t.is_storage() => {
+            Err(E::Void("textureStore".to_string()))
+        }
         _ => Err(err()),
     }
 }
@@ -771,6 +898,25 @@ pub fn is_builtin_fn(name: &str) -> bool {
                 | "unpack2x16snorm"
                 | "unpack2x16unorm"
                 | "unpack2x16float"
+                | "storageBarrier"
+                | "textureBarrier"
+                | "workgroupBarrier"
+                | "workgroupUniformLoad"
+                | "textureDimensions"
+                | "textureGather"
+                | "textureGatherCompare"
+                | "textureLoad"
+                | "textureNumLayers"
+                | "textureNumLevels"
+                | "textureNumSamples"
+                | "textureSample"
+                | "textureSampleBias"
+                | "textureSampleCompare"
+                | "textureSampleCompareLevel"
+                | "textureSampleGrad"
+                | "textureSampleLevel"
+                | "textureSampleBaseClampToEdge"
+                | "textureStore"
         )
 }
 
@@ -921,6 +1067,29 @@ pub fn call_builtin(
         ("unpack2x16snorm", None, [a]) => call_unpack2x16snorm(a),
         ("unpack2x16unorm", None, [a]) => call_unpack2x16unorm(a),
         ("unpack2x16float", None, [a]) => call_unpack2x16float(a),
+        // synchronization
+        ("storageBarrier", None, []) => call_barrier("storageBarrier", ctx),
+        ("textureBarrier", None, []) => call_barrier("textureBarrier", ctx),
+        ("workgroupBarrier", None, []) => call_barrier("workgroupBarrier", ctx),
+        ("workgroupUniformLoad", None, [a]) => call_workgroupuniformload(a, ctx),
+        // texture
+        ("textureDimensions", None, [t]) => call_texturedimensions(t, None),
+        ("textureDimensions", None, [t, l]) => call_texturedimensions(t, Some(l)),
+        ("textureGather", None, a) => call_texturegather(a),
+        ("textureGatherCompare", None, a) => call_texturegathercompare(a),
+        ("textureLoad", None, a) => call_textureload(a),
+        ("textureNumLayers", None, [t]) => call_texturenumlayers(t),
+        ("textureNumLevels", None, [t]) => call_texturenumlevels(t),
+        ("textureNumSamples", None, [t]) => call_texturenumsamples(t),
+        ("textureSample", None, a) => call_texturesample(a),
+        ("textureSampleBias", None, a) => call_texturesamplebias(a),
+        ("textureSampleCompare", None, a) => call_texturesamplecompare(a),
+        // NOTE: the level of detail is always 0 in textureSampleCompare.
+        ("textureSampleCompareLevel", None, a) => call_texturesamplecompare(a),
+        ("textureSampleGrad", None, a) => call_texturesamplegrad(a),
+        ("textureSampleLevel", None, a) => call_texturesamplelevel(a),
+        ("textureSampleBaseClampToEdge", None, a) => call_texturesamplebaseclamptoedge(a),
+        ("textureStore", None, a) => call_texturestore(a),
 
         _ => Err(E::Signature(ty.clone(), args.iter().map(Ty::ty).collect())),
     }
@@ -1078,7 +1247,9 @@ impl VecTemplate {
             },
             _ => Err(E::TemplateArgs("vector")),
         }?;
-        if ty.is_scalar() && ty.is_concrete() {
+        // abstract vectors cannot be spelled in user code, but they appear in the
+        // built-in `frexp` and `modf` result structs.
+        if ty.is_scalar() {
             Ok(VecTemplate { ty })
         } else {
             Err(EvalError::Builtin("vector template type must be a scalar"))
@@ -1364,7 +1535,7 @@ fn call_array_t(tplt: ArrayTemplate, args: &[Instance]) -> Result<Instance, E> {
     Ok(ArrayInstance::new(args, false).into())
 }
 fn call_array(args: &[Instance]) -> Result<Instance, E> {
-    let args = convert_all(args).ok_or_else(|| E::Builtin("array elements are incompatible"))?;
+    let args = convert_all(args).ok_or(E::Builtin("array elements are incompatible"))?;
 
     if args.is_empty() {
         return Err(E::Builtin("array constructor expects at least 1 argument"));
@@ -1396,7 +1567,7 @@ fn call_i32_1(a1: &Instance) -> Result<Instance, E> {
                 LiteralInstance::F32(n) => Some(*n as i32),    // rounding towards 0
                 LiteralInstance::F16(n) => Some(f16::to_f32(*n) as i32), // rounding towards 0
             }
-            .ok_or_else(|| E::ConvOverflow(*l, Type::I32))?;
+            .ok_or(E::ConvOverflow(*l, Type::I32))?;
             Ok(LiteralInstance::I32(val).into())
         }
         _ => Err(E::Builtin("i32 constructor expects a scalar argument")),
@@ -1415,7 +1586,7 @@ fn call_u32_1(a1: &Instance) -> Result<Instance, E> {
                 LiteralInstance::F32(n) => Some(*n as u32),    // rounding towards 0
                 LiteralInstance::F16(n) => Some(f16::to_f32(*n) as u32), // rounding towards 0
             }
-            .ok_or_else(|| E::ConvOverflow(*l, Type::U32))?;
+            .ok_or(E::ConvOverflow(*l, Type::U32))?;
             Ok(LiteralInstance::U32(val).into())
         }
         _ => Err(E::Builtin("u32 constructor expects a scalar argument")),
@@ -1437,7 +1608,7 @@ fn call_f32_1(a1: &Instance, _stage: EvalStage) -> Result<Instance, E> {
                 LiteralInstance::F32(n) => Some(*n),           // identity operation
                 LiteralInstance::F16(n) => Some(f16::to_f32(*n)), // exactly representable
             }
-            .ok_or_else(|| E::ConvOverflow(*l, Type::F32))?;
+            .ok_or(E::ConvOverflow(*l, Type::F32))?;
             Ok(LiteralInstance::F32(val).into())
         }
         _ => Err(E::Builtin("f32 constructor expects a scalar argument")),
@@ -1496,7 +1667,7 @@ fn call_f16_1(a1: &Instance, stage: EvalStage) -> Result<Instance, E> {
                 }
                 LiteralInstance::F16(n) => Some(*n), // identity operation
             }
-            .ok_or_else(|| E::ConvOverflow(*l, Type::F16))?;
+            .ok_or(E::ConvOverflow(*l, Type::F16))?;
             Ok(LiteralInstance::F16(val).into())
         }
         _ => Err(E::Builtin("f16 constructor expects a scalar argument")),
@@ -1537,13 +1708,13 @@ fn call_mat_t(
     } else {
         let ty = args
             .first()
-            .ok_or_else(|| E::Builtin("matrix constructor expects arguments"))?
+            .ok_or(E::Builtin("matrix constructor expects arguments"))?
             .ty();
         let ty = ty
             .convert_inner_to(tplt.inner_ty())
             .ok_or_else(|| E::Conversion(ty.inner_ty(), tplt.inner_ty().clone()))?;
-        let args = convert_all_to(args, &ty)
-            .ok_or_else(|| E::Builtin("matrix components are incompatible"))?;
+        let args =
+            convert_all_to(args, &ty).ok_or(E::Builtin("matrix components are incompatible"))?;
 
         // overload 2: mat from column vectors
         if ty.is_vec() {
@@ -1566,9 +1737,9 @@ fn call_mat_t(
 
             Ok(MatInstance::from_cols(args).into())
         } else {
-            return Err(E::Builtin(
+            Err(E::Builtin(
                 "matrix constructor expects float or vector of float arguments",
-            ));
+            ))
         }
     }
 }
@@ -1584,8 +1755,7 @@ fn call_mat(c: usize, r: usize, args: &[Instance]) -> Result<Instance, E> {
         Ok(m.clone().into())
     } else {
         let tys = args.iter().map(|a| a.ty()).collect_vec();
-        let ty =
-            convert_all_ty(&tys).ok_or_else(|| E::Builtin("matrix components are incompatible"))?;
+        let ty = convert_all_ty(&tys).ok_or(E::Builtin("matrix components are incompatible"))?;
         let mut inner_ty = ty.inner_ty();
 
         if inner_ty.is_abstract_int() {
@@ -1598,7 +1768,7 @@ fn call_mat(c: usize, r: usize, args: &[Instance]) -> Result<Instance, E> {
         }
 
         let args = convert_all_inner_to(args, &inner_ty)
-            .ok_or_else(|| E::Builtin("matrix components are incompatible"))?;
+            .ok_or(E::Builtin("matrix components are incompatible"))?;
 
         // overload 2: mat from column vectors
         if ty.is_vec() {
@@ -1620,9 +1790,9 @@ fn call_mat(c: usize, r: usize, args: &[Instance]) -> Result<Instance, E> {
 
             Ok(MatInstance::from_cols(args).into())
         } else {
-            return Err(E::Builtin(
+            Err(E::Builtin(
                 "matrix constructor expects float or vector of float arguments",
-            ));
+            ))
         }
     }
 }
@@ -1727,8 +1897,7 @@ fn call_vec(n: usize, args: &[Instance]) -> Result<Instance, E> {
             return Err(E::ParamCount(format!("vec{n}"), n, args.len()));
         }
 
-        let comps =
-            convert_all(&args).ok_or_else(|| E::Builtin("vector components are incompatible"))?;
+        let comps = convert_all(&args).ok_or(E::Builtin("vector components are incompatible"))?;
 
         if !comps.first().unwrap(/* SAFETY: len() checked above */).ty().is_scalar() {
             return Err(E::Builtin("vec constructor expects scalar arguments"));
@@ -1754,17 +1923,17 @@ fn call_bitcast_t(tplt: BitcastTemplate, e: &Instance) -> Result<Instance, E> {
                 if ty == &Type::U32 {
                     n.to_u32()
                         .map(|n| n.to_le_bytes().to_vec())
-                        .ok_or_else(|| E::ConvOverflow(*l, Type::U32))
+                        .ok_or(E::ConvOverflow(*l, Type::U32))
                 } else {
                     n.to_i32()
                         .map(|n| n.to_le_bytes().to_vec())
-                        .ok_or_else(|| E::ConvOverflow(*l, Type::I32))
+                        .ok_or(E::ConvOverflow(*l, Type::I32))
                 }
             }
             LiteralInstance::AbstractFloat(n) => n
                 .to_f32()
                 .map(|n| n.to_le_bytes().to_vec())
-                .ok_or_else(|| E::ConvOverflow(*l, Type::F32)),
+                .ok_or(E::ConvOverflow(*l, Type::F32)),
             LiteralInstance::I32(n) => Ok(n.to_le_bytes().to_vec()),
             LiteralInstance::U32(n) => Ok(n.to_le_bytes().to_vec()),
             LiteralInstance::F32(n) => Ok(n.to_le_bytes().to_vec()),
@@ -1878,8 +2047,9 @@ fn call_any(e: &Instance) -> Result<Instance, E> {
 }
 
 fn call_select(f: &Instance, t: &Instance, cond: &Instance) -> Result<Instance, E> {
-    let (f, t) = convert(f, t)
-        .ok_or_else(|| E::Builtin("`select` 1st and 2nd arguments are incompatible"))?;
+    let (f, t) = convert(f, t).ok_or(E::Builtin(
+        "`select` 1st and 2nd arguments are incompatible",
+    ))?;
 
     match cond {
         Instance::Literal(LiteralInstance::Bool(b)) => Ok(b.then_some(t).unwrap_or(f)),
@@ -2024,10 +2194,10 @@ fn call_atan2(y: &Instance, x: &Instance) -> Result<Instance, E> {
             LiteralInstance::AbstractInt(_) => {
                 let y = y
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?;
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?;
                 let x = x
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?;
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?;
                 Ok(LiteralInstance::from(
                     y.unwrap_abstract_float().atan2(x.unwrap_abstract_float()),
                 ))
@@ -2041,7 +2211,7 @@ fn call_atan2(y: &Instance, x: &Instance) -> Result<Instance, E> {
             LiteralInstance::F16(y) => Ok(LiteralInstance::from(y.atan2(x.unwrap_f_16()))),
         }
     }
-    let (y, x) = convert(y, x).ok_or_else(|| E::Builtin("`atan2` arguments are incompatible"))?;
+    let (y, x) = convert(y, x).ok_or(E::Builtin("`atan2` arguments are incompatible"))?;
     match (y, x) {
         (Instance::Literal(y), Instance::Literal(x)) => lit_atan2(&y, &x).map(Into::into),
         (Instance::Vec(y), Instance::Vec(x)) => y.compwise_binary(&x, lit_atan2).map(Into::into),
@@ -2366,7 +2536,7 @@ fn call_inversesqrt(e: &Instance) -> Result<Instance, E> {
             LiteralInstance::Bool(_) => Err(ERR),
             LiteralInstance::AbstractInt(_) => l
                 .convert_to(&Type::AbstractFloat)
-                .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))
+                .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))
                 .map(|n| LiteralInstance::from(1.0 / n.unwrap_abstract_float().sqrt())),
             LiteralInstance::AbstractFloat(n) => Ok(LiteralInstance::from(1.0 / n.sqrt())),
             LiteralInstance::I32(_) => Err(ERR),
@@ -2501,7 +2671,7 @@ fn call_max(e1: &Instance, e2: &Instance) -> Result<Instance, E> {
             LiteralInstance::F16(e1) => Ok(LiteralInstance::from(e1.max(e2.unwrap_f_16()))),
         }
     }
-    let (e1, e2) = convert(e1, e2).ok_or_else(|| E::Builtin("`max` arguments are incompatible"))?;
+    let (e1, e2) = convert(e1, e2).ok_or(E::Builtin("`max` arguments are incompatible"))?;
     match (e1, e2) {
         (Instance::Literal(e1), Instance::Literal(e2)) => lit_max(&e1, &e2).map(Into::into),
         (Instance::Vec(e1), Instance::Vec(e2)) => e1.compwise_binary(&e2, lit_max).map(Into::into),
@@ -2526,7 +2696,7 @@ fn call_min(e1: &Instance, e2: &Instance) -> Result<Instance, E> {
             LiteralInstance::F16(e1) => Ok(LiteralInstance::from(e1.min(e2.unwrap_f_16()))),
         }
     }
-    let (e1, e2) = convert(e1, e2).ok_or_else(|| E::Builtin("`min` arguments are incompatible"))?;
+    let (e1, e2) = convert(e1, e2).ok_or(E::Builtin("`min` arguments are incompatible"))?;
     match (e1, e2) {
         (Instance::Literal(e1), Instance::Literal(e2)) => lit_min(&e1, &e2).map(Into::into),
         (Instance::Vec(e1), Instance::Vec(e2)) => e1.compwise_binary(&e2, lit_min).map(Into::into),
@@ -2575,11 +2745,11 @@ fn call_pow(e1: &Instance, e2: &Instance) -> Result<Instance, E> {
             LiteralInstance::AbstractInt(_) => {
                 let e1 = e1
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
                     .unwrap_abstract_float();
                 let e2 = e2
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
                     .unwrap_abstract_float();
                 Ok(LiteralInstance::from(e1.powf(e2)))
             }
@@ -2592,7 +2762,7 @@ fn call_pow(e1: &Instance, e2: &Instance) -> Result<Instance, E> {
             LiteralInstance::F16(e1) => Ok(LiteralInstance::from(e1.powf(e2.unwrap_f_16()))),
         }
     }
-    let (e1, e2) = convert(e1, e2).ok_or_else(|| E::Builtin("`pow` arguments are incompatible"))?;
+    let (e1, e2) = convert(e1, e2).ok_or(E::Builtin("`pow` arguments are incompatible"))?;
     match (e1, e2) {
         (Instance::Literal(e1), Instance::Literal(e2)) => lit_powf(&e1, &e2).map(Into::into),
         (Instance::Vec(e1), Instance::Vec(e2)) => e1.compwise_binary(&e2, lit_powf).map(Into::into),
@@ -2628,7 +2798,7 @@ fn call_round(e: &Instance) -> Result<Instance, E> {
             LiteralInstance::AbstractInt(_) => {
                 let n = l
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
                     .unwrap_abstract_float();
                 Ok(LiteralInstance::from(n.round_ties_even()))
             }
@@ -2729,11 +2899,11 @@ fn call_step(edge: &Instance, x: &Instance) -> Result<Instance, E> {
             LiteralInstance::AbstractInt(_) => {
                 let edge = edge
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
                     .unwrap_abstract_float();
                 let x = x
                     .convert_to(&Type::AbstractFloat)
-                    .ok_or_else(|| E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
+                    .ok_or(E::Conversion(Type::AbstractInt, Type::AbstractFloat))?
                     .unwrap_abstract_float();
                 Ok(LiteralInstance::from(if edge <= x { 1.0 } else { 0.0 }))
             }
@@ -2856,6 +3026,384 @@ fn call_unpack2x16float(_a1: &Instance) -> Result<Instance, E> {
     Err(E::Todo("unpack2x16float".to_string()))
 }
 
+// ---------------
+// SYNCHRONIZATION
+// ---------------
+// reference: <https://www.w3.org/TR/WGSL/#sync-builtin-functions>
+
+// NOTE: all barriers are control barriers: the memory is always coherent in the evaluator.
+fn call_barrier(name: &str, ctx: &mut Context) -> Result<Instance, E> {
+    ctx.barrier()?;
+    Err(E::Void(name.to_string()))
+}
+
+fn call_workgroupuniformload(p: &Instance, ctx: &mut Context) -> Result<Instance, E> {
+    let err = E::Builtin("`workgroupUniformLoad` expects a pointer to workgroup argument");
+    let r = match p {
+        Instance::Ptr(p) if p.ptr.space == AddressSpace::Workgroup => RefInstance::from(p.clone()),
+        _ => return Err(err),
+    };
+    // all invocations load the value after the writes that precede the barrier, and before
+    // the writes that follow it.
+    ctx.barrier()?;
+    let inst = r.read()?.to_owned();
+    ctx.barrier()?;
+    Ok(inst)
+}
+
+// -------
+// TEXTURE
+// -------
+// reference: <https://www.w3.org/TR/WGSL/#texture-builtin-functions>
+
+fn tex_int(e: &Instance) -> Option<i64> {
+    match e {
+        Instance::Literal(LiteralInstance::AbstractInt(n)) => Some(*n),
+        Instance::Literal(LiteralInstance::I32(n)) => Some(*n as i64),
+        Instance::Literal(LiteralInstance::U32(n)) => Some(*n as i64),
+        _ => None,
+    }
+}
+
+fn tex_float(e: &Instance) -> Option<f32> {
+    match e {
+        Instance::Literal(LiteralInstance::AbstractFloat(f)) => Some(*f as f32),
+        Instance::Literal(LiteralInstance::F32(f)) => Some(*f),
+        e => tex_int(e).map(|n| n as f32),
+    }
+}
+
+/// the `n` components of a scalar or vector texture coordinate. Missing components are 0.
+fn tex_coords<T: Copy + Default>(
+    e: &Instance,
+    n: usize,
+    f: impl Fn(&Instance) -> Option<T>,
+) -> Result<[T; 3], E> {
+    let err = E::Builtin("invalid texture coordinates");
+    let mut res = [T::default(); 3];
+    match e {
+        Instance::Vec(v) if v.n() == n => {
+            for (r, c) in res.iter_mut().zip(v.iter()) {
+                *r = f(c).ok_or(err.clone())?;
+            }
+        }
+        e if n == 1 => res[0] = f(e).ok_or(err)?,
+        _ => return Err(err),
+    }
+    Ok(res)
+}
+
+fn tex_texture(e: &Instance) -> Result<&TextureInstance, E> {
+    match e {
+        Instance::Texture(t) => Ok(t),
+        _ => Err(E::Builtin("expected a texture argument")),
+    }
+}
+
+fn tex_sampler(e: &Instance, ty: SamplerType) -> Result<&SamplerInstance, E> {
+    match e {
+        Instance::Sampler(s) if s.ty == ty => Ok(s),
+        _ if ty == SamplerType::SamplerComparison => {
+            Err(E::Builtin("expected a `sampler_comparison` argument"))
+        }
+        _ => Err(E::Builtin("expected a `sampler` argument")),
+    }
+}
+
+fn tex_texel(texel: Texel, t: &TextureInstance) -> Instance {
+    if t.texture_type().is_depth() {
+        texel[0].into()
+    } else {
+        VecInstance::from(texel).into()
+    }
+}
+
+fn tex_sample_texel(texel: [f32; 4], t: &TextureInstance) -> Instance {
+    tex_texel(texel.map(LiteralInstance::F32), t)
+}
+
+/// the arguments of the sample and gather functions:
+/// `t, s, coords, [array_index], extra..., [offset]`
+struct SampleArgs<'a> {
+    t: &'a TextureInstance,
+    s: &'a SamplerInstance,
+    coords: [f32; 3],
+    array_index: i64,
+    extra: &'a [Instance],
+    offset: [i32; 3],
+}
+
+fn sample_args<'a>(
+    args: &'a [Instance],
+    sampler_ty: SamplerType,
+    n_extra: usize,
+) -> Result<SampleArgs<'a>, E> {
+    let err = E::Builtin("invalid texture sampling arguments");
+    let [t, s, coords, rest @ ..] = args else {
+        return Err(err);
+    };
+    let t = tex_texture(t)?;
+    let s = tex_sampler(s, sampler_ty)?;
+    let ty = t.texture_type();
+    if ty.is_storage() || ty.is_multisampled() {
+        return Err(E::Builtin(
+            "storage and multisampled textures cannot be sampled",
+        ));
+    }
+    let n = ty.dimension().coords_len();
+    let coords = tex_coords(coords, n, tex_float)?;
+    let (array_index, rest) = match rest {
+        [i, rest @ ..] if ty.is_arrayed() => (tex_int(i).ok_or(err.clone())?, rest),
+        _ => (0, rest),
+    };
+    let (extra, offset) = match rest.len() {
+        m if m == n_extra => (rest, [0; 3]),
+        m if m == n_extra + 1 => {
+            let offset = tex_coords(&rest[n_extra], n.min(2), tex_int)?.map(|i| i as i32);
+            (&rest[..n_extra], offset)
+        }
+        _ => return Err(err),
+    };
+    Ok(SampleArgs {
+        t,
+        s,
+        coords,
+        array_index,
+        extra,
+        offset,
+    })
+}
+
+fn call_texturedimensions(t: &Instance, level: Option<&Instance>) -> Result<Instance, E> {
+    let t = tex_texture(t)?;
+    let level = match level {
+        Some(l) if t.texture_type().is_mipmapped() => {
+            tex_int(l).ok_or(E::Builtin("invalid mip level argument"))?
+        }
+        Some(_) => return Err(E::Builtin("this texture type has no mip levels")),
+        None => 0,
+    };
+    let [w, h, d] = t
+        .level_size(level.clamp(0, u32::MAX as i64) as u32)
+        .map(|n| Instance::from(LiteralInstance::U32(n)));
+    let res = match t.texture_type().dimension() {
+        TextureDimension::D1 => w,
+        TextureDimension::D2 | TextureDimension::Cube => VecInstance::new(vec![w, h]).into(),
+        TextureDimension::D3 => VecInstance::new(vec![w, h, d]).into(),
+    };
+    Ok(res)
+}
+
+fn call_textureload(args: &[Instance]) -> Result<Instance, E> {
+    let err = E::Builtin("invalid `textureLoad` arguments");
+    let [t, coords, rest @ ..] = args else {
+        return Err(err);
+    };
+    let t = tex_texture(t)?;
+    let ty = t.texture_type();
+    if matches!(
+        ty,
+        TextureType::Storage1D(_, AccessMode::Write)
+            | TextureType::Storage2D(_, AccessMode::Write)
+            | TextureType::Storage2DArray(_, AccessMode::Write)
+            | TextureType::Storage3D(_, AccessMode::Write)
+    ) {
+        return Err(E::NotRead);
+    }
+    let [x, y, z] = tex_coords(coords, ty.dimension().coords_len(), tex_int)?;
+    let (layer, rest) = match rest {
+        [i, rest @ ..] if ty.is_arrayed() => (tex_int(i).ok_or(err.clone())?, rest),
+        _ => (z, rest),
+    };
+    // multisampled textures have a single sample.
+    let (level, sample) = match rest {
+        [] if ty.is_storage() || *ty == TextureType::External => (0, 0),
+        [s] if ty.is_multisampled() => (0, tex_int(s).ok_or(err.clone())?),
+        [l] if ty.is_mipmapped() => (tex_int(l).ok_or(err.clone())?, 0),
+        _ => return Err(err),
+    };
+    let texel = (sample == 0 && level >= 0)
+        .then(|| t.load(level as u32, [x, y, layer]))
+        .flatten()
+        // out-of-bounds loads return an invalid texel.
+        .unwrap_or_else(|| zero_texel(ty.sampled_type()));
+    Ok(tex_texel(texel, t))
+}
+
+fn call_texturestore(args: &[Instance]) -> Result<Instance, E> {
+    let err = E::Builtin("invalid `textureStore` arguments");
+    let (t, coords, layer, value) = match args {
+        [t, c, v] => (t, c, None, v),
+        [t, c, i, v] => (t, c, Some(i), v),
+        _ => return Err(err),
+    };
+    let t = tex_texture(t)?;
+    let ty = t.texture_type();
+    match ty {
+        TextureType::Storage1D(_, access)
+        | TextureType::Storage2D(_, access)
+        | TextureType::Storage2DArray(_, access)
+        | TextureType::Storage3D(_, access) => {
+            if *access == AccessMode::Read {
+                return Err(E::NotWrite);
+            }
+        }
+        _ => return Err(E::Builtin("`textureStore` expects a storage texture")),
+    }
+    let [x, y, z] = tex_coords(coords, ty.dimension().coords_len(), tex_int)?;
+    let layer = match layer {
+        Some(i) if ty.is_arrayed() => tex_int(i).ok_or(err.clone())?,
+        None if !ty.is_arrayed() => z,
+        _ => return Err(err),
+    };
+    let channel_ty = Type::from(ty.sampled_type());
+    let texel: Texel = match value {
+        Instance::Vec(v) if v.n() == 4 => {
+            let v = v
+                .iter()
+                .map(|c| match c.convert_to(&channel_ty) {
+                    Some(Instance::Literal(l)) => Ok(l),
+                    _ => Err(E::ParamType(
+                        Type::Vec(4, channel_ty.clone().into()),
+                        value.ty(),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            [v[0], v[1], v[2], v[3]]
+        }
+        _ => return Err(E::ParamType(Type::Vec(4, channel_ty.into()), value.ty())),
+    };
+    // out-of-bounds stores are discarded.
+    t.store(0, [x, y, layer], texel);
+    Err(E::Void("textureStore".to_string()))
+}
+
+fn call_texturenumlayers(t: &Instance) -> Result<Instance, E> {
+    let t = tex_texture(t)?;
+    if !t.texture_type().is_arrayed() {
+        return Err(E::Builtin("`textureNumLayers` expects an array texture"));
+    }
+    Ok(LiteralInstance::U32(t.num_layers()).into())
+}
+
+fn call_texturenumlevels(t: &Instance) -> Result<Instance, E> {
+    let t = tex_texture(t)?;
+    if !t.texture_type().is_mipmapped() {
+        return Err(E::Builtin("this texture type has no mip levels"));
+    }
+    Ok(LiteralInstance::U32(t.num_levels()).into())
+}
+
+fn call_texturenumsamples(t: &Instance) -> Result<Instance, E> {
+    let t = tex_texture(t)?;
+    if !t.texture_type().is_multisampled() {
+        return Err(E::Builtin(
+            "`textureNumSamples` expects a multisampled texture",
+        ));
+    }
+    // multisampled textures have a single sample in the evaluator.
+    Ok(LiteralInstance::U32(1).into())
+}
+
+// NOTE: there are no implicit derivatives in the evaluator: the level of detail is always 0.
+fn call_texturesample(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::Sampler, 0)?;
+    let texel =
+        a.t.sample(a.s, a.coords, a.array_index, a.offset, 0.0, None);
+    Ok(tex_sample_texel(texel, a.t))
+}
+
+fn call_texturesamplebias(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::Sampler, 1)?;
+    let bias = tex_float(&a.extra[0]).ok_or(E::Builtin("invalid bias argument"))?;
+    let lod = bias.clamp(-16.0, 15.99);
+    let texel =
+        a.t.sample(a.s, a.coords, a.array_index, a.offset, lod, None);
+    Ok(tex_sample_texel(texel, a.t))
+}
+
+fn call_texturesamplelevel(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::Sampler, 1)?;
+    let lod = tex_float(&a.extra[0]).ok_or(E::Builtin("invalid level argument"))?;
+    let texel =
+        a.t.sample(a.s, a.coords, a.array_index, a.offset, lod, None);
+    Ok(tex_sample_texel(texel, a.t))
+}
+
+fn call_texturesamplegrad(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::Sampler, 2)?;
+    let n = a.t.texture_type().dimension().coords_len();
+    let ddx = tex_coords(&a.extra[0], n, tex_float)?;
+    let ddy = tex_coords(&a.extra[1], n, tex_float)?;
+    let lod = a.t.lod_from_grad(ddx, ddy);
+    let texel =
+        a.t.sample(a.s, a.coords, a.array_index, a.offset, lod, None);
+    Ok(tex_sample_texel(texel, a.t))
+}
+
+fn call_texturesamplecompare(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::SamplerComparison, 1)?;
+    if !a.t.texture_type().is_depth() {
+        return Err(E::Builtin("depth comparisons expect a depth texture"));
+    }
+    let depth_ref = tex_float(&a.extra[0]).ok_or(E::Builtin("invalid depth_ref argument"))?;
+    let texel =
+        a.t.sample(a.s, a.coords, a.array_index, a.offset, 0.0, Some(depth_ref));
+    Ok(LiteralInstance::F32(texel[0]).into())
+}
+
+fn call_texturesamplebaseclamptoedge(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::Sampler, 0)?;
+    if !matches!(
+        a.t.texture_type(),
+        TextureType::Sampled2D(SampledType::F32) | TextureType::External
+    ) || a.offset != [0; 3]
+    {
+        return Err(E::Builtin(
+            "invalid `textureSampleBaseClampToEdge` arguments",
+        ));
+    }
+    let size = a.t.level_size(0);
+    let coords: [f32; 3] = std::array::from_fn(|i| {
+        let half_texel = 0.5 / size[i] as f32;
+        a.coords[i].clamp(half_texel, 1.0 - half_texel)
+    });
+    let texel = a.t.sample(a.s, coords, 0, [0; 3], 0.0, None);
+    Ok(VecInstance::from(texel.map(LiteralInstance::F32)).into())
+}
+
+fn call_texturegather(args: &[Instance]) -> Result<Instance, E> {
+    let (component, args) = match args {
+        [Instance::Texture(t), ..] if t.texture_type().is_depth() => (0, args),
+        [c, args @ ..] => {
+            let c = tex_int(c)
+                .filter(|c| (0..4).contains(c))
+                .ok_or(E::Builtin("invalid `textureGather` component"))?;
+            (c as usize, args)
+        }
+        [] => return Err(E::Builtin("invalid `textureGather` arguments")),
+    };
+    let a = sample_args(args, SamplerType::Sampler, 0)?;
+    if a.t.texture_type().dimension() == TextureDimension::D1
+        || a.t.texture_type().dimension() == TextureDimension::D3
+    {
+        return Err(E::Builtin("`textureGather` expects a 2D or cube texture"));
+    }
+    let texels = a.t.gather(a.s, a.coords, a.array_index, a.offset, None);
+    Ok(VecInstance::from(texels.map(|t| t[component])).into())
+}
+
+fn call_texturegathercompare(args: &[Instance]) -> Result<Instance, E> {
+    let a = sample_args(args, SamplerType::SamplerComparison, 1)?;
+    if !a.t.texture_type().is_depth() {
+        return Err(E::Builtin("depth comparisons expect a depth texture"));
+    }
+    let depth_ref = tex_float(&a.extra[0]).ok_or(E::Builtin("invalid depth_ref argument"))?;
+    let texels =
+        a.t.gather(a.s, a.coords, a.array_index, a.offset, Some(depth_ref));
+    Ok(VecInstance::from(texels.map(|t| t[0])).into())
+}
+
 impl VecInstance {
     /// warning, this function does not check operand types
     pub fn dot(&self, rhs: &VecInstance, stage: EvalStage) -> Result<LiteralInstance, E> {
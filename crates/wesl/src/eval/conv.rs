@@ -63,6 +63,13 @@ impl Convert for Type {
             Self::Array(n, ty) => Type::Array(*n, ty.concretize().into()),
             Self::Vec(n, ty) => Type::Vec(*n, ty.concretize().into()),
             Self::Mat(c, r, ty) => Type::Mat(*c, *r, ty.concretize().into()),
+            // the result structs of `frexp` and `modf`.
+            Self::Struct(name) if PRELUDE.decl_struct(name).is_some() => {
+                match name.strip_suffix("abstract") {
+                    Some(name) => Type::Struct(format!("{name}f32")),
+                    None => self.clone(),
+                }
+            }
             _ => self.clone(),
         }
     }
@@ -189,25 +196,26 @@ impl Convert for StructInstance {
                 && PRELUDE.decl_struct(s2).is_some()
                 && s1.ends_with("abstract")
             {
-                if s2.ends_with("f32") {
-                    let members = self
-                        .iter_members()
-                        .map(|(name, inst)| {
-                            Some((name.clone(), inst.convert_inner_to(&Type::F32)?))
-                        })
-                        .collect::<Option<Vec<_>>>()?;
-                    Some(StructInstance::new(s2.to_string(), members))
+                let float = if s2.ends_with("f32") {
+                    Type::F32
                 } else if s2.ends_with("f16") {
-                    let members = self
-                        .iter_members()
-                        .map(|(name, inst)| {
-                            Some((name.clone(), inst.convert_inner_to(&Type::F16)?))
-                        })
-                        .collect::<Option<Vec<_>>>()?;
-                    Some(StructInstance::new(s2.to_string(), members))
+                    Type::F16
                 } else {
-                    None
-                }
+                    return None;
+                };
+                // the `exp` member of `frexp` results is an integer.
+                let members = self
+                    .iter_members()
+                    .map(|(name, inst)| {
+                        let ty = if inst.inner_ty() == Type::AbstractInt {
+                            &Type::I32
+                        } else {
+                            &float
+                        };
+                        Some((name.clone(), inst.convert_inner_to(ty)?))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(StructInstance::new(s2.to_string(), members))
             } else {
                 None
             }
@@ -231,6 +239,7 @@ impl Convert for Instance {
             Self::Ptr(_) => None,
             Self::Ref(r) => r.read().ok().and_then(|r| r.convert_to(ty)), // this is the "load rule". Also performed by `eval_value`.
             Self::Atomic(_) => None,
+            Self::Texture(_) | Self::Sampler(_) => None,
             Self::Deferred(_) => None,
         }
     }
@@ -245,6 +254,7 @@ impl Convert for Instance {
             Self::Ptr(_) => None,
             Self::Ref(r) => r.read().ok().and_then(|r| r.convert_inner_to(ty)), // this is the "load rule". Also performed by `eval_value`.
             Self::Atomic(_) => None,
+            Self::Texture(_) | Self::Sampler(_) => None,
             Self::Deferred(_) => None,
         }
     }
@@ -6,8 +6,8 @@ use crate::eval::Ty;
 
 use super::{
     ArrayInstance, AtomicInstance, Instance, LiteralInstance, MatInstance, MemView, PtrInstance,
-    RefInstance, SampledType, SamplerType, StructInstance, TexelFormat, TextureType, Type,
-    VecInstance,
+    RefInstance, SampledType, SamplerInstance, SamplerType, StructInstance, TexelFormat,
+    TextureInstance, TextureType, Type, VecInstance,
 };
 
 impl Display for Instance {
@@ -21,6 +21,8 @@ impl Display for Instance {
             Instance::Ptr(inst) => write!(f, "{inst}"),
             Instance::Ref(inst) => write!(f, "{inst}"),
             Instance::Atomic(inst) => write!(f, "{inst}"),
+            Instance::Texture(inst) => write!(f, "{inst}"),
+            Instance::Sampler(inst) => write!(f, "{inst}"),
             Instance::Deferred(ty) => write!(f, "__deferred<{ty}>"),
         }
     }
@@ -123,6 +125,41 @@ impl Display for AtomicInstance {
     }
 }
 
+impl Display for TextureInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ty = self.texture_type();
+        let [w, h, d] = self.level_size(0);
+        let texels = self
+            .texels(0)
+            .unwrap_or_default()
+            .into_iter()
+            .map(VecInstance::from)
+            .format(", ");
+        write!(f, "{ty}[{w}, {h}, {d}]({texels})")
+    }
+}
+
+impl Display for SamplerInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ty = &self.ty;
+        let (u, v, w) = (
+            self.address_mode_u,
+            self.address_mode_v,
+            self.address_mode_w,
+        );
+        let (mag, min, mip) = (self.mag_filter, self.min_filter, self.mipmap_filter);
+        write!(
+            f,
+            "{ty}(address_mode=[{u}, {v}, {w}], filter=[{mag}, {min}, {mip}], lod_clamp=[{}, {}]",
+            self.lod_min_clamp, self.lod_max_clamp
+        )?;
+        if *ty == SamplerType::SamplerComparison {
+            write!(f, ", compare={}", self.compare)?;
+        }
+        write!(f, ")")
+    }
+}
+
 impl Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
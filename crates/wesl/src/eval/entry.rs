@@ -0,0 +1,788 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use derive_more::Display;
+use itertools::{iproduct, Itertools};
+use wgsl_parse::{
+    span::Span,
+    syntax::{Attribute, BuiltinValue, Function, TranslationUnit},
+};
+
+use super::{
+    attrs::attr_location, call_fn, ty_eval_ty, Context, Convert, EvalAttrs, EvalError, EvalStage,
+    Exec, Instance, LiteralInstance, RefInstance, StructInstance, SyntaxUtil, Ty, Type,
+    VecInstance,
+};
+
+type E = EvalError;
+
+/// The pipeline stage of an entrypoint.
+///
+/// reference: <https://www.w3.org/TR/WGSL/#shader-stages-sec>
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum ShaderStage {
+    #[display("vertex")]
+    Vertex,
+    #[display("fragment")]
+    Fragment,
+    #[display("compute")]
+    Compute,
+}
+
+impl ShaderStage {
+    /// get the shader stage of a function, or `None` if it is not an entrypoint.
+    pub fn of(decl: &Function) -> Option<Self> {
+        decl.attributes.iter().find_map(|attr| match attr {
+            Attribute::Vertex => Some(Self::Vertex),
+            Attribute::Fragment => Some(Self::Fragment),
+            Attribute::Compute => Some(Self::Compute),
+            _ => None,
+        })
+    }
+}
+
+/// An input or output of an entrypoint: a user-defined `@location` or a `@builtin`
+/// value.
+///
+/// reference: <https://www.w3.org/TR/WGSL/#stage-inputs-outputs>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
+pub enum ShaderIO {
+    #[display("@location({_0})")]
+    Location(u32),
+    #[display("@builtin({_0})")]
+    Builtin(BuiltinValue),
+}
+
+impl ShaderIO {
+    /// whether this is an input of the shader stage.
+    pub fn is_input(&self, stage: ShaderStage) -> bool {
+        match (self, stage) {
+            (ShaderIO::Location(_), ShaderStage::Compute) => false,
+            (ShaderIO::Location(_), _) => true,
+            (ShaderIO::Builtin(b), ShaderStage::Vertex) => {
+                matches!(b, BuiltinValue::VertexIndex | BuiltinValue::InstanceIndex)
+            }
+            (ShaderIO::Builtin(b), ShaderStage::Fragment) => matches!(
+                b,
+                BuiltinValue::Position
+                    | BuiltinValue::FrontFacing
+                    | BuiltinValue::SampleIndex
+                    | BuiltinValue::SampleMask
+            ),
+            (ShaderIO::Builtin(b), ShaderStage::Compute) => matches!(
+                b,
+                BuiltinValue::LocalInvocationId
+                    | BuiltinValue::LocalInvocationIndex
+                    | BuiltinValue::GlobalInvocationId
+                    | BuiltinValue::WorkgroupId
+                    | BuiltinValue::NumWorkgroups
+            ),
+        }
+    }
+
+    /// whether this is an output of the shader stage.
+    pub fn is_output(&self, stage: ShaderStage) -> bool {
+        matches!(
+            (self, stage),
+            (
+                ShaderIO::Location(_),
+                ShaderStage::Vertex | ShaderStage::Fragment
+            ) | (
+                ShaderIO::Builtin(BuiltinValue::Position),
+                ShaderStage::Vertex
+            ) | (
+                ShaderIO::Builtin(BuiltinValue::FragDepth | BuiltinValue::SampleMask),
+                ShaderStage::Fragment,
+            )
+        )
+    }
+}
+
+/// get the `@location` or `@builtin` attribute of an entrypoint parameter, return value
+/// or structure member.
+fn attr_io(attrs: &[Attribute], ctx: &mut Context) -> Result<Option<ShaderIO>, E> {
+    let builtin = attrs.iter().find_map(|attr| match attr {
+        Attribute::Builtin(b) => Some(*b),
+        _ => None,
+    });
+    if let Some(b) = builtin {
+        return Ok(Some(ShaderIO::Builtin(b)));
+    }
+    let location = attr_location(attrs, ctx).transpose()?;
+    Ok(location.map(ShaderIO::Location))
+}
+
+/// The builtin input values of a single compute shader invocation.
+///
+/// reference: <https://www.w3.org/TR/WGSL/#builtin-inputs-outputs>
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComputeInvocation {
+    pub local_invocation_id: [u32; 3],
+    pub local_invocation_index: u32,
+    pub global_invocation_id: [u32; 3],
+    pub workgroup_id: [u32; 3],
+    pub num_workgroups: [u32; 3],
+}
+
+impl ComputeInvocation {
+    /// get the value of a builtin input, if it is a compute shader input.
+    pub fn builtin(&self, builtin: BuiltinValue) -> Option<Instance> {
+        match builtin {
+            BuiltinValue::LocalInvocationId => Some(vec3u(self.local_invocation_id)),
+            BuiltinValue::LocalInvocationIndex => {
+                Some(LiteralInstance::U32(self.local_invocation_index).into())
+            }
+            BuiltinValue::GlobalInvocationId => Some(vec3u(self.global_invocation_id)),
+            BuiltinValue::WorkgroupId => Some(vec3u(self.workgroup_id)),
+            BuiltinValue::NumWorkgroups => Some(vec3u(self.num_workgroups)),
+            _ => None,
+        }
+    }
+
+    /// iterate over the invocations of a single workgroup, in `local_invocation_index`
+    /// order.
+    pub fn workgroup(
+        workgroup_id: [u32; 3],
+        workgroup_size: [u32; 3],
+        num_workgroups: [u32; 3],
+    ) -> impl Iterator<Item = Self> {
+        let [sx, sy, sz] = workgroup_size;
+        iproduct!(0..sz, 0..sy, 0..sx).map(move |(z, y, x)| Self {
+            local_invocation_id: [x, y, z],
+            local_invocation_index: x + y * sx + z * sx * sy,
+            global_invocation_id: [
+                workgroup_id[0] * sx + x,
+                workgroup_id[1] * sy + y,
+                workgroup_id[2] * sz + z,
+            ],
+            workgroup_id,
+            num_workgroups,
+        })
+    }
+
+    /// iterate over the workgroup ids of a dispatch.
+    pub fn workgroups(num_workgroups: [u32; 3]) -> impl Iterator<Item = [u32; 3]> {
+        let [nx, ny, nz] = num_workgroups;
+        iproduct!(0..nz, 0..ny, 0..nx).map(|(z, y, x)| [x, y, z])
+    }
+}
+
+fn vec3u(v: [u32; 3]) -> Instance {
+    VecInstance::new(v.map(|c| LiteralInstance::U32(c).into()).to_vec()).into()
+}
+
+/// get the `@workgroup_size` of a compute entrypoint. Missing dimensions default to 1.
+///
+/// The workgroup size can be an override-expression: the context must contain the
+/// module-scope declarations evaluated at the pipeline-creation stage or later.
+pub fn workgroup_size(decl: &Function, ctx: &mut Context) -> Result<[u32; 3], E> {
+    let (x, y, z) = decl.attr_workgroup_size(ctx)?;
+    Ok([x, y.unwrap_or(1), z.unwrap_or(1)])
+}
+
+/// build the arguments of an entrypoint from its input values.
+///
+/// Parameters are either decorated with a `@location` or `@builtin` attribute, or are
+/// structures with all members decorated with a `@location` or `@builtin` attribute.
+fn entry_args(
+    decl: &Function,
+    stage: ShaderStage,
+    input: impl Fn(ShaderIO) -> Option<Instance>,
+    ctx: &mut Context,
+) -> Result<Vec<Instance>, E> {
+    let input = |io: ShaderIO, ty: &Type| {
+        if !io.is_input(stage) {
+            return Err(E::StageInput(io, stage));
+        }
+        let inst = input(io).ok_or(E::MissingInput(io))?;
+        inst.convert_to(ty)
+            .ok_or_else(|| E::Type(ty.clone(), inst.ty()))
+    };
+
+    decl.parameters
+        .iter()
+        .map(|param| {
+            let ty = ty_eval_ty(&param.ty, ctx)?;
+            if let Some(io) = attr_io(&param.attributes, ctx)? {
+                return input(io, &ty);
+            }
+            match ty {
+                Type::Struct(name) => {
+                    let decl = ctx
+                        .source
+                        .decl_struct(&name)
+                        .ok_or_else(|| E::UnknownStruct(name.clone()))?;
+                    let members = decl
+                        .members
+                        .iter()
+                        .map(|m| {
+                            let io = attr_io(&m.attributes, ctx)?
+                                .ok_or_else(|| E::MissingIOAttr(m.ident.to_string()))?;
+                            let ty = ty_eval_ty(&m.ty, ctx)?;
+                            Ok((m.ident.to_string(), input(io, &ty)?))
+                        })
+                        .collect::<Result<Vec<_>, E>>()?;
+                    Ok(StructInstance::new(name, members).into())
+                }
+                _ => Err(E::MissingIOAttr(param.ident.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// map the return value of an entrypoint to its outputs.
+///
+/// The return value is either decorated with a `@location` or `@builtin` attribute, or
+/// is a structure with all members decorated with a `@location` or `@builtin` attribute.
+fn entry_outputs(
+    decl: &Function,
+    stage: ShaderStage,
+    ret: Option<Instance>,
+    ctx: &mut Context,
+) -> Result<HashMap<ShaderIO, Instance>, E> {
+    let check = |io: ShaderIO| {
+        if io.is_output(stage) {
+            Ok(io)
+        } else {
+            Err(E::StageOutput(io, stage))
+        }
+    };
+
+    let Some(ret) = ret else {
+        return Ok(HashMap::new());
+    };
+    if let Some(io) = attr_io(&decl.return_attributes, ctx)? {
+        return Ok(HashMap::from_iter([(check(io)?, ret)]));
+    }
+    match ret {
+        Instance::Struct(s) => {
+            let decl = ctx
+                .source
+                .decl_struct(s.name())
+                .ok_or_else(|| E::UnknownStruct(s.name().to_string()))?;
+            decl.members
+                .iter()
+                .map(|m| {
+                    let io = attr_io(&m.attributes, ctx)?
+                        .ok_or_else(|| E::MissingIOAttr(m.ident.to_string()))?;
+                    let inst = s
+                        .member(&m.ident.name())
+                        .expect("struct instance has the declared members")
+                        .clone();
+                    Ok((check(io)?, inst))
+                })
+                .collect()
+        }
+        _ => Err(E::MissingIOAttr(decl.ident.to_string())),
+    }
+}
+
+/// Execute a single invocation of a compute entrypoint.
+///
+/// The context must contain the module-scope declarations (see [`super::Exec`]).
+pub fn exec_compute(
+    decl: &Function,
+    invocation: &ComputeInvocation,
+    ctx: &mut Context,
+) -> Result<(), E> {
+    if ShaderStage::of(decl) != Some(ShaderStage::Compute) {
+        return Err(E::NotEntrypoint(
+            decl.ident.to_string(),
+            ShaderStage::Compute,
+        ));
+    }
+    let input = |io| match io {
+        ShaderIO::Builtin(b) => invocation.builtin(b),
+        ShaderIO::Location(_) => None,
+    };
+    let args = entry_args(decl, ShaderStage::Compute, input, ctx)?;
+    call_fn(decl, args, ctx)?;
+    Ok(())
+}
+
+/// Execute a single invocation of a vertex entrypoint.
+///
+/// `inputs` are the vertex attributes (`@location`) and builtin input values
+/// (`vertex_index` and `instance_index`) used by the entrypoint. Returns the outputs
+/// of the entrypoint.
+///
+/// The context must contain the module-scope declarations (see [`super::Exec`]).
+pub fn exec_vertex(
+    decl: &Function,
+    inputs: &HashMap<ShaderIO, Instance>,
+    ctx: &mut Context,
+) -> Result<HashMap<ShaderIO, Instance>, E> {
+    if ShaderStage::of(decl) != Some(ShaderStage::Vertex) {
+        return Err(E::NotEntrypoint(
+            decl.ident.to_string(),
+            ShaderStage::Vertex,
+        ));
+    }
+    let args = entry_args(
+        decl,
+        ShaderStage::Vertex,
+        |io| inputs.get(&io).cloned(),
+        ctx,
+    )?;
+    let ret = call_fn(decl, args, ctx)?;
+    entry_outputs(decl, ShaderStage::Vertex, ret, ctx)
+}
+
+/// Execute a single invocation of a fragment entrypoint.
+///
+/// `inputs` are the inter-stage variables (`@location`) and builtin input values
+/// (`position`, `front_facing`, `sample_index` and `sample_mask`) used by the
+/// entrypoint. Returns the outputs of the entrypoint, or `None` if the fragment was
+/// discarded.
+///
+/// The context must contain the module-scope declarations (see [`super::Exec`]).
+pub fn exec_fragment(
+    decl: &Function,
+    inputs: &HashMap<ShaderIO, Instance>,
+    ctx: &mut Context,
+) -> Result<Option<HashMap<ShaderIO, Instance>>, E> {
+    if ShaderStage::of(decl) != Some(ShaderStage::Fragment) {
+        return Err(E::NotEntrypoint(
+            decl.ident.to_string(),
+            ShaderStage::Fragment,
+        ));
+    }
+    let args = entry_args(
+        decl,
+        ShaderStage::Fragment,
+        |io| inputs.get(&io).cloned(),
+        ctx,
+    )?;
+    let ret = match call_fn(decl, args, ctx) {
+        Ok(ret) => ret,
+        Err(E::Discard) => {
+            ctx.err_decl = None;
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    entry_outputs(decl, ShaderStage::Fragment, ret, ctx).map(Some)
+}
+
+/// Schedules the invocations of a workgroup, which run in separate threads.
+///
+/// Only one invocation runs at a time, in `local_invocation_index` order. The running
+/// invocation passes the turn to the next one when it reaches a barrier or terminates.
+/// Each invocation therefore executes up to its next barrier before any invocation
+/// crosses it, and the execution is deterministic.
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+    cvar: Condvar,
+}
+
+struct SchedulerState {
+    turn: usize,
+    done: Vec<bool>,
+    aborted: bool,
+}
+
+impl Scheduler {
+    fn new(n: usize) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                turn: 0,
+                done: vec![false; n],
+                aborted: false,
+            }),
+            cvar: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SchedulerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait_turn(&self, id: usize, state: MutexGuard<'_, SchedulerState>) -> Result<(), E> {
+        let state = self
+            .cvar
+            .wait_while(state, |s| s.turn != id && !s.aborted)
+            .unwrap_or_else(PoisonError::into_inner);
+        if state.aborted {
+            Err(E::InvocationAborted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// give the turn to the next invocation that has not terminated, possibly itself.
+    fn pass_turn(&self, id: usize, state: &mut SchedulerState) {
+        let n = state.done.len();
+        if let Some(next) = (1..=n).map(|i| (id + i) % n).find(|i| !state.done[*i]) {
+            state.turn = next;
+        }
+        self.cvar.notify_all();
+    }
+}
+
+/// A handle to the [`Scheduler`] of the workgroup, owned by the [`Context`] of an
+/// invocation.
+#[derive(Clone)]
+pub(crate) struct Barrier {
+    scheduler: Arc<Scheduler>,
+    id: usize,
+}
+
+impl Barrier {
+    /// wait for all other invocations of the workgroup to reach a barrier or terminate.
+    pub(crate) fn wait(&self) -> Result<(), E> {
+        let mut state = self.scheduler.lock();
+        self.scheduler.pass_turn(self.id, &mut state);
+        self.scheduler.wait_turn(self.id, state)
+    }
+
+    fn start(&self) -> Result<(), E> {
+        self.scheduler.wait_turn(self.id, self.scheduler.lock())
+    }
+}
+
+/// Terminates the invocation when dropped, even if it panicked.
+struct Termination {
+    barrier: Barrier,
+    failed: bool,
+}
+
+impl Drop for Termination {
+    fn drop(&mut self) {
+        let mut state = self.barrier.scheduler.lock();
+        state.done[self.barrier.id] = true;
+        state.aborted |= self.failed || std::thread::panicking();
+        self.barrier
+            .scheduler
+            .pass_turn(self.barrier.id, &mut state);
+    }
+}
+
+/// Dispatch a compute entrypoint, with `workgroups` the number of workgroups in each
+/// dimension.
+///
+/// The context must contain the module-scope declarations evaluated at the execution
+/// stage (see [`super::Exec`]), and serves as a template for the context of each
+/// invocation: they share its shader resources and overrides, but have their own private
+/// variables. Variables in the `workgroup` address space are shared by the invocations of
+/// a workgroup.
+///
+/// The invocations of a workgroup run in separate threads, but one at a time and in a
+/// deterministic order: each invocation runs up to its next barrier (`workgroupBarrier`,
+/// `storageBarrier`, `textureBarrier` or `workgroupUniformLoad`) before the next
+/// invocation is resumed. Workgroups are run one after the other.
+pub fn dispatch_compute(decl: &Function, workgroups: [u32; 3], ctx: &mut Context) -> Result<(), E> {
+    if ShaderStage::of(decl) != Some(ShaderStage::Compute) {
+        return Err(E::NotEntrypoint(
+            decl.ident.to_string(),
+            ShaderStage::Compute,
+        ));
+    }
+    let workgroup_size = workgroup_size(decl, ctx)?;
+
+    for workgroup_id in ComputeInvocation::workgroups(workgroups) {
+        // the workgroup variables are zero-initialized when the module is executed.
+        let workgroup = {
+            let mut wg_ctx = invocation_ctx(
+                ctx.source,
+                ctx.resources.clone(),
+                ctx.overrides.clone(),
+                HashMap::new(),
+            );
+            ctx.source.exec(&mut wg_ctx)?;
+            wg_ctx.workgroup
+        };
+
+        let invocations =
+            ComputeInvocation::workgroup(workgroup_id, workgroup_size, workgroups).collect_vec();
+        let scheduler = Arc::new(Scheduler::new(invocations.len()));
+        let source = ctx.source;
+
+        let results = std::thread::scope(|s| {
+            let threads = invocations
+                .iter()
+                .enumerate()
+                .map(|(id, invocation)| {
+                    let barrier = Barrier {
+                        scheduler: scheduler.clone(),
+                        id,
+                    };
+                    let resources = ctx.resources.clone();
+                    let overrides = ctx.overrides.clone();
+                    let workgroup = workgroup.clone();
+                    // the context is not `Send`, it is created in the invocation thread.
+                    s.spawn(move || {
+                        let inv_ctx = invocation_ctx(source, resources, overrides, workgroup);
+                        exec_invocation(decl, invocation, barrier, inv_ctx)
+                    })
+                })
+                .collect_vec();
+            threads
+                .into_iter()
+                .map(|t| t.join().expect("invocation panicked"))
+                .collect_vec()
+        });
+
+        // the first error is the one that aborted the other invocations.
+        if let Some(err) = results
+            .into_iter()
+            .filter_map(Result::err)
+            .find(|err| !matches!(err.error, E::InvocationAborted))
+        {
+            ctx.err_decl = err.decl;
+            ctx.err_span = err.span;
+            return Err(err.error);
+        }
+    }
+
+    Ok(())
+}
+
+fn invocation_ctx<'s>(
+    source: &'s TranslationUnit,
+    resources: HashMap<(u32, u32), RefInstance>,
+    overrides: HashMap<String, Instance>,
+    workgroup: HashMap<String, RefInstance>,
+) -> Context<'s> {
+    let mut inv_ctx = Context::new(source);
+    inv_ctx.add_bindings(resources);
+    inv_ctx.add_overrides(overrides);
+    inv_ctx.workgroup = workgroup;
+    inv_ctx.set_stage(EvalStage::Exec);
+    inv_ctx
+}
+
+/// An error in an invocation thread, with the error context of the invocation.
+struct InvocationError {
+    error: E,
+    decl: Option<String>,
+    span: Option<Span>,
+}
+
+/// Run a single invocation, in its own thread.
+fn exec_invocation(
+    decl: &Function,
+    invocation: &ComputeInvocation,
+    barrier: Barrier,
+    mut ctx: Context,
+) -> Result<(), Box<InvocationError>> {
+    let mut termination = Termination {
+        barrier: barrier.clone(),
+        failed: false,
+    };
+    ctx.barrier = Some(barrier);
+    let res = ctx
+        .barrier
+        .as_ref()
+        .expect("invocation has a barrier")
+        .start()
+        .and_then(|_| {
+            ctx.source.exec(&mut ctx)?;
+            exec_compute(decl, invocation, &mut ctx)
+        });
+    termination.failed = res.is_err();
+    drop(termination);
+    res.map_err(|error| {
+        Box::new(InvocationError {
+            error,
+            decl: ctx.err_decl,
+            span: ctx.err_span,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eval::ArrayInstance, CompileResult};
+    use wgsl_parse::syntax::{AccessMode, AddressSpace};
+
+    #[test]
+    fn test_dispatch() {
+        let source = "@group(0) @binding(0) var<storage, read_write> out: array<u32, 8>;
+            @compute @workgroup_size(2, 2)
+            fn main(@builtin(global_invocation_id) gid: vec3u) {
+                out[gid.x + gid.y * 4u] = gid.x * 10u + gid.y;
+            }";
+        let comp = CompileResult {
+            syntax: source.parse().unwrap(),
+            sourcemap: None,
+            deps: Default::default(),
+            warnings: Vec::new(),
+        };
+        let zeros = vec![LiteralInstance::U32(0).into(); 8];
+        let out = RefInstance::new(
+            ArrayInstance::new(zeros, false).into(),
+            AddressSpace::Storage(Some(AccessMode::ReadWrite)),
+            AccessMode::ReadWrite,
+        );
+        let bindings = HashMap::from_iter([((0, 0), out)]);
+        let res = comp
+            .dispatch("main", [2, 1, 1], bindings, Default::default())
+            .unwrap();
+        let out = res.resource(0, 0).unwrap().read().unwrap().to_owned();
+        let expected = [0, 10, 20, 30, 1, 11, 21, 31].map(|n| LiteralInstance::U32(n).into());
+        assert_eq!(
+            out,
+            Instance::from(ArrayInstance::new(expected.to_vec(), false))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_barrier() {
+        // each invocation reads the value written by another invocation before the barrier.
+        let source = "@group(0) @binding(0) var<storage, read_write> out: array<u32, 4>;
+            var<workgroup> tile: array<u32, 4>;
+            @compute @workgroup_size(4)
+            fn main(@builtin(local_invocation_index) lid: u32) {
+                tile[lid] = lid + 1u;
+                workgroupBarrier();
+                out[lid] = tile[3u - lid];
+            }";
+        let comp = CompileResult {
+            syntax: source.parse().unwrap(),
+            sourcemap: None,
+            deps: Default::default(),
+            warnings: Vec::new(),
+        };
+        let zeros = vec![LiteralInstance::U32(0).into(); 4];
+        let out = RefInstance::new(
+            ArrayInstance::new(zeros, false).into(),
+            AddressSpace::Storage(Some(AccessMode::ReadWrite)),
+            AccessMode::ReadWrite,
+        );
+        let bindings = HashMap::from_iter([((0, 0), out)]);
+        let res = comp
+            .dispatch("main", [1, 1, 1], bindings, Default::default())
+            .unwrap();
+        let out = res.resource(0, 0).unwrap().read().unwrap().to_owned();
+        let expected = [4, 3, 2, 1].map(|n| LiteralInstance::U32(n).into());
+        assert_eq!(
+            out,
+            Instance::from(ArrayInstance::new(expected.to_vec(), false))
+        );
+    }
+
+    #[test]
+    fn test_dispatch_texture() {
+        use crate::eval::{
+            FilterMode, SampledType, SamplerInstance, SamplerType, TexelFormat, TextureInstance,
+            TextureType,
+        };
+
+        // each invocation averages its texel with the (linearly filtered) center of the image.
+        let source = "@group(0) @binding(0) var img: texture_2d<f32>;
+            @group(0) @binding(1) var smp: sampler;
+            @group(0) @binding(2) var out: texture_storage_2d<rgba8unorm, write>;
+            @compute @workgroup_size(2, 2)
+            fn main(@builtin(local_invocation_id) lid: vec3u) {
+                let texel = textureLoad(img, lid.xy, 0);
+                let center = textureSampleLevel(img, smp, vec2f(0.5), 0.0);
+                textureStore(out, lid.xy, (texel + center) * 0.5);
+            }";
+        let comp = CompileResult {
+            syntax: source.parse().unwrap(),
+            sourcemap: None,
+            deps: Default::default(),
+            warnings: Vec::new(),
+        };
+        let fmt = TexelFormat::Rgba8Unorm;
+        let img = TextureInstance::from_bytes(
+            TextureType::Sampled2D(SampledType::F32),
+            fmt,
+            [2, 2, 1],
+            &[
+                255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255,
+            ],
+        )
+        .unwrap();
+        let smp = SamplerInstance::new(SamplerType::Sampler).with_filter(FilterMode::Linear);
+        let out = TextureInstance::from_bytes(
+            TextureType::Storage2D(fmt, AccessMode::Write),
+            fmt,
+            [2, 2, 1],
+            &[0; 16],
+        )
+        .unwrap();
+        let handle = |inst| RefInstance::new(inst, AddressSpace::Handle, AccessMode::Read);
+        let bindings = HashMap::from_iter([
+            ((0, 0), handle(img.into())),
+            ((0, 1), handle(smp.into())),
+            ((0, 2), handle(out.clone().into())),
+        ]);
+        comp.dispatch("main", [1, 1, 1], bindings, Default::default())
+            .unwrap();
+        assert_eq!(
+            out.to_bytes(fmt, 0).unwrap(),
+            [191, 64, 64, 255, 64, 191, 64, 255, 64, 64, 191, 255, 191, 191, 191, 255]
+        );
+    }
+
+    #[test]
+    fn test_exec_vertex_fragment() {
+        let source = "struct VertexOut {
+                @builtin(position) position: vec4f,
+                @location(0) color: vec3f,
+            }
+            @vertex
+            fn vs(@builtin(vertex_index) index: u32, @location(0) pos: vec2f) -> VertexOut {
+                let color = array(vec3f(1.0, 0.0, 0.0), vec3f(0.0, 1.0, 0.0));
+                return VertexOut(vec4f(pos * 2.0, 0.0, 1.0), color[index]);
+            }
+            @fragment
+            fn fs(@location(0) color: vec3f, @builtin(front_facing) front: bool) -> @location(0) vec4f {
+                if !front {
+                    discard;
+                }
+                return vec4f(color * 0.5, 1.0);
+            }";
+        let comp = CompileResult {
+            syntax: source.parse().unwrap(),
+            sourcemap: None,
+            deps: Default::default(),
+            warnings: Vec::new(),
+        };
+        let vec = |v: &[f32]| -> Instance {
+            VecInstance::new(v.iter().map(|f| LiteralInstance::F32(*f).into()).collect()).into()
+        };
+
+        let inputs = HashMap::from_iter([
+            (
+                ShaderIO::Builtin(BuiltinValue::VertexIndex),
+                LiteralInstance::U32(1).into(),
+            ),
+            (ShaderIO::Location(0), vec(&[0.5, -0.25])),
+        ]);
+        let res = comp
+            .exec_vertex("vs", inputs, Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(
+            res.builtin(BuiltinValue::Position),
+            Some(&vec(&[1.0, -0.5, 0.0, 1.0]))
+        );
+        assert_eq!(res.location(0), Some(&vec(&[0.0, 1.0, 0.0])));
+
+        let mut inputs = HashMap::from_iter([
+            (ShaderIO::Location(0), vec(&[0.0, 1.0, 0.0])),
+            (
+                ShaderIO::Builtin(BuiltinValue::FrontFacing),
+                LiteralInstance::Bool(true).into(),
+            ),
+        ]);
+        let res = comp
+            .exec_fragment("fs", inputs.clone(), Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(res.location(0), Some(&vec(&[0.0, 0.5, 0.0, 1.0])));
+
+        inputs.insert(
+            ShaderIO::Builtin(BuiltinValue::FrontFacing),
+            LiteralInstance::Bool(false).into(),
+        );
+        let res = comp
+            .exec_fragment("fs", inputs, Default::default(), Default::default())
+            .unwrap();
+        assert!(res.is_discarded());
+    }
+}
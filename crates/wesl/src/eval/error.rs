@@ -2,7 +2,10 @@ use itertools::Itertools;
 use thiserror::Error;
 use wgsl_parse::syntax::*;
 
-use super::{EvalStage, Flow, Instance, LiteralInstance, MemView, ScopeKind, Type};
+use super::{
+    EvalStage, Flow, Instance, LiteralInstance, MemView, ScopeKind, ShaderIO, ShaderStage,
+    TextureType, Type,
+};
 
 /// Evaluation and Execution errors.
 #[derive(Clone, Debug, Error)]
@@ -97,8 +100,14 @@ pub enum EvalError {
     NotCallable(String),
     #[error("invalid function call signature: `{0}({})`", (.1).iter().format(", "))]
     Signature(TypeExpression, Vec<Type>),
+    #[error("no overload of `{0}` accepts the arguments `({})`", (.1).iter().format(", "))]
+    NoOverload(String, Vec<Type>),
+    #[error("ambiguous call to overloaded function `{0}` with arguments `({})`", (.1).iter().format(", "))]
+    AmbiguousOverload(String, Vec<Type>),
     #[error("{0}")]
     Builtin(&'static str),
+    #[error("invalid `{0}`: {1}")]
+    InvalidTexture(TextureType, String),
     #[error("invalid template arguments to `{0}`")]
     TemplateArgs(&'static str),
     #[error("incorrect number of arguments to `{0}`, expected `{1}`, got `{2}`")]
@@ -107,6 +116,8 @@ pub enum EvalError {
     ParamType(Type, Type),
     #[error("returned `{0}` from function `{1}` that returns `{2}`")]
     ReturnType(Type, String, Type),
+    #[error("returned `{0}` from function `{1}` that has no return type")]
+    UnexpectedReturn(Type, String),
     #[error("call to function `{0}` did not return any value, expected `{1}`")]
     NoReturn(String, Type),
     #[error("calling non-const function `{0}` in const context")]
@@ -146,6 +157,20 @@ pub enum EvalError {
     #[error("incorrect resource access mode, expected `{0}`, got `{1}`")]
     AccessMode(AccessMode, AccessMode),
 
+    // entrypoints
+    #[error("function `{0}` is not a {1} entrypoint")]
+    NotEntrypoint(String, ShaderStage),
+    #[error("entrypoint IO `{0}` is missing a `@location` or `@builtin` attribute")]
+    MissingIOAttr(String),
+    #[error("`{0}` is not an input of the {1} shader stage")]
+    StageInput(ShaderIO, ShaderStage),
+    #[error("`{0}` is not an output of the {1} shader stage")]
+    StageOutput(ShaderIO, ShaderStage),
+    #[error("missing entrypoint input value for `{0}`")]
+    MissingInput(ShaderIO),
+    #[error("the invocation was aborted because another invocation of the workgroup failed")]
+    InvocationAborted,
+
     // attributes
     #[error("missing `@group` or `@binding` attributes")]
     MissingBindAttr,
@@ -161,6 +186,8 @@ pub enum EvalError {
     NotRef(Instance),
     #[error("cannot assign a `{0}` to a `{1}`")]
     AssignType(Type, Type),
+    #[error("cannot assign to `{0}`, it is not a reference")]
+    NotAssignable(ExpressionNode),
     #[error("cannot increment a `{0}`")]
     IncrType(Type),
     #[error("attempt to increment with overflow")]
@@ -173,6 +200,8 @@ pub enum EvalError {
     FlowInContinuing(Flow),
     #[error("discard statements are not permitted in const contexts")]
     DiscardInConst,
+    #[error("discard statements are only permitted in fragment shaders")]
+    Discard,
     #[error("const assertion failed: `{0}` is `false`")]
     ConstAssertFailure(ExpressionNode),
     #[error("a function body cannot contain a `{0}` statement")]
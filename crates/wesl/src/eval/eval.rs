@@ -1,9 +1,9 @@
 use std::iter::zip;
 
 use super::{
-    call_builtin, compound_exec_no_scope, is_constructor_fn, ty_eval_ty, with_scope, Context,
-    Convert, EvalError, EvalStage, Flow, Instance, LiteralInstance, PtrInstance, RefInstance,
-    StructInstance, SyntaxUtil, Ty, Type, VecInstance, ATTR_INTRINSIC,
+    call_builtin, compound_exec_no_scope, is_constructor_fn, is_swizzle, ty_eval_ty, with_scope,
+    Context, Convert, EvalError, EvalStage, Flow, Instance, LiteralInstance, PtrInstance,
+    RefInstance, StructInstance, SyntaxUtil, Ty, Type, VecInstance, ATTR_INTRINSIC,
 };
 
 use half::f16;
@@ -91,7 +91,7 @@ impl Eval for ParenthesizedExpression {
 impl Eval for NamedComponentExpression {
     fn eval(&self, ctx: &mut Context) -> Result<Instance, E> {
         fn vec_comp(v: &VecInstance, comp: &str, r: Option<&RefInstance>) -> Result<Instance, E> {
-            if !check_swizzle(comp) {
+            if !is_swizzle(comp, v.n() as u8) {
                 return Err(E::Swizzle(comp.to_string()));
             }
             let indices = comp
@@ -101,7 +101,7 @@ impl Eval for NamedComponentExpression {
                     'y' | 'g' => 1usize,
                     'z' | 'b' => 2usize,
                     'w' | 'a' => 3usize,
-                    _ => unreachable!(), // SAFETY: check_swizzle above checks it.
+                    _ => unreachable!(), // SAFETY: is_swizzle above checks it.
                 })
                 .collect_vec();
             if let [i] = indices.as_slice() {
@@ -283,52 +283,7 @@ impl Eval for FunctionCall {
                     return call_builtin(ty, args, ctx);
                 }
 
-                if self.arguments.len() != decl.parameters.len() {
-                    return Err(E::ParamCount(
-                        decl.ident.to_string(),
-                        decl.parameters.len(),
-                        self.arguments.len(),
-                    ));
-                }
-
-                let ret_ty = decl
-                    .return_type
-                    .as_ref()
-                    .ok_or_else(|| E::Void(fn_name.clone()))?;
-                let ret_ty = ty_eval_ty(ret_ty, ctx)?;
-
-                let flow = with_scope!(ctx, {
-                    let args = args
-                        .iter()
-                        .zip(&decl.parameters)
-                        .map(|(arg, param)| {
-                            let param_ty = ty_eval_ty(&param.ty, ctx)?;
-                            arg.convert_to(&param_ty)
-                                .ok_or_else(|| E::ParamType(param_ty.clone(), arg.ty()))
-                        })
-                        .collect::<Result<Vec<_>, _>>()
-                        .inspect_err(|_| ctx.set_err_decl_ctx(fn_name.clone()))?;
-
-                    for (a, p) in zip(args, &decl.parameters) {
-                        ctx.scope.add(p.ident.to_string(), a);
-                    }
-
-                    // the arguments must be in the same scope as the function body.
-                    let flow = compound_exec_no_scope(&decl.body, ctx)
-                        .inspect_err(|_| ctx.set_err_decl_ctx(fn_name.clone()))?;
-
-                    Ok(flow)
-                })?;
-
-                match flow {
-                    Flow::Next => Err(E::NoReturn(fn_name, ret_ty)),
-                    Flow::Break | Flow::Continue => Err(E::FlowInFunction(flow)),
-                    Flow::Return(Some(inst)) => inst
-                        .convert_to(&ret_ty)
-                        .ok_or(E::ReturnType(inst.ty(), fn_name.clone(), ret_ty))
-                        .inspect_err(|_| ctx.set_err_decl_ctx(fn_name)),
-                    Flow::Return(None) => Err(E::NoReturn(fn_name, ret_ty)),
-                }
+                call_fn(decl, args, ctx)?.ok_or(E::Void(fn_name))
             }
             // struct constructor
             else if let GlobalDeclaration::Struct(decl) = decl {
@@ -384,9 +339,61 @@ impl Eval for TemplateArg {
     }
 }
 
-fn check_swizzle(swizzle: &str) -> bool {
-    // reference: https://www.w3.org/TR/WGSL/#swizzle-names
-    (1..=4).contains(&swizzle.len())
-        && (swizzle.chars().all(|c| "xyzw".contains(c))
-            || swizzle.chars().all(|c| "rgba".contains(c)))
+/// Call a user-defined function with evaluated arguments.
+///
+/// Returns `None` if the function has no return type.
+pub fn call_fn(
+    decl: &Function,
+    args: Vec<Instance>,
+    ctx: &mut Context,
+) -> Result<Option<Instance>, E> {
+    let fn_name = decl.ident.to_string();
+
+    if args.len() != decl.parameters.len() {
+        return Err(E::ParamCount(fn_name, decl.parameters.len(), args.len()));
+    }
+
+    let ret_ty = decl
+        .return_type
+        .as_ref()
+        .map(|ty| ty_eval_ty(ty, ctx))
+        .transpose()?;
+
+    let flow = with_scope!(ctx, {
+        let args = args
+            .iter()
+            .zip(&decl.parameters)
+            .map(|(arg, param)| {
+                let param_ty = ty_eval_ty(&param.ty, ctx)?;
+                arg.convert_to(&param_ty)
+                    .ok_or_else(|| E::ParamType(param_ty.clone(), arg.ty()))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .inspect_err(|_| ctx.set_err_decl_ctx(fn_name.clone()))?;
+
+        for (a, p) in zip(args, &decl.parameters) {
+            ctx.scope.add(p.ident.to_string(), a);
+        }
+
+        // the arguments must be in the same scope as the function body.
+        let flow = compound_exec_no_scope(&decl.body, ctx)
+            .inspect_err(|_| ctx.set_err_decl_ctx(fn_name.clone()))?;
+
+        Ok(flow)
+    })?;
+
+    match (flow, ret_ty) {
+        (Flow::Next | Flow::Return(None), None) => Ok(None),
+        (Flow::Return(Some(inst)), None) => {
+            ctx.set_err_decl_ctx(fn_name.clone());
+            Err(E::UnexpectedReturn(inst.ty(), fn_name))
+        }
+        (Flow::Next | Flow::Return(None), Some(ret_ty)) => Err(E::NoReturn(fn_name, ret_ty)),
+        (flow @ (Flow::Break | Flow::Continue), _) => Err(E::FlowInFunction(flow)),
+        (Flow::Return(Some(inst)), Some(ret_ty)) => inst
+            .convert_to(&ret_ty)
+            .map(Some)
+            .ok_or(E::ReturnType(inst.ty(), fn_name.clone(), ret_ty))
+            .inspect_err(|_| ctx.set_err_decl_ctx(fn_name)),
+    }
 }
@@ -493,8 +493,12 @@ impl Exec for ReturnStatement {
 }
 
 impl Exec for DiscardStatement {
-    fn exec(&self, _ctx: &mut Context) -> Result<Flow, E> {
-        Err(E::DiscardInConst)
+    fn exec(&self, ctx: &mut Context) -> Result<Flow, E> {
+        // the invocation is terminated, see `exec_fragment`.
+        match ctx.stage {
+            EvalStage::Exec => Err(E::Discard),
+            _ => Err(E::DiscardInConst),
+        }
     }
 }
 
@@ -691,8 +695,44 @@ impl Exec for Declaration {
                             }
                             ctx.scope.add(self.ident.to_string(), inst.clone().into())
                         }
-                        AddressSpace::Workgroup => todo!("workgroup address space"),
-                        AddressSpace::Handle => todo!("handle address space"),
+                        AddressSpace::Workgroup => {
+                            if self.initializer.is_some() {
+                                return Err(E::ForbiddenInitializer(addr_space));
+                            }
+                            // workgroup variables are zero-initialized once per workgroup.
+                            let inst = match ctx.workgroup_var(&self.ident.name()) {
+                                Some(inst) => inst.clone(),
+                                None => {
+                                    let inst = RefInstance::new(
+                                        Instance::zero_value(&ty, ctx)?,
+                                        AddressSpace::Workgroup,
+                                        AccessMode::ReadWrite,
+                                    );
+                                    ctx.add_workgroup_var(self.ident.to_string(), inst.clone());
+                                    inst
+                                }
+                            };
+                            if inst.ty() != ty {
+                                return Err(E::Type(ty, inst.ty()));
+                            }
+                            ctx.scope.add(self.ident.to_string(), inst.into())
+                        }
+                        AddressSpace::Handle => {
+                            if self.initializer.is_some() {
+                                return Err(E::ForbiddenInitializer(addr_space));
+                            }
+                            let (group, binding) = self.attr_group_binding(ctx)?;
+                            let inst = ctx
+                                .resource(group, binding)
+                                .ok_or(E::MissingResource(group, binding))?;
+                            if inst.ty() != ty {
+                                return Err(E::Type(ty, inst.ty()));
+                            }
+                            if inst.space != AddressSpace::Handle {
+                                return Err(E::AddressSpace(addr_space, inst.space));
+                            }
+                            ctx.scope.add(self.ident.to_string(), inst.clone().into())
+                        }
                     }
                 }
             }
@@ -1,7 +1,7 @@
 use std::{
-    cell::{Ref, RefCell, RefMut},
-    ops::Index,
-    rc::Rc,
+    fmt::Display,
+    ops::{Deref, DerefMut, Index},
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 use derive_more::derive::{From, IsVariant, Unwrap};
@@ -11,7 +11,7 @@ use wgsl_parse::syntax::{AccessMode, AddressSpace};
 
 use crate::eval::Ty;
 
-use super::{EvalError, Type};
+use super::{EvalError, SamplerInstance, TextureInstance, Type};
 
 type E = EvalError;
 
@@ -26,6 +26,8 @@ pub enum Instance {
     Ptr(PtrInstance),
     Ref(RefInstance),
     Atomic(AtomicInstance),
+    Texture(TextureInstance),
+    Sampler(SamplerInstance),
     /// for instances that cannot be computed at the current eval stage, we still store the type.
     Deferred(Type),
 }
@@ -338,13 +340,75 @@ impl From<RefInstance> for PtrInstance {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// A reference to a memory location.
+///
+/// The memory is behind an [`Arc`] so that it can be shared between the invocations of a
+/// compute shader dispatch, which run in separate threads (see [`super::dispatch_compute`]).
+#[derive(Clone, Debug)]
 pub struct RefInstance {
     pub ty: Type,
     pub space: AddressSpace,
     pub access: AccessMode,
     pub view: MemView,
-    pub ptr: Rc<RefCell<Instance>>,
+    pub ptr: Arc<RwLock<Instance>>,
+}
+
+impl PartialEq for RefInstance {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty
+            && self.space == other.space
+            && self.access == other.access
+            && self.view == other.view
+            && (Arc::ptr_eq(&self.ptr, &other.ptr)
+                || *lock_read(&self.ptr) == *lock_read(&other.ptr))
+    }
+}
+
+// a panicking invocation must not make the memory unusable for the others.
+fn lock_read(ptr: &RwLock<Instance>) -> RwLockReadGuard<'_, Instance> {
+    ptr.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn lock_write(ptr: &RwLock<Instance>) -> RwLockWriteGuard<'_, Instance> {
+    ptr.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A read guard on the memory viewed by a [`RefInstance`]. See [`RefInstance::read`].
+pub struct RefGuard<'a> {
+    guard: RwLockReadGuard<'a, Instance>,
+    view: &'a MemView,
+}
+
+impl Deref for RefGuard<'_> {
+    type Target = Instance;
+    fn deref(&self) -> &Self::Target {
+        self.guard.view(self.view).expect("invalid reference")
+    }
+}
+
+impl Display for RefGuard<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// A write guard on the memory viewed by a [`RefInstance`]. See [`RefInstance::read_write`].
+pub struct RefMutGuard<'a> {
+    guard: RwLockWriteGuard<'a, Instance>,
+    view: &'a MemView,
+}
+
+impl Deref for RefMutGuard<'_> {
+    type Target = Instance;
+    fn deref(&self) -> &Self::Target {
+        self.guard.view(self.view).expect("invalid reference")
+    }
+}
+
+impl DerefMut for RefMutGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.guard.view_mut(self.view).expect("invalid reference")
+    }
 }
 
 impl RefInstance {
@@ -355,7 +419,7 @@ impl RefInstance {
             space,
             access,
             view: MemView::Whole,
-            ptr: Rc::new(RefCell::new(inst)),
+            ptr: Arc::new(RwLock::new(inst)),
         }
     }
 }
@@ -374,7 +438,7 @@ impl RefInstance {
         }
         let mut view = self.view.clone();
         view.append_member(comp);
-        let ty = self.ptr.borrow().view(&view)?.ty();
+        let ty = lock_read(&self.ptr).view(&view)?.ty();
         Ok(Self {
             ty,
             space: self.space,
@@ -390,7 +454,7 @@ impl RefInstance {
         }
         let mut view = self.view.clone();
         view.append_index(index);
-        let ty = self.ptr.borrow().view(&view)?.ty();
+        let ty = lock_read(&self.ptr).view(&view)?.ty();
         Ok(Self {
             ty,
             space: self.space,
@@ -400,13 +464,14 @@ impl RefInstance {
         })
     }
 
-    pub fn read<'a>(&'a self) -> Result<Ref<'a, Instance>, E> {
+    pub fn read(&self) -> Result<RefGuard<'_>, E> {
         if !self.access.is_read() {
             return Err(E::NotRead);
         }
-        Ok(Ref::<'a, Instance>::map(self.ptr.borrow(), |r| {
-            r.view(&self.view).expect("invalid reference")
-        }))
+        Ok(RefGuard {
+            guard: lock_read(&self.ptr),
+            view: &self.view,
+        })
     }
 
     pub fn write(&mut self, value: Instance) -> Result<(), E> {
@@ -416,20 +481,21 @@ impl RefInstance {
         if value.ty() != self.ty() {
             return Err(E::WriteRefType(value.ty(), self.ty()));
         }
-        let mut r = self.ptr.borrow_mut();
+        let mut r = lock_write(&self.ptr);
         let view = r.view_mut(&self.view).expect("invalid reference");
         assert!(view.ty() == value.ty());
         let _ = std::mem::replace(view, value);
         Ok(())
     }
 
-    pub fn read_write<'a>(&'a mut self) -> Result<RefMut<'a, Instance>, E> {
+    pub fn read_write(&mut self) -> Result<RefMutGuard<'_>, E> {
         if !self.access.is_write() {
             return Err(E::NotReadWrite);
         }
-        Ok(RefMut::<'a, Instance>::map(self.ptr.borrow_mut(), |r| {
-            r.view_mut(&self.view).expect("invalid reference")
-        }))
+        Ok(RefMutGuard {
+            guard: lock_write(&self.ptr),
+            view: &self.view,
+        })
     }
 }
 
@@ -178,7 +178,7 @@ impl Lower for Attributes {
                     expr.lower(ctx)?;
                 }
                 #[cfg(feature = "condcomp")]
-                Attribute::If(expr) => {
+                Attribute::If(expr) | Attribute::Elif(expr) => {
                     expr.lower(ctx)?;
                 }
                 Attribute::WorkgroupSize(attr) => {
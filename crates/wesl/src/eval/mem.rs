@@ -23,6 +23,7 @@ impl HostShareable for Instance {
             Instance::Ptr(_) => None,
             Instance::Ref(_) => None,
             Instance::Atomic(a) => a.inner().to_buffer(ctx),
+            Instance::Texture(_) | Instance::Sampler(_) => None,
             Instance::Deferred(_) => None,
         }
     }
@@ -3,6 +3,7 @@ mod builtin;
 mod constant;
 mod conv;
 mod display;
+mod entry;
 mod error;
 mod eval;
 mod exec;
@@ -10,6 +11,7 @@ mod instance;
 mod lower;
 mod mem;
 mod ops;
+mod texture;
 mod to_expr;
 mod ty;
 
@@ -17,12 +19,14 @@ pub use attrs::*;
 pub use builtin::*;
 pub(crate) use constant::*;
 pub use conv::*;
+pub use entry::*;
 pub use error::*;
 pub use eval::*;
 pub use exec::*;
 pub use instance::*;
 pub use lower::*;
 pub use mem::*;
+pub use texture::*;
 pub use to_expr::*;
 pub use ty::*;
 
@@ -160,8 +164,16 @@ pub struct Context<'s> {
     pub(crate) scope: Scope<Instance>,
     pub(crate) resources: HashMap<(u32, u32), RefInstance>,
     pub(crate) overrides: HashMap<String, Instance>,
+    pub(crate) workgroup: HashMap<String, RefInstance>,
+    pub(crate) barrier: Option<Barrier>,
     pub(crate) kind: ScopeKind,
     pub(crate) stage: EvalStage,
+    /// the overloaded function called by each call site, keyed by the unique name given to
+    /// the call site during overload resolution.
+    pub(crate) calls: Option<&'s HashMap<String, String>>,
+    /// the declaration index of the overload selected by calls to overloaded functions,
+    /// keyed by the name of the call site.
+    pub(crate) overloads: HashMap<String, usize>,
     err_decl: Option<String>,
     err_span: Option<Span>,
 }
@@ -173,8 +185,12 @@ impl<'s> Context<'s> {
             scope: Default::default(),
             resources: Default::default(),
             overrides: Default::default(),
+            workgroup: Default::default(),
+            barrier: None,
             kind: ScopeKind::Function,
             stage: EvalStage::Const,
+            calls: None,
+            overloads: Default::default(),
             err_span: None,
             err_decl: None,
         }
@@ -223,6 +239,23 @@ impl<'s> Context<'s> {
     pub fn overridable(&self, name: &str) -> Option<&Instance> {
         self.overrides.get(name)
     }
+    /// add a variable in the `workgroup` address space. It is shared with the other
+    /// invocations of the workgroup when dispatching a compute entrypoint.
+    pub fn add_workgroup_var(&mut self, name: String, inst: RefInstance) {
+        self.workgroup.insert(name, inst);
+    }
+    pub fn workgroup_var(&self, name: &str) -> Option<&RefInstance> {
+        self.workgroup.get(name)
+    }
+    /// execute a control barrier. This is a no-op outside of a compute shader dispatch.
+    ///
+    /// see [`dispatch_compute`].
+    pub fn barrier(&self) -> Result<(), EvalError> {
+        match &self.barrier {
+            Some(barrier) => barrier.wait(),
+            None => Ok(()),
+        }
+    }
 }
 
 pub trait SyntaxUtil {
@@ -0,0 +1,881 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard},
+};
+
+use derive_more::Display;
+use half::f16;
+use itertools::{iproduct, Itertools};
+
+use super::{
+    EvalError, Instance, LiteralInstance, SampledType, SamplerType, TexelFormat, TextureType,
+    VecInstance,
+};
+
+type E = EvalError;
+
+/// A texel: 4 components of the same type (`i32`, `u32` or `f32`).
+///
+/// Components missing from the texel format are 0, except alpha which is 1.
+pub type Texel = [LiteralInstance; 4];
+
+/// The dimensionality of a texture.
+///
+/// reference: <https://www.w3.org/TR/WGSL/#texture-types>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureDimension {
+    D1,
+    D2,
+    D3,
+    Cube,
+}
+
+impl TextureDimension {
+    /// number of components of the coordinates used to sample the texture.
+    pub fn coords_len(&self) -> usize {
+        match self {
+            TextureDimension::D1 => 1,
+            TextureDimension::D2 => 2,
+            TextureDimension::D3 | TextureDimension::Cube => 3,
+        }
+    }
+}
+
+impl TextureType {
+    pub fn dimension(&self) -> TextureDimension {
+        match self {
+            TextureType::Sampled1D(_) | TextureType::Storage1D(_, _) => TextureDimension::D1,
+            TextureType::Sampled2D(_)
+            | TextureType::Sampled2DArray(_)
+            | TextureType::Multisampled2D(_)
+            | TextureType::DepthMultisampled2D
+            | TextureType::External
+            | TextureType::Storage2D(_, _)
+            | TextureType::Storage2DArray(_, _)
+            | TextureType::Depth2D
+            | TextureType::Depth2DArray => TextureDimension::D2,
+            TextureType::Sampled3D(_) | TextureType::Storage3D(_, _) => TextureDimension::D3,
+            TextureType::SampledCube(_)
+            | TextureType::SampledCubeArray(_)
+            | TextureType::DepthCube
+            | TextureType::DepthCubeArray => TextureDimension::Cube,
+        }
+    }
+    pub fn is_arrayed(&self) -> bool {
+        matches!(
+            self,
+            TextureType::Sampled2DArray(_)
+                | TextureType::SampledCubeArray(_)
+                | TextureType::Storage2DArray(_, _)
+                | TextureType::Depth2DArray
+                | TextureType::DepthCubeArray
+        )
+    }
+    pub fn is_depth(&self) -> bool {
+        matches!(
+            self,
+            TextureType::DepthMultisampled2D
+                | TextureType::Depth2D
+                | TextureType::Depth2DArray
+                | TextureType::DepthCube
+                | TextureType::DepthCubeArray
+        )
+    }
+    pub fn is_storage(&self) -> bool {
+        matches!(
+            self,
+            TextureType::Storage1D(_, _)
+                | TextureType::Storage2D(_, _)
+                | TextureType::Storage2DArray(_, _)
+                | TextureType::Storage3D(_, _)
+        )
+    }
+    pub fn is_multisampled(&self) -> bool {
+        matches!(
+            self,
+            TextureType::Multisampled2D(_) | TextureType::DepthMultisampled2D
+        )
+    }
+    /// whether the texture can have more than one mip level.
+    pub fn is_mipmapped(&self) -> bool {
+        !(self.is_storage() || self.is_multisampled() || self.is_external())
+    }
+    /// the type of the texel components.
+    pub fn sampled_type(&self) -> SampledType {
+        match self {
+            TextureType::Sampled1D(ty)
+            | TextureType::Sampled2D(ty)
+            | TextureType::Sampled2DArray(ty)
+            | TextureType::Sampled3D(ty)
+            | TextureType::SampledCube(ty)
+            | TextureType::SampledCubeArray(ty)
+            | TextureType::Multisampled2D(ty) => *ty,
+            TextureType::Storage1D(fmt, _)
+            | TextureType::Storage2D(fmt, _)
+            | TextureType::Storage2DArray(fmt, _)
+            | TextureType::Storage3D(fmt, _) => fmt.channel_type(),
+            _ => SampledType::F32,
+        }
+    }
+}
+
+/// How the texel components are encoded in memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Unorm,
+    Snorm,
+    Uint,
+    Sint,
+    Float,
+}
+
+impl TexelFormat {
+    /// number of components, component size in bytes, and component encoding.
+    fn layout(&self) -> (usize, usize, Encoding) {
+        match self {
+            TexelFormat::Rgba8Unorm | TexelFormat::Bgra8Unorm => (4, 1, Encoding::Unorm),
+            TexelFormat::Rgba8Snorm => (4, 1, Encoding::Snorm),
+            TexelFormat::Rgba8Uint => (4, 1, Encoding::Uint),
+            TexelFormat::Rgba8Sint => (4, 1, Encoding::Sint),
+            TexelFormat::Rgba16Uint => (4, 2, Encoding::Uint),
+            TexelFormat::Rgba16Sint => (4, 2, Encoding::Sint),
+            TexelFormat::Rgba16Float => (4, 2, Encoding::Float),
+            TexelFormat::R32Uint => (1, 4, Encoding::Uint),
+            TexelFormat::R32Sint => (1, 4, Encoding::Sint),
+            TexelFormat::R32Float => (1, 4, Encoding::Float),
+            TexelFormat::Rg32Uint => (2, 4, Encoding::Uint),
+            TexelFormat::Rg32Sint => (2, 4, Encoding::Sint),
+            TexelFormat::Rg32Float => (2, 4, Encoding::Float),
+            TexelFormat::Rgba32Uint => (4, 4, Encoding::Uint),
+            TexelFormat::Rgba32Sint => (4, 4, Encoding::Sint),
+            TexelFormat::Rgba32Float => (4, 4, Encoding::Float),
+        }
+    }
+
+    /// the type of the texel components in the shader.
+    ///
+    /// reference: <https://www.w3.org/TR/WGSL/#storage-texel-formats>
+    pub fn channel_type(&self) -> SampledType {
+        match self.layout().2 {
+            Encoding::Unorm | Encoding::Snorm | Encoding::Float => SampledType::F32,
+            Encoding::Uint => SampledType::U32,
+            Encoding::Sint => SampledType::I32,
+        }
+    }
+
+    /// size of a texel in bytes.
+    pub fn texel_size(&self) -> usize {
+        let (n, size, _) = self.layout();
+        n * size
+    }
+
+    /// decode a texel from its memory representation.
+    ///
+    /// # Panics
+    /// * if `bytes` is smaller than [`Self::texel_size`].
+    pub fn decode(&self, bytes: &[u8]) -> Texel {
+        let (n, size, encoding) = self.layout();
+        let mut texel = zero_texel(self.channel_type());
+        for (i, b) in bytes[..n * size].chunks_exact(size).enumerate() {
+            texel[i] = match (encoding, b) {
+                (Encoding::Unorm, [b]) => LiteralInstance::F32(*b as f32 / 255.0),
+                (Encoding::Snorm, [b]) => LiteralInstance::F32((*b as i8 as f32 / 127.0).max(-1.0)),
+                (Encoding::Uint, [b]) => LiteralInstance::U32(*b as u32),
+                (Encoding::Sint, [b]) => LiteralInstance::I32(*b as i8 as i32),
+                (Encoding::Uint, &[b0, b1]) => {
+                    LiteralInstance::U32(u16::from_le_bytes([b0, b1]) as u32)
+                }
+                (Encoding::Sint, &[b0, b1]) => {
+                    LiteralInstance::I32(i16::from_le_bytes([b0, b1]) as i32)
+                }
+                (Encoding::Float, &[b0, b1]) => {
+                    LiteralInstance::F32(f16::from_le_bytes([b0, b1]).to_f32())
+                }
+                (Encoding::Uint, &[b0, b1, b2, b3]) => {
+                    LiteralInstance::U32(u32::from_le_bytes([b0, b1, b2, b3]))
+                }
+                (Encoding::Sint, &[b0, b1, b2, b3]) => {
+                    LiteralInstance::I32(i32::from_le_bytes([b0, b1, b2, b3]))
+                }
+                (Encoding::Float, &[b0, b1, b2, b3]) => {
+                    LiteralInstance::F32(f32::from_le_bytes([b0, b1, b2, b3]))
+                }
+                _ => unreachable!("invalid texel layout"),
+            }
+        }
+        if *self == TexelFormat::Bgra8Unorm {
+            texel.swap(0, 2);
+        }
+        texel
+    }
+
+    /// encode a texel to its memory representation. Components are converted to the
+    /// format, with quantization and clamping.
+    pub fn encode(&self, texel: &Texel) -> Vec<u8> {
+        let (n, size, encoding) = self.layout();
+        let mut texel = *texel;
+        if *self == TexelFormat::Bgra8Unorm {
+            texel.swap(0, 2);
+        }
+        texel[..n]
+            .iter()
+            .flat_map(|c| match (encoding, size, c) {
+                (Encoding::Unorm, 1, LiteralInstance::F32(f)) => {
+                    vec![(f.clamp(0.0, 1.0) * 255.0).round() as u8]
+                }
+                (Encoding::Snorm, 1, LiteralInstance::F32(f)) => {
+                    vec![(f.clamp(-1.0, 1.0) * 127.0).round() as i8 as u8]
+                }
+                (Encoding::Uint, 1, LiteralInstance::U32(u)) => vec![*u as u8],
+                (Encoding::Sint, 1, LiteralInstance::I32(i)) => vec![*i as i8 as u8],
+                (Encoding::Uint, 2, LiteralInstance::U32(u)) => (*u as u16).to_le_bytes().to_vec(),
+                (Encoding::Sint, 2, LiteralInstance::I32(i)) => (*i as i16).to_le_bytes().to_vec(),
+                (Encoding::Float, 2, LiteralInstance::F32(f)) => {
+                    f16::from_f32(*f).to_le_bytes().to_vec()
+                }
+                (Encoding::Uint, 4, LiteralInstance::U32(u)) => u.to_le_bytes().to_vec(),
+                (Encoding::Sint, 4, LiteralInstance::I32(i)) => i.to_le_bytes().to_vec(),
+                (Encoding::Float, 4, LiteralInstance::F32(f)) => f.to_le_bytes().to_vec(),
+                _ => vec![0; size],
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn zero_texel(ty: SampledType) -> Texel {
+    match ty {
+        SampledType::I32 => [0, 0, 0, 1].map(LiteralInstance::I32),
+        SampledType::U32 => [0, 0, 0, 1].map(LiteralInstance::U32),
+        SampledType::F32 => [0.0, 0.0, 0.0, 1.0].map(LiteralInstance::F32),
+    }
+}
+
+pub(crate) fn texel_f32(texel: &Texel) -> [f32; 4] {
+    texel.map(|c| match c {
+        LiteralInstance::F32(f) => f,
+        LiteralInstance::I32(i) => i as f32,
+        LiteralInstance::U32(u) => u as f32,
+        _ => unreachable!("invalid texel component type"),
+    })
+}
+
+/// A texture resource.
+///
+/// Textures are handles: cloning a texture instance does not copy the texels, and
+/// `textureStore` writes are visible to all clones.
+#[derive(Clone, Debug)]
+pub struct TextureInstance {
+    ty: TextureType,
+    size: [u32; 3],
+    levels: Arc<RwLock<Vec<Vec<Texel>>>>,
+}
+
+impl PartialEq for TextureInstance {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty
+            && self.size == other.size
+            && (Arc::ptr_eq(&self.levels, &other.levels) || *self.read() == *other.read())
+    }
+}
+
+impl TextureInstance {
+    /// Create a texture from the texels of each mip level.
+    ///
+    /// `size` is the size of the base mip level: width, height, and depth (3D textures) or
+    /// number of array layers (array textures) or 6 times the number of cubes (cube
+    /// textures). Texels are ordered by x, then y, then depth or layer.
+    pub fn new(ty: TextureType, size: [u32; 3], levels: Vec<Vec<Texel>>) -> Result<Self, E> {
+        let tex = Self {
+            ty,
+            size,
+            levels: Arc::new(RwLock::new(Vec::new())),
+        };
+        let err = |msg: &str| Err(E::InvalidTexture(tex.ty.clone(), msg.to_string()));
+        let [w, h, d] = size;
+        if w == 0 || h == 0 || d == 0 {
+            return err("the size must not be zero");
+        }
+        let dim = tex.ty.dimension();
+        let arrayed = tex.ty.is_arrayed();
+        match dim {
+            TextureDimension::D1 if h != 1 || d != 1 => {
+                return err("1D textures have a height and depth of 1")
+            }
+            TextureDimension::D2 if !arrayed && d != 1 => {
+                return err("2D textures have a depth of 1")
+            }
+            TextureDimension::Cube if w != h => return err("cube texture faces must be square"),
+            TextureDimension::Cube if !arrayed && d != 6 => {
+                return err("cube textures have 6 layers")
+            }
+            TextureDimension::Cube if d % 6 != 0 => {
+                return err("cube array textures have a multiple of 6 layers")
+            }
+            _ => (),
+        }
+        if levels.is_empty() {
+            return err("a texture must have at least one mip level");
+        }
+        if !tex.ty.is_mipmapped() && levels.len() != 1 {
+            return err("this texture type cannot have mip levels");
+        }
+        let extent = match dim {
+            TextureDimension::D3 => w.max(h).max(d),
+            _ => w.max(h),
+        };
+        if levels.len() > (32 - extent.leading_zeros()) as usize {
+            return err("too many mip levels for the texture size");
+        }
+        let sampled_ty = tex.ty.sampled_type();
+        for (i, level) in levels.iter().enumerate() {
+            let [w, h, d] = tex.level_size(i as u32);
+            if level.len() != (w * h * d) as usize {
+                return err("the number of texels does not match the texture size");
+            }
+            if level
+                .iter()
+                .flatten()
+                .any(|c| !is_sampled_type(c, sampled_ty))
+            {
+                return err("the texel components do not match the texture sampled type");
+            }
+        }
+        *tex.levels.write().unwrap_or_else(PoisonError::into_inner) = levels;
+        Ok(tex)
+    }
+
+    /// Create a texture with a single mip level from the memory representation of its
+    /// texels. See [`Self::new`].
+    pub fn from_bytes(
+        ty: TextureType,
+        format: TexelFormat,
+        size: [u32; 3],
+        bytes: &[u8],
+    ) -> Result<Self, E> {
+        if format.channel_type() != ty.sampled_type() {
+            return Err(E::InvalidTexture(
+                ty,
+                format!("texel format `{format}` does not match the sampled type"),
+            ));
+        }
+        let n = size.iter().product::<u32>() as usize;
+        if bytes.len() != n * format.texel_size() {
+            return Err(E::InvalidTexture(
+                ty,
+                format!(
+                    "expected {} bytes of `{format}` texels, got {}",
+                    n * format.texel_size(),
+                    bytes.len()
+                ),
+            ));
+        }
+        let texels = bytes
+            .chunks_exact(format.texel_size())
+            .map(|b| format.decode(b))
+            .collect_vec();
+        Self::new(ty, size, vec![texels])
+    }
+
+    /// The memory representation of the texels of a mip level.
+    pub fn to_bytes(&self, format: TexelFormat, level: u32) -> Option<Vec<u8>> {
+        let levels = self.read();
+        let texels = levels.get(level as usize)?;
+        Some(texels.iter().flat_map(|t| format.encode(t)).collect())
+    }
+
+    /// The texels of a mip level.
+    pub fn texels(&self, level: u32) -> Option<Vec<Texel>> {
+        self.read().get(level as usize).cloned()
+    }
+
+    pub fn texture_type(&self) -> &TextureType {
+        &self.ty
+    }
+
+    pub fn num_levels(&self) -> u32 {
+        self.read().len() as u32
+    }
+
+    /// number of array layers. Each cube counts as one layer.
+    pub fn num_layers(&self) -> u32 {
+        match self.ty.dimension() {
+            TextureDimension::Cube => self.size[2] / 6,
+            TextureDimension::D3 => 1,
+            _ => self.size[2],
+        }
+    }
+
+    /// size of a mip level: width, height, and depth or layers.
+    pub fn level_size(&self, level: u32) -> [u32; 3] {
+        let [w, h, d] = self.size;
+        let mip = |n: u32| (n >> level).max(1);
+        match self.ty.dimension() {
+            TextureDimension::D1 => [mip(w), 1, 1],
+            TextureDimension::D2 | TextureDimension::Cube => [mip(w), mip(h), d],
+            TextureDimension::D3 => [mip(w), mip(h), mip(d)],
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Vec<Vec<Texel>>> {
+        self.levels.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn index(&self, level: u32, coords: [i64; 3]) -> Option<usize> {
+        let [w, h, d] = self.level_size(level).map(|n| n as i64);
+        let [x, y, z] = coords;
+        ((0..w).contains(&x) && (0..h).contains(&y) && (0..d).contains(&z))
+            .then_some((x + y * w + z * w * h) as usize)
+    }
+
+    /// Get a texel. The third coordinate is the depth or layer.
+    ///
+    /// Returns `None` if the coordinates or level are out of bounds.
+    pub fn load(&self, level: u32, coords: [i64; 3]) -> Option<Texel> {
+        let i = self.index(level, coords)?;
+        self.read().get(level as usize)?.get(i).copied()
+    }
+
+    /// Set a texel. The third coordinate is the depth or layer.
+    ///
+    /// For storage textures, the texel is converted to the texel format.
+    /// Returns `false` if the coordinates or level are out of bounds.
+    pub fn store(&self, level: u32, coords: [i64; 3], texel: Texel) -> bool {
+        let Some(i) = self.index(level, coords) else {
+            return false;
+        };
+        let texel = match &self.ty {
+            TextureType::Storage1D(fmt, _)
+            | TextureType::Storage2D(fmt, _)
+            | TextureType::Storage2DArray(fmt, _)
+            | TextureType::Storage3D(fmt, _) => fmt.decode(&fmt.encode(&texel)),
+            _ => texel,
+        };
+        let mut levels = self.levels.write().unwrap_or_else(PoisonError::into_inner);
+        match levels.get_mut(level as usize).and_then(|l| l.get_mut(i)) {
+            Some(t) => {
+                *t = texel;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sample the texture with a sampler.
+    ///
+    /// * `coords` are the normalized coordinates, or the direction for cube textures.
+    /// * `array_index` is the array layer, for array textures. It is clamped.
+    /// * `offset` is a texel offset applied to the unnormalized coordinates.
+    /// * `lod` is the level of detail. It is clamped by the sampler and the mip levels.
+    /// * `depth_ref` is the reference value of depth comparisons. If set, the compared
+    ///   values are filtered instead of the texel values.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample(
+        &self,
+        sampler: &SamplerInstance,
+        coords: [f32; 3],
+        array_index: i64,
+        offset: [i32; 3],
+        lod: f32,
+        depth_ref: Option<f32>,
+    ) -> [f32; 4] {
+        let (coords, layer) = self.resolve_coords(coords, array_index);
+        let lod = lod.clamp(sampler.lod_min_clamp, sampler.lod_max_clamp);
+        let filter = if lod <= 0.0 {
+            sampler.mag_filter
+        } else {
+            sampler.min_filter
+        };
+        let max_level = (self.num_levels() - 1) as f32;
+        let lod = lod.clamp(0.0, max_level);
+        let sample_level = |level: f32| {
+            self.sample_level(
+                sampler,
+                filter,
+                level as u32,
+                coords,
+                layer,
+                offset,
+                depth_ref,
+            )
+        };
+        match sampler.mipmap_filter {
+            FilterMode::Nearest => sample_level((lod + 0.5).floor().min(max_level)),
+            FilterMode::Linear => {
+                let l0 = lod.floor();
+                let t = lod - l0;
+                let s0 = sample_level(l0);
+                if t == 0.0 {
+                    s0
+                } else {
+                    let s1 = sample_level((l0 + 1.0).min(max_level));
+                    std::array::from_fn(|i| s0[i] * (1.0 - t) + s1[i] * t)
+                }
+            }
+        }
+    }
+
+    /// Get the 4 texels that would be used for bilinear filtering at the base mip level,
+    /// in the order `(u_min, v_max)`, `(u_max, v_max)`, `(u_max, v_min)`,
+    /// `(u_min, v_min)`.
+    ///
+    /// If `depth_ref` is set, the texels are the results of the depth comparisons.
+    pub fn gather(
+        &self,
+        sampler: &SamplerInstance,
+        coords: [f32; 3],
+        array_index: i64,
+        offset: [i32; 3],
+        depth_ref: Option<f32>,
+    ) -> [Texel; 4] {
+        let (coords, layer) = self.resolve_coords(coords, array_index);
+        let size = self.level_size(0);
+        let addr = self.address_modes(sampler);
+        let [(u0, u1), (v0, v1)] = [0, 1].map(|a| {
+            let x = coords[a] * size[a] as f32 - 0.5;
+            let i = x.floor() as i64 + offset[a] as i64;
+            (addr[a].apply(i, size[a]), addr[a].apply(i + 1, size[a]))
+        });
+        [(u0, v1), (u1, v1), (u1, v0), (u0, v0)].map(|(u, v)| {
+            let texel = self
+                .load(0, [u, v, layer])
+                .unwrap_or_else(|| zero_texel(self.ty.sampled_type()));
+            match depth_ref {
+                Some(r) => {
+                    let c = sampler.compare_depth(r, texel_f32(&texel)[0]);
+                    [LiteralInstance::F32(c); 4]
+                }
+                None => texel,
+            }
+        })
+    }
+
+    /// Compute the level of detail from the derivatives of the coordinates.
+    pub fn lod_from_grad(&self, ddx: [f32; 3], ddy: [f32; 3]) -> f32 {
+        let n = match self.ty.dimension() {
+            TextureDimension::Cube => 2,
+            dim => dim.coords_len(),
+        };
+        let size = self.level_size(0);
+        let len = |d: [f32; 3]| {
+            (0..n)
+                .map(|a| (d[a] * size[a] as f32).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        };
+        len(ddx).max(len(ddy)).log2()
+    }
+
+    /// normalized texel coordinates and layer (or depth) of a sampling operation.
+    fn resolve_coords(&self, coords: [f32; 3], array_index: i64) -> ([f32; 3], i64) {
+        let layer = if self.ty.is_arrayed() {
+            array_index.clamp(0, self.num_layers() as i64 - 1)
+        } else {
+            0
+        };
+        match self.ty.dimension() {
+            TextureDimension::Cube => {
+                let (face, u, v) = cube_face(coords);
+                ([u, v, 0.0], layer * 6 + face)
+            }
+            TextureDimension::D3 => (coords, 0),
+            _ => (coords, layer),
+        }
+    }
+
+    fn address_modes(&self, sampler: &SamplerInstance) -> [AddressMode; 3] {
+        match self.ty.dimension() {
+            TextureDimension::Cube => [AddressMode::ClampToEdge; 3],
+            _ => [
+                sampler.address_mode_u,
+                sampler.address_mode_v,
+                sampler.address_mode_w,
+            ],
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sample_level(
+        &self,
+        sampler: &SamplerInstance,
+        filter: FilterMode,
+        level: u32,
+        coords: [f32; 3],
+        layer: i64,
+        offset: [i32; 3],
+        depth_ref: Option<f32>,
+    ) -> [f32; 4] {
+        let size = self.level_size(level);
+        let addr = self.address_modes(sampler);
+        let n = match self.ty.dimension() {
+            TextureDimension::Cube => 2,
+            dim => dim.coords_len(),
+        };
+
+        // for each axis, the texel indices and their filtering weights.
+        let axes: [Vec<(i64, f32)>; 3] = std::array::from_fn(|a| {
+            if a >= n {
+                let i = if a == 2 { layer } else { 0 };
+                return vec![(i, 1.0)];
+            }
+            let x = coords[a] * size[a] as f32;
+            match filter {
+                FilterMode::Nearest => {
+                    let i = x.floor() as i64 + offset[a] as i64;
+                    vec![(addr[a].apply(i, size[a]), 1.0)]
+                }
+                FilterMode::Linear => {
+                    let x = x - 0.5;
+                    let t = x - x.floor();
+                    let i = x.floor() as i64 + offset[a] as i64;
+                    vec![
+                        (addr[a].apply(i, size[a]), 1.0 - t),
+                        (addr[a].apply(i + 1, size[a]), t),
+                    ]
+                }
+            }
+        });
+
+        let levels = self.read();
+        let mut res = [0.0; 4];
+        for ((x, wx), (y, wy), (z, wz)) in iproduct!(&axes[0], &axes[1], &axes[2]) {
+            let texel = self
+                .index(level, [*x, *y, *z])
+                .and_then(|i| levels.get(level as usize)?.get(i))
+                .map(texel_f32)
+                .unwrap_or_default();
+            let texel = match depth_ref {
+                Some(r) => [sampler.compare_depth(r, texel[0]); 4],
+                None => texel,
+            };
+            let w = wx * wy * wz;
+            for (r, t) in res.iter_mut().zip(texel) {
+                *r += w * t;
+            }
+        }
+        res
+    }
+}
+
+impl From<Texel> for VecInstance {
+    fn from(texel: Texel) -> Self {
+        VecInstance::new(texel.map(Instance::from).to_vec())
+    }
+}
+
+fn is_sampled_type(c: &LiteralInstance, ty: SampledType) -> bool {
+    matches!(
+        (c, ty),
+        (LiteralInstance::I32(_), SampledType::I32)
+            | (LiteralInstance::U32(_), SampledType::U32)
+            | (LiteralInstance::F32(_), SampledType::F32)
+    )
+}
+
+/// select the cube face from a direction. Returns the face index and the normalized
+/// coordinates in the face.
+///
+/// reference: <https://registry.khronos.org/vulkan/specs/latest/html/vkspec.html#_cube_map_face_selection>
+fn cube_face(dir: [f32; 3]) -> (i64, f32, f32) {
+    let [x, y, z] = dir;
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+        if x >= 0.0 {
+            (0, -z, -y, ax)
+        } else {
+            (1, z, -y, ax)
+        }
+    } else if ay >= az {
+        if y >= 0.0 {
+            (2, x, z, ay)
+        } else {
+            (3, x, -z, ay)
+        }
+    } else if z >= 0.0 {
+        (4, x, -y, az)
+    } else {
+        (5, -x, -y, az)
+    };
+    let ma = if ma == 0.0 { 1.0 } else { ma };
+    (face, (sc / ma + 1.0) / 2.0, (tc / ma + 1.0) / 2.0)
+}
+
+/// reference: <https://www.w3.org/TR/webgpu/#enumdef-gpuaddressmode>
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Display)]
+pub enum AddressMode {
+    #[default]
+    #[display("clamp-to-edge")]
+    ClampToEdge,
+    #[display("repeat")]
+    Repeat,
+    #[display("mirror-repeat")]
+    MirrorRepeat,
+}
+
+impl FromStr for AddressMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp-to-edge" => Ok(Self::ClampToEdge),
+            "repeat" => Ok(Self::Repeat),
+            "mirror-repeat" => Ok(Self::MirrorRepeat),
+            _ => Err(()),
+        }
+    }
+}
+
+impl AddressMode {
+    /// map a texel index to the texture extent `n`.
+    fn apply(&self, i: i64, n: u32) -> i64 {
+        let n = n as i64;
+        match self {
+            AddressMode::ClampToEdge => i.clamp(0, n - 1),
+            AddressMode::Repeat => i.rem_euclid(n),
+            AddressMode::MirrorRepeat => {
+                let m = i.rem_euclid(2 * n);
+                if m >= n {
+                    2 * n - 1 - m
+                } else {
+                    m
+                }
+            }
+        }
+    }
+}
+
+/// reference: <https://www.w3.org/TR/webgpu/#enumdef-gpufiltermode>
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Display)]
+pub enum FilterMode {
+    #[default]
+    #[display("nearest")]
+    Nearest,
+    #[display("linear")]
+    Linear,
+}
+
+impl FromStr for FilterMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Self::Nearest),
+            "linear" => Ok(Self::Linear),
+            _ => Err(()),
+        }
+    }
+}
+
+/// reference: <https://www.w3.org/TR/webgpu/#enumdef-gpucomparefunction>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum CompareFunction {
+    #[display("never")]
+    Never,
+    #[display("less")]
+    Less,
+    #[display("equal")]
+    Equal,
+    #[display("less-equal")]
+    LessEqual,
+    #[display("greater")]
+    Greater,
+    #[display("not-equal")]
+    NotEqual,
+    #[display("greater-equal")]
+    GreaterEqual,
+    #[display("always")]
+    Always,
+}
+
+impl FromStr for CompareFunction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Self::Never),
+            "less" => Ok(Self::Less),
+            "equal" => Ok(Self::Equal),
+            "less-equal" => Ok(Self::LessEqual),
+            "greater" => Ok(Self::Greater),
+            "not-equal" => Ok(Self::NotEqual),
+            "greater-equal" => Ok(Self::GreaterEqual),
+            "always" => Ok(Self::Always),
+            _ => Err(()),
+        }
+    }
+}
+
+impl CompareFunction {
+    /// compare the reference value with the texel value.
+    pub fn compare(&self, reference: f32, value: f32) -> bool {
+        match self {
+            CompareFunction::Never => false,
+            CompareFunction::Less => reference < value,
+            CompareFunction::Equal => reference == value,
+            CompareFunction::LessEqual => reference <= value,
+            CompareFunction::Greater => reference > value,
+            CompareFunction::NotEqual => reference != value,
+            CompareFunction::GreaterEqual => reference >= value,
+            CompareFunction::Always => true,
+        }
+    }
+}
+
+/// A sampler resource.
+///
+/// reference: <https://www.w3.org/TR/webgpu/#GPUSamplerDescriptor>
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplerInstance {
+    pub ty: SamplerType,
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub address_mode_w: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+    /// Only used by comparison samplers.
+    pub compare: CompareFunction,
+}
+
+impl SamplerInstance {
+    /// Create a sampler with the WebGPU default descriptor values.
+    pub fn new(ty: SamplerType) -> Self {
+        Self {
+            ty,
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+            compare: CompareFunction::Less,
+        }
+    }
+
+    /// Set the address mode of all coordinates.
+    pub fn with_address_mode(mut self, mode: AddressMode) -> Self {
+        self.address_mode_u = mode;
+        self.address_mode_v = mode;
+        self.address_mode_w = mode;
+        self
+    }
+
+    /// Set the magnification, minification and mipmap filters.
+    pub fn with_filter(mut self, filter: FilterMode) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self.mipmap_filter = filter;
+        self
+    }
+
+    fn compare_depth(&self, reference: f32, value: f32) -> f32 {
+        if self.compare.compare(reference, value) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
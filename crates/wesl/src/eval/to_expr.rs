@@ -20,9 +20,12 @@ impl ToExpr for Instance {
             Instance::Array(inst) => inst.to_expr(ctx),
             Instance::Vec(inst) => inst.to_expr(ctx),
             Instance::Mat(inst) => inst.to_expr(ctx),
-            Instance::Ptr(_) | Instance::Ref(_) | Instance::Atomic(_) | Instance::Deferred(_) => {
-                Err(E::NotConstructible(self.ty()))
-            }
+            Instance::Ptr(_)
+            | Instance::Ref(_)
+            | Instance::Atomic(_)
+            | Instance::Texture(_)
+            | Instance::Sampler(_)
+            | Instance::Deferred(_) => Err(E::NotConstructible(self.ty())),
         }
     }
 }
@@ -1,10 +1,11 @@
 use std::str::FromStr;
 
 use super::{
-    builtin_fn_type, constructor_type, convert_ty, is_constructor_fn, ArrayInstance, ArrayTemplate,
-    AtomicInstance, AtomicTemplate, Context, Convert, EvalError, Instance, LiteralInstance,
-    MatInstance, MatTemplate, PtrInstance, PtrTemplate, RefInstance, StructInstance, SyntaxUtil,
-    TextureTemplate, VecInstance, VecTemplate, ATTR_INTRINSIC,
+    builtin_fn_type, constructor_type, conversion_rank, convert_ty, is_constructor_fn,
+    ArrayInstance, ArrayTemplate, AtomicInstance, AtomicTemplate, Context, Convert, EvalError,
+    Instance, LiteralInstance, MatInstance, MatTemplate, PtrInstance, PtrTemplate, RefInstance,
+    SamplerInstance, StructInstance, SyntaxUtil, TextureInstance, TextureTemplate, VecInstance,
+    VecTemplate, ATOMIC_RESULT, ATTR_INTRINSIC,
 };
 
 type E = EvalError;
@@ -32,6 +33,16 @@ impl FromStr for SampledType {
     }
 }
 
+impl From<SampledType> for Type {
+    fn from(ty: SampledType) -> Self {
+        match ty {
+            SampledType::I32 => Type::I32,
+            SampledType::U32 => Type::U32,
+            SampledType::F32 => Type::F32,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, IsVariant, Unwrap)]
 pub enum SamplerType {
     Sampler,
@@ -259,6 +270,8 @@ impl Ty for Instance {
             Instance::Ptr(p) => p.ty(),
             Instance::Ref(r) => r.ty(),
             Instance::Atomic(a) => a.ty(),
+            Instance::Texture(t) => t.ty(),
+            Instance::Sampler(s) => s.ty(),
             Instance::Deferred(t) => t.ty(),
         }
     }
@@ -272,6 +285,8 @@ impl Ty for Instance {
             Instance::Ptr(p) => p.inner_ty(),
             Instance::Ref(r) => r.inner_ty(),
             Instance::Atomic(a) => a.inner_ty(),
+            Instance::Texture(t) => t.inner_ty(),
+            Instance::Sampler(s) => s.inner_ty(),
             Instance::Deferred(t) => t.inner_ty(),
         }
     }
@@ -353,6 +368,18 @@ impl Ty for RefInstance {
     }
 }
 
+impl Ty for TextureInstance {
+    fn ty(&self) -> Type {
+        Type::Texture(self.texture_type().clone())
+    }
+}
+
+impl Ty for SamplerInstance {
+    fn ty(&self) -> Type {
+        Type::Sampler(self.ty.clone())
+    }
+}
+
 impl Ty for AtomicInstance {
     fn ty(&self) -> Type {
         Type::Atomic(self.inner_ty().into())
@@ -477,6 +504,7 @@ pub fn ty_eval_ty(expr: &TypeExpression, ctx: &mut Context) -> Result<Type, E> {
             "texture_depth_2d_array" => Ok(Type::Texture(TextureType::Depth2DArray)),
             "texture_depth_cube" => Ok(Type::Texture(TextureType::DepthCube)),
             "texture_depth_cube_array" => Ok(Type::Texture(TextureType::DepthCubeArray)),
+            "texture_external" => Ok(Type::Texture(TextureType::External)),
             "sampler" => Ok(Type::Sampler(SamplerType::Sampler)),
             "sampler_comparison" => Ok(Type::Sampler(SamplerType::SamplerComparison)),
             _ => Err(E::UnknownType(name.to_string())),
@@ -500,7 +528,23 @@ impl EvalTy for ParenthesizedExpression {
 
 impl EvalTy for NamedComponentExpression {
     fn eval_ty(&self, ctx: &mut Context) -> Result<Type, E> {
-        match self.base.eval_ty(ctx)? {
+        // pointers are implicitly dereferenced when accessing a component.
+        let ty = match self.base.eval_ty(ctx)? {
+            Type::Ptr(_, ty) => *ty,
+            ty => ty,
+        };
+        match ty {
+            Type::Struct(name) if name.starts_with(ATOMIC_RESULT) => {
+                match self.component.name().as_str() {
+                    "old_value" => Ok(if name.ends_with("i32") {
+                        Type::I32
+                    } else {
+                        Type::U32
+                    }),
+                    "exchanged" => Ok(Type::Bool),
+                    _ => Err(E::Component(Type::Struct(name), self.component.to_string())),
+                }
+            }
             Type::Struct(name) => {
                 let decl = ctx
                     .source
@@ -513,15 +557,14 @@ impl EvalTy for NamedComponentExpression {
                     .ok_or_else(|| E::Component(Type::Struct(name), self.component.to_string()))?;
                 ty_eval_ty(&m.ty, ctx)
             }
-            Type::Vec(_, ty) => {
-                // TODO: check valid swizzle
-                let m = self.component.name().len();
-                if m == 1 {
+            Type::Vec(n, ty) => {
+                let name = self.component.name();
+                if !is_swizzle(&name, n) {
+                    Err(E::Swizzle(self.component.to_string()))
+                } else if name.len() == 1 {
                     Ok(*ty)
-                } else if m <= 4 {
-                    Ok(Type::Vec(m as u8, ty))
                 } else {
-                    Err(E::Swizzle(self.component.to_string()))
+                    Ok(Type::Vec(name.len() as u8, ty))
                 }
             }
             ty => Err(E::Component(ty, self.component.to_string())),
@@ -529,11 +572,26 @@ impl EvalTy for NamedComponentExpression {
     }
 }
 
+/// whether `components` is a valid component selection of a vector with `n` components:
+/// one to four letters of the same set, `xyzw` or `rgba`, in bounds.
+pub fn is_swizzle(components: &str, n: u8) -> bool {
+    // reference: https://www.w3.org/TR/WGSL/#swizzle-names
+    let n = n as usize;
+    (1..=4).contains(&components.len())
+        && (components.chars().all(|c| "xyzw"[..n].contains(c))
+            || components.chars().all(|c| "rgba"[..n].contains(c)))
+}
+
 impl EvalTy for IndexingExpression {
     fn eval_ty(&self, ctx: &mut Context) -> Result<Type, E> {
         let index_ty = self.index.eval_ty(ctx)?;
         if index_ty.is_integer() {
-            match self.base.eval_ty(ctx)? {
+            // pointers are implicitly dereferenced when indexing.
+            let ty = match self.base.eval_ty(ctx)? {
+                Type::Ptr(_, ty) => *ty,
+                ty => ty,
+            };
+            match ty {
                 Type::Array(_, ty) => Ok(*ty),
                 Type::Vec(_, ty) => Ok(*ty),
                 Type::Mat(_, r, ty) => Ok(Type::Vec(r, ty)),
@@ -548,143 +606,187 @@ impl EvalTy for IndexingExpression {
 impl EvalTy for UnaryExpression {
     fn eval_ty(&self, ctx: &mut Context) -> Result<Type, E> {
         let ty = self.operand.eval_ty(ctx)?;
-        let inner = ty.inner_ty();
-        if ty != inner
-            && !ty.is_vec()
-            && !self.operator.is_address_of()
-            && !self.operator.is_indirection()
-        {
-            return Err(E::Unary(self.operator, ty));
-        }
-        match self.operator {
-            UnaryOperator::LogicalNegation if inner == Type::Bool => Ok(ty),
-            UnaryOperator::Negation if inner.is_scalar() && !inner.is_u_32() => Ok(ty),
-            UnaryOperator::BitwiseComplement if inner.is_integer() => Ok(ty),
-            UnaryOperator::AddressOf => Ok(Type::Ptr(AddressSpace::Function, Box::new(ty))), // TODO: we don't know the address space
-            UnaryOperator::Indirection if ty.is_ptr() => Ok(*ty.unwrap_ptr().1),
-            _ => Err(E::Unary(self.operator, ty)),
-        }
+        unary_op_type(self.operator, ty)
+    }
+}
+
+/// the type of a unary expression with operand type `ty`.
+pub fn unary_op_type(op: UnaryOperator, ty: Type) -> Result<Type, E> {
+    let inner = ty.inner_ty();
+    if ty != inner && !ty.is_vec() && !op.is_address_of() && !op.is_indirection() {
+        return Err(E::Unary(op, ty));
+    }
+    match op {
+        UnaryOperator::LogicalNegation if inner == Type::Bool => Ok(ty),
+        UnaryOperator::Negation if inner.is_scalar() && !inner.is_u_32() => Ok(ty),
+        UnaryOperator::BitwiseComplement if inner.is_integer() => Ok(ty),
+        UnaryOperator::AddressOf => Ok(Type::Ptr(AddressSpace::Function, Box::new(ty))), // TODO: we don't know the address space
+        UnaryOperator::Indirection if ty.is_ptr() => Ok(*ty.unwrap_ptr().1),
+        _ => Err(E::Unary(op, ty)),
     }
 }
 
 impl EvalTy for BinaryExpression {
     fn eval_ty(&self, ctx: &mut Context) -> Result<Type, E> {
-        type BinOp = BinaryOperator;
         let ty1 = self.left.eval_ty(ctx)?;
         let ty2 = self.right.eval_ty(ctx)?;
-
-        // ty1 and ty2 must always have the same inner type, except for << and >> operators.
-        let (inner, ty1, ty2) = if matches!(self.operator, BinOp::ShiftLeft | BinOp::ShiftRight) {
-            (ty1.inner_ty(), ty1, ty2)
-        } else {
-            let inner = convert_ty(&ty1.inner_ty(), &ty2.inner_ty())
-                .ok_or_else(|| E::Binary(self.operator, ty1.clone(), ty2.clone()))?
-                .clone();
-            let ty1 = ty1.convert_inner_to(&inner).unwrap();
-            let ty2 = ty2.convert_inner_to(&inner).unwrap();
-            (inner, ty1, ty2)
-        };
-
-        let is_num = (ty1.is_vec() || ty1.is_numeric())
-            && (ty2.is_vec() || ty2.is_numeric())
-            && inner.is_numeric();
-
-        Ok(match self.operator {
-            BinOp::ShortCircuitOr | BinOp::ShortCircuitAnd
-                if (ty1.is_bool() || ty1.is_vec() && inner.is_bool()) && ty1 == ty2 =>
-            {
-                Type::Bool
-            }
-            BinOp::Addition
-            | BinOp::Subtraction
-            | BinOp::Multiplication
-            | BinOp::Division
-            | BinOp::Remainder
-                if is_num && ty1 == ty2 =>
-            {
-                ty1
-            }
-            BinOp::Addition
-            | BinOp::Subtraction
-            | BinOp::Multiplication
-            | BinOp::Division
-            | BinOp::Remainder
-                if is_num && ty1.is_vec() =>
-            {
-                ty1
-            }
-            BinOp::Addition
-            | BinOp::Subtraction
-            | BinOp::Multiplication
-            | BinOp::Division
-            | BinOp::Remainder
-                if is_num && ty2.is_vec() =>
-            {
-                ty2
-            }
-            BinOp::Addition | BinOp::Subtraction if ty1.is_mat() && ty1 == ty2 => ty1,
-            BinOp::Multiplication if ty1.is_mat() && ty2.is_float() => ty1,
-            BinOp::Multiplication if ty1.is_float() && ty2.is_mat() => ty2,
-            BinOp::Multiplication => match (ty1, ty2) {
-                (Type::Mat(c, r, _), Type::Vec(n, _)) if n == c => Type::Vec(r, Box::new(inner)),
-                (Type::Vec(n, _), Type::Mat(c, r, _)) if n == r => Type::Vec(c, Box::new(inner)),
-                (Type::Mat(k1, r1, _), Type::Mat(c2, k2, _)) if k1 == k2 => {
-                    Type::Mat(c2, r1, Box::new(inner))
-                }
-                (ty1, ty2) => return Err(E::Binary(self.operator, ty1, ty2)),
-            },
-            BinOp::Equality | BinOp::Inequality if ty1.is_scalar() => Type::Bool,
-            BinOp::Equality | BinOp::Inequality if ty1.is_vec() && ty1 == ty2 => {
-                Type::Vec(ty1.unwrap_vec().0, Box::new(Type::Bool))
-            }
-            BinOp::LessThan
-            | BinOp::LessThanEqual
-            | BinOp::GreaterThan
-            | BinOp::GreaterThanEqual
-                if ty1.is_numeric() =>
-            {
-                Type::Bool
-            }
-            BinOp::LessThan
-            | BinOp::LessThanEqual
-            | BinOp::GreaterThan
-            | BinOp::GreaterThanEqual
-                if ty1.is_vec() && ty1 == ty2 =>
-            {
-                Type::Vec(ty1.unwrap_vec().0, Box::new(Type::Bool))
-            }
-            BinOp::BitwiseOr | BinOp::BitwiseAnd
-                if ty1.is_bool() || ty1.is_vec() && inner.is_bool() && ty1 == ty2 =>
-            {
-                ty1
-            }
-            BinOp::BitwiseOr | BinOp::BitwiseAnd | BinOp::BitwiseXor
-                if ty1.is_integer() || ty1.is_vec() && inner.is_integer() && ty1 == ty2 =>
-            {
-                ty1
-            }
-            BinOp::ShiftLeft | BinOp::ShiftRight
-                if ty1.is_integer() && ty2.is_convertible_to(&Type::U32)
-                    || ty1.is_vec()
-                        && ty2.is_vec()
-                        && ty1.inner_ty().is_integer()
-                        && ty2.inner_ty().is_convertible_to(&Type::U32) =>
-            {
-                ty1
+        binary_op_type(self.operator, ty1, ty2)
+    }
+}
+
+/// the type of a binary expression with operand types `ty1` and `ty2`.
+pub fn binary_op_type(op: BinaryOperator, ty1: Type, ty2: Type) -> Result<Type, E> {
+    type BinOp = BinaryOperator;
+    // ty1 and ty2 must always have the same inner type, except for << and >> operators.
+    let (inner, ty1, ty2) = if matches!(op, BinOp::ShiftLeft | BinOp::ShiftRight) {
+        (ty1.inner_ty(), ty1, ty2)
+    } else {
+        let err = || E::Binary(op, ty1.clone(), ty2.clone());
+        let inner = convert_ty(&ty1.inner_ty(), &ty2.inner_ty())
+            .ok_or_else(err)?
+            .clone();
+        let conv1 = ty1.convert_inner_to(&inner).ok_or_else(err)?;
+        let conv2 = ty2.convert_inner_to(&inner).ok_or_else(err)?;
+        (inner, conv1, conv2)
+    };
+
+    let is_num = (ty1.is_vec() || ty1.is_numeric())
+        && (ty2.is_vec() || ty2.is_numeric())
+        && inner.is_numeric();
+
+    Ok(match op {
+        BinOp::ShortCircuitOr | BinOp::ShortCircuitAnd
+            if (ty1.is_bool() || ty1.is_vec() && inner.is_bool()) && ty1 == ty2 =>
+        {
+            Type::Bool
+        }
+        BinOp::Addition
+        | BinOp::Subtraction
+        | BinOp::Multiplication
+        | BinOp::Division
+        | BinOp::Remainder
+            if is_num && ty1 == ty2 =>
+        {
+            ty1
+        }
+        BinOp::Addition
+        | BinOp::Subtraction
+        | BinOp::Multiplication
+        | BinOp::Division
+        | BinOp::Remainder
+            if is_num && ty1.is_vec() =>
+        {
+            ty1
+        }
+        BinOp::Addition
+        | BinOp::Subtraction
+        | BinOp::Multiplication
+        | BinOp::Division
+        | BinOp::Remainder
+            if is_num && ty2.is_vec() =>
+        {
+            ty2
+        }
+        BinOp::Addition | BinOp::Subtraction if ty1.is_mat() && ty1 == ty2 => ty1,
+        BinOp::Multiplication if ty1.is_mat() && ty2.is_float() => ty1,
+        BinOp::Multiplication if ty1.is_float() && ty2.is_mat() => ty2,
+        BinOp::Multiplication => match (ty1, ty2) {
+            (Type::Mat(c, r, _), Type::Vec(n, _)) if n == c => Type::Vec(r, Box::new(inner)),
+            (Type::Vec(n, _), Type::Mat(c, r, _)) if n == r => Type::Vec(c, Box::new(inner)),
+            (Type::Mat(k1, r1, _), Type::Mat(c2, k2, _)) if k1 == k2 => {
+                Type::Mat(c2, r1, Box::new(inner))
             }
-            _ => return Err(E::Binary(self.operator, ty1, ty2)),
-        })
+            (ty1, ty2) => return Err(E::Binary(op, ty1, ty2)),
+        },
+        BinOp::Equality | BinOp::Inequality if ty1.is_scalar() => Type::Bool,
+        BinOp::Equality | BinOp::Inequality if ty1.is_vec() && ty1 == ty2 => {
+            Type::Vec(ty1.unwrap_vec().0, Box::new(Type::Bool))
+        }
+        BinOp::LessThan | BinOp::LessThanEqual | BinOp::GreaterThan | BinOp::GreaterThanEqual
+            if ty1.is_numeric() =>
+        {
+            Type::Bool
+        }
+        BinOp::LessThan | BinOp::LessThanEqual | BinOp::GreaterThan | BinOp::GreaterThanEqual
+            if ty1.is_vec() && ty1 == ty2 =>
+        {
+            Type::Vec(ty1.unwrap_vec().0, Box::new(Type::Bool))
+        }
+        BinOp::BitwiseOr | BinOp::BitwiseAnd
+            if ty1.is_bool() || ty1.is_vec() && inner.is_bool() && ty1 == ty2 =>
+        {
+            ty1
+        }
+        BinOp::BitwiseOr | BinOp::BitwiseAnd | BinOp::BitwiseXor
+            if ty1.is_integer() || ty1.is_vec() && inner.is_integer() && ty1 == ty2 =>
+        {
+            ty1
+        }
+        BinOp::ShiftLeft | BinOp::ShiftRight
+            if ty1.is_integer() && ty2.is_convertible_to(&Type::U32)
+                || ty1.is_vec()
+                    && ty2.is_vec()
+                    && ty1.inner_ty().is_integer()
+                    && ty2.inner_ty().is_convertible_to(&Type::U32) =>
+        {
+            ty1
+        }
+        _ => return Err(E::Binary(op, ty1, ty2)),
+    })
+}
+
+/// the binary operator of a compound assignment, `None` for a simple assignment `=`.
+pub fn assignment_op(op: &AssignmentOperator) -> Option<BinaryOperator> {
+    match op {
+        AssignmentOperator::Equal => None,
+        AssignmentOperator::PlusEqual => Some(BinaryOperator::Addition),
+        AssignmentOperator::MinusEqual => Some(BinaryOperator::Subtraction),
+        AssignmentOperator::TimesEqual => Some(BinaryOperator::Multiplication),
+        AssignmentOperator::DivisionEqual => Some(BinaryOperator::Division),
+        AssignmentOperator::ModuloEqual => Some(BinaryOperator::Remainder),
+        AssignmentOperator::AndEqual => Some(BinaryOperator::BitwiseAnd),
+        AssignmentOperator::OrEqual => Some(BinaryOperator::BitwiseOr),
+        AssignmentOperator::XorEqual => Some(BinaryOperator::BitwiseXor),
+        AssignmentOperator::ShiftRightAssign => Some(BinaryOperator::ShiftRight),
+        AssignmentOperator::ShiftLeftAssign => Some(BinaryOperator::ShiftLeft),
     }
 }
 
 impl EvalTy for FunctionCallExpression {
     fn eval_ty(&self, ctx: &mut Context) -> Result<Type, E> {
         let ty = ctx.source.resolve_ty(&self.ty);
+        let call = ty.ident.to_string();
+        let name = ctx
+            .calls
+            .and_then(|calls| calls.get(&call))
+            .unwrap_or(&call);
+        if let Some((idx, decl)) = select_overload(name, &self.arguments, ctx)? {
+            ctx.overloads.insert(call, idx);
+            let ty = decl
+                .return_type
+                .as_ref()
+                .ok_or_else(|| E::Void(decl.ident.to_string()))?;
+            return ty_eval_ty(ty, ctx);
+        }
         if let Some(decl) = ctx.source.decl(&ty.ident.name()) {
             match decl {
                 GlobalDeclaration::Struct(decl) => {
-                    // TODO: check member types
-                    Ok(Type::Struct(decl.ident.to_string()))
+                    let name = decl.ident.to_string();
+                    // the zero-value constructor takes no arguments.
+                    if !self.arguments.is_empty() {
+                        if self.arguments.len() != decl.members.len() {
+                            return Err(E::ParamCount(
+                                name,
+                                decl.members.len(),
+                                self.arguments.len(),
+                            ));
+                        }
+                        for (arg, m) in self.arguments.iter().zip(&decl.members) {
+                            let ty = ty_eval_ty(&m.ty, ctx)?;
+                            check_arg_ty(arg, &ty, ctx)?;
+                        }
+                    }
+                    Ok(Type::Struct(name))
                 }
                 GlobalDeclaration::Function(decl) => {
                     if decl.body.attributes.contains(&ATTR_INTRINSIC) {
@@ -695,7 +797,7 @@ impl EvalTy for FunctionCallExpression {
                             .collect::<Result<Vec<_>, _>>()?;
                         builtin_fn_type(&self.ty, &args, ctx)
                     } else {
-                        // TODO: check argument types
+                        check_fn_args(decl, &self.arguments, ctx)?;
                         let ty = decl
                             .return_type
                             .as_ref()
@@ -718,6 +820,106 @@ impl EvalTy for FunctionCallExpression {
     }
 }
 
+/// check the number and types of the arguments of a call to a user-defined function.
+pub fn check_fn_args(decl: &Function, args: &[ExpressionNode], ctx: &mut Context) -> Result<(), E> {
+    if args.len() != decl.parameters.len() {
+        return Err(E::ParamCount(
+            decl.ident.to_string(),
+            decl.parameters.len(),
+            args.len(),
+        ));
+    }
+    for (arg, param) in args.iter().zip(&decl.parameters) {
+        let ty = ty_eval_ty(&param.ty, ctx)?;
+        check_arg_ty(arg, &ty, ctx)?;
+    }
+    Ok(())
+}
+
+/// select the overload of a user-defined function that best matches the arguments.
+///
+/// Returns `None` if the function is not overloaded. Otherwise, returns the candidate with
+/// the lowest sum of [conversion ranks](https://www.w3.org/TR/WGSL/#conversion-rank) from
+/// the arguments to the parameters, and its declaration index.
+fn select_overload<'s>(
+    name: &str,
+    args: &[ExpressionNode],
+    ctx: &mut Context<'s>,
+) -> Result<Option<(usize, &'s Function)>, E> {
+    let source = ctx.source;
+    let candidates = source
+        .global_declarations
+        .iter()
+        .enumerate()
+        .filter_map(|(i, decl)| match decl {
+            GlobalDeclaration::Function(decl) if *decl.ident.name() == name => Some((i, decl)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if candidates.len() < 2 {
+        return Ok(None);
+    }
+
+    let args = args
+        .iter()
+        .map(|arg| arg.eval_ty(ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut best = Vec::new();
+    let mut best_rank = u32::MAX;
+    for (i, decl) in candidates {
+        if decl.parameters.len() != args.len() {
+            continue;
+        }
+        let mut rank = Some(0);
+        for (arg, param) in args.iter().zip(&decl.parameters) {
+            let param = ty_eval_ty(&param.ty, ctx)?;
+            let arg_rank = match (arg, &param) {
+                // see `is_assignable`.
+                (Type::Ptr(_, ty1), Type::Ptr(_, ty2)) => (ty1 == ty2).then_some(0),
+                _ => conversion_rank(arg, &param),
+            };
+            rank = rank.zip(arg_rank).map(|(r1, r2)| r1 + r2);
+        }
+        match rank {
+            Some(rank) if rank < best_rank => {
+                best_rank = rank;
+                best = vec![(i, decl)];
+            }
+            Some(rank) if rank == best_rank => best.push((i, decl)),
+            _ => (),
+        }
+    }
+
+    match best.as_slice() {
+        [best] => Ok(Some(*best)),
+        [] => Err(E::NoOverload(name.to_string(), args)),
+        _ => Err(E::AmbiguousOverload(name.to_string(), args)),
+    }
+}
+
+/// check that an argument converts to the parameter type `ty`.
+fn check_arg_ty(arg: &ExpressionNode, ty: &Type, ctx: &mut Context) -> Result<(), E> {
+    let arg_ty = arg.eval_ty(ctx)?;
+    if is_assignable(&arg_ty, ty) {
+        Ok(())
+    } else {
+        ctx.set_err_span_ctx(arg.span());
+        Err(E::ParamType(ty.clone(), arg_ty))
+    }
+}
+
+/// whether a value of type `ty1` can be used where a `ty2` is expected, after automatic
+/// conversion.
+///
+/// The address space of pointers is not compared, because it is not known for pointers
+/// created with the address-of operator `&`.
+pub fn is_assignable(ty1: &Type, ty2: &Type) -> bool {
+    match (ty1, ty2) {
+        (Type::Ptr(_, ty1), Type::Ptr(_, ty2)) => ty1 == ty2,
+        _ => ty1.is_convertible_to(ty2),
+    }
+}
+
 impl EvalTy for Expression {
     fn eval_ty(&self, ctx: &mut Context) -> Result<Type, E> {
         match self {
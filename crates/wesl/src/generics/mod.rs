@@ -1,127 +1,318 @@
-mod mangle;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use itertools::Itertools;
 use thiserror::Error;
 use wgsl_parse::{syntax::*, Decorated};
 
-use crate::visit::Visit;
+use crate::{visit::Visit, Diagnostic, Error, Mangler};
 
 /// Generics error (experimental)
 #[derive(Clone, Debug, Error)]
 pub enum GenericsError {
     #[error("template not allowed on a generic parameter")]
     DisallowedTemplate,
+    #[error("`{0}` is generic and requires template arguments")]
+    MissingTemplateArgs(String),
+    #[error("`{0}` expects {1} template argument(s), but {2} were given")]
+    TemplateArgCount(String, usize, usize),
+    #[error("template arguments of `{0}` must be types")]
+    InvalidTemplateArg(String),
+    #[error("type `{2}` is not allowed for `{1}` in `{0}`, expected one of {3}")]
+    UnsatisfiedConstraint(String, String, String, String),
 }
 
 type E = GenericsError;
 
-pub fn generate_variants(wesl: &mut TranslationUnit) -> Result<(), E> {
-    let mut new_decls = Vec::new();
+/// A declaration with `@type` attributes and its instances.
+struct Generic {
+    decl: GlobalDeclaration,
+    params: Vec<TypeConstraint>,
+    /// The template arguments and the name of each instance.
+    instances: Vec<(String, Ident)>,
+    /// The instantiated declarations, once their own generic references are instantiated.
+    decls: Vec<GlobalDeclaration>,
+}
+
+struct Instantiator<'a> {
+    generics: Vec<Generic>,
+    indices: HashMap<String, usize>,
+    /// Declaration names in use, to avoid collisions with instance names.
+    names: HashSet<String>,
+    /// Instances whose generic references are not instantiated yet.
+    pending: VecDeque<(usize, GlobalDeclaration)>,
+    mangler: &'a dyn Mangler,
+}
+
+/// Instantiate the generic declarations (functions, structs and type aliases with `@type`
+/// attributes) that are referenced with template arguments, e.g. `foo<f32>(x)`.
+///
+/// Only the instances that are used are generated, including the ones referenced by other
+/// instances. Generic declarations are replaced by their instances, named with
+/// [`Mangler::mangle_types`].
+#[allow(clippy::result_large_err)]
+pub fn instantiate(
+    wesl: &mut TranslationUnit,
+    mangler: &impl Mangler,
+) -> Result<(), Diagnostic<Error>> {
+    let mut inst = Instantiator {
+        generics: Vec::new(),
+        indices: HashMap::new(),
+        names: HashSet::new(),
+        pending: VecDeque::new(),
+        mangler,
+    };
+
     for decl in &wesl.global_declarations {
-        if let GlobalDeclaration::Function(decl) = decl {
-            let ty_constrs = decl.attributes.iter().rev().filter_map(|attr| match attr {
-                Attribute::Type(t) => Some(t),
-                _ => None,
+        let Some(ident) = decl.ident() else {
+            continue;
+        };
+        let name = ident.name().to_string();
+        let params = type_params(decl);
+        if !params.is_empty() {
+            inst.indices.insert(name.clone(), inst.generics.len());
+            inst.generics.push(Generic {
+                decl: decl.clone(),
+                params,
+                instances: Vec::new(),
+                decls: Vec::new(),
             });
+        }
+        inst.names.insert(name);
+    }
 
-            let variants = ty_constrs
-                .map(|t| t.variants.iter().map(|v| (&t.ident, v)))
-                .multi_cartesian_product();
+    if inst.generics.is_empty() {
+        return Ok(());
+    }
 
-            for variant in variants {
-                if variant.is_empty() {
-                    break;
-                }
-                let mut decl = decl.clone();
-                decl.attributes
-                    .retain(|attr| !matches!(attr, Attribute::Type(_)));
-
-                // rename uses of the generic args with the concrete variant
-                for (old_id, new_ty) in &variant {
-                    let new_id = Ident::new(format!("{new_ty}"));
-                    for ty in Visit::<TypeExpression>::visit_mut(&mut decl) {
-                        if &ty.ident == *old_id {
-                            if ty.template_args.is_some() {
-                                return Err(E::DisallowedTemplate);
-                            }
-                            ty.ident = new_id.clone();
-                        }
-                    }
-                }
+    for decl in &mut wesl.global_declarations {
+        if type_params(decl).is_empty() {
+            inst.instantiate_decl(decl)?;
+        }
+    }
+    while let Some((idx, mut decl)) = inst.pending.pop_front() {
+        inst.instantiate_decl(&mut decl)?;
+        inst.generics[idx].decls.push(decl);
+    }
 
-                let constraints = variant.iter().map(|&(name, ty)| TypeConstraint {
-                    ident: name.clone(),
-                    variants: vec![ty.clone()],
-                });
+    // replace generic declarations with their instances
+    let decls = std::mem::take(&mut wesl.global_declarations);
+    wesl.global_declarations = decls
+        .into_iter()
+        .flat_map(|decl| {
+            let idx = decl
+                .ident()
+                .and_then(|ident| inst.indices.get(&*ident.name()).copied());
+            match idx {
+                Some(idx) => std::mem::take(&mut inst.generics[idx].decls),
+                None => vec![decl],
+            }
+        })
+        .collect();
 
-                // evaluate type attributes
-                for ty in constraints {
-                    // this is future-proofing. We'll want to generate fewer variants in
-                    // the future by grouping them.
-                    eval_ty_attrs(&mut decl.parameters, &ty)?;
-                    stat_eval_ty_attrs(&mut decl.body.statements, &ty)?;
-                }
+    Ok(())
+}
 
-                // remove evaluated type attributes
-                for stmt in &mut decl.body.statements {
-                    for attrs in Visit::<Attributes>::visit_mut(stmt.node_mut()) {
-                        attrs.retain(|attr| match attr {
-                            Attribute::Type(c) => !c.variants.is_empty(),
-                            _ => true,
-                        })
-                    }
-                }
+impl Instantiator<'_> {
+    #[allow(clippy::result_large_err)]
+    fn instantiate_decl(&mut self, decl: &mut GlobalDeclaration) -> Result<(), Diagnostic<Error>> {
+        let decl_name = decl.ident().map(|ident| ident.name().to_string());
+        for ty in Visit::<TypeExpression>::visit_mut(decl) {
+            self.instantiate_ty(ty).map_err(|e| {
+                let mut d = Diagnostic::from(e);
+                d.declaration = decl_name.clone();
+                d
+            })?;
+        }
+        Ok(())
+    }
 
-                let signature = decl.parameters.iter().map(|p| p.ty.clone()).collect_vec();
+    fn instantiate_ty(&mut self, ty: &mut TypeExpression) -> Result<(), E> {
+        // template arguments are instantiated first, e.g. `foo<Bar<f32>>`.
+        for ty in Visit::<TypeExpression>::visit_mut(ty) {
+            self.instantiate_ty(ty)?;
+        }
+
+        let Some(&idx) = self.indices.get(&*ty.ident.name()) else {
+            return Ok(());
+        };
+        let name = ty.ident.name().to_string();
+        let args = ty
+            .template_args
+            .take()
+            .ok_or_else(|| E::MissingTemplateArgs(name.clone()))?
+            .into_iter()
+            .map(|arg| match arg.expression.into_inner() {
+                Expression::TypeOrIdentifier(ty) => Ok(ty),
+                _ => Err(E::InvalidTemplateArg(name.clone())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-                let new_name = mangle::mangle(&decl.ident.name(), &signature);
-                decl.ident = Ident::new(new_name);
-                new_decls.push(decl.into());
+        ty.ident = self.instance(idx, args)?;
+        Ok(())
+    }
+
+    /// Get the instance of a generic declaration, or create it.
+    fn instance(&mut self, idx: usize, args: Vec<TypeExpression>) -> Result<Ident, E> {
+        let generic = &mut self.generics[idx];
+        let name = generic.decl.ident().unwrap().name().to_string();
+
+        if args.len() != generic.params.len() {
+            return Err(E::TemplateArgCount(name, generic.params.len(), args.len()));
+        }
+        for (param, arg) in generic.params.iter().zip(&args) {
+            if !contains(&param.variants, arg) {
+                let variants = param
+                    .variants
+                    .iter()
+                    .map(|ty| format!("`{ty}`"))
+                    .format(", ")
+                    .to_string();
+                return Err(E::UnsatisfiedConstraint(
+                    name,
+                    param.ident.to_string(),
+                    arg.to_string(),
+                    variants,
+                ));
+            }
+        }
+
+        let key = args.iter().format(", ").to_string();
+        if let Some((_, ident)) = generic.instances.iter().find(|(k, _)| *k == key) {
+            return Ok(ident.clone());
+        }
+
+        let mut decl = generic.decl.clone();
+        if let Some(attrs) = decl_attributes_mut(&mut decl) {
+            attrs.retain(|attr| !attr.is_type());
+        }
+
+        // replace uses of the type parameters with the template arguments
+        for (param, arg) in generic.params.iter().zip(&args) {
+            for ty in Visit::<TypeExpression>::visit_mut(&mut decl) {
+                substitute(ty, &param.ident, arg)?;
             }
         }
+
+        if let GlobalDeclaration::Function(decl) = &mut decl {
+            // evaluate type attributes
+            for (param, arg) in generic.params.iter().zip(&args) {
+                let ty = TypeConstraint {
+                    ident: param.ident.clone(),
+                    variants: vec![arg.clone()],
+                };
+                eval_ty_attrs(&mut decl.parameters, &ty)?;
+                stat_eval_ty_attrs(&mut decl.body.statements, &ty)?;
+            }
+
+            // remove evaluated type attributes
+            for stmt in &mut decl.body.statements {
+                for attrs in Visit::<Attributes>::visit_mut(stmt.node_mut()) {
+                    attrs.retain(|attr| match attr {
+                        Attribute::Type(c) => !c.variants.is_empty(),
+                        _ => true,
+                    })
+                }
+            }
+
+            fresh_locals(decl);
+        }
+
+        let mut variant = generic.instances.len() as u32;
+        let mut new_name = self.mangler.mangle_types(&name, variant, &args);
+        while self.names.contains(&new_name) {
+            variant += 1;
+            new_name = self.mangler.mangle_types(&name, variant, &args);
+        }
+        self.names.insert(new_name.clone());
+
+        let ident = Ident::new(new_name);
+        *decl.ident_mut().unwrap() = ident.clone();
+        generic.instances.push((key, ident.clone()));
+        self.pending.push_back((idx, decl));
+        Ok(ident)
     }
+}
 
-    // remove generic function declarations
-    wesl.global_declarations.retain(|decl| !matches!(decl, GlobalDeclaration::Function(f) if f.attributes.iter().any(|attr| attr.is_type())));
+/// The type parameters of a generic declaration.
+fn type_params(decl: &GlobalDeclaration) -> Vec<TypeConstraint> {
+    match decl {
+        GlobalDeclaration::TypeAlias(_)
+        | GlobalDeclaration::Struct(_)
+        | GlobalDeclaration::Function(_) => decl
+            .attributes()
+            .iter()
+            .filter_map(|attr| match attr {
+                Attribute::Type(t) => Some(t.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
 
-    // add generic variants
-    wesl.global_declarations.extend(new_decls);
+fn decl_attributes_mut(decl: &mut GlobalDeclaration) -> Option<&mut Attributes> {
+    match decl {
+        GlobalDeclaration::TypeAlias(decl) => Some(&mut decl.attributes),
+        GlobalDeclaration::Struct(decl) => Some(&mut decl.attributes),
+        GlobalDeclaration::Function(decl) => Some(&mut decl.attributes),
+        _ => None,
+    }
+}
 
+fn substitute(ty: &mut TypeExpression, param: &Ident, arg: &TypeExpression) -> Result<(), E> {
+    if ty.ident == *param {
+        if ty.template_args.is_some() {
+            return Err(E::DisallowedTemplate);
+        }
+        *ty = arg.clone();
+    } else {
+        for ty in Visit::<TypeExpression>::visit_mut(ty) {
+            substitute(ty, param, arg)?;
+        }
+    }
     Ok(())
 }
 
-pub fn replace_calls(wesl: &mut TranslationUnit) -> Result<(), E> {
-    let idents = wesl
-        .global_declarations
-        .iter()
-        .filter_map(|decl| decl.ident())
-        .cloned()
-        .collect_vec();
-    for expr in Visit::<ExpressionNode>::visit_mut(wesl) {
-        if let Expression::FunctionCall(f) = expr.node_mut() {
-            if let Some(args) = &f.ty.template_args {
-                let signature = args
-                    .iter()
-                    .map(|arg| match arg.expression.node() {
-                        Expression::Literal(_) => todo!("literal generics"),
-                        Expression::TypeOrIdentifier(ty) => ty.clone(),
-                        _ => panic!("invalid template arg"),
-                    })
-                    .collect_vec();
+/// Types are compared by name: identifiers of builtin types are not shared.
+fn contains(variants: &[TypeExpression], ty: &TypeExpression) -> bool {
+    let ty = ty.to_string();
+    variants.iter().any(|v| v.to_string() == ty)
+}
 
-                let new_name = mangle::mangle(&f.ty.ident.name(), &signature);
-                f.ty.ident = idents
-                    .iter()
-                    .find(|ident| *ident.name() == new_name)
-                    .unwrap()
-                    .clone();
-                f.ty.template_args = None;
-            }
+/// Give new identifiers to the parameters and local declarations of a function instance,
+/// so they are not shared with other instances of the same generic function.
+fn fresh_locals(decl: &mut Function) {
+    fn rec(stmt: &mut StatementNode, locals: &mut Vec<(Ident, Ident)>) {
+        if let Statement::Declaration(decl) = stmt.node_mut() {
+            let ident = Ident::new(decl.ident.name().to_string());
+            let old = std::mem::replace(&mut decl.ident, ident.clone());
+            locals.push((old, ident));
+        }
+        for stmt in Visit::<StatementNode>::visit_mut(stmt.node_mut()) {
+            rec(stmt, locals);
         }
-        // TODO recursive
-        // expr.visit_mut()
     }
-    Ok(())
+    fn retarget(ty: &mut TypeExpression, locals: &[(Ident, Ident)]) {
+        if let Some((_, ident)) = locals.iter().find(|(old, _)| *old == ty.ident) {
+            ty.ident = ident.clone();
+        }
+        for ty in Visit::<TypeExpression>::visit_mut(ty) {
+            retarget(ty, locals);
+        }
+    }
+
+    let mut locals = Vec::new();
+    for param in &mut decl.parameters {
+        let ident = Ident::new(param.ident.name().to_string());
+        let old = std::mem::replace(&mut param.ident, ident.clone());
+        locals.push((old, ident));
+    }
+    for stmt in &mut decl.body.statements {
+        rec(stmt, &mut locals);
+    }
+    for ty in Visit::<TypeExpression>::visit_mut(decl) {
+        retarget(ty, &locals);
+    }
 }
 
 fn eval_ty_attr(opt_node: &mut Option<impl Decorated>, ty: &TypeConstraint) -> Result<(), E> {
@@ -133,12 +324,12 @@ fn eval_ty_attr(opt_node: &mut Option<impl Decorated>, ty: &TypeConstraint) -> R
                 Attribute::Type(TypeConstraint {
                     ident: name,
                     variants,
-                }) if name == &ty.ident => Some(variants),
+                }) if *name.name() == *ty.ident.name() => Some(variants),
                 _ => None,
             });
 
         if let Some(vars) = vars {
-            let keep = ty.variants.iter().all(|ty| vars.contains(ty));
+            let keep = ty.variants.iter().all(|ty| contains(vars, ty));
             if !keep {
                 *opt_node = None;
             }
@@ -155,12 +346,12 @@ fn eval_ty_attrs(nodes: &mut Vec<impl Decorated>, ty: &TypeConstraint) -> Result
                 Attribute::Type(TypeConstraint {
                     ident: name,
                     variants,
-                }) if name == &ty.ident => Some(variants),
+                }) if *name.name() == *ty.ident.name() => Some(variants),
                 _ => None,
             });
 
             if let Some(vars) = vars {
-                ty.variants.iter().all(|ty| vars.contains(ty))
+                ty.variants.iter().all(|ty| contains(vars, ty))
             } else {
                 true
             }
@@ -178,7 +369,7 @@ fn eval_ty_attrs(nodes: &mut Vec<impl Decorated>, ty: &TypeConstraint) -> Result
                     Attribute::Type(TypeConstraint {
                         ident: name,
                         variants,
-                    }) if name == &ty.ident => Some(variants),
+                    }) if *name.name() == *ty.ident.name() => Some(variants),
                     _ => None,
                 });
             if let Some(ty_attr) = ty_attr {
@@ -246,3 +437,59 @@ fn stat_eval_ty_attrs(statements: &mut Vec<StatementNode>, ty: &TypeConstraint)
     }
     rec(statements, ty)
 }
+
+#[cfg(all(test, feature = "imports"))]
+mod tests {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    #[test]
+    fn test_generics() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::math::{sum_sq, Pair};
+            @fragment fn fs() -> @location(0) vec4f {
+                return vec4f(sum_sq<f32>(Pair<f32>(1.0, 2.0)));
+            }"
+            .into(),
+        );
+        resolver.add_module(
+            "math",
+            "@type(T, f32 | u32 | i32)
+            fn square(x: T) -> T { return x * x; }
+            @type(T, f32 | u32)
+            fn sum_sq(p: Pair<T>) -> T { return square<T>(p.a) + square<T>(p.b); }
+            @type(T, f32 | u32)
+            struct Pair { a: T, b: T }"
+                .into(),
+        );
+        resolver.add_module(
+            "bad",
+            "import package::math::square;
+            @compute @workgroup_size(1) fn main() { let x = square<bool>(true); }"
+                .into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions {
+            generics: true,
+            lower: false,
+            ..Default::default()
+        });
+        let compiler = compiler.set_custom_resolver(resolver);
+
+        // only the instances that are used are generated.
+        let wgsl = compiler.compile("main").unwrap().to_string();
+        assert_eq!(wgsl.matches("fn square_").count(), 1);
+        assert_eq!(wgsl.matches("fn sum_sq_").count(), 1);
+        assert_eq!(wgsl.matches("struct Pair_").count(), 1);
+        assert!(wgsl.contains("fn square_0(x: f32) -> f32"));
+        assert!(!wgsl.contains("@type"));
+
+        let Err(err) = compiler.compile("bad") else {
+            panic!("expected an error");
+        };
+        assert!(err
+            .to_string()
+            .contains("type `bool` is not allowed for `T`"));
+    }
+}
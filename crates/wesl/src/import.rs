@@ -7,10 +7,11 @@ use std::{
 
 use itertools::Itertools;
 use wgsl_parse::syntax::{
-    self, Ident, ImportContent, ImportStatement, ModulePath, TranslationUnit, TypeExpression,
+    self, GlobalDeclaration, Ident, ImportContent, ImportStatement, ModulePath, TranslationUnit,
+    TypeExpression,
 };
 
-use crate::{visit::Visit, Mangler, ResolveError, Resolver};
+use crate::{overload::OverloadGroup, visit::Visit, Mangler, ResolveError, Resolver};
 
 type Imports = HashMap<Ident, (ModulePath, Ident)>;
 type Decls = HashMap<ModulePath, HashSet<usize>>;
@@ -65,6 +66,7 @@ impl Module {
 pub(crate) struct Resolutions {
     modules: Modules,
     order: Vec<ModulePath>,
+    overloads: HashMap<(ModulePath, String), OverloadGroup>,
 }
 
 impl Resolutions {
@@ -74,9 +76,33 @@ impl Resolutions {
     pub(crate) fn modules(&self) -> impl Iterator<Item = Ref<Module>> {
         self.order.iter().map(|res| self.modules[res].borrow())
     }
+    /// The functions overloaded across modules, see [`OverloadGroup`].
+    pub(crate) fn overloads(&self) -> Vec<OverloadGroup> {
+        self.overloads.values().cloned().collect()
+    }
+}
+
+/// The items a module refers to with `name`: its declaration and the items imported as
+/// `name`. A name refers to several items when a function is overloaded across modules.
+fn name_items<'a>(
+    path: &ModulePath,
+    imports: impl IntoIterator<Item = (&'a Ident, &'a (ModulePath, Ident))>,
+    mut idents: impl Iterator<Item = &'a Ident>,
+    name: &str,
+) -> Vec<(ModulePath, String)> {
+    let local = idents
+        .any(|id| *id.name() == name)
+        .then(|| (path.clone(), name.to_string()));
+    // sorted, the order of the items is the order of the declarations in the output.
+    let imported = imports
+        .into_iter()
+        .filter(|(id, _)| *id.name() == name)
+        .map(|(_, (path, id))| (path.clone(), id.name().to_string()))
+        .sorted_by_key(|(path, name)| (path.to_string(), name.clone()));
+    local.into_iter().chain(imported).unique().collect()
 }
 
-fn resolve_inline_path(
+pub(crate) fn resolve_inline_path(
     path: &ModulePath,
     parent_path: &ModulePath,
     imports: &Imports,
@@ -178,6 +204,48 @@ pub fn resolve_lazy(
             )?;
         }
 
+        // a name declared and imported, or imported from several modules, refers to the
+        // overloads of a function. They are all used.
+        if ty.path.is_none()
+            && (mod_imports.contains_key(&ty.ident) || mod_idents.contains_key(&ty.ident))
+        {
+            let name = ty.ident.name().to_string();
+            let items = name_items(mod_path, mod_imports, mod_idents.keys(), &name);
+            if items.len() > 1 {
+                let key = (mod_path.clone(), name);
+                if let Some(group) = resolutions.overloads.get(&key) {
+                    ty.ident = group[0].clone();
+                    return Ok(());
+                }
+                let mut group = OverloadGroup::new();
+                for (ext_path, ext_name) in items {
+                    let ext_id = if &ext_path == mod_path {
+                        let (id, decl) = mod_idents
+                            .iter()
+                            .find(|(id, _)| *id.name() == ext_name)
+                            .ok_or_else(|| {
+                            E::MissingDecl(ext_path.clone(), ext_name.clone())
+                        })?;
+                        local_decls.insert(*decl);
+                        id.clone()
+                    } else {
+                        resolve_extern(
+                            mod_path,
+                            &ext_path,
+                            &ext_name,
+                            extern_decls,
+                            resolutions,
+                            resolver,
+                        )?
+                    };
+                    group.push(ext_id);
+                }
+                ty.ident = group[0].clone();
+                resolutions.overloads.insert(key, group);
+                return Ok(());
+            }
+        }
+
         if mod_treated_idents.contains(&ty.ident) {
             return Ok(());
         }
@@ -206,8 +274,31 @@ pub fn resolve_lazy(
             }
         }
 
+        ty.path = None;
+        ty.ident = resolve_extern(
+            mod_path,
+            &ext_path,
+            &ext_id.name(),
+            extern_decls,
+            resolutions,
+            resolver,
+        )?;
+        Ok(())
+    }
+
+    /// The ident of the declaration `ext_name` in the external module, which is marked
+    /// as used.
+    #[allow(clippy::result_large_err)]
+    fn resolve_extern(
+        mod_path: &ModulePath,
+        ext_path: &ModulePath,
+        ext_name: &str,
+        extern_decls: &mut Decls,
+        resolutions: &mut Resolutions,
+        resolver: &impl Resolver,
+    ) -> Result<Ident, E> {
         // get or load the external module
-        let ext_mod = load_module(&ext_path, &mut HashSet::new(), resolutions, resolver)?;
+        let ext_mod = load_module(ext_path, &mut HashSet::new(), resolutions, resolver)?;
         let mut ext_mod = ext_mod
             .try_borrow_mut()
             .map_err(|_| E::CircularDependency(mod_path.clone()))?;
@@ -217,17 +308,18 @@ pub fn resolve_lazy(
         let (ext_id, ext_decl) = ext_mod
             .idents
             .iter()
-            .find(|(id, _)| *id.name() == *ext_id.name())
+            .find(|(id, _)| *id.name() == ext_name)
             .map(|(id, decl)| (id.clone(), *decl))
-            .ok_or_else(|| E::MissingDecl(ext_path.clone(), ext_id.to_string()))?;
+            .ok_or_else(|| E::MissingDecl(ext_path.clone(), ext_name.to_string()))?;
 
         if !ext_mod.treated_idents.contains(&ext_id) {
-            extern_decls.entry(ext_path).or_default().insert(ext_decl);
+            extern_decls
+                .entry(ext_path.clone())
+                .or_default()
+                .insert(ext_decl);
         }
 
-        ty.path = None;
-        ty.ident = ext_id;
-        Ok(())
+        Ok(ext_id)
     }
 
     fn resolve_decl(
@@ -246,6 +338,17 @@ pub fn resolve_lazy(
             }
         }
 
+        // overloaded functions share the same name, they are all used together.
+        if let GlobalDeclaration::Function(f) = decl {
+            let name = f.ident.name().to_string();
+            let overloads = module
+                .idents
+                .iter()
+                .filter(|(id, _)| *id.name() == name && !module.treated_idents.contains(*id))
+                .map(|(_, decl)| *decl);
+            local_decls.extend(overloads);
+        }
+
         for ty in Visit::<TypeExpression>::visit_mut(decl) {
             resolve_ty(
                 &module.path,
@@ -367,6 +470,39 @@ pub fn resolve_eager(
         }
 
         for ty in Visit::<TypeExpression>::visit_mut(&mut module.source) {
+            // a name declared and imported, or imported from several modules, refers to
+            // the overloads of a function.
+            if ty.path.is_none()
+                && (module.imports.contains_key(&ty.ident) || module.idents.contains_key(&ty.ident))
+            {
+                let name = ty.ident.name().to_string();
+                let items = name_items(&module.path, &module.imports, module.idents.keys(), &name);
+                if items.len() > 1 {
+                    let key = (module.path.clone(), name);
+                    if let Some(group) = resolutions.overloads.get(&key) {
+                        ty.ident = group[0].clone();
+                        continue;
+                    }
+                    let mut group = OverloadGroup::new();
+                    for (ext_res, ext_name) in items {
+                        let ext_id = if ext_res == module.path {
+                            module
+                                .idents
+                                .keys()
+                                .find(|id| *id.name() == ext_name)
+                                .cloned()
+                                .ok_or_else(|| E::MissingDecl(ext_res.clone(), ext_name))?
+                        } else {
+                            extern_ident(&ext_res, &ext_name, resolutions, resolver)?
+                        };
+                        group.push(ext_id);
+                    }
+                    ty.ident = group[0].clone();
+                    resolutions.overloads.insert(key, group);
+                    continue;
+                }
+            }
+
             let (ext_res, ext_id) = if let Some(path) = &ty.path {
                 let res = resolve_inline_path(path, &module.path, &module.imports);
                 (res, ty.ident.clone())
@@ -386,34 +522,44 @@ pub fn resolve_eager(
                 }
             }
 
-            // load the external module for this external ident
-            let ext_mod = if let Some(module) = resolutions.modules.get(&ext_res) {
-                module.clone()
-            } else {
-                let source = resolver.resolve_module(&ext_res)?;
-                let module = Module::new(source, ext_res.clone());
-                let module = Rc::new(RefCell::new(module));
-                resolutions.push_module(ext_res.clone(), module.clone());
-                resolve_module(module.borrow_mut().deref_mut(), resolutions, resolver)?;
-                module
-            };
-
-            // get the ident of the external declaration pointed to by the type
-            let ext_id = ext_mod
-                .borrow() // safety: only 1 module is borrowed at a time, the current one.
-                .idents
-                .iter()
-                .find(|(id, _)| *id.name() == *ext_id.name())
-                .map(|(id, _)| id.clone())
-                .ok_or_else(|| E::MissingDecl(ext_res.clone(), ext_id.to_string()))?;
-
             ty.path = None;
-            ty.ident = ext_id;
+            ty.ident = extern_ident(&ext_res, &ext_id.name(), resolutions, resolver)?;
         }
 
         Ok(())
     }
 
+    /// The ident of the declaration `ext_name` in the external module.
+    #[allow(clippy::result_large_err)]
+    fn extern_ident(
+        ext_res: &ModulePath,
+        ext_name: &str,
+        resolutions: &mut Resolutions,
+        resolver: &impl Resolver,
+    ) -> Result<Ident, E> {
+        // load the external module for this external ident
+        let ext_mod = if let Some(module) = resolutions.modules.get(ext_res) {
+            module.clone()
+        } else {
+            let source = resolver.resolve_module(ext_res)?;
+            let module = Module::new(source, ext_res.clone());
+            let module = Rc::new(RefCell::new(module));
+            resolutions.push_module(ext_res.clone(), module.clone());
+            resolve_module(module.borrow_mut().deref_mut(), resolutions, resolver)?;
+            module
+        };
+
+        // get the ident of the external declaration pointed to by the type
+        let ext_id = ext_mod
+            .borrow() // safety: only 1 module is borrowed at a time, the current one.
+            .idents
+            .iter()
+            .find(|(id, _)| *id.name() == ext_name)
+            .map(|(id, _)| id.clone())
+            .ok_or_else(|| E::MissingDecl(ext_res.clone(), ext_name.to_string()))?;
+        Ok(ext_id)
+    }
+
     resolve_module(module.borrow_mut().deref_mut(), &mut resolutions, resolver)?;
 
     Ok(resolutions)
@@ -463,6 +609,7 @@ impl Resolutions {
         Resolutions {
             modules: Default::default(),
             order: Default::default(),
+            overloads: Default::default(),
         }
     }
     fn push_module(&mut self, path: ModulePath, module: Rc<RefCell<Module>>) {
@@ -1,52 +1,83 @@
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "bindgen")]
+mod bindgen;
 #[cfg(feature = "condcomp")]
 mod condcomp;
+#[cfg(feature = "package")]
+mod doc;
 #[cfg(feature = "eval")]
 pub mod eval;
 #[cfg(feature = "generics")]
 mod generics;
 #[cfg(feature = "imports")]
 mod import;
+mod overload;
 #[cfg(feature = "package")]
 mod package;
+#[cfg(feature = "eval")]
+mod reflect;
+#[cfg(feature = "naga")]
+mod to_naga;
+#[cfg(feature = "condcomp")]
+mod variants;
 
+mod deps;
 mod error;
+mod lint;
 mod lower;
 mod mangle;
 mod resolve;
+mod session;
 mod sourcemap;
 mod strip;
 mod syntax_util;
 mod validate;
 mod visit;
 
+#[cfg(feature = "bindgen")]
+pub use bindgen::{bindgen, BindgenError, BindgenOptions};
+
 #[cfg(feature = "condcomp")]
-pub use condcomp::CondCompError;
+pub use condcomp::{undeclared_features, CondCompError, FeatureInfo};
 
 #[cfg(feature = "imports")]
 pub use import::ImportError;
 
 #[cfg(feature = "eval")]
-pub use eval::{Eval, EvalError, Exec};
+pub use eval::{Eval, EvalError, Exec, ShaderStage};
+#[cfg(feature = "eval")]
+pub use reflect::{reflect, Binding, EntryPoint, Location, Override, Reflection};
 
 #[cfg(feature = "generics")]
 pub use generics::GenericsError;
 
+#[cfg(feature = "naga")]
+pub use to_naga::{to_naga, NagaError};
+
+#[cfg(feature = "package")]
+pub use doc::{DocFormat, DocPage};
 #[cfg(feature = "package")]
 pub use package::PkgBuilder;
 
+#[cfg(feature = "condcomp")]
+pub use variants::{permutations, Features, Variant, VariantError, Variants};
+
+pub use deps::Dependencies;
 pub use error::{Diagnostic, Error};
+pub use lint::{lint, Lint, LintConfig, LintError, LintRule};
 pub use lower::lower;
 pub use mangle::{CacheMangler, EscapeMangler, HashMangler, Mangler, NoMangler, UnicodeMangler};
 pub use resolve::{
     FileResolver, NoResolver, PkgModule, PkgResolver, Preprocessor, ResolveError, Resolver, Router,
     StandardResolver, VirtualResolver,
 };
-pub use sourcemap::{BasicSourceMap, SourceMap, SourceMapper};
+pub use session::CompileSession;
+pub use sourcemap::{BasicSourceMap, SourceMap, SourceMapper, SpanMap, SpanMapping};
 pub use strip::strip_except;
 pub use syntax_util::SyntaxUtil;
 pub use validate::{validate_wgsl, ValidateError};
+pub use wgsl_parse::format::{format, AttributePlacement, FormatOptions};
 pub use wgsl_parse::syntax;
 
 use std::{
@@ -55,12 +86,14 @@ use std::{
     path::Path,
 };
 
+use deps::DepsRecorder;
 use itertools::Itertools;
+use overload::OverloadGroup;
 use validate::validate_wesl;
 use wgsl_parse::syntax::{Ident, ModulePath, PathOrigin, TranslationUnit};
 
 /// Compilation options. Used in [`compile`] and [`Wesl::set_options`].
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CompileOptions {
     pub imports: bool,
     pub condcomp: bool,
@@ -136,6 +169,18 @@ macro_rules! wesl_pkg {
     };
 }
 
+/// Include the Rust structs generated with [`Wesl::build_bindings`] as a module.
+///
+/// The argument corresponds to the `out_name` passed to [`Wesl::build_bindings`].
+#[macro_export]
+macro_rules! wesl_bindings {
+    ($name:ident) => {
+        pub mod $name {
+            include!(concat!(env!("OUT_DIR"), "/", stringify!($name), ".rs"));
+        }
+    };
+}
+
 /// The WESL compiler high-level API.
 ///
 /// # Basic Usage
@@ -361,6 +406,10 @@ impl<R: Resolver> Wesl<R> {
 
     /// Enable generics.
     ///
+    /// Functions, structs and type aliases with `@type(T, f32 | u32)` attributes are
+    /// generic over `T`. They are instantiated when referenced with template arguments,
+    /// e.g. `foo<f32>(x)` or `Foo<u32>`.
+    ///
     /// # WESL Reference
     /// Generics is an *experimental* WESL extension.
     ///
@@ -372,6 +421,9 @@ impl<R: Resolver> Wesl<R> {
     }
     /// Set a conditional compilation feature flag.
     ///
+    /// Features set here override the defaults declared in WESL modules with a `feature`
+    /// directive, e.g. `feature shadows = true;`. See [`Self::features`].
+    ///
     /// # WESL Reference
     /// Conditional translation is a *mandatory* WESL extension.
     ///
@@ -490,12 +542,28 @@ impl<R: Resolver> Wesl<R> {
 pub struct CompileResult {
     pub syntax: TranslationUnit,
     pub sourcemap: Option<BasicSourceMap>,
+    /// The modules and files that were read during compilation.
+    pub deps: Dependencies,
+    /// Warnings of the compilation, e.g. feature flags used in `@if` attributes but
+    /// declared in none of the modules with a `feature` directive.
+    ///
+    /// Display them with [`Diagnostic::display_warning`].
+    pub warnings: Vec<Diagnostic<Error>>,
 }
 
 impl CompileResult {
     pub fn write_to_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
         std::fs::write(path, self.to_string())
     }
+
+    /// Compute the mapping from spans of the compiled WGSL to the source modules.
+    ///
+    /// Returns `None` if [`Wesl`] was invoked with sourcemapping disabled.
+    /// See [`SpanMap`].
+    pub fn span_map(&self) -> Option<SpanMap> {
+        let sourcemap = self.sourcemap.as_ref()?;
+        Some(SpanMap::new(&self.syntax, sourcemap))
+    }
 }
 
 impl Display for CompileResult {
@@ -535,6 +603,46 @@ impl<'a> ExecResult<'a> {
     }
 }
 
+/// The result of [`CompileResult::exec_vertex`] and [`CompileResult::exec_fragment`].
+///
+/// This type contains both the outputs of the entrypoint and the evaluation context
+/// (including bindings).
+#[cfg(feature = "eval")]
+pub struct StageResult<'a> {
+    /// The entrypoint outputs, keyed by `@location` or `@builtin`. `None` if the fragment
+    /// was discarded.
+    pub outputs: Option<HashMap<eval::ShaderIO, eval::Instance>>,
+    /// Context after execution
+    pub ctx: eval::Context<'a>,
+}
+
+#[cfg(feature = "eval")]
+impl<'a> StageResult<'a> {
+    /// Get the output with a `@location` attribute.
+    pub fn location(&self, location: u32) -> Option<&eval::Instance> {
+        self.outputs
+            .as_ref()?
+            .get(&eval::ShaderIO::Location(location))
+    }
+
+    /// Get the output with a `@builtin` attribute.
+    pub fn builtin(&self, builtin: syntax::BuiltinValue) -> Option<&eval::Instance> {
+        self.outputs
+            .as_ref()?
+            .get(&eval::ShaderIO::Builtin(builtin))
+    }
+
+    /// Whether the fragment was discarded.
+    pub fn is_discarded(&self) -> bool {
+        self.outputs.is_none()
+    }
+
+    /// Get a [shader resource](https://www.w3.org/TR/WGSL/#resource).
+    pub fn resource(&self, group: u32, binding: u32) -> Option<&eval::RefInstance> {
+        self.ctx.resource(group, binding)
+    }
+}
+
 /// The result of [`CompileResult::eval`].
 ///
 /// This type contains both the resulting WGSL instance and the evaluation context
@@ -599,6 +707,20 @@ impl CompileResult {
         Ok(res)
     }
 
+    /// Reflect the entry points, resource bindings and overrides of the compiled WGSL.
+    ///
+    /// Use it to build pipeline layouts. With the `serde` feature, the [`Reflection`]
+    /// serializes to JSON.
+    #[allow(clippy::result_large_err)]
+    pub fn reflect(&self) -> Result<Reflection, Error> {
+        let refl = reflect(&self.syntax);
+        if let Some(sourcemap) = &self.sourcemap {
+            refl.map_err(|e| Error::Error(e.with_sourcemap(sourcemap)))
+        } else {
+            refl.map_err(Error::Error)
+        }
+    }
+
     /// Execute an entrypoint in the same way that it would be executed on the GPU.
     ///
     /// Highly experimental.
@@ -633,6 +755,128 @@ impl CompileResult {
 
         Ok(ExecResult { inst, ctx })
     }
+
+    /// Dispatch a compute entrypoint in the same way that it would be dispatched on the
+    /// GPU, with `workgroups` the number of workgroups in each dimension.
+    ///
+    /// Every invocation is executed with its own builtin input values
+    /// (`global_invocation_id`, `local_invocation_id`, ...). Shader resources are shared
+    /// between invocations, and `workgroup` variables between the invocations of a
+    /// workgroup. Barriers are honored, see [`eval::dispatch_compute`].
+    /// The returned [`ExecResult`] never has a return value.
+    ///
+    /// Highly experimental.
+    #[allow(clippy::result_large_err)]
+    pub fn dispatch(
+        &self,
+        entrypoint: &str,
+        workgroups: [u32; 3],
+        bindings: HashMap<(u32, u32), eval::RefInstance>,
+        overrides: HashMap<String, eval::Instance>,
+    ) -> Result<ExecResult<'_>, Error> {
+        let (res, ctx) = dispatch(entrypoint, &self.syntax, workgroups, bindings, overrides);
+        res.map_err(|e| {
+            if let Some(sourcemap) = &self.sourcemap {
+                Diagnostic::from(e)
+                    .with_source(entrypoint.to_string())
+                    .with_ctx(&ctx)
+                    .with_sourcemap(sourcemap)
+            } else {
+                Diagnostic::from(e)
+                    .with_source(entrypoint.to_string())
+                    .with_ctx(&ctx)
+            }
+        })?;
+
+        Ok(ExecResult { inst: None, ctx })
+    }
+
+    /// Execute a single invocation of a vertex entrypoint.
+    ///
+    /// `inputs` are the vertex attributes and builtin input values, keyed by `@location`
+    /// or `@builtin`. They are converted to the types of the entrypoint parameters or
+    /// structure members. See [`eval::exec_vertex`].
+    ///
+    /// Highly experimental.
+    #[allow(clippy::result_large_err)]
+    pub fn exec_vertex(
+        &self,
+        entrypoint: &str,
+        inputs: HashMap<eval::ShaderIO, eval::Instance>,
+        bindings: HashMap<(u32, u32), eval::RefInstance>,
+        overrides: HashMap<String, eval::Instance>,
+    ) -> Result<StageResult<'_>, Error> {
+        let (res, ctx) = exec_vertex(entrypoint, &self.syntax, inputs, bindings, overrides);
+        let outputs = res.map_err(|e| self.stage_error(e, entrypoint, &ctx))?;
+        Ok(StageResult {
+            outputs: Some(outputs),
+            ctx,
+        })
+    }
+
+    /// Execute a single invocation of a fragment entrypoint.
+    ///
+    /// `inputs` are the inter-stage variables and builtin input values, keyed by
+    /// `@location` or `@builtin`. They are converted to the types of the entrypoint
+    /// parameters or structure members. See [`eval::exec_fragment`].
+    ///
+    /// Highly experimental.
+    #[allow(clippy::result_large_err)]
+    pub fn exec_fragment(
+        &self,
+        entrypoint: &str,
+        inputs: HashMap<eval::ShaderIO, eval::Instance>,
+        bindings: HashMap<(u32, u32), eval::RefInstance>,
+        overrides: HashMap<String, eval::Instance>,
+    ) -> Result<StageResult<'_>, Error> {
+        let (res, ctx) = exec_fragment(entrypoint, &self.syntax, inputs, bindings, overrides);
+        let outputs = res.map_err(|e| self.stage_error(e, entrypoint, &ctx))?;
+        Ok(StageResult { outputs, ctx })
+    }
+
+    fn stage_error(&self, e: EvalError, entrypoint: &str, ctx: &eval::Context) -> Error {
+        let diagnostic = Diagnostic::from(e)
+            .with_source(entrypoint.to_string())
+            .with_ctx(ctx);
+        match &self.sourcemap {
+            Some(sourcemap) => diagnostic.with_sourcemap(sourcemap),
+            None => diagnostic,
+        }
+        .into()
+    }
+}
+
+#[cfg(feature = "naga")]
+impl CompileResult {
+    /// Lower the compiled WGSL to a [`naga::Module`].
+    ///
+    /// Contrary to parsing the WGSL output with naga's front-end, spans in the module
+    /// point to the original WESL sources when a sourcemap is available. The module
+    /// is not validated, use [`naga::valid::Validator`] for that.
+    #[allow(clippy::result_large_err)]
+    pub fn to_naga(&self) -> Result<naga::Module, Error> {
+        let module = to_naga(&self.syntax);
+        if let Some(sourcemap) = &self.sourcemap {
+            module.map_err(|e| Error::Error(e.with_sourcemap(sourcemap)))
+        } else {
+            module.map_err(Error::Error)
+        }
+    }
+}
+
+#[cfg(feature = "bindgen")]
+impl CompileResult {
+    /// Generate Rust structs with the memory layout of the WGSL structs used in `uniform`
+    /// and `storage` bindings. See [`bindgen`].
+    #[allow(clippy::result_large_err)]
+    pub fn bindgen(&self, options: &BindgenOptions) -> Result<String, Error> {
+        let code = bindgen(&self.syntax, options);
+        if let Some(sourcemap) = &self.sourcemap {
+            code.map_err(|e| Error::Error(e.with_sourcemap(sourcemap)))
+        } else {
+            code.map_err(Error::Error)
+        }
+    }
 }
 
 impl<R: Resolver> Wesl<R> {
@@ -644,20 +888,37 @@ impl<R: Resolver> Wesl<R> {
         let mut root = root.into();
         root.origin = PathOrigin::Absolute; // we force absolute paths
 
-        if self.use_sourcemap {
+        let deps = DepsRecorder::new(&self.resolver);
+        // feature flags are recorded before conditional translation.
+        #[cfg(feature = "condcomp")]
+        let resolver = condcomp::FeatureRecorder::new(&deps);
+        #[cfg(not(feature = "condcomp"))]
+        let resolver = &deps;
+
+        let (syntax, sourcemap) = if self.use_sourcemap {
             let (syntax, sourcemap) =
-                compile_sourcemap(&root, &self.resolver, &self.mangler, &self.options);
-            Ok(CompileResult {
-                syntax: syntax?,
-                sourcemap: Some(sourcemap),
-            })
+                compile_sourcemap(&root, &resolver, &self.mangler, &self.options);
+            (syntax?, Some(sourcemap))
         } else {
-            let syntax = compile(&root, &self.resolver, &self.mangler, &self.options);
-            Ok(CompileResult {
-                syntax: syntax?,
-                sourcemap: None,
-            })
-        }
+            let syntax = compile(&root, &resolver, &self.mangler, &self.options);
+            (syntax?, None)
+        };
+
+        #[cfg(feature = "condcomp")]
+        let warnings = if self.options.condcomp {
+            resolver.finish()
+        } else {
+            Vec::new()
+        };
+        #[cfg(not(feature = "condcomp"))]
+        let warnings = Vec::new();
+
+        Ok(CompileResult {
+            syntax,
+            sourcemap,
+            deps: deps.finish(),
+            warnings,
+        })
     }
 
     /// Compile a WESL program from a root file and output the result in rust's `OUT_DIR`.
@@ -670,6 +931,9 @@ impl<R: Resolver> Wesl<R> {
     ///   directory.
     /// * The second argument is the name of the artefact, used in [`include_wesl`].
     ///
+    /// The files read during compilation are emitted as `cargo:rerun-if-changed`
+    /// instructions, so that the build script reruns when one of them changes.
+    ///
     /// # Panics
     /// Panics when compilation fails or if the output file cannot be written.
     /// Pretty-prints the WESL error message to stderr.
@@ -683,15 +947,155 @@ impl<R: Resolver> Wesl<R> {
         }
         let mut output = Path::new(&dirname).join(out_name);
         output.set_extension("wgsl");
-        self.compile(entrypoint.clone())
+        let comp = self
+            .compile(entrypoint.clone())
             .inspect_err(|e| {
                 eprintln!("failed to build WESL shader `{entrypoint}`.\n{e}");
                 panic!();
             })
-            .unwrap()
-            .write_to_file(output)
+            .unwrap();
+        comp.deps.emit_rerun_if_changed();
+        comp.write_to_file(output)
             .expect("failed to write output shader");
     }
+
+    /// Compile a WESL program from a root file and generate the Rust structs of its
+    /// `uniform` and `storage` bindings in rust's `OUT_DIR`. See [`bindgen`].
+    ///
+    /// This function is meant to be used in a `build.rs` workflow, like
+    /// [`Self::build_artefact`]. The generated module is included with the
+    /// [`wesl_bindings`] macro.
+    ///
+    /// * The first argument is the path to the entrypoint file relative to the base
+    ///   directory.
+    /// * The second argument is the name of the generated module, used in
+    ///   [`wesl_bindings`].
+    ///
+    /// # Panics
+    /// Panics when compilation or code generation fails or if the output file cannot be
+    /// written. Pretty-prints the WESL error message to stderr.
+    #[cfg(feature = "bindgen")]
+    #[allow(clippy::result_large_err)]
+    pub fn build_bindings(
+        &self,
+        entrypoint: impl Into<ModulePath>,
+        out_name: &str,
+        options: &BindgenOptions,
+    ) {
+        let entrypoint = entrypoint.into();
+        let dirname = std::env::var("OUT_DIR").unwrap();
+        let out_name = Path::new(out_name);
+        if out_name.iter().count() != 1 || out_name.extension().is_some() {
+            eprintln!("`out_name` cannot contain path separators or file extension");
+            panic!()
+        }
+        let mut output = Path::new(&dirname).join(out_name);
+        output.set_extension("rs");
+        let code = self
+            .compile(entrypoint.clone())
+            .inspect(|comp| comp.deps.emit_rerun_if_changed())
+            .and_then(|comp| comp.bindgen(options))
+            .inspect_err(|e| {
+                eprintln!("failed to generate bindings of WESL shader `{entrypoint}`.\n{e}");
+                panic!();
+            })
+            .unwrap();
+        std::fs::write(output, code).expect("failed to write output bindings");
+    }
+
+    /// List the conditional compilation features declared or used by a root module and
+    /// the modules it imports, transitively.
+    ///
+    /// Features are declared in WESL with a `feature` directive, e.g.
+    /// `feature shadows = true;`. The doc comment of the directive is the description of
+    /// the feature.
+    #[cfg(feature = "condcomp")]
+    #[allow(clippy::result_large_err)]
+    pub fn features(&self, root: impl Into<ModulePath>) -> Result<Vec<FeatureInfo>, Error> {
+        let mut root = root.into();
+        root.origin = PathOrigin::Absolute; // we force absolute paths
+        let wesl = self.resolver.resolve_module(&root)?;
+
+        #[cfg(feature = "imports")]
+        {
+            // all imports are loaded, including those disabled by `@if` attributes.
+            let resolutions = import::resolve_eager(wesl, &root, &self.resolver)?;
+            let modules = resolutions.modules().collect_vec();
+            Ok(condcomp::features(
+                modules.iter().map(|module| (&module.path, &module.source)),
+            ))
+        }
+        #[cfg(not(feature = "imports"))]
+        Ok(condcomp::features([(&root, &wesl)]))
+    }
+
+    /// Run the lint rules on a root module and the modules it imports, transitively.
+    ///
+    /// Lints are run on the modules source, before conditional translation: code disabled
+    /// by `@if` attributes is linted too. See [`lint()`].
+    ///
+    /// The [`LintRule::DerivativeUniformity`] rule is an exception: the uniformity analysis
+    /// runs on the linked module, with the compiler options. Its lints are mapped back to
+    /// the source modules with the sourcemap.
+    #[allow(clippy::result_large_err)]
+    pub fn lint(
+        &self,
+        root: impl Into<ModulePath>,
+        config: &LintConfig,
+    ) -> Result<Vec<Lint>, Error> {
+        let mut root = root.into();
+        root.origin = PathOrigin::Absolute; // we force absolute paths
+
+        #[cfg(feature = "imports")]
+        let paths = {
+            let wesl = self.resolver.resolve_module(&root)?;
+            let resolutions = import::resolve_eager(wesl, &root, &self.resolver)?;
+            resolutions
+                .modules()
+                .map(|module| module.path.clone())
+                .collect_vec()
+        };
+        #[cfg(not(feature = "imports"))]
+        let paths = [root.clone()];
+
+        let mut res = Vec::new();
+        for path in paths {
+            // the syntax tree of resolved modules is modified, we parse the source again.
+            let source = self.resolver.resolve_source(&path)?;
+            let wesl = self.resolver.source_to_module(&source, &path)?;
+            let display_name = self.resolver.display_name(&path);
+            res.extend(lint(&wesl, config).into_iter().map(|mut lint| {
+                lint.diagnostic = lint
+                    .diagnostic
+                    .with_module_path(path.clone(), display_name.clone())
+                    .with_source(source.to_string());
+                lint
+            }));
+        }
+
+        let options = CompileOptions {
+            validate: false,
+            lower: false,
+            ..self.options.clone()
+        };
+        let (wgsl, sourcemap) = compile_sourcemap(&root, &self.resolver, &self.mangler, &options);
+        let severity = config.severity(LintRule::DerivativeUniformity);
+        // barrier violations are not lints, they are compilation errors.
+        let violations = validate::uniformity::analyze(&wgsl?, severity)
+            .into_iter()
+            .filter(|violation| violation.derivative);
+        res.extend(violations.map(|violation| {
+            Lint {
+                rule: LintRule::DerivativeUniformity,
+                severity: violation.severity,
+                diagnostic: violation
+                    .diagnostic
+                    .with_sourcemap(&sourcemap)
+                    .unmangle(Some(&sourcemap), Some(&self.mangler)),
+            }
+        }));
+        Ok(res)
+    }
 }
 
 fn keep_idents(wesl: &TranslationUnit, keep: &Option<Vec<String>>, strip: bool) -> HashSet<Ident> {
@@ -726,7 +1130,7 @@ fn compile_pre_assembly(
     mangler: &impl Mangler,
     options: &CompileOptions,
     root_decls: &mut Vec<String>,
-) -> Result<TranslationUnit, Error> {
+) -> Result<(TranslationUnit, Vec<OverloadGroup>), Error> {
     let resolver = Box::new(resolver);
 
     #[cfg(feature = "condcomp")]
@@ -750,7 +1154,7 @@ fn compile_pre_assembly(
         .collect_vec();
 
     #[cfg(feature = "imports")]
-    let wesl = if options.imports {
+    let (wesl, overloads) = if options.imports {
         let mut resolution = if options.lazy {
             let keep = keep_idents(&wesl, &options.keep, options.strip);
             import::resolve_lazy(wesl, root, keep, &resolver)?
@@ -765,23 +1169,36 @@ fn compile_pre_assembly(
                 })?;
             }
         }
-        resolution.assemble(options.strip)
+        (resolution.assemble(options.strip), resolution.overloads())
     } else {
-        wesl
+        (wesl, Vec::new())
     };
-    Ok(wesl)
+    #[cfg(not(feature = "imports"))]
+    let overloads = Vec::new();
+    Ok((wesl, overloads))
 }
 
 fn compile_post_assembly(
     wesl: &mut TranslationUnit,
+    overloads: &[OverloadGroup],
+    mangler: &impl Mangler,
     options: &CompileOptions,
     keep: &[String],
 ) -> Result<(), Error> {
+    // `feature` directives are not WGSL. They are stripped even when conditional
+    // translation is disabled.
+    #[cfg(feature = "condcomp")]
+    wesl.global_directives
+        .retain(|directive| !directive.is_feature());
     #[cfg(feature = "generics")]
     if options.generics {
-        generics::generate_variants(wesl)?;
-        generics::replace_calls(wesl)?;
+        generics::instantiate(wesl, mangler)?;
     };
+    // the type checker selects overloads, it only runs when a function is overloaded.
+    let overloads = overload::overload_sets(wesl, overloads)?;
+    if !overloads.is_empty() {
+        overload::resolve_overloads(wesl, overloads, mangler)?;
+    }
     if options.validate {
         validate_wgsl(wesl)?;
     }
@@ -799,9 +1216,10 @@ pub fn compile(
     options: &CompileOptions,
 ) -> Result<TranslationUnit, Diagnostic<Error>> {
     let mut root_names = Vec::new();
-    let mut wesl = compile_pre_assembly(root, resolver, mangler, options, &mut root_names)?;
+    let (mut wesl, overloads) =
+        compile_pre_assembly(root, resolver, mangler, options, &mut root_names)?;
     let keep = options.keep.as_deref().unwrap_or(&root_names);
-    compile_post_assembly(&mut wesl, options, keep)?;
+    compile_post_assembly(&mut wesl, &overloads, mangler, options, keep)?;
     Ok(wesl)
 }
 
@@ -815,14 +1233,23 @@ pub fn compile_sourcemap(
     let sourcemapper = SourceMapper::new(&resolver, &mangler);
     let mut root_names = Vec::new();
     let comp = compile_pre_assembly(root, &sourcemapper, &sourcemapper, options, &mut root_names);
-    let mut sourcemap = sourcemapper.finish();
     for name in &root_names {
-        sourcemap.add_decl(name.clone(), root.clone(), name.clone());
+        sourcemapper
+            .sourcemap
+            .borrow_mut()
+            .add_decl(name.clone(), root.clone(), name.clone());
     }
     let keep = options.keep.as_deref().unwrap_or(&root_names);
 
+    // the post-assembly passes name the instances of generic declarations.
+    let comp = comp.map(|(mut wesl, overloads)| {
+        let res = compile_post_assembly(&mut wesl, &overloads, &sourcemapper, options, keep);
+        (wesl, res)
+    });
+    let sourcemap = sourcemapper.finish();
+
     let comp = match comp {
-        Ok(mut wesl) => compile_post_assembly(&mut wesl, options, keep)
+        Ok((wesl, res)) => res
             .map_err(|e| {
                 Diagnostic::from(e)
                     .with_output(wesl.to_string())
@@ -894,6 +1321,87 @@ pub fn exec<'s>(
     (res, ctx)
 }
 
+/// Low-level version of [`CompileResult::dispatch`].
+#[cfg(feature = "eval")]
+pub fn dispatch<'s>(
+    entrypoint: &str,
+    wgsl: &'s TranslationUnit,
+    workgroups: [u32; 3],
+    bindings: HashMap<(u32, u32), eval::RefInstance>,
+    overrides: HashMap<String, eval::Instance>,
+) -> (Result<(), EvalError>, eval::Context<'s>) {
+    use eval::SyntaxUtil;
+
+    let mut ctx = eval::Context::new(wgsl);
+    ctx.add_bindings(bindings);
+    ctx.add_overrides(overrides);
+    ctx.set_stage(eval::EvalStage::Exec);
+
+    let res = wgsl.exec(&mut ctx).and_then(|_| {
+        let decl = wgsl
+            .decl_function(entrypoint)
+            .ok_or_else(|| EvalError::UnknownFunction(entrypoint.to_string()))?;
+        eval::dispatch_compute(decl, workgroups, &mut ctx)
+    });
+    (res, ctx)
+}
+
+/// Low-level version of [`CompileResult::exec_vertex`].
+#[cfg(feature = "eval")]
+pub fn exec_vertex<'s>(
+    entrypoint: &str,
+    wgsl: &'s TranslationUnit,
+    inputs: HashMap<eval::ShaderIO, eval::Instance>,
+    bindings: HashMap<(u32, u32), eval::RefInstance>,
+    overrides: HashMap<String, eval::Instance>,
+) -> (
+    Result<HashMap<eval::ShaderIO, eval::Instance>, EvalError>,
+    eval::Context<'s>,
+) {
+    use eval::SyntaxUtil;
+
+    let mut ctx = eval::Context::new(wgsl);
+    ctx.add_bindings(bindings);
+    ctx.add_overrides(overrides);
+    ctx.set_stage(eval::EvalStage::Exec);
+
+    let res = wgsl.exec(&mut ctx).and_then(|_| {
+        let decl = wgsl
+            .decl_function(entrypoint)
+            .ok_or_else(|| EvalError::UnknownFunction(entrypoint.to_string()))?;
+        eval::exec_vertex(decl, &inputs, &mut ctx)
+    });
+    (res, ctx)
+}
+
+/// Low-level version of [`CompileResult::exec_fragment`].
+#[cfg(feature = "eval")]
+pub fn exec_fragment<'s>(
+    entrypoint: &str,
+    wgsl: &'s TranslationUnit,
+    inputs: HashMap<eval::ShaderIO, eval::Instance>,
+    bindings: HashMap<(u32, u32), eval::RefInstance>,
+    overrides: HashMap<String, eval::Instance>,
+) -> (
+    Result<Option<HashMap<eval::ShaderIO, eval::Instance>>, EvalError>,
+    eval::Context<'s>,
+) {
+    use eval::SyntaxUtil;
+
+    let mut ctx = eval::Context::new(wgsl);
+    ctx.add_bindings(bindings);
+    ctx.add_overrides(overrides);
+    ctx.set_stage(eval::EvalStage::Exec);
+
+    let res = wgsl.exec(&mut ctx).and_then(|_| {
+        let decl = wgsl
+            .decl_function(entrypoint)
+            .ok_or_else(|| EvalError::UnknownFunction(entrypoint.to_string()))?;
+        eval::exec_fragment(decl, &inputs, &mut ctx)
+    });
+    (res, ctx)
+}
+
 #[test]
 fn test_send_sync() {
     fn assert_send_sync<T: Send + Sync>() {}
@@ -0,0 +1,737 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use wgsl_parse::{span::Span, syntax::*};
+
+use crate::{Diagnostic, Error};
+
+/// A lint rule. Each rule has a name, used in `diagnostic` directives and attributes and
+/// on the command-line, and a default severity.
+///
+/// Lint rules can be referred to by their name, e.g. `unused_import`, or prefixed with
+/// `wesl.`, e.g. `wesl.unused_import`, which is the WGSL convention for non-standard rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintRule {
+    /// An imported item is not used in the module.
+    UnusedImport,
+    /// A function-scope `const`, `let` or `var` declaration is not used. Names starting
+    /// with an underscore are ignored.
+    UnusedDeclaration,
+    /// A function-scope declaration has the same name as a declaration of an enclosing
+    /// scope.
+    Shadowing,
+    /// The condition of an `if`, `while`, `for` or `break if` statement is a constant.
+    ConstantCondition,
+    /// A statement follows a `return`, `break`, `continue` or `discard` statement.
+    UnreachableCode,
+    /// A derivative built-in function is called from non-uniform control flow. This is the
+    /// WGSL `derivative_uniformity` rule.
+    ///
+    /// The uniformity analysis needs the whole shader, it is run on the linked module by
+    /// [`crate::Wesl::lint`]. The [`lint`] function does not report it.
+    DerivativeUniformity,
+}
+
+impl LintRule {
+    /// All lint rules.
+    pub const ALL: [LintRule; 6] = [
+        LintRule::UnusedImport,
+        LintRule::UnusedDeclaration,
+        LintRule::Shadowing,
+        LintRule::ConstantCondition,
+        LintRule::UnreachableCode,
+        LintRule::DerivativeUniformity,
+    ];
+
+    /// The name of the rule.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::UnusedImport => "unused_import",
+            LintRule::UnusedDeclaration => "unused_declaration",
+            LintRule::Shadowing => "shadowing",
+            LintRule::ConstantCondition => "constant_condition",
+            LintRule::UnreachableCode => "unreachable_code",
+            LintRule::DerivativeUniformity => "derivative_uniformity",
+        }
+    }
+
+    /// The severity of the rule when it is not configured.
+    pub fn default_severity(&self) -> DiagnosticSeverity {
+        DiagnosticSeverity::Warning
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("wesl.").unwrap_or(s);
+        LintRule::ALL
+            .into_iter()
+            .find(|rule| rule.name() == name)
+            .ok_or(())
+    }
+}
+
+impl Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Lint diagnostic message.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum LintError {
+    #[error("unused import `{0}`")]
+    UnusedImport(String),
+    #[error("unused declaration `{0}`")]
+    UnusedDeclaration(String),
+    #[error("declaration of `{0}` shadows a previous declaration")]
+    Shadowing(String),
+    #[error("condition is always `{0}`")]
+    ConstantCondition(bool),
+    #[error("unreachable code after `{0}` statement")]
+    UnreachableCode(&'static str),
+}
+
+impl LintError {
+    /// The rule that produced the lint.
+    pub fn rule(&self) -> LintRule {
+        match self {
+            LintError::UnusedImport(_) => LintRule::UnusedImport,
+            LintError::UnusedDeclaration(_) => LintRule::UnusedDeclaration,
+            LintError::Shadowing(_) => LintRule::Shadowing,
+            LintError::ConstantCondition(_) => LintRule::ConstantCondition,
+            LintError::UnreachableCode(_) => LintRule::UnreachableCode,
+        }
+    }
+}
+
+/// The severity of each lint rule.
+///
+/// This sets the default severity of rules. Source code can override it with `diagnostic`
+/// directives (for the whole module) and `@diagnostic` attributes (for a function or a
+/// statement).
+#[derive(Clone, Debug, Default)]
+pub struct LintConfig {
+    severities: HashMap<LintRule, DiagnosticSeverity>,
+}
+
+impl LintConfig {
+    /// All rules have their default severity.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the severity of a rule. Use [`DiagnosticSeverity::Off`] to disable it.
+    pub fn set(&mut self, rule: LintRule, severity: DiagnosticSeverity) -> &mut Self {
+        self.severities.insert(rule, severity);
+        self
+    }
+
+    /// The configured severity of a rule.
+    pub fn severity(&self, rule: LintRule) -> DiagnosticSeverity {
+        self.severities
+            .get(&rule)
+            .cloned()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// A diagnostic produced by a lint rule. Display user-friendly snippets with `Display`.
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub rule: LintRule,
+    /// The severity of the lint. Never [`DiagnosticSeverity::Off`].
+    pub severity: DiagnosticSeverity,
+    pub diagnostic: Diagnostic<Error>,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use annotate_snippets::Level;
+        let level = match self.severity {
+            DiagnosticSeverity::Error => Level::Error,
+            DiagnosticSeverity::Warning => Level::Warning,
+            DiagnosticSeverity::Info | DiagnosticSeverity::Off => Level::Info,
+        };
+        let note = format!("lint rule `{}` is set to `{}`", self.rule, self.severity);
+        self.diagnostic.fmt_with_level(f, level, Some(&note))
+    }
+}
+
+/// Run the lint rules on a module.
+///
+/// The module must be the source of a single module: before import resolution and
+/// conditional translation. Lints do not have a module path and a source attached, see
+/// [`Diagnostic::with_module_path`] and [`Diagnostic::with_source`].
+///
+/// ```
+/// # use wesl::{lint, LintConfig, LintRule};
+/// let source = "fn f() -> u32 { let x = 1u; return 2u; }";
+/// let lints = lint(&source.parse().unwrap(), &LintConfig::new());
+/// assert_eq!(lints.len(), 1);
+/// assert_eq!(lints[0].rule, LintRule::UnusedDeclaration);
+/// ```
+pub fn lint(wesl: &TranslationUnit, config: &LintConfig) -> Vec<Lint> {
+    let mut linter = Linter::new(config);
+    linter.module(wesl);
+    let mut lints = linter.lints;
+    lints.sort_by_key(|lint| lint.diagnostic.span.as_ref().map(|span| span.range().start));
+    lints
+}
+
+/// A declaration in scope.
+struct Local {
+    name: String,
+    span: Option<Span>,
+    severity: DiagnosticSeverity,
+    used: bool,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    /// Severities set by `diagnostic` directives and attributes, innermost last.
+    overrides: Vec<(LintRule, DiagnosticSeverity)>,
+    /// Imported items. Those are module-scope declarations.
+    imports: Vec<Local>,
+    /// Module-scope declarations.
+    globals: Vec<String>,
+    /// Function-scope declarations, innermost scope last.
+    scopes: Vec<Vec<Local>>,
+    declaration: Option<String>,
+    lints: Vec<Lint>,
+}
+
+/// Evaluates conditions made of boolean literals.
+fn const_condition(expr: &Expression) -> Option<bool> {
+    match expr {
+        Expression::Literal(LiteralExpression::Bool(b)) => Some(*b),
+        Expression::Parenthesized(paren) => const_condition(&paren.expression),
+        Expression::Unary(UnaryExpression {
+            operator: UnaryOperator::LogicalNegation,
+            operand,
+        }) => const_condition(operand).map(|b| !b),
+        Expression::Binary(binary) => {
+            let left = const_condition(&binary.left)?;
+            let right = const_condition(&binary.right)?;
+            match binary.operator {
+                BinaryOperator::ShortCircuitAnd => Some(left && right),
+                BinaryOperator::ShortCircuitOr => Some(left || right),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+impl<'a> Linter<'a> {
+    fn new(config: &'a LintConfig) -> Self {
+        Self {
+            config,
+            overrides: Vec::new(),
+            imports: Vec::new(),
+            globals: Vec::new(),
+            scopes: Vec::new(),
+            declaration: None,
+            lints: Vec::new(),
+        }
+    }
+
+    fn severity(&self, rule: LintRule) -> DiagnosticSeverity {
+        self.overrides
+            .iter()
+            .rev()
+            .find(|(r, _)| *r == rule)
+            .map(|(_, severity)| severity.clone())
+            .unwrap_or_else(|| self.config.severity(rule))
+    }
+
+    fn emit_with(&mut self, severity: DiagnosticSeverity, error: LintError, span: Option<Span>) {
+        if severity == DiagnosticSeverity::Off {
+            return;
+        }
+        let rule = error.rule();
+        let mut diagnostic = Diagnostic::from(error);
+        diagnostic.span = span;
+        diagnostic.declaration = self.declaration.clone();
+        self.lints.push(Lint {
+            rule,
+            severity,
+            diagnostic,
+        });
+    }
+
+    fn emit(&mut self, error: LintError, span: Option<Span>) {
+        let severity = self.severity(error.rule());
+        self.emit_with(severity, error, span);
+    }
+
+    /// Push the severities of `@diagnostic` attributes. Returns the number of pushed
+    /// overrides.
+    fn push_attributes(&mut self, attrs: &[Attribute]) -> usize {
+        let len = self.overrides.len();
+        for attr in attrs {
+            if let Attribute::Diagnostic(attr) = attr {
+                if let Ok(rule) = attr.rule.parse() {
+                    self.overrides.push((rule, attr.severity.clone()));
+                }
+            }
+        }
+        self.overrides.len() - len
+    }
+
+    fn pop_attributes(&mut self, count: usize) {
+        self.overrides.truncate(self.overrides.len() - count);
+    }
+
+    fn module(&mut self, wesl: &TranslationUnit) {
+        for directive in &wesl.global_directives {
+            if let GlobalDirective::Diagnostic(directive) = directive {
+                if let Ok(rule) = directive.rule_name.parse() {
+                    self.overrides.push((rule, directive.severity.clone()));
+                }
+            }
+        }
+
+        #[cfg(feature = "imports")]
+        {
+            fn items(content: &ImportContent, res: &mut Vec<String>) {
+                match content {
+                    ImportContent::Item(item) => {
+                        res.push(item.rename.as_ref().unwrap_or(&item.ident).to_string())
+                    }
+                    ImportContent::Collection(coll) => {
+                        for import in coll {
+                            items(&import.content, res);
+                        }
+                    }
+                }
+            }
+            let mut names = Vec::new();
+            for import in &wesl.imports {
+                items(&import.content, &mut names);
+            }
+            let severity = self.severity(LintRule::UnusedImport);
+            self.imports = names
+                .into_iter()
+                .map(|name| Local {
+                    name,
+                    span: None,
+                    severity: severity.clone(),
+                    used: false,
+                })
+                .collect();
+        }
+
+        for decl in &wesl.global_declarations {
+            if let Some(ident) = decl.ident() {
+                self.globals.push(ident.to_string());
+            }
+        }
+
+        for decl in &wesl.global_declarations {
+            self.declaration = decl.ident().map(|ident| ident.to_string());
+            match decl {
+                GlobalDeclaration::Void => (),
+                GlobalDeclaration::Declaration(decl) => {
+                    self.attributes(&decl.attributes);
+                    if let Some(ty) = &decl.ty {
+                        self.ty(ty);
+                    }
+                    if let Some(init) = &decl.initializer {
+                        self.expr(init);
+                    }
+                }
+                GlobalDeclaration::TypeAlias(decl) => {
+                    self.ty(&decl.ty);
+                }
+                GlobalDeclaration::Struct(decl) => {
+                    for member in &decl.members {
+                        self.attributes(&member.attributes);
+                        self.ty(&member.ty);
+                    }
+                }
+                GlobalDeclaration::Function(decl) => self.function(decl),
+                GlobalDeclaration::ConstAssert(decl) => {
+                    self.expr(&decl.expression);
+                }
+            }
+        }
+        self.declaration = None;
+
+        for import in std::mem::take(&mut self.imports) {
+            if !import.used {
+                self.emit_with(import.severity, LintError::UnusedImport(import.name), None);
+            }
+        }
+    }
+
+    fn function(&mut self, decl: &Function) {
+        let count = self.push_attributes(&decl.attributes);
+        self.attributes(&decl.attributes);
+
+        let params = decl
+            .parameters
+            .iter()
+            .map(|param| {
+                self.attributes(&param.attributes);
+                self.ty(&param.ty);
+                Local {
+                    name: param.ident.to_string(),
+                    span: None,
+                    severity: DiagnosticSeverity::Off, // parameters are not linted
+                    used: true,
+                }
+            })
+            .collect();
+        self.attributes(&decl.return_attributes);
+        if let Some(ty) = &decl.return_type {
+            self.ty(ty);
+        }
+
+        self.scopes.push(params);
+        self.compound(&decl.body);
+        self.pop_scope();
+        self.pop_attributes(count);
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        for local in scope {
+            if !local.used && !local.name.starts_with('_') {
+                self.emit_with(
+                    local.severity,
+                    LintError::UnusedDeclaration(local.name),
+                    local.span,
+                );
+            }
+        }
+    }
+
+    fn compound(&mut self, stmt: &CompoundStatement) {
+        let count = self.push_attributes(&stmt.attributes);
+        self.scopes.push(Vec::new());
+        self.statements(&stmt.statements);
+        self.pop_scope();
+        self.pop_attributes(count);
+    }
+
+    fn statements(&mut self, stmts: &[StatementNode]) {
+        let mut terminator = None;
+        for stmt in stmts {
+            if let Some(keyword) = terminator.take() {
+                self.emit(
+                    LintError::UnreachableCode(keyword),
+                    Some(stmt.span().clone()),
+                );
+            }
+            self.statement(stmt);
+            terminator = match stmt.node() {
+                Statement::Return(_) => Some("return"),
+                Statement::Break(_) => Some("break"),
+                Statement::Continue(_) => Some("continue"),
+                Statement::Discard(_) => Some("discard"),
+                _ => None,
+            };
+        }
+    }
+
+    fn condition(&mut self, expr: &ExpressionNode) {
+        if let Some(value) = const_condition(expr) {
+            self.emit(
+                LintError::ConstantCondition(value),
+                Some(expr.span().clone()),
+            );
+        }
+        self.expr(expr);
+    }
+
+    fn statement(&mut self, node: &StatementNode) {
+        match node.node() {
+            Statement::Void => (),
+            Statement::Compound(stmt) => self.compound(stmt),
+            Statement::Assignment(stmt) => {
+                self.expr(&stmt.rhs);
+                self.expr(&stmt.lhs);
+            }
+            Statement::Increment(stmt) => {
+                self.expr(&stmt.expression);
+            }
+            Statement::Decrement(stmt) => {
+                self.expr(&stmt.expression);
+            }
+            Statement::If(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                self.condition(&stmt.if_clause.expression);
+                self.compound(&stmt.if_clause.body);
+                for clause in &stmt.else_if_clauses {
+                    self.condition(&clause.expression);
+                    self.compound(&clause.body);
+                }
+                if let Some(clause) = &stmt.else_clause {
+                    self.compound(&clause.body);
+                }
+                self.pop_attributes(count);
+            }
+            Statement::Switch(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                self.expr(&stmt.expression);
+                for clause in &stmt.clauses {
+                    for selector in &clause.case_selectors {
+                        if let CaseSelector::Expression(expr) = selector {
+                            self.expr(expr);
+                        }
+                    }
+                    self.compound(&clause.body);
+                }
+                self.pop_attributes(count);
+            }
+            Statement::Loop(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                // the continuing statement is in the scope of the loop body.
+                self.scopes.push(Vec::new());
+                self.statements(&stmt.body.statements);
+                if let Some(cont) = &stmt.continuing {
+                    self.scopes.push(Vec::new());
+                    self.statements(&cont.body.statements);
+                    if let Some(break_if) = &cont.break_if {
+                        self.condition(&break_if.expression);
+                    }
+                    self.pop_scope();
+                }
+                self.pop_scope();
+                self.pop_attributes(count);
+            }
+            Statement::For(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                self.scopes.push(Vec::new());
+                if let Some(init) = &stmt.initializer {
+                    self.statement(init);
+                }
+                if let Some(cond) = &stmt.condition {
+                    self.condition(cond);
+                }
+                if let Some(update) = &stmt.update {
+                    self.statement(update);
+                }
+                self.compound(&stmt.body);
+                self.pop_scope();
+                self.pop_attributes(count);
+            }
+            Statement::While(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                self.condition(&stmt.condition);
+                self.compound(&stmt.body);
+                self.pop_attributes(count);
+            }
+            Statement::Break(_) | Statement::Continue(_) | Statement::Discard(_) => (),
+            Statement::Return(stmt) => {
+                if let Some(expr) = &stmt.expression {
+                    self.expr(expr);
+                }
+            }
+            Statement::FunctionCall(stmt) => {
+                self.call(&stmt.call);
+            }
+            Statement::ConstAssert(stmt) => {
+                self.expr(&stmt.expression);
+            }
+            Statement::Declaration(decl) => {
+                if let Some(ty) = &decl.ty {
+                    self.ty(ty);
+                }
+                if let Some(init) = &decl.initializer {
+                    self.expr(init);
+                }
+                let name = decl.ident.to_string();
+                let shadows = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .skip(1)
+                    .flatten()
+                    .map(|local| &local.name)
+                    .chain(&self.globals)
+                    .chain(self.imports.iter().map(|import| &import.name))
+                    .any(|other| *other == name);
+                if shadows {
+                    self.emit(
+                        LintError::Shadowing(name.clone()),
+                        Some(node.span().clone()),
+                    );
+                }
+                let local = Local {
+                    name,
+                    span: Some(node.span().clone()),
+                    severity: self.severity(LintRule::UnusedDeclaration),
+                    used: false,
+                };
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(local);
+                }
+            }
+        }
+    }
+
+    fn local_mut(&mut self, name: &str) -> Option<&mut Local> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|local| local.name == name)
+    }
+
+    /// Mark a name as used.
+    fn use_name(&mut self, name: &str) {
+        if let Some(local) = self.local_mut(name) {
+            local.used = true;
+        } else if !self.globals.iter().any(|global| global == name) {
+            if let Some(import) = self.imports.iter_mut().find(|import| import.name == name) {
+                import.used = true;
+            }
+        }
+    }
+
+    fn attributes(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            match attr {
+                Attribute::Align(expr)
+                | Attribute::Binding(expr)
+                | Attribute::BlendSrc(expr)
+                | Attribute::Group(expr)
+                | Attribute::Id(expr)
+                | Attribute::Location(expr)
+                | Attribute::Size(expr) => {
+                    self.expr(expr);
+                }
+                Attribute::WorkgroupSize(attr) => {
+                    for expr in std::iter::once(&attr.x).chain(&attr.y).chain(&attr.z) {
+                        self.expr(expr);
+                    }
+                }
+                Attribute::Custom(attr) => {
+                    for expr in attr.arguments.iter().flatten() {
+                        self.expr(expr);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn ty(&mut self, ty: &TypeExpression) {
+        #[cfg(feature = "imports")]
+        match &ty.path {
+            // a qualified name can start with an imported module name.
+            Some(path) => {
+                if let Some(first) = path.components.first() {
+                    self.use_name(first);
+                }
+            }
+            None => self.use_name(&ty.ident.name()),
+        };
+        #[cfg(not(feature = "imports"))]
+        self.use_name(&ty.ident.name());
+        for arg in ty.template_args.iter().flatten() {
+            self.expr(&arg.expression);
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall) {
+        self.ty(&call.ty);
+        for arg in &call.arguments {
+            self.expr(arg);
+        }
+    }
+
+    fn expr(&mut self, expr: &ExpressionNode) {
+        match expr.node() {
+            Expression::Literal(_) => (),
+            Expression::Parenthesized(paren) => self.expr(&paren.expression),
+            Expression::NamedComponent(comp) => self.expr(&comp.base),
+            Expression::Indexing(index) => {
+                self.expr(&index.base);
+                self.expr(&index.index);
+            }
+            Expression::Unary(unary) => self.expr(&unary.operand),
+            Expression::Binary(binary) => {
+                self.expr(&binary.left);
+                self.expr(&binary.right);
+            }
+            Expression::FunctionCall(call) => self.call(call),
+            Expression::TypeOrIdentifier(ty) => self.ty(ty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use wgsl_parse::syntax;
+
+    use crate::{VirtualResolver, Wesl};
+
+    #[cfg(feature = "imports")]
+    #[test]
+    fn test_lint() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::util::{one, two};
+            diagnostic(off, shadowing);
+            fn f(x: u32) -> u32 { let x = one(); if !false { return x; } return 0u; let y = x; }
+            @diagnostic(error, unused_declaration) fn g() { let z = 1u; }"
+                .into(),
+        );
+        resolver.add_module(
+            "util",
+            "fn one() -> u32 { return 1u; } fn two() -> u32 { while (true) {} }".into(),
+        );
+        let compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        let mut config = LintConfig::new();
+        config.set(LintRule::UnreachableCode, syntax::DiagnosticSeverity::Off);
+        let lints = compiler.lint("main", &config).unwrap();
+        let lints = lints
+            .iter()
+            .map(|lint| {
+                (
+                    lint.rule,
+                    lint.severity.clone(),
+                    lint.diagnostic.error.to_string(),
+                )
+            })
+            .collect_vec();
+        use syntax::DiagnosticSeverity::*;
+        assert_eq!(
+            lints,
+            [
+                (
+                    LintRule::UnusedImport,
+                    Warning,
+                    "unused import `two`".to_string()
+                ),
+                (
+                    LintRule::ConstantCondition,
+                    Warning,
+                    "condition is always `true`".to_string()
+                ),
+                (
+                    LintRule::UnusedDeclaration,
+                    Warning,
+                    "unused declaration `y`".to_string()
+                ),
+                (
+                    LintRule::UnusedDeclaration,
+                    Error,
+                    "unused declaration `z`".to_string()
+                ),
+                (
+                    LintRule::ConstantCondition,
+                    Warning,
+                    "condition is always `true`".to_string()
+                ),
+            ]
+        );
+    }
+}
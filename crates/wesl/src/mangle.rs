@@ -37,6 +37,11 @@ pub trait Mangler {
     fn mangle_types(&self, item: &str, variant: u32, _types: &[TypeExpression]) -> String {
         format!("{item}_{variant}")
     }
+    /// Used for function overloading, `signature` is the list of parameter types.
+    /// Implementing is optional.
+    fn mangle_signature(&self, item: &str, variant: u32, _signature: &[TypeExpression]) -> String {
+        format!("{item}_{variant}")
+    }
 }
 
 impl<T: Mangler + ?Sized> Mangler for Box<T> {
@@ -49,6 +54,9 @@ impl<T: Mangler + ?Sized> Mangler for Box<T> {
     fn mangle_types(&self, item: &str, variant: u32, types: &[TypeExpression]) -> String {
         (**self).mangle_types(item, variant, types)
     }
+    fn mangle_signature(&self, item: &str, variant: u32, signature: &[TypeExpression]) -> String {
+        (**self).mangle_signature(item, variant, signature)
+    }
 }
 
 impl<T: Mangler> Mangler for &T {
@@ -61,6 +69,9 @@ impl<T: Mangler> Mangler for &T {
     fn mangle_types(&self, item: &str, variant: u32, types: &[TypeExpression]) -> String {
         (**self).mangle_types(item, variant, types)
     }
+    fn mangle_signature(&self, item: &str, variant: u32, signature: &[TypeExpression]) -> String {
+        (**self).mangle_signature(item, variant, signature)
+    }
 }
 
 /// A mangler that hashes the module path.
@@ -1,42 +1,376 @@
-use std::collections::HashMap;
+#[cfg(feature = "eval")]
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
-use wgsl_parse::syntax::{
-    Function, GlobalDeclaration, Ident, Import, TranslationUnit, TypeExpression,
-};
+#[cfg(feature = "eval")]
+use wgsl_parse::syntax::TypeExpression;
+use wgsl_parse::syntax::{GlobalDeclaration, Ident, TranslationUnit};
 
+#[cfg(feature = "eval")]
 use crate::{
-    import::{absolute_resource, imported_resources, normalize_resource},
+    eval::{ty_eval_ty, Context, SyntaxUtil as _},
+    syntax_util::SyntaxUtil as _,
+    validate::types::select_overloads,
     visit::Visit,
-    Mangler, Resource,
 };
+use crate::{validate::ValidateError, Diagnostic, Error, Mangler};
 
-fn normalize_ty(
-    ty: &TypeExpression,
-    parent_res: &Resource,
-    imports: &HashMap<Ident, (Resource, Ident)>,
-) -> TypeExpression {
-    let mut ty = ty.clone();
-    if let Some(path) = &ty.path {
-        let res = normalize_resource(path, parent_res, imports);
-        ty.path = Some(res.path().to_path_buf());
-    } else {
-        ty.path = Some(parent_res.path().to_path_buf());
-    }
-    ty
-}
+/// A function declared more than once: its name and the indices of its declarations.
+pub(crate) type OverloadSet = (String, Vec<usize>);
 
-pub(crate) fn mangle(wesl: &mut TranslationUnit, resource: &Resource, mangler: &impl Mangler) {
-    let imports = imported_resources(&wesl.imports, resource);
+/// Declarations that a module refers to by the same name, because it declares the name and
+/// imports it, or imports it from several modules. They are overloads of one function.
+pub(crate) type OverloadGroup = Vec<Ident>;
 
-    for decl in &mut wesl.global_declarations {
+/// The overloaded functions, i.e. functions that share a name but differ in their
+/// parameter types.
+///
+/// Overloads declared in several modules have different names after mangling. `groups`
+/// merges them into a single set, named after its first declaration.
+#[allow(clippy::result_large_err)]
+pub(crate) fn overload_sets(
+    wesl: &TranslationUnit,
+    groups: &[OverloadGroup],
+) -> Result<Vec<OverloadSet>, Diagnostic<Error>> {
+    let mut sets: Vec<OverloadSet> = Vec::new();
+    for (i, decl) in wesl.global_declarations.iter().enumerate() {
         if let GlobalDeclaration::Function(decl) = decl {
-            // TODO: type aliases
+            let name = decl.ident.name();
+            match sets.iter_mut().find(|(n, _)| *n == *name) {
+                Some((_, decls)) => decls.push(i),
+                None => sets.push((name.to_string(), vec![i])),
+            }
+        }
+    }
+
+    for group in groups {
+        let decls = wesl
+            .global_declarations
+            .iter()
+            .filter(|decl| decl.ident().is_some_and(|id| group.contains(id)));
+        // only functions can be overloaded, other declarations cannot share a name.
+        if let Some(decl) = decls.clone().find(|decl| !decl.is_function()) {
+            let name = decl.ident().unwrap().to_string();
+            return Err(
+                Diagnostic::from(ValidateError::Duplicate(name.clone())).with_declaration(name)
+            );
+        }
+        let names = decls
+            .map(|decl| decl.ident().unwrap().to_string())
+            .collect_vec();
+        let merged = sets
+            .iter()
+            .positions(|(name, _)| names.contains(name))
+            .collect_vec();
+        if let Some((first, rest)) = merged.split_first() {
+            for i in rest.iter().rev() {
+                let (_, decls) = sets.remove(*i);
+                sets[*first].1.extend(decls);
+            }
+            sets[*first].1.sort_unstable();
+        }
+    }
+
+    sets.retain(|(_, decls)| decls.len() > 1);
+    Ok(sets)
+}
+
+/// Resolve calls to the overloaded functions, see [`overload_sets`]. Overloads come from
+/// the same module, or from the module of an imported item.
+///
+/// Each call points to the overload with the lowest
+/// [conversion rank](crate::eval::conversion_rank) from the arguments to the parameters.
+/// Overloads are renamed with [`Mangler::mangle_signature`], from the name of their own
+/// declaration.
+#[cfg(feature = "eval")]
+#[allow(clippy::result_large_err)]
+pub(crate) fn resolve_overloads(
+    wesl: &mut TranslationUnit,
+    sets: Vec<OverloadSet>,
+    mangler: &impl Mangler,
+) -> Result<(), Diagnostic<Error>> {
+    let entry_points = wesl
+        .entry_points()
+        .map(|id| id.name().to_string())
+        .collect::<HashSet<_>>();
+    let mut names = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident().map(|id| id.name().to_string()))
+        .collect::<HashSet<_>>();
+    let mut idents = HashMap::new();
+
+    for (name, decls) in &sets {
+        let duplicate = || {
+            Diagnostic::from(ValidateError::Duplicate(name.clone())).with_declaration(name.clone())
+        };
+        // entry points are called by name from the API, they cannot be renamed.
+        if entry_points.contains(name) {
+            return Err(duplicate());
+        }
+        let mut signatures = Vec::new();
+        for (variant, idx) in decls.iter().enumerate() {
+            let GlobalDeclaration::Function(decl) = &wesl.global_declarations[*idx] else {
+                unreachable!("overloads are functions")
+            };
+
+            // type aliases are resolved, two overloads cannot have the same parameter types.
+            let mut ctx = Context::new(wesl);
             let sig = decl
                 .parameters
                 .iter()
-                .map(|p| normalize_ty(&p.ty, resource, &imports)).collect_vec()
-            let res = mangler.mangle_signature(decl.ident.name().as_str(), &sig);
+                .map(|p| ty_eval_ty(&p.ty, &mut ctx))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Diagnostic::from(e).with_declaration(name.clone()))?;
+            if signatures.contains(&sig) {
+                return Err(duplicate());
+            }
+            signatures.push(sig);
+
+            let sig = decl
+                .parameters
+                .iter()
+                .map(|p| wesl.resolve_ty(&p.ty).clone())
+                .collect_vec();
+            let decl_name = decl.ident.name();
+            let mut variant = variant as u32;
+            let mut new_name = mangler.mangle_signature(&decl_name, variant, &sig);
+            while names.contains(&new_name) {
+                variant += 1;
+                new_name = mangler.mangle_signature(&decl_name, variant, &sig);
+            }
+            names.insert(new_name.clone());
+            idents.insert(*idx, Ident::new(new_name));
         }
     }
+
+    // each call site is given a unique name, which identifies it in the type checker.
+    // These names are not valid identifiers, they are replaced before returning.
+    let overloaded = sets
+        .iter()
+        .map(|(name, decls)| {
+            let decls = decls
+                .iter()
+                .filter_map(|idx| wesl.global_declarations[*idx].ident().cloned())
+                .collect_vec();
+            (name, decls)
+        })
+        .collect_vec();
+    let mut calls = HashMap::new();
+    let mut callees = HashMap::new();
+    for_each_ty(wesl, &mut |ty| {
+        if let Some((name, _)) = overloaded.iter().find(|(_, ids)| ids.contains(&ty.ident)) {
+            let call = format!("{name}#{}", calls.len());
+            let callee = std::mem::replace(&mut ty.ident, Ident::new(call.clone()));
+            calls.insert(call.clone(), name.to_string());
+            callees.insert(call, callee);
+        }
+    });
+
+    // the type checker finds the overloads of a set by name, overloads declared in another
+    // module are named like the set while it runs.
+    let mut decl_idents = HashMap::new();
+    for (name, decls) in &sets {
+        for idx in decls {
+            let ident = wesl.global_declarations[*idx].ident_mut().unwrap();
+            if *ident.name() != *name {
+                decl_idents.insert(*idx, std::mem::replace(ident, Ident::new(name.clone())));
+            }
+        }
+    }
+
+    let choices = select_overloads(wesl, &calls);
+
+    for (idx, ident) in decl_idents {
+        *wesl.global_declarations[idx].ident_mut().unwrap() = ident;
+    }
+
+    // calls point to the selected overload, or back to the callee if the check failed.
+    for_each_ty(wesl, &mut |ty| {
+        let call = ty.ident.name().to_string();
+        if let Some(callee) = callees.remove(&call) {
+            let choice = choices.as_ref().ok().and_then(|choices| choices.get(&call));
+            ty.ident = match choice.and_then(|idx| idents.get(idx)) {
+                Some(ident) => ident.clone(),
+                None => callee,
+            };
+        }
+    });
+    choices?;
+
+    for (idx, ident) in idents {
+        *wesl.global_declarations[idx].ident_mut().unwrap() = ident;
+    }
+
+    Ok(())
+}
+
+/// Call a function on each type expression of the module, including nested ones.
+#[cfg(feature = "eval")]
+fn for_each_ty(wesl: &mut TranslationUnit, f: &mut impl FnMut(&mut TypeExpression)) {
+    fn rec(ty: &mut TypeExpression, f: &mut impl FnMut(&mut TypeExpression)) {
+        f(ty);
+        for ty in Visit::<TypeExpression>::visit_mut(ty) {
+            rec(ty, f);
+        }
+    }
+    for decl in &mut wesl.global_declarations {
+        for ty in Visit::<TypeExpression>::visit_mut(decl) {
+            rec(ty, f);
+        }
+    }
+}
+
+/// Selecting an overload requires the type checker, which is part of the `eval` feature.
+#[cfg(not(feature = "eval"))]
+#[allow(clippy::result_large_err)]
+pub(crate) fn resolve_overloads(
+    _wesl: &mut TranslationUnit,
+    sets: Vec<OverloadSet>,
+    _mangler: &impl Mangler,
+) -> Result<(), Diagnostic<Error>> {
+    let (name, _) = &sets[0];
+    Err(Diagnostic::from(ValidateError::Overload(name.clone())).with_declaration(name.clone()))
+}
+
+#[cfg(all(test, feature = "imports"))]
+mod tests {
+    use crate::{CompileOptions, ManglerKind, VirtualResolver, Wesl};
+
+    #[cfg(feature = "eval")]
+    #[test]
+    fn test_overloads() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::math::scale;
+            fn area(x: i32) -> i32 { return x * x; }
+            fn area(x: f32) -> f32 { return x * x; }
+            @compute @workgroup_size(1) fn main() {
+                let a = area(2);
+                let b = area(2.5);
+                let c = scale(vec2f(1.0));
+                let d = area(f32(area(2)));
+            }"
+            .into(),
+        );
+        resolver.add_module(
+            "math",
+            "fn scale(x: u32) -> u32 { return 2u * x; }
+            fn scale(x: vec2f) -> vec2f { return 2.0 * x; }"
+                .into(),
+        );
+        resolver.add_module(
+            "bad",
+            "fn g(a: i32, b: f32) {}
+            fn g(a: f32, b: i32) {}
+            @compute @workgroup_size(1) fn main() { g(1, 1); }"
+                .into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions {
+            lower: false,
+            ..Default::default()
+        });
+        let compiler = compiler.set_custom_resolver(resolver);
+
+        // the abstract int argument converts to `i32` with a lower rank than to `f32`.
+        let wgsl = compiler.compile("main").unwrap().to_string();
+        assert!(wgsl.contains("fn area_0(x: i32) -> i32"));
+        assert!(wgsl.contains("let a = area_0(2);"));
+        assert!(wgsl.contains("let b = area_1(2.5);"));
+        assert!(wgsl.contains("let c = scale_1(vec2f(1.0));"));
+        assert!(wgsl.contains("let d = area_1(f32(area_0(2)));"));
+
+        let Err(err) = compiler.compile("bad") else {
+            panic!("expected an error");
+        };
+        assert!(err.to_string().contains("ambiguous call"));
+    }
+
+    #[cfg(feature = "eval")]
+    #[test]
+    fn test_overloads_across_modules() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "a",
+            "fn area(x: f32) -> f32 { return x * x; }
+            const C = 1.0;"
+                .into(),
+        );
+        resolver.add_module(
+            "b",
+            "fn area(x: i32) -> i32 { return x * x; }
+            fn area(x: vec2i) -> i32 { return x.x * x.y; }
+            const C = 1;"
+                .into(),
+        );
+        resolver.add_module(
+            "cross",
+            "import package::a::area;
+            import package::b::area;
+            @compute @workgroup_size(1) fn main() { let x = area(2i); }"
+                .into(),
+        );
+        resolver.add_module(
+            "mixed",
+            "import package::b::area;
+            fn area(x: f32) -> f32 { return x; }
+            @compute @workgroup_size(1) fn main() { let x = area(2i); }"
+                .into(),
+        );
+        resolver.add_module(
+            "consts",
+            "import package::a::C;
+            import package::b::C;
+            @compute @workgroup_size(1) fn main() { let x = C; }"
+                .into(),
+        );
+        resolver.add_module(
+            "twice",
+            "import package::b::area;
+            import package::b::{area};
+            @compute @workgroup_size(1) fn main() { let x = area(2i); }"
+                .into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler
+            .set_options(CompileOptions {
+                lower: false,
+                ..Default::default()
+            })
+            .set_mangler(ManglerKind::Escape);
+        let mut compiler = compiler.set_custom_resolver(resolver);
+
+        // the overloads of the imported items are merged in a single set.
+        let wgsl = compiler.compile("cross").unwrap().to_string();
+        assert!(wgsl.contains("let x = package_b_area_1(2i);"));
+        assert!(wgsl.contains("fn package_a_area_0(x: f32) -> f32"));
+        assert!(wgsl.contains("fn package_b_area_2(x: vec2i) -> i32"));
+        compiler.set_options(CompileOptions {
+            lower: false,
+            lazy: false,
+            strip: false,
+            ..Default::default()
+        });
+        let wgsl = compiler.compile("cross").unwrap().to_string();
+        assert!(wgsl.contains("let x = package_b_area_"));
+        compiler.set_options(CompileOptions {
+            lower: false,
+            ..Default::default()
+        });
+        // and with the local declarations of the same name.
+        let wgsl = compiler.compile("mixed").unwrap().to_string();
+        assert!(wgsl.contains("let x = package_b_area_1(2i);"));
+        assert!(wgsl.contains("fn area_0(x: f32) -> f32"));
+        // only functions can be overloaded.
+        let Err(err) = compiler.compile("consts") else {
+            panic!("expected an error");
+        };
+        assert!(err.to_string().contains("duplicate declaration"));
+        // importing the same item twice is not a clash.
+        let wgsl = compiler.compile("twice").unwrap().to_string();
+        assert!(wgsl.contains("let x = package_b_area_0(2i);"));
+        assert!(!wgsl.contains("package_a_area"));
+    }
 }
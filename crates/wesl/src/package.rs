@@ -4,7 +4,11 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 use wgsl_parse::syntax::{PathOrigin, TranslationUnit};
 
-use crate::{validate::validate_wesl, Diagnostic, Error, ModulePath, SyntaxUtil};
+use crate::{
+    doc::{document, DocFormat, DocPage},
+    validate::validate_wesl,
+    Diagnostic, Error, ModulePath, SyntaxUtil,
+};
 
 /// A builder that generates code for WESL packages.
 ///
@@ -209,6 +213,37 @@ impl Module {
         Ok(self)
     }
 
+    /// generate the documentation of the package, one page per module.
+    ///
+    /// Declarations are documented with `///` doc comments. See [`DocFormat`] for the
+    /// output formats.
+    #[allow(clippy::result_large_err)]
+    pub fn doc(&self, format: DocFormat) -> Result<Vec<DocPage>, Error> {
+        #[allow(clippy::result_large_err)]
+        fn parse_module(
+            module: &Module,
+            path: ModulePath,
+            modules: &mut Vec<(ModulePath, TranslationUnit)>,
+        ) -> Result<(), Error> {
+            let wesl: TranslationUnit = module.source.parse().map_err(|e| {
+                Diagnostic::from(e)
+                    .with_module_path(path.clone(), None)
+                    .with_source(module.source.clone())
+            })?;
+            modules.push((path.clone(), wesl));
+            for module in &module.submodules {
+                let mut path = path.clone();
+                path.push(&module.name);
+                parse_module(module, path, modules)?;
+            }
+            Ok(())
+        }
+        let mut modules = Vec::new();
+        let path = ModulePath::new(PathOrigin::Absolute, Vec::new());
+        parse_module(self, path, &mut modules)?;
+        Ok(document(&self.name, &modules, format))
+    }
+
     /// generate the build artefact that can then be exposed by the [`super::wesl_pkg`] macro.
     ///
     /// this function must be called from a `build.rs` file. Refer to the crate documentation
@@ -0,0 +1,372 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+use wgsl_parse::syntax::{
+    AccessMode, AddressSpace, Attribute, Declaration, DeclarationKind, Function, GlobalDeclaration,
+    TranslationUnit, TypeExpression,
+};
+
+use crate::{
+    eval::{
+        attr_location, ty_eval_ty, Context, Convert, Eval, EvalAttrs, EvalError, EvalTy, Exec,
+        Instance, LiteralInstance, ShaderStage, SyntaxUtil as _, TextureType, Type,
+    },
+    visit::Visit,
+    Diagnostic, Error,
+};
+
+type E = EvalError;
+
+/// The interface of a compiled shader: entry points, resource bindings and overrides.
+///
+/// Names are the ones of the compiled WGSL. See [`reflect`].
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reflection {
+    pub entry_points: Vec<EntryPoint>,
+    pub bindings: Vec<Binding>,
+    pub overrides: Vec<Override>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: ShaderStage,
+    /// `None` for vertex and fragment shaders, or if the workgroup size depends on
+    /// overrides.
+    pub workgroup_size: Option<[u32; 3]>,
+    /// The user-defined inputs (with a `@location` attribute).
+    pub inputs: Vec<Location>,
+    /// The user-defined outputs (with a `@location` attribute).
+    pub outputs: Vec<Location>,
+    /// The group and binding of the resources used by the entry point, directly or in
+    /// the functions it calls.
+    pub bindings: Vec<(u32, u32)>,
+}
+
+/// A user-defined input or output of an entry point.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// The parameter or struct member name. `None` for a return value that is not a
+    /// struct.
+    pub name: Option<String>,
+    pub location: u32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_ty"))]
+    pub ty: Type,
+}
+
+/// A resource binding: buffer, texture or sampler.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    pub group: u32,
+    pub binding: u32,
+    /// `Handle` for textures and samplers.
+    pub address_space: AddressSpace,
+    /// The access mode of storage buffers and storage textures.
+    pub access_mode: Option<AccessMode>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_ty"))]
+    pub ty: Type,
+    /// The minimum size of a buffer binding in bytes. For a runtime-sized array, it is the
+    /// size with one element.
+    pub min_size: Option<u32>,
+}
+
+/// A pipeline-overridable constant.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Override {
+    pub name: String,
+    pub id: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_ty"))]
+    pub ty: Type,
+    /// The default value, if the initializer is a const-expression. Booleans are 0 or 1,
+    /// like the pipeline constants of WebGPU.
+    pub default: Option<f64>,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_ty<S: Serializer>(ty: &Type, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(ty)
+}
+
+/// Reflect the entry points, resource bindings and overrides of a WGSL module.
+#[allow(clippy::result_large_err)]
+pub fn reflect(wgsl: &TranslationUnit) -> Result<Reflection, Diagnostic<Error>> {
+    let mut ctx = Context::new(wgsl);
+    wgsl.exec(&mut ctx)
+        .map_err(|e| Diagnostic::from(e).with_ctx(&ctx))?;
+
+    let mut refl = Reflection {
+        entry_points: Vec::new(),
+        bindings: Vec::new(),
+        overrides: Vec::new(),
+    };
+
+    for decl in &wgsl.global_declarations {
+        let name = decl.ident().map(|id| id.to_string());
+        let res = match decl {
+            GlobalDeclaration::Declaration(decl) => match decl.kind {
+                DeclarationKind::Override => {
+                    override_(decl, &mut ctx).map(|o| refl.overrides.push(o))
+                }
+                DeclarationKind::Var(space) => {
+                    binding(decl, space, &mut ctx).map(|b| refl.bindings.extend(b))
+                }
+                _ => Ok(()),
+            },
+            GlobalDeclaration::Function(decl) => {
+                entry_point(decl, &mut ctx).map(|e| refl.entry_points.extend(e))
+            }
+            _ => Ok(()),
+        };
+        res.map_err(|e| {
+            let mut err = Diagnostic::from(e);
+            err.declaration = name;
+            err
+        })?;
+    }
+
+    for entry in &mut refl.entry_points {
+        let used = used_globals(&entry.name, wgsl);
+        entry.bindings = refl
+            .bindings
+            .iter()
+            .filter(|b| used.contains(&b.name))
+            .map(|b| (b.group, b.binding))
+            .collect();
+    }
+
+    Ok(refl)
+}
+
+/// The names of the global declarations used by a function, transitively.
+fn used_globals(name: &str, wgsl: &TranslationUnit) -> HashSet<String> {
+    fn visit(ty: &TypeExpression, used: &mut HashSet<String>, stack: &mut Vec<String>) {
+        let name = ty.ident.to_string();
+        if used.insert(name.clone()) {
+            stack.push(name);
+        }
+        for ty in Visit::<TypeExpression>::visit(ty) {
+            visit(ty, used, stack);
+        }
+    }
+
+    let mut used = HashSet::new();
+    let mut stack = vec![name.to_string()];
+    while let Some(name) = stack.pop() {
+        if let Some(decl) = wgsl.decl_function(&name) {
+            for ty in Visit::<TypeExpression>::visit(decl) {
+                visit(ty, &mut used, &mut stack);
+            }
+        }
+    }
+    used
+}
+
+fn override_(decl: &Declaration, ctx: &mut Context) -> Result<Override, E> {
+    let ty = decl_ty(decl, ctx)?;
+    // initializers that depend on other overrides are evaluated at pipeline creation.
+    let default = decl
+        .initializer
+        .as_ref()
+        .and_then(|init| init.eval_value(ctx).ok())
+        .and_then(|inst| inst.convert_to(&ty))
+        .and_then(|inst| match inst {
+            Instance::Literal(lit) => to_f64(&lit),
+            _ => None,
+        });
+    Ok(Override {
+        name: decl.ident.to_string(),
+        id: decl.attr_id(ctx)?,
+        ty,
+        default,
+    })
+}
+
+fn binding(
+    decl: &Declaration,
+    space: Option<AddressSpace>,
+    ctx: &mut Context,
+) -> Result<Option<Binding>, E> {
+    let space = space.unwrap_or(AddressSpace::Handle);
+    let (access_mode, is_buffer) = match space {
+        AddressSpace::Uniform => (None, true),
+        AddressSpace::Storage(access) => (Some(access.unwrap_or(AccessMode::Read)), true),
+        AddressSpace::Handle => (None, false),
+        _ => return Ok(None),
+    };
+    let (group, binding) = decl.attr_group_binding(ctx)?;
+    let ty = decl_ty(decl, ctx)?;
+    let access_mode = match &ty {
+        Type::Texture(
+            TextureType::Storage1D(_, access)
+            | TextureType::Storage2D(_, access)
+            | TextureType::Storage2DArray(_, access)
+            | TextureType::Storage3D(_, access),
+        ) => Some(*access),
+        _ => access_mode,
+    };
+    let min_size = if is_buffer { ty.min_size_of(ctx) } else { None };
+    Ok(Some(Binding {
+        name: decl.ident.to_string(),
+        group,
+        binding,
+        address_space: space,
+        access_mode,
+        ty,
+        min_size,
+    }))
+}
+
+fn entry_point(decl: &Function, ctx: &mut Context) -> Result<Option<EntryPoint>, E> {
+    let Some(stage) = ShaderStage::of(decl) else {
+        return Ok(None);
+    };
+    let workgroup_size = match stage {
+        ShaderStage::Compute => match decl.attr_workgroup_size(ctx) {
+            Ok((x, y, z)) => Some([x, y.unwrap_or(1), z.unwrap_or(1)]),
+            Err(E::NotAccessible(..)) => None,
+            Err(e) => return Err(e),
+        },
+        _ => None,
+    };
+
+    let mut inputs = Vec::new();
+    for param in &decl.parameters {
+        let ty = ty_eval_ty(&param.ty, ctx)?;
+        let name = param.ident.to_string();
+        locations(Some(name), &param.attributes, &ty, &mut inputs, ctx)?;
+    }
+    let mut outputs = Vec::new();
+    if let Some(ty) = &decl.return_type {
+        let ty = ty_eval_ty(ty, ctx)?;
+        locations(None, &decl.return_attributes, &ty, &mut outputs, ctx)?;
+    }
+
+    Ok(Some(EntryPoint {
+        name: decl.ident.to_string(),
+        stage,
+        workgroup_size,
+        inputs,
+        outputs,
+        bindings: Vec::new(),
+    }))
+}
+
+/// The locations of an entry point parameter or return value, or of the members of its
+/// struct type.
+fn locations(
+    name: Option<String>,
+    attrs: &[Attribute],
+    ty: &Type,
+    locations: &mut Vec<Location>,
+    ctx: &mut Context,
+) -> Result<(), E> {
+    if let Some(location) = attr_location(attrs, ctx).transpose()? {
+        locations.push(Location {
+            name,
+            location,
+            ty: ty.clone(),
+        });
+    } else if let Type::Struct(s) = ty {
+        let source = ctx.source;
+        let decl = source
+            .decl_struct(s)
+            .ok_or_else(|| E::UnknownStruct(s.clone()))?;
+        for m in &decl.members {
+            if let Some(location) = attr_location(&m.attributes, ctx).transpose()? {
+                locations.push(Location {
+                    name: Some(m.ident.to_string()),
+                    location,
+                    ty: ty_eval_ty(&m.ty, ctx)?,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the declared type, or the concrete type of the initializer.
+fn decl_ty(decl: &Declaration, ctx: &mut Context) -> Result<Type, E> {
+    match (&decl.ty, &decl.initializer) {
+        (Some(ty), _) => ty_eval_ty(ty, ctx),
+        (None, Some(init)) => Ok(init.eval_ty(ctx)?.concretize()),
+        (None, None) => Err(E::UntypedDecl),
+    }
+}
+
+fn to_f64(lit: &LiteralInstance) -> Option<f64> {
+    match lit {
+        LiteralInstance::Bool(b) => Some(*b as u32 as f64),
+        LiteralInstance::AbstractInt(_) | LiteralInstance::AbstractFloat(_) => None,
+        LiteralInstance::I32(n) => Some(*n as f64),
+        LiteralInstance::U32(n) => Some(*n as f64),
+        LiteralInstance::F32(n) => Some(*n as f64),
+        LiteralInstance::F16(n) => Some(n.to_f64()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{VirtualResolver, Wesl};
+
+    #[test]
+    fn test_reflect() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "@id(3) override scale: f32 = 2.0;
+            override enabled = true;
+            override count: u32 = u32(scale) * 4u;
+            struct VsOut { @builtin(position) pos: vec4f, @location(0) uv: vec2f }
+            @group(0) @binding(0) var<uniform> offset: vec3f;
+            @group(1) @binding(2) var<storage> data: array<vec4f>;
+            @group(1) @binding(3) var tex: texture_storage_2d<rgba8unorm, write>;
+            @vertex fn vs(@location(0) pos: vec3f, @location(1) uv: vec2f) -> VsOut {
+                return VsOut(vec4f(pos + offset, scale), uv);
+            }
+            @compute @workgroup_size(8, 4) fn cs() {
+                textureStore(tex, vec2u(0), data[count]);
+            }"
+            .into(),
+        );
+        let compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        let refl = compiler.compile("main").unwrap().reflect().unwrap();
+
+        let [vs, cs] = refl.entry_points.as_slice() else {
+            panic!("expected 2 entry points")
+        };
+        assert_eq!(vs.stage, ShaderStage::Vertex);
+        assert_eq!(vs.inputs.len(), 2);
+        assert_eq!(vs.inputs[1].name.as_deref(), Some("uv"));
+        assert_eq!(vs.inputs[1].location, 1);
+        assert_eq!(vs.outputs.len(), 1);
+        assert_eq!(cs.workgroup_size, Some([8, 4, 1]));
+        assert_eq!(vs.bindings, [(0, 0)]);
+        assert_eq!(cs.bindings, [(1, 2), (1, 3)]);
+
+        let [offset, data, tex] = refl.bindings.as_slice() else {
+            panic!("expected 3 bindings")
+        };
+        assert_eq!((offset.group, offset.binding), (0, 0));
+        assert_eq!(offset.min_size, Some(12));
+        assert_eq!(data.access_mode, Some(AccessMode::Read));
+        assert_eq!(data.min_size, Some(16));
+        assert_eq!(tex.address_space, AddressSpace::Handle);
+        assert_eq!(tex.access_mode, Some(AccessMode::Write));
+
+        let [scale, enabled, count] = refl.overrides.as_slice() else {
+            panic!("expected 3 overrides")
+        };
+        assert_eq!((scale.id, scale.default), (Some(3), Some(2.0)));
+        assert_eq!(enabled.default, Some(1.0));
+        // the initializer depends on another override.
+        assert_eq!(count.default, None);
+    }
+}
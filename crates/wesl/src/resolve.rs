@@ -217,7 +217,7 @@ impl<T: Fn(&mut TranslationUnit) -> Result<(), Error>> ResolveFn for T {}
 /// A WESL module preprocessor.
 ///
 /// The preprocess function will be called each time the WESL compiler tries to load a
-/// module.
+/// module, on the module parsed by the inner resolver with [`Resolver::source_to_module`].
 pub struct Preprocessor<R: Resolver, F: ResolveFn> {
     pub resolver: R,
     pub preprocess: F,
@@ -240,17 +240,15 @@ impl<R: Resolver, F: ResolveFn> Resolver for Preprocessor<R, F> {
         Ok(res)
     }
     fn source_to_module(&self, source: &str, path: &ModulePath) -> Result<TranslationUnit, E> {
-        let mut wesl: TranslationUnit = source.parse().map_err(|e| {
-            Diagnostic::from(e)
-                .with_module_path(path.clone(), self.display_name(path))
-                .with_source(source.to_string())
-        })?;
-        wesl.retarget_idents(); // it's important to call that early on to have identifiers point at the right declaration.
+        let mut wesl = self.resolver.source_to_module(source, path)?;
         (self.preprocess)(&mut wesl).map_err(|e| {
             Diagnostic::from(e)
                 .with_module_path(path.clone(), self.display_name(path))
                 .with_source(source.to_string())
         })?;
+        // preprocessing can remove declarations, e.g. one of several conditional
+        // declarations with the same name.
+        wesl.retarget_idents();
         Ok(wesl)
     }
     fn display_name(&self, path: &ModulePath) -> Option<String> {
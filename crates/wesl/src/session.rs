@@ -0,0 +1,553 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::*;
+
+use crate::{
+    compile, compile_sourcemap, deps::DepsRecorder, visit::Visit, CompileOptions, CompileResult,
+    Error, ResolveError, Resolver, Wesl,
+};
+#[cfg(feature = "condcomp")]
+use crate::{Diagnostic, SyntaxUtil};
+
+/// A parsed module and the hash of the source it was parsed from.
+struct CachedModule {
+    hash: u64,
+    syntax: TranslationUnit,
+}
+
+/// A compilation result and the modules it was compiled from.
+struct CachedResult {
+    result: CompileResult,
+    deps: Vec<(ModulePath, u64)>,
+}
+
+/// A compilation root and the set of enabled conditional compilation features.
+type VariantKey = (ModulePath, Vec<(String, bool)>);
+
+/// A long-lived compiler that caches modules and compilation results between
+/// compilations.
+///
+/// Use it when compiling the same shaders repeatedly, e.g. for hot reloading: each module
+/// is parsed once and re-parsed only when its source changes. Compilation results are
+/// cached per root module and set of features, and are reused until one of the modules
+/// they depend on changes.
+///
+/// Changes are detected by comparing the hash of the module sources, which requires
+/// reading all sources on each compilation. Alternatively, disable hash checks with
+/// [`Self::check_hashes`] and notify the session of changes with [`Self::invalidate`] or
+/// [`Self::invalidate_file`].
+///
+/// Import resolution is not cached separately from the compilation results. Which
+/// declarations are resolved, and from which modules, depends on the content of every
+/// module of the compilation: a changed module can import new modules, or use other
+/// declarations of the modules it imports. Moreover, resolution mangles the declarations in
+/// place. Reusing a partial resolution after a change would therefore be as costly as
+/// resolving again. The resolution of an unchanged module tree is reused with the cached
+/// compilation result, and only the changed modules are parsed again.
+///
+/// # Usage
+///
+/// ```ignore
+/// # use wesl::{CompileSession, Wesl};
+/// let mut session = CompileSession::new(Wesl::new("path/to/dir/containing/shaders"));
+/// let wgsl_string = session.compile("main.wesl").unwrap().to_string();
+/// // ... `lighting.wesl` changes on disk
+/// for root in session.invalidate_file("path/to/dir/containing/shaders/lighting.wesl") {
+///     // recompiles `lighting.wesl` only, other modules are cached.
+///     let wgsl_string = session.compile(root).unwrap().to_string();
+/// }
+/// ```
+pub struct CompileSession<R: Resolver> {
+    compiler: Wesl<R>,
+    check_hashes: bool,
+    modules: RefCell<HashMap<ModulePath, CachedModule>>,
+    results: HashMap<VariantKey, CachedResult>,
+}
+
+impl<R: Resolver> CompileSession<R> {
+    /// Create a new session with a compiler and its options.
+    pub fn new(compiler: Wesl<R>) -> Self {
+        Self {
+            compiler,
+            check_hashes: true,
+            modules: Default::default(),
+            results: Default::default(),
+        }
+    }
+
+    /// Get the compiler.
+    pub fn compiler(&self) -> &Wesl<R> {
+        &self.compiler
+    }
+
+    /// Get the compiler to change its options. This clears the cached compilation results,
+    /// but not the cached modules.
+    pub fn compiler_mut(&mut self) -> &mut Wesl<R> {
+        self.results.clear();
+        &mut self.compiler
+    }
+
+    /// Check the hash of the module sources to detect changes. Default: `true`.
+    ///
+    /// When disabled, cached modules and results are reused until they are invalidated
+    /// with [`Self::invalidate`] or [`Self::invalidate_file`].
+    pub fn check_hashes(&mut self, val: bool) -> &mut Self {
+        self.check_hashes = val;
+        self
+    }
+
+    /// Compile a WESL program from a root file, with the compiler's features.
+    ///
+    /// See [`Wesl::compile`].
+    #[allow(clippy::result_large_err)]
+    pub fn compile(&mut self, root: impl Into<ModulePath>) -> Result<CompileResult, Error> {
+        self.compile_variant(root, &HashMap::new())
+    }
+
+    /// Compile a WESL program from a root file, with a set of conditional compilation
+    /// features. The features override the compiler's features.
+    #[allow(clippy::result_large_err)]
+    pub fn compile_variant(
+        &mut self,
+        root: impl Into<ModulePath>,
+        features: &HashMap<String, bool>,
+    ) -> Result<CompileResult, Error> {
+        let mut root = root.into();
+        root.origin = PathOrigin::Absolute; // we force absolute paths
+
+        let mut options = self.compiler.options.clone();
+        options.features.extend(features.clone());
+        let key = (
+            root.clone(),
+            options
+                .features
+                .iter()
+                .map(|(name, val)| (name.clone(), *val))
+                .sorted_unstable()
+                .collect(),
+        );
+
+        if let Some(cached) = self.results.get(&key) {
+            if !self.check_hashes || self.is_fresh(&cached.deps) {
+                return Ok(CompileResult {
+                    syntax: fresh_copy(&cached.result.syntax),
+                    sourcemap: cached.result.sourcemap.clone(),
+                    deps: cached.result.deps.clone(),
+                    warnings: cached.result.warnings.clone(),
+                });
+            }
+        }
+
+        let (result, deps) = self.compile_uncached(&root, options);
+        let result = result?;
+        self.results.insert(
+            key,
+            CachedResult {
+                result: CompileResult {
+                    syntax: fresh_copy(&result.syntax),
+                    sourcemap: result.sourcemap.clone(),
+                    deps: result.deps.clone(),
+                    warnings: result.warnings.clone(),
+                },
+                deps,
+            },
+        );
+        Ok(result)
+    }
+
+    fn compile_uncached(
+        &self,
+        root: &ModulePath,
+        mut options: CompileOptions,
+    ) -> (Result<CompileResult, Error>, Vec<(ModulePath, u64)>) {
+        let recorder = DepsRecorder::new(&self.compiler.resolver);
+        let resolver = SessionResolver {
+            resolver: &recorder,
+            modules: &self.modules,
+            condcomp: options.condcomp,
+            features: options.features.clone(),
+            deps: Default::default(),
+            #[cfg(feature = "condcomp")]
+            usage: Default::default(),
+        };
+        // conditional compilation runs in the session resolver, after the cache.
+        options.condcomp = false;
+
+        let mangler = &self.compiler.mangler;
+        let result = if self.compiler.use_sourcemap {
+            let (syntax, sourcemap) = compile_sourcemap(root, &resolver, mangler, &options);
+            syntax.map(|syntax| (syntax, Some(sourcemap)))
+        } else {
+            compile(root, &resolver, mangler, &options)
+                .map(|syntax| (syntax, None))
+                .map_err(Into::into)
+        };
+        let deps = resolver.deps.into_inner();
+        #[cfg(feature = "condcomp")]
+        let warnings = resolver.usage.into_inner().warnings();
+        #[cfg(not(feature = "condcomp"))]
+        let warnings = Vec::new();
+        let result = result.map(|(syntax, sourcemap)| CompileResult {
+            syntax,
+            sourcemap,
+            deps: recorder.finish(),
+            warnings,
+        });
+        (result, deps)
+    }
+
+    fn is_fresh(&self, deps: &[(ModulePath, u64)]) -> bool {
+        deps.iter().all(|(path, hash)| {
+            self.compiler
+                .resolver
+                .resolve_source(path)
+                .is_ok_and(|source| hash_source(&source) == *hash)
+        })
+    }
+
+    /// Invalidate a module after it changed. Returns the roots of the cached compilation
+    /// results that depend on it. They will be recompiled on the next call to
+    /// [`Self::compile`] or [`Self::compile_variant`].
+    pub fn invalidate(&mut self, path: impl Into<ModulePath>) -> Vec<ModulePath> {
+        let mut path = path.into();
+        path.origin = PathOrigin::Absolute; // like in `compile`
+        self.modules.get_mut().remove(&path);
+        let mut roots = Vec::new();
+        self.results.retain(|(root, _), cached| {
+            let stale = cached.deps.iter().any(|(dep, _)| *dep == path);
+            if stale && !roots.contains(root) {
+                roots.push(root.clone());
+            }
+            !stale
+        });
+        roots
+    }
+
+    /// Invalidate the module read from a file after it changed, e.g. from a file watcher
+    /// notification. Modules are matched against the file by their
+    /// [display name](Resolver::display_name).
+    ///
+    /// Returns the roots of the cached compilation results that depend on it. See
+    /// [`Self::invalidate`].
+    pub fn invalidate_file(&mut self, file: impl AsRef<Path>) -> Vec<ModulePath> {
+        let file = file.as_ref();
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let paths = self
+            .results
+            .values()
+            .flat_map(|cached| cached.deps.iter().map(|(path, _)| path))
+            .chain(self.modules.get_mut().keys())
+            .filter(|path| {
+                self.compiler
+                    .resolver
+                    .display_name(path)
+                    .is_some_and(|name| {
+                        let name = Path::new(&name);
+                        name == file || name.canonicalize().is_ok_and(|name| name == file)
+                    })
+            })
+            .cloned()
+            .collect::<HashSet<_>>();
+        let mut roots = Vec::new();
+        for path in paths {
+            for root in self.invalidate(path) {
+                if !roots.contains(&root) {
+                    roots.push(root);
+                }
+            }
+        }
+        roots
+    }
+
+    /// Clear all cached modules and compilation results.
+    pub fn clear(&mut self) {
+        self.modules.get_mut().clear();
+        self.results.clear();
+    }
+}
+
+fn hash_source(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Resolves modules through the session cache and records the modules used by a
+/// compilation.
+struct SessionResolver<'a, R: Resolver> {
+    resolver: &'a R,
+    modules: &'a RefCell<HashMap<ModulePath, CachedModule>>,
+    #[cfg_attr(not(feature = "condcomp"), allow(unused))]
+    condcomp: bool,
+    #[cfg_attr(not(feature = "condcomp"), allow(unused))]
+    features: HashMap<String, bool>,
+    deps: RefCell<Vec<(ModulePath, u64)>>,
+    #[cfg(feature = "condcomp")]
+    usage: RefCell<crate::condcomp::FeatureUsage>,
+}
+
+impl<R: Resolver> Resolver for SessionResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn source_to_module(
+        &self,
+        source: &str,
+        path: &ModulePath,
+    ) -> Result<TranslationUnit, ResolveError> {
+        let hash = hash_source(source);
+        let mut modules = self.modules.borrow_mut();
+        let cached = modules.get(path).filter(|cached| cached.hash == hash);
+        #[allow(unused_mut)]
+        let mut wesl = match cached {
+            Some(cached) => fresh_copy(&cached.syntax),
+            None => {
+                let syntax = self.resolver.source_to_module(source, path)?;
+                let wesl = fresh_copy(&syntax);
+                modules.insert(path.clone(), CachedModule { hash, syntax });
+                wesl
+            }
+        };
+        self.deps.borrow_mut().push((path.clone(), hash));
+
+        #[cfg(feature = "condcomp")]
+        if self.condcomp {
+            self.usage
+                .borrow_mut()
+                .add_module(&wesl, path, self.display_name(path), source);
+            crate::condcomp::run(&mut wesl, &self.features).map_err(|e| {
+                Diagnostic::from(e)
+                    .with_module_path(path.clone(), self.display_name(path))
+                    .with_source(source.to_string())
+            })?;
+            wesl.retarget_idents();
+        }
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+}
+
+/// A deep copy of a syntax tree.
+///
+/// Identifiers are shared pointers (see [`Ident`]) and renaming one renames all its
+/// copies. The copy gets new identifiers, shared in the same way as in the original, so
+/// that renaming (e.g. mangling) the copy leaves the original intact.
+fn fresh_copy(wesl: &TranslationUnit) -> TranslationUnit {
+    let mut wesl = wesl.clone();
+    let mut fresh = Fresh::default();
+
+    #[cfg(feature = "imports")]
+    for import in &mut wesl.imports {
+        fresh.import(&mut import.content);
+    }
+    for decl in &mut wesl.global_declarations {
+        match decl {
+            GlobalDeclaration::Declaration(decl) => fresh.ident(&mut decl.ident),
+            GlobalDeclaration::TypeAlias(decl) => fresh.ident(&mut decl.ident),
+            GlobalDeclaration::Struct(decl) => {
+                fresh.ident(&mut decl.ident);
+                for member in &mut decl.members {
+                    fresh.ident(&mut member.ident);
+                }
+            }
+            GlobalDeclaration::Function(decl) => {
+                fresh.ident(&mut decl.ident);
+                for param in &mut decl.parameters {
+                    fresh.ident(&mut param.ident);
+                }
+                for stmt in &mut decl.body.statements {
+                    fresh.stmt(stmt);
+                }
+            }
+            GlobalDeclaration::Void | GlobalDeclaration::ConstAssert(_) => (),
+        }
+    }
+    #[cfg(feature = "generics")]
+    for attrs in Visit::<Attributes>::visit_mut(&mut wesl) {
+        for attr in attrs {
+            if let Attribute::Type(constraint) = attr {
+                fresh.ident(&mut constraint.ident);
+            }
+        }
+    }
+    for ty in Visit::<TypeExpression>::visit_mut(&mut wesl) {
+        fresh.ty(ty);
+    }
+    wesl
+}
+
+/// Maps the identifiers of the original syntax tree to the identifiers of the copy.
+#[derive(Default)]
+struct Fresh(HashMap<Ident, Ident>);
+
+impl Fresh {
+    fn ident(&mut self, ident: &mut Ident) {
+        let new = match self.0.get(ident) {
+            Some(new) => new.clone(),
+            None => {
+                let new = Ident::new(ident.name().clone());
+                self.0.insert(ident.clone(), new.clone());
+                // idents of the copy may be visited more than once.
+                self.0.insert(new.clone(), new.clone());
+                new
+            }
+        };
+        *ident = new;
+    }
+
+    #[cfg(feature = "imports")]
+    fn import(&mut self, content: &mut ImportContent) {
+        match content {
+            ImportContent::Item(item) => {
+                self.ident(&mut item.ident);
+                if let Some(rename) = &mut item.rename {
+                    self.ident(rename);
+                }
+            }
+            ImportContent::Collection(coll) => {
+                for import in coll {
+                    self.import(&mut import.content);
+                }
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &mut StatementNode) {
+        if let Statement::Declaration(decl) = stmt.node_mut() {
+            self.ident(&mut decl.ident);
+        }
+        for stmt in Visit::<StatementNode>::visit_mut(stmt.node_mut()) {
+            self.stmt(stmt);
+        }
+    }
+
+    fn ty(&mut self, ty: &mut TypeExpression) {
+        self.ident(&mut ty.ident);
+        for ty in Visit::<TypeExpression>::visit_mut(ty) {
+            self.ty(ty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_session() {
+        let dir = std::env::temp_dir().join(format!("wesl-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |file: &str, source: &str| std::fs::write(dir.join(file), source).unwrap();
+        write("util.wesl", "fn helper() -> u32 { return 1u; }");
+        write("main.wesl", "import package::util::helper;\n@compute @workgroup_size(1) fn main() { let x = helper(); }");
+        write("other.wesl", "@compute @workgroup_size(1) fn main() {}");
+
+        // the files read are recorded, with or without the session.
+        let comp = Wesl::new(&dir).compile("main").unwrap();
+        assert_eq!(
+            comp.deps.files,
+            [dir.join("main.wesl"), dir.join("util.wesl")]
+        );
+
+        let mut session = CompileSession::new(Wesl::new(&dir));
+        let comp = session.compile("main").unwrap();
+        assert_eq!(
+            comp.deps.files,
+            [dir.join("main.wesl"), dir.join("util.wesl")]
+        );
+        let first = comp.to_string();
+        // cached results and modules must not be altered by mangling.
+        assert_eq!(session.compile("main").unwrap().to_string(), first);
+        session.compile("other").unwrap();
+
+        // changes are detected by hash.
+        write("util.wesl", "fn helper() -> u32 { return 2u; }");
+        assert!(session.compile("main").unwrap().to_string().contains("2u"));
+
+        // changes are notified.
+        session.check_hashes(false);
+        write("util.wesl", "fn helper() -> u32 { return 3u; }");
+        assert_eq!(
+            session.compile("main").unwrap().to_string(),
+            first.replace("1u", "2u")
+        );
+        let roots = session.invalidate_file(dir.join("util.wesl"));
+        assert_eq!(
+            roots,
+            [ModulePath::new(
+                PathOrigin::Absolute,
+                vec!["main".to_string()]
+            )]
+        );
+        assert!(session.compile("main").unwrap().to_string().contains("3u"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Counts the modules parsed by the inner resolver.
+    struct ParseCounter<R: Resolver> {
+        resolver: R,
+        parsed: RefCell<Vec<String>>,
+    }
+
+    impl<R: Resolver> Resolver for ParseCounter<R> {
+        fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+            self.resolver.resolve_source(path)
+        }
+        fn source_to_module(
+            &self,
+            source: &str,
+            path: &ModulePath,
+        ) -> Result<TranslationUnit, ResolveError> {
+            self.parsed.borrow_mut().push(path.to_string());
+            self.resolver.source_to_module(source, path)
+        }
+    }
+
+    #[test]
+    fn test_session_resolution() {
+        let mut resolver = crate::VirtualResolver::new();
+        resolver.add_module("main", "import package::util::helper;\n@compute @workgroup_size(1) fn main() { let x = helper(); }".into());
+        resolver.add_module("util", "fn helper() -> u32 { return 1u; }".into());
+        resolver.add_module("other", "fn other() -> u32 { return 2u; }".into());
+        let counter = ParseCounter {
+            resolver,
+            parsed: Default::default(),
+        };
+        let compiler = Wesl {
+            options: CompileOptions::default(),
+            use_sourcemap: false,
+            mangler: Wesl::new_barebones().mangler,
+            resolver: counter,
+        };
+        let mut session = CompileSession::new(compiler);
+        fn parsed<R: Resolver>(session: &mut CompileSession<ParseCounter<R>>) -> Vec<String> {
+            session.compiler().resolver.parsed.take()
+        }
+
+        session.compile("main").unwrap();
+        assert_eq!(parsed(&mut session), ["package::main", "package::util"]);
+
+        // the resolution of an unchanged module tree is cached with the result.
+        session.compile("main").unwrap();
+        assert!(parsed(&mut session).is_empty());
+
+        // a changed module can import other modules: the module tree is resolved again,
+        // but only the changed module is parsed again.
+        session.compiler_mut().resolver.resolver.add_module(
+            "util",
+            "import package::other::other;\nfn helper() -> u32 { return other(); }".into(),
+        );
+        let wgsl = session.compile("main").unwrap().to_string();
+        assert!(wgsl.contains("2u"));
+        assert_eq!(parsed(&mut session), ["package::util", "package::other"]);
+    }
+}
@@ -1,8 +1,14 @@
-use std::{cell::RefCell, collections::HashMap};
+use std::{cell::RefCell, collections::HashMap, str::FromStr};
 
-use wgsl_parse::syntax::{TranslationUnit, TypeExpression};
+use wgsl_parse::{
+    span::Span,
+    syntax::{
+        Expression, ExpressionNode, GlobalDeclaration, StatementNode, TranslationUnit,
+        TypeExpression,
+    },
+};
 
-use crate::{Mangler, ModulePath, ResolveError, Resolver};
+use crate::{visit::Visit, Mangler, ModulePath, ResolveError, Resolver};
 
 /// A SourceMap is a lookup from compiled WGSL to source WESL. It translates a mangled
 /// name into a module path and declaration name.
@@ -138,9 +144,6 @@ impl<'a> Resolver for SourceMapper<'a> {
     ) -> Result<TranslationUnit, ResolveError> {
         self.resolver.source_to_module(source, path)
     }
-    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
-        self.resolver.resolve_module(path)
-    }
     fn display_name(&self, path: &ModulePath) -> Option<String> {
         self.resolver.display_name(path)
     }
@@ -157,6 +160,289 @@ impl<'a> Mangler for SourceMapper<'a> {
         self.mangler.unmangle(mangled)
     }
     fn mangle_types(&self, item: &str, variant: u32, types: &[TypeExpression]) -> String {
-        self.mangler.mangle_types(item, variant, types)
+        let res = self.mangler.mangle_types(item, variant, types);
+        let mut sourcemap = self.sourcemap.borrow_mut();
+        // instances of generic declarations map to the generic declaration.
+        if let Some((path, decl)) = sourcemap.get_decl(item) {
+            let (path, decl) = (path.clone(), decl.to_string());
+            sourcemap.add_decl(res.clone(), path, decl);
+        }
+        res
+    }
+    fn mangle_signature(&self, item: &str, variant: u32, signature: &[TypeExpression]) -> String {
+        let res = self.mangler.mangle_signature(item, variant, signature);
+        let mut sourcemap = self.sourcemap.borrow_mut();
+        // overloads map to the overloaded declaration.
+        if let Some((path, decl)) = sourcemap.get_decl(item) {
+            let (path, decl) = (path.clone(), decl.to_string());
+            sourcemap.add_decl(res.clone(), path, decl);
+        }
+        res
+    }
+}
+
+/// A byte range of the output WGSL and the source it was compiled from. See [`SpanMap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanMapping {
+    /// Byte range in the output WGSL.
+    pub output: Span,
+    /// The module the code comes from.
+    pub module_path: ModulePath,
+    /// Byte range in the module source.
+    pub source: Span,
+}
+
+/// A span-level mapping from the output WGSL to the source modules.
+///
+/// Statements and expressions of the output are mapped to their span in the source
+/// module. Use it to locate errors reported on the output WGSL, e.g. by a WebGPU
+/// implementation, or export it as a source map v3 with [`Self::to_json`].
+#[derive(Clone, Debug, Default)]
+pub struct SpanMap {
+    output: String,
+    /// Sorted by start of the output range.
+    mappings: Vec<SpanMapping>,
+}
+
+impl SpanMap {
+    /// Compute the span map of a compiled module. The sourcemap tells the module each
+    /// declaration comes from.
+    pub fn new(wgsl: &TranslationUnit, sourcemap: &impl SourceMap) -> Self {
+        let output = wgsl.to_string();
+        let mut mappings = Vec::new();
+
+        // the output is parsed again to get the spans in the output.
+        if let Ok(out) = TranslationUnit::from_str(&output) {
+            for (decl, out_decl) in wgsl
+                .global_declarations
+                .iter()
+                .zip(&out.global_declarations)
+            {
+                let Some((path, _)) = decl
+                    .ident()
+                    .and_then(|ident| sourcemap.get_decl(&ident.name()))
+                else {
+                    continue;
+                };
+                let (mut spans, mut out_spans) = (Vec::new(), Vec::new());
+                decl_spans(decl, &mut spans);
+                decl_spans(out_decl, &mut out_spans);
+                if spans.len() != out_spans.len() {
+                    continue; // should not happen, the syntax trees are the same.
+                }
+                mappings.extend(
+                    spans
+                        .into_iter()
+                        .zip(out_spans)
+                        // nodes created by the compiler have an empty span.
+                        .filter(|(source, _)| !source.is_empty())
+                        .map(|(source, output)| SpanMapping {
+                            output,
+                            module_path: path.clone(),
+                            source,
+                        }),
+                );
+            }
+        }
+
+        // inner nodes come after outer nodes that start at the same position.
+        mappings.sort_by_key(|m| (m.output.start, std::cmp::Reverse(m.output.end)));
+        Self { output, mappings }
+    }
+
+    /// The output WGSL.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// The mappings, sorted by start of the output range.
+    pub fn mappings(&self) -> &[SpanMapping] {
+        &self.mappings
+    }
+
+    /// Map a byte range of the output to its source module and span.
+    ///
+    /// The span is exact if the code of the innermost statement or expression containing
+    /// `span` was not modified by the compiler. Otherwise, it is the span of that statement
+    /// or expression.
+    pub fn get<'a>(
+        &'a self,
+        span: &Span,
+        sourcemap: &impl SourceMap,
+    ) -> Option<(&'a ModulePath, Span)> {
+        let mapping = self
+            .mappings
+            .iter()
+            .filter(|m| m.output.start <= span.start && span.end <= m.output.end)
+            .min_by_key(|m| m.output.len())?;
+        let unmodified = sourcemap
+            .get_source(&mapping.module_path)
+            .and_then(|source| source.get(mapping.source.range()))
+            .is_some_and(|source| *source == self.output[mapping.output.range()]);
+        let span = if unmodified {
+            let start = mapping.source.start + span.start - mapping.output.start;
+            Span::new(start..start + span.len())
+        } else {
+            mapping.source.clone()
+        };
+        Some((&mapping.module_path, span))
+    }
+
+    /// Export as a [source map v3](https://tc39.es/ecma426/) JSON string.
+    ///
+    /// `file` is the name of the output file. Sources are named after their display name,
+    /// or their module path, and their contents are embedded.
+    pub fn to_json(&self, sourcemap: &impl SourceMap, file: Option<&str>) -> String {
+        let mut builder = ::sourcemap::SourceMapBuilder::new(file);
+        let out_lines = LineIndex::new(&self.output);
+        let mut sources = HashMap::new();
+
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            // when nodes start at the same position, the innermost one is mapped.
+            if self
+                .mappings
+                .get(i + 1)
+                .is_some_and(|next| next.output.start == mapping.output.start)
+            {
+                continue;
+            }
+            let path = &mapping.module_path;
+            let Some(source) = sourcemap.get_source(path) else {
+                continue;
+            };
+            let (id, lines) = sources.entry(path).or_insert_with(|| {
+                let name = sourcemap
+                    .get_display_name(path)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| path.to_string());
+                let id = builder.add_source(&name);
+                builder.set_source_contents(id, Some(source));
+                (id, LineIndex::new(source))
+            });
+            let (dst_line, dst_col) = out_lines.line_col(mapping.output.start);
+            let (src_line, src_col) = lines.line_col(mapping.source.start);
+            builder.add_raw(dst_line, dst_col, src_line, src_col, Some(*id), None, false);
+        }
+
+        let mut json = Vec::new();
+        builder
+            .into_sourcemap()
+            .to_writer(&mut json)
+            .expect("writing to a Vec does not fail");
+        String::from_utf8(json).expect("source maps are valid UTF-8")
+    }
+}
+
+/// The spans of statements and expressions of a declaration, in a deterministic order.
+fn decl_spans(decl: &GlobalDeclaration, spans: &mut Vec<Span>) {
+    fn stmt_spans(stmt: &StatementNode, spans: &mut Vec<Span>) {
+        spans.push(stmt.span().clone());
+        for stmt in Visit::<StatementNode>::visit(stmt.node()) {
+            stmt_spans(stmt, spans);
+        }
+    }
+    fn expr_spans(expr: &ExpressionNode, spans: &mut Vec<Span>) {
+        spans.push(expr.span().clone());
+        match expr.node() {
+            Expression::Literal(_) | Expression::TypeOrIdentifier(_) => {}
+            Expression::Parenthesized(e) => expr_spans(&e.expression, spans),
+            Expression::NamedComponent(e) => expr_spans(&e.base, spans),
+            Expression::Indexing(e) => {
+                expr_spans(&e.base, spans);
+                expr_spans(&e.index, spans);
+            }
+            Expression::Unary(e) => expr_spans(&e.operand, spans),
+            Expression::Binary(e) => {
+                expr_spans(&e.left, spans);
+                expr_spans(&e.right, spans);
+            }
+            Expression::FunctionCall(e) => {
+                for arg in &e.arguments {
+                    expr_spans(arg, spans);
+                }
+            }
+        }
+    }
+    if let GlobalDeclaration::Function(decl) = decl {
+        for stmt in &decl.body.statements {
+            stmt_spans(stmt, spans);
+        }
+    }
+    for expr in Visit::<ExpressionNode>::visit(decl) {
+        expr_spans(expr, spans);
+    }
+}
+
+/// Converts byte offsets to zero-based line and column numbers. Columns are counted in
+/// UTF-16 code units, as required by source maps.
+struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    fn line_col(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let col = self.text[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line as u32, col as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    #[cfg(feature = "imports")]
+    #[test]
+    fn test_span_map() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::util::scale;
+            @fragment fn fs() -> @location(0) vec4f { return vec4f(scale(1.0)); }"
+                .into(),
+        );
+        resolver.add_module("util", "fn scale(x: f32) -> f32 { return x * 2.0; }".into());
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions::default());
+        compiler.use_sourcemap(true);
+        let compiler = compiler.set_custom_resolver(resolver);
+
+        let result = compiler.compile("main").unwrap();
+        let sourcemap = result.sourcemap.as_ref().unwrap();
+        let span_map = result.span_map().unwrap();
+        assert_eq!(span_map.output(), result.to_string());
+
+        // spans in the output WGSL map to the module they come from.
+        let start = span_map.output().find("x * 2.0").unwrap();
+        let span = wgsl_parse::span::Span::new(start + 4..start + 7);
+        let (path, span) = span_map.get(&span, sourcemap).unwrap();
+        assert_eq!(path.to_string(), "package::util");
+        let source = sourcemap.get_source(path).unwrap();
+        assert_eq!(&source[span.range()], "2.0");
+
+        let start = span_map.output().find("vec4f(").unwrap();
+        let (path, span) = span_map
+            .get(&wgsl_parse::span::Span::new(start..start + 5), sourcemap)
+            .unwrap();
+        assert_eq!(path.to_string(), "package::main");
+        assert_eq!(
+            span.start,
+            sourcemap.get_source(path).unwrap().find("vec4f(").unwrap()
+        );
+
+        let json = span_map.to_json(sourcemap, Some("main.wgsl"));
+        assert!(json.contains(r#""version":3"#));
+        assert!(json.contains(r#""file":"main.wgsl""#));
     }
 }
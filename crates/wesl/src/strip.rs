@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use wgsl_parse::syntax::{Ident, TranslationUnit, TypeExpression};
 
@@ -12,9 +12,19 @@ pub fn strip_except(wgsl: &mut TranslationUnit, keep: &[String]) {
         .filter_map(|decl| decl.ident().cloned())
         .collect::<HashSet<_>>();
 
+    // overloaded functions share the same name, they are kept or removed together.
+    let mut global_names: HashMap<String, Vec<Ident>> = HashMap::new();
+    for ident in &global_idents {
+        global_names
+            .entry(ident.name().to_string())
+            .or_default()
+            .push(ident.clone());
+    }
+
     let mut keep: HashSet<Ident> = HashSet::from_iter(
         keep.iter()
-            .filter_map(|name| global_idents.iter().find(|ident| ident.name().eq(name)))
+            .filter_map(|name| global_names.get(name))
+            .flatten()
             .cloned(),
     );
     let mut next_keep: HashSet<Ident> = HashSet::new();
@@ -25,7 +35,8 @@ pub fn strip_except(wgsl: &mut TranslationUnit, keep: &[String]) {
                 if keep.contains(ident) {
                     let used = Visit::<TypeExpression>::visit(decl)
                         .filter(|ty| global_idents.contains(&ty.ident))
-                        .map(|ty| ty.ident.clone())
+                        .flat_map(|ty| &global_names[&*ty.ident.name()])
+                        .cloned()
                         .collect::<HashSet<_>>();
                     next_keep.extend(used);
                 }
@@ -85,6 +85,19 @@ impl SyntaxUtil for TranslationUnit {
                 .collect::<HashSet<_>>(),
         );
 
+        // type parameters of generic declarations are in scope in the declaration.
+        #[cfg(feature = "generics")]
+        fn generic_scope<'a>(attrs: &[Attribute], scope: &Scope<'a>) -> Scope<'a> {
+            let mut scope = scope.clone();
+            scope
+                .to_mut()
+                .extend(attrs.iter().filter_map(|attr| match attr {
+                    Attribute::Type(attr) => Some(attr.ident.clone()),
+                    _ => None,
+                }));
+            scope
+        }
+
         fn retarget_ty(ty: &mut TypeExpression, scope: &HashSet<Ident>) {
             if let Some(id) = scope.iter().find(|ident| *ident.name() == *ty.ident.name()) {
                 ty.ident = id.clone();
@@ -304,23 +317,18 @@ impl SyntaxUtil for TranslationUnit {
                     Visit::<TypeExpression>::visit_mut(d).for_each(|ty| retarget_ty(ty, &scope))
                 }
                 GlobalDeclaration::TypeAlias(d) => {
+                    #[cfg(feature = "generics")]
+                    let scope = generic_scope(&d.attributes, &scope);
                     Visit::<TypeExpression>::visit_mut(d).for_each(|ty| retarget_ty(ty, &scope))
                 }
                 GlobalDeclaration::Struct(d) => {
+                    #[cfg(feature = "generics")]
+                    let scope = generic_scope(&d.attributes, &scope);
                     Visit::<TypeExpression>::visit_mut(d).for_each(|ty| retarget_ty(ty, &scope))
                 }
                 GlobalDeclaration::Function(d) => {
                     #[cfg(feature = "generics")]
-                    let scope = {
-                        let mut scope = scope.clone();
-                        scope
-                            .to_mut()
-                            .extend(d.attributes.iter().filter_map(|attr| match attr {
-                                Attribute::Type(attr) => Some(attr.ident.clone()),
-                                _ => None,
-                            }));
-                        scope
-                    };
+                    let scope = generic_scope(&d.attributes, &scope);
                     let d2 = &mut *d; // COMBAK: not sure why this is needed?
                     query_mut!(d2.{
                         attributes.[].(x => x.visit_mut()),
@@ -1,148 +1,2380 @@
-use std::ops::Deref;
+//! Lowering of a WGSL syntax tree to a [`naga::Module`].
+//!
+//! Const-expressions are evaluated with the [`crate::eval`] machinery and emitted as
+//! naga constants, everything else is lowered one-to-one to naga IR. Spans in the
+//! resulting module point to the source of the syntax tree.
+
+use std::{collections::HashMap, iter::zip, num::NonZeroU32, ops::Deref};
 
 use derive_more::derive::{Deref, DerefMut};
-use itertools::Itertools;
-use naga::{Arena, SpecialTypes, UniqueArena};
+use naga::{Block, Handle};
 use thiserror::Error;
 
-use wgsl_parse::{span::Span, syntax::*, Decorated};
+use wgsl_parse::{span::Span, syntax::*};
 
 use crate::{
-    eval::{self, Context, EvalAttrs, EvalStage, EvalTy, SyntaxUtil, Type},
-    EvalError, Exec,
+    eval::{
+        assignment_op, attr_blend_src, attr_location, binary_op_type, builtin_fn_type,
+        constructor_type, convert_ty, is_constructor_fn, round_up, ty_eval_ty, unary_op_type,
+        Context, Convert, Eval, EvalAttrs, EvalTy, Exec, Instance, LiteralInstance, SampledType,
+        SamplerType, Scope, SyntaxUtil, TexelFormat, TextureDimension, TextureType, Ty, Type,
+        ATOMIC_RESULT, ATTR_INTRINSIC,
+    },
+    Diagnostic, Error, EvalError,
 };
 
+/// WGSL to naga lowering errors.
 #[derive(Clone, Debug, Error)]
 pub enum NagaError {
     #[error("{0}")]
-    Eval(EvalError),
+    Eval(#[from] EvalError),
     #[error("f16 are not supported by naga")]
     F16NotSupported,
-    #[error("TODO in wgsl to naga conversion")]
-    TODO,
-    #[error("expected an AbstractInt, wgsl to naga does not support all expressions here")]
-    ExpectedAbstractInt,
     #[error("wgsl to naga does not support {0}")]
     Unsupported(&'static str),
-    #[error("uninitialized const-declaration")]
-    UninitConst,
-    #[error("let-declarations are not permitted at the module scope")]
-    LetInMod,
+    #[error("type `{0}` cannot be lowered to naga")]
+    Type(Type),
+    #[error("expected a reference, got a value of type `{0}`")]
+    NotRef(Type),
+    #[error("expected a pointer, got a value of type `{0}`")]
+    NotPtr(Type),
+    #[error("the {0} must be a const-expression")]
+    NotConst(&'static str),
+    #[error("function `{0}` is recursive")]
+    Recursion(String),
+    #[error("override id `{0}` is too large")]
+    OverrideId(u32),
 }
 
 type E = NagaError;
-type ExprArena = Arena<naga::Expression>;
-type TypeArena = UniqueArena<naga::Type>;
+
+/// Lower a WGSL syntax tree to a naga module.
+///
+/// The module is not validated, use [`naga::valid::Validator`] for that. Declarations
+/// that are not used by any entrypoint are lowered too.
+#[allow(clippy::result_large_err)]
+pub fn to_naga(wgsl: &TranslationUnit) -> Result<naga::Module, Diagnostic<Error>> {
+    let mut lowerer = Lowerer::new(wgsl);
+    match lowerer.lower() {
+        Ok(()) => Ok(lowerer.module),
+        Err(e) => {
+            let mut diag = Diagnostic::from(e);
+            if let Some(decl) = lowerer.err_decl {
+                diag = diag.with_declaration(decl);
+            }
+            if let Some(span) = lowerer.err_span {
+                diag = diag.with_span(span);
+            }
+            Err(diag)
+        }
+    }
+}
 
 #[derive(Deref, DerefMut)]
 struct ToNaga<T>(T);
 
-impl ToNaga<&Type> {
-    pub fn to_naga(&self, ctx: &mut Context, arena: &mut TypeArena) -> Result<naga::TypeInner, E> {
+/// A module-scope declaration.
+#[derive(Clone, Debug)]
+enum Global {
+    Const(Handle<naga::Constant>, Type),
+    Override(Handle<naga::Override>, Type),
+    Var(Handle<naga::GlobalVariable>, Type, AddressSpace),
+}
+
+/// A function-scope declaration.
+#[derive(Clone, Debug)]
+enum Local {
+    /// let-declarations and formal parameters.
+    Let(Handle<naga::Expression>, Type),
+    Var(Handle<naga::LocalVariable>, Type),
+    /// const-declarations live in the evaluation context scope.
+    Const,
+}
+
+/// The result of lowering an expression.
+#[derive(Clone, Debug)]
+enum Value {
+    /// A const-expression, lowered when it is used so it can be converted to the type
+    /// expected by the consumer.
+    Const(Instance),
+    /// A runtime value.
+    Expr(Handle<naga::Expression>, Type),
+    /// A memory view: the expression is a pointer to the store type.
+    Ref(Handle<naga::Expression>, Type, AddressSpace),
+}
+
+impl Value {
+    fn ty(&self) -> Type {
         match self {
-            Type::Bool => Ok(naga::TypeInner::Scalar(naga::Scalar::BOOL)),
-            Type::AbstractInt => Ok(naga::TypeInner::Scalar(naga::Scalar::ABSTRACT_INT)),
-            Type::AbstractFloat => Ok(naga::TypeInner::Scalar(naga::Scalar::ABSTRACT_FLOAT)),
-            Type::I32 => Ok(naga::TypeInner::Scalar(naga::Scalar::I32)),
-            Type::U32 => Ok(naga::TypeInner::Scalar(naga::Scalar::U32)),
-            Type::F32 => Ok(naga::TypeInner::Scalar(naga::Scalar::F32)),
-            Type::F16 => Err(E::F16NotSupported),
-            Type::Struct(name) => {
-                let decl = ctx.source.decl_struct(name).unwrap();
-                ToNaga(decl).to_naga(ctx, arena)
+            Value::Const(inst) => inst.ty(),
+            Value::Expr(_, ty) | Value::Ref(_, ty, _) => ty.clone(),
+        }
+    }
+}
+
+struct FnBuilder {
+    func: naga::Function,
+    /// stack of blocks being built, the root is the function body.
+    blocks: Vec<Block>,
+    /// start of the expressions that are not yet covered by an `Emit` statement.
+    emit_start: usize,
+    locals: Scope<Local>,
+    ret: Option<Type>,
+}
+
+impl FnBuilder {
+    fn new() -> Self {
+        Self {
+            func: Default::default(),
+            blocks: vec![Block::new()],
+            emit_start: 0,
+            locals: Scope::new(),
+            ret: None,
+        }
+    }
+
+    fn append(&mut self, expr: naga::Expression, span: naga::Span) -> Handle<naga::Expression> {
+        // these expressions must not be covered by an `Emit` statement.
+        let interrupt = expr.needs_pre_emit()
+            || matches!(
+                expr,
+                naga::Expression::CallResult(_)
+                    | naga::Expression::AtomicResult { .. }
+                    | naga::Expression::WorkGroupUniformLoadResult { .. }
+            );
+        if interrupt {
+            self.flush();
+            let handle = self.func.expressions.append(expr, span);
+            self.emit_start = self.func.expressions.len();
+            handle
+        } else {
+            self.func.expressions.append(expr, span)
+        }
+    }
+
+    /// emit the pending expressions in the current block.
+    fn flush(&mut self) {
+        if self.emit_start < self.func.expressions.len() {
+            let range = self.func.expressions.range_from(self.emit_start);
+            self.block()
+                .push(naga::Statement::Emit(range), naga::Span::UNDEFINED);
+            self.emit_start = self.func.expressions.len();
+        }
+    }
+
+    fn block(&mut self) -> &mut Block {
+        self.blocks.last_mut().expect("no block")
+    }
+
+    fn push(&mut self, stmt: naga::Statement, span: naga::Span) {
+        self.flush();
+        self.block().push(stmt, span);
+    }
+
+    fn open(&mut self) {
+        self.flush();
+        self.blocks.push(Block::new());
+    }
+
+    fn close(&mut self) -> Block {
+        self.flush();
+        self.blocks.pop().expect("no block")
+    }
+}
+
+struct Lowerer<'s> {
+    ctx: Context<'s>,
+    module: naga::Module,
+    /// the module-scope evaluation scope, in which function bodies are lowered.
+    scope: Scope<Instance>,
+    globals: HashMap<String, Global>,
+    structs: HashMap<String, Handle<naga::Type>>,
+    /// lowered functions, `None` while the function is being lowered.
+    functions: HashMap<String, Option<Handle<naga::Function>>>,
+    err_decl: Option<String>,
+    err_span: Option<Span>,
+}
+
+impl<'s> Lowerer<'s> {
+    fn new(source: &'s TranslationUnit) -> Self {
+        Self {
+            ctx: Context::new(source),
+            module: Default::default(),
+            scope: Scope::new(),
+            globals: Default::default(),
+            structs: Default::default(),
+            functions: Default::default(),
+            err_decl: None,
+            err_span: None,
+        }
+    }
+
+    fn set_err_decl(&mut self, decl: String) {
+        if self.err_decl.is_none() {
+            self.err_decl = Some(decl)
+        }
+    }
+
+    fn set_err_span(&mut self, span: &Span) {
+        if self.err_span.is_none() {
+            self.err_span = Some(span.clone())
+        }
+    }
+
+    fn lower(&mut self) -> Result<(), E> {
+        let source = self.ctx.source;
+
+        // adds const-declarations to the scope and evaluates const-assertions.
+        if let Err(e) = source.exec(&mut self.ctx) {
+            let (decl, span) = self.ctx.err_ctx();
+            self.err_decl = decl;
+            self.err_span = span;
+            return Err(e.into());
+        }
+        self.scope = self.ctx.scope.clone();
+
+        // functions can refer to module-scope declarations declared after them.
+        for decl in &source.global_declarations {
+            let res = match decl {
+                GlobalDeclaration::Declaration(decl) => self.global_decl(decl),
+                GlobalDeclaration::Struct(decl) => self.struct_ty(&decl.ident.name()).map(|_| ()),
+                _ => Ok(()),
+            };
+            if let (Err(_), Some(ident)) = (&res, decl.ident()) {
+                self.set_err_decl(ident.to_string());
+            }
+            res?;
+        }
+
+        for decl in &source.global_declarations {
+            if let GlobalDeclaration::Function(decl) = decl {
+                if is_entry_point(decl) {
+                    self.entry_point(decl)
+                        .inspect_err(|_| self.set_err_decl(decl.ident.to_string()))?;
+                } else {
+                    self.function(&decl.ident.name())?;
+                }
             }
-            Type::Array(n, ty) => {
-                let base = arena.insert(ToNaga(&ty).to_naga(ctx, arena)?, naga::Span::UNDEFINED);
+        }
+
+        Ok(())
+    }
+
+    fn global_decl(&mut self, decl: &Declaration) -> Result<(), E> {
+        let name = decl.ident.to_string();
+        let span = naga::Span::UNDEFINED;
+
+        match decl.kind {
+            DeclarationKind::Const => {
+                let inst = self
+                    .ctx
+                    .scope
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| EvalError::UnknownDecl(name.clone()))?;
+                let ty = inst.ty();
+                // abstract constants are inlined where they are used.
+                if ty.is_concrete() {
+                    let init = self.inst(None, &inst, span)?;
+                    let constant = naga::Constant {
+                        name: Some(name.clone()),
+                        ty: self.ty(&ty)?,
+                        init,
+                    };
+                    let handle = self.module.constants.append(constant, span);
+                    self.globals.insert(name, Global::Const(handle, ty));
+                }
+            }
+            DeclarationKind::Override => {
+                let ty = self.decl_ty(decl)?;
+                let init = self.global_init(decl, &ty)?;
+                let id = decl
+                    .attr_id(&mut self.ctx)?
+                    .map(|id| u16::try_from(id).map_err(|_| E::OverrideId(id)))
+                    .transpose()?;
+                let override_ = naga::Override {
+                    name: Some(name.clone()),
+                    id,
+                    ty: self.ty(&ty)?,
+                    init,
+                };
+                let handle = self.module.overrides.append(override_, span);
+                self.globals.insert(name, Global::Override(handle, ty));
+            }
+            DeclarationKind::Let => return Err(EvalError::LetInMod.into()),
+            DeclarationKind::Var(space) => {
+                let space = space.unwrap_or(AddressSpace::Handle);
+                let ty = self.decl_ty(decl)?;
+                let init = self.global_init(decl, &ty)?;
+                let binding = match space {
+                    AddressSpace::Uniform | AddressSpace::Storage(_) | AddressSpace::Handle => {
+                        let (group, binding) = decl.attr_group_binding(&mut self.ctx)?;
+                        Some(naga::ResourceBinding { group, binding })
+                    }
+                    _ => None,
+                };
+                let var = naga::GlobalVariable {
+                    name: Some(name.clone()),
+                    space: ToNaga(&space).to_naga(),
+                    binding,
+                    ty: self.ty(&ty)?,
+                    init,
+                };
+                let handle = self.module.global_variables.append(var, span);
+                self.globals.insert(name, Global::Var(handle, ty, space));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the declared type, or the concrete type of the initializer.
+    fn decl_ty(&mut self, decl: &Declaration) -> Result<Type, E> {
+        match (&decl.ty, &decl.initializer) {
+            (Some(ty), _) => Ok(ty_eval_ty(ty, &mut self.ctx)?),
+            (None, Some(init)) => Ok(init.eval_ty(&mut self.ctx)?.concretize()),
+            (None, None) => Err(EvalError::UntypedDecl.into()),
+        }
+    }
+
+    /// module-scope initializers must be const-expressions.
+    fn global_init(
+        &mut self,
+        decl: &Declaration,
+        ty: &Type,
+    ) -> Result<Option<Handle<naga::Expression>>, E> {
+        decl.initializer
+            .as_ref()
+            .map(|init| {
+                let inst = init
+                    .eval_value(&mut self.ctx)
+                    .inspect_err(|_| self.set_err_span(init.span()))?;
+                let inst = inst
+                    .convert_to(ty)
+                    .ok_or_else(|| EvalError::Conversion(inst.ty(), ty.clone()))?;
+                self.inst(None, &inst, ToNaga(init.span()).to_naga())
+            })
+            .transpose()
+    }
+
+    // ---------------------------------------------------------------------------------
+    // types
+
+    fn ty(&mut self, ty: &Type) -> Result<Handle<naga::Type>, E> {
+        let ty = ty.concretize();
+        let inner = match &ty {
+            Type::Struct(name) => return self.struct_ty(name),
+            Type::Array(n, elem) => {
+                let size = elem.size_of(&mut self.ctx);
+                let align = elem.align_of(&mut self.ctx);
+                let (Some(size), Some(align)) = (size, align) else {
+                    return Err(E::Type(ty.clone()));
+                };
+                let stride = round_up(align, size);
                 let size = match n {
-                    Some(n) => naga::ArraySize::Constant(n),
+                    Some(n) => naga::ArraySize::Constant(
+                        u32::try_from(*n)
+                            .ok()
+                            .and_then(NonZeroU32::new)
+                            .ok_or_else(|| E::Type(ty.clone()))?,
+                    ),
                     None => naga::ArraySize::Dynamic,
                 };
                 naga::TypeInner::Array {
-                    base,
+                    base: self.ty(elem)?,
                     size,
-                    stride: (),
+                    stride,
                 }
             }
-            Type::Vec(_, _) => todo!(),
-            Type::Mat(_, _, _) => todo!(),
-            Type::Atomic(_) => todo!(),
-            Type::Ptr(address_space, _) => todo!(),
-            Type::Texture(texture_type) => todo!(),
-            Type::Sampler(sampler_type) => todo!(),
-            Type::Void => todo!(),
+            Type::Vec(n, elem) => naga::TypeInner::Vector {
+                size: vec_size(*n)?,
+                scalar: scalar(elem)?,
+            },
+            Type::Mat(c, r, elem) => naga::TypeInner::Matrix {
+                columns: vec_size(*c)?,
+                rows: vec_size(*r)?,
+                scalar: scalar(elem)?,
+            },
+            Type::Atomic(elem) => naga::TypeInner::Atomic(scalar(elem)?),
+            Type::Ptr(space, elem) => naga::TypeInner::Pointer {
+                base: self.ty(elem)?,
+                space: ToNaga(space).to_naga(),
+            },
+            Type::Texture(tex) => ToNaga(tex).to_naga()?,
+            Type::Sampler(sampler) => naga::TypeInner::Sampler {
+                comparison: *sampler == SamplerType::SamplerComparison,
+            },
+            _ => naga::TypeInner::Scalar(scalar(&ty)?),
+        };
+        let ty = naga::Type { name: None, inner };
+        Ok(self.module.types.insert(ty, naga::Span::UNDEFINED))
+    }
+
+    fn struct_ty(&mut self, name: &str) -> Result<Handle<naga::Type>, E> {
+        if let Some(handle) = self.structs.get(name) {
+            return Ok(*handle);
         }
+
+        let handle = if let Some(ty) = name.strip_prefix(ATOMIC_RESULT) {
+            let ty = if ty == "i32" { Type::I32 } else { Type::U32 };
+            let special = naga::PredeclaredType::AtomicCompareExchangeWeakResult(scalar(&ty)?);
+            self.module.generate_predeclared_type(special)
+        } else {
+            let source = self.ctx.source;
+            let decl = source
+                .decl_struct(name)
+                .ok_or_else(|| EvalError::UnknownStruct(name.to_string()))?;
+
+            // the frexp and modf results must be naga's predeclared types.
+            let frexp = name.starts_with("__frexp_result");
+            if frexp || name.starts_with("__modf_result") {
+                let fract = ty_eval_ty(&decl.members[0].ty, &mut self.ctx)?.concretize();
+                let size = match &fract {
+                    Type::Vec(n, _) => Some(vec_size(*n)?),
+                    _ => None,
+                };
+                let width = scalar(&fract.inner_ty())?.width;
+                let special = if frexp {
+                    naga::PredeclaredType::FrexpResult { size, width }
+                } else {
+                    naga::PredeclaredType::ModfResult { size, width }
+                };
+                self.module.generate_predeclared_type(special)
+            } else {
+                let inner = self.struct_inner(decl)?;
+                let ty = naga::Type {
+                    name: Some(name.to_string()),
+                    inner,
+                };
+                self.module.types.insert(ty, naga::Span::UNDEFINED)
+            }
+        };
+
+        self.structs.insert(name.to_string(), handle);
+        Ok(handle)
     }
-}
 
-impl ToNaga<&TypeExpression> {
-    pub fn to_naga(&self, ctx: &mut Context) -> Result<naga::Type, E> {
-        Ok(naga::Type {
-            name: Some(self.ident.to_string()),
-            inner: match self.ident.name().as_str() {
-                "f32" => naga::TypeInner::Scalar(naga::Scalar {
-                    kind: naga::ScalarKind::Float,
-                    width: 4,
-                }),
-                _ => return Err(E::TODO),
-            },
+    fn struct_inner(&mut self, decl: &Struct) -> Result<naga::TypeInner, E> {
+        let mut members = Vec::with_capacity(decl.members.len());
+        let mut offset = 0;
+        let mut struct_align = 1;
+
+        for m in &decl.members {
+            let ty = ty_eval_ty(&m.ty, &mut self.ctx)?;
+            let size = m
+                .attr_size(&mut self.ctx)?
+                .or_else(|| ty.min_size_of(&mut self.ctx))
+                .ok_or_else(|| E::Type(ty.clone()))?;
+            let align = m
+                .attr_align(&mut self.ctx)?
+                .or_else(|| ty.align_of(&mut self.ctx))
+                .ok_or_else(|| E::Type(ty.clone()))?;
+
+            offset = round_up(align, offset);
+            let binding = self.binding(&m.attributes, &ty)?;
+            members.push(naga::StructMember {
+                name: Some(m.ident.to_string()),
+                ty: self.ty(&ty)?,
+                binding,
+                offset,
+            });
+            offset += size;
+            struct_align = struct_align.max(align);
+        }
+
+        Ok(naga::TypeInner::Struct {
+            members,
+            span: round_up(struct_align, offset),
         })
     }
-}
 
-impl ToNaga<&LiteralExpression> {
-    pub fn to_naga(&self) -> Result<naga::Literal, E> {
-        Ok(match self.deref() {
-            LiteralExpression::Bool(x) => naga::Literal::Bool(*x),
-            LiteralExpression::AbstractInt(x) => naga::Literal::AbstractInt(*x),
-            LiteralExpression::AbstractFloat(x) => naga::Literal::AbstractFloat(*x),
-            LiteralExpression::I32(x) => naga::Literal::I32(*x),
-            LiteralExpression::U32(x) => naga::Literal::U32(*x),
-            LiteralExpression::F32(x) => naga::Literal::F32(*x),
-            LiteralExpression::F16(_) => return Err(E::F16NotSupported),
+    /// the name and type of the struct members.
+    fn struct_members(&mut self, name: &str) -> Result<Vec<(String, Type)>, E> {
+        if let Some(ty) = name.strip_prefix(ATOMIC_RESULT) {
+            let ty = if ty == "i32" { Type::I32 } else { Type::U32 };
+            return Ok(vec![
+                ("old_value".to_string(), ty),
+                ("exchanged".to_string(), Type::Bool),
+            ]);
+        }
+        let source = self.ctx.source;
+        let decl = source
+            .decl_struct(name)
+            .ok_or_else(|| EvalError::UnknownStruct(name.to_string()))?;
+        decl.members
+            .iter()
+            .map(|m| Ok((m.ident.to_string(), ty_eval_ty(&m.ty, &mut self.ctx)?)))
+            .collect()
+    }
+
+    /// the user-defined IO binding of an entrypoint parameter, return type or struct
+    /// member.
+    fn binding(&mut self, attrs: &[Attribute], ty: &Type) -> Result<Option<naga::Binding>, E> {
+        let builtin = attrs.iter().find_map(|attr| match attr {
+            Attribute::Builtin(builtin) => Some(*builtin),
+            _ => None,
+        });
+        if let Some(builtin) = builtin {
+            let invariant = attrs.contains(&Attribute::Invariant);
+            return Ok(Some(naga::Binding::BuiltIn(
+                ToNaga(&builtin).to_naga(invariant),
+            )));
+        }
+
+        let Some(location) = attr_location(attrs, &mut self.ctx).transpose()? else {
+            return Ok(None);
+        };
+        let second_blend_source = attr_blend_src(attrs, &mut self.ctx)
+            .transpose()?
+            .unwrap_or(false);
+        let interpolate = attrs.iter().find_map(|attr| match attr {
+            Attribute::Interpolate(interpolate) => Some(interpolate),
+            _ => None,
+        });
+        let mut binding = naga::Binding::Location {
+            location,
+            second_blend_source,
+            interpolation: interpolate.map(|i| ToNaga(&i.ty).to_naga()),
+            sampling: interpolate.and_then(|i| i.sampling.as_ref().map(|s| ToNaga(s).to_naga())),
+        };
+        let ty = self.ty(ty)?;
+        binding.apply_default_interpolation(&self.module.types[ty].inner);
+        Ok(Some(binding))
+    }
+
+    // ---------------------------------------------------------------------------------
+    // functions
+
+    fn function(&mut self, name: &str) -> Result<Handle<naga::Function>, E> {
+        match self.functions.get(name) {
+            Some(Some(handle)) => return Ok(*handle),
+            Some(None) => return Err(E::Recursion(name.to_string())),
+            None => (),
+        }
+        let source = self.ctx.source;
+        let decl = source
+            .decl_function(name)
+            .ok_or_else(|| EvalError::UnknownFunction(name.to_string()))?;
+
+        self.functions.insert(name.to_string(), None);
+        let func = self
+            .lower_fn(decl, false)
+            .inspect_err(|_| self.set_err_decl(name.to_string()))?;
+        let handle = self.module.functions.append(func, naga::Span::UNDEFINED);
+        self.functions.insert(name.to_string(), Some(handle));
+        Ok(handle)
+    }
+
+    fn entry_point(&mut self, decl: &Function) -> Result<(), E> {
+        let (stage, workgroup_size) = if decl.attributes.contains(&Attribute::Vertex) {
+            (naga::ShaderStage::Vertex, [0; 3])
+        } else if decl.attributes.contains(&Attribute::Fragment) {
+            (naga::ShaderStage::Fragment, [0; 3])
+        } else {
+            let (x, y, z) = decl
+                .attr_workgroup_size(&mut self.ctx)
+                .map_err(|e| match e {
+                    EvalError::NotAccessible(..) => {
+                        E::Unsupported("override-expressions in @workgroup_size")
+                    }
+                    e => e.into(),
+                })?;
+            let size = [x, y.unwrap_or(1), z.unwrap_or(1)];
+            (naga::ShaderStage::Compute, size)
+        };
+        let function = self.lower_fn(decl, true)?;
+        self.module.entry_points.push(naga::EntryPoint {
+            name: decl.ident.to_string(),
+            stage,
+            early_depth_test: None,
+            workgroup_size,
+            function,
+        });
+        Ok(())
+    }
+
+    /// function bodies are lowered in the module scope, callees are lowered on demand.
+    fn lower_fn(&mut self, decl: &Function, entry: bool) -> Result<naga::Function, E> {
+        let scope = std::mem::replace(&mut self.ctx.scope, self.scope.clone());
+        let res = self.lower_fn_inner(decl, entry);
+        self.ctx.scope = scope;
+        res
+    }
+
+    fn lower_fn_inner(&mut self, decl: &Function, entry: bool) -> Result<naga::Function, E> {
+        let mut f = FnBuilder::new();
+        f.func.name = Some(decl.ident.to_string());
+        self.push_scope(&mut f);
+
+        for (i, param) in decl.parameters.iter().enumerate() {
+            let ty = ty_eval_ty(&param.ty, &mut self.ctx)?;
+            let binding = if entry {
+                self.binding(&param.attributes, &ty)?
+            } else {
+                None
+            };
+            f.func.arguments.push(naga::FunctionArgument {
+                name: Some(param.ident.to_string()),
+                ty: self.ty(&ty)?,
+                binding,
+            });
+            let expr = naga::Expression::FunctionArgument(i as u32);
+            let handle = f.append(expr, naga::Span::UNDEFINED);
+            self.add_local(&mut f, &param.ident, Local::Let(handle, ty.clone()), ty)?;
+        }
+
+        if let Some(ty) = &decl.return_type {
+            let ty = ty_eval_ty(ty, &mut self.ctx)?;
+            let binding = if entry {
+                self.binding(&decl.return_attributes, &ty)?
+            } else {
+                None
+            };
+            f.func.result = Some(naga::FunctionResult {
+                ty: self.ty(&ty)?,
+                binding,
+            });
+            f.ret = Some(ty);
+        }
+
+        self.compound(&mut f, &decl.body)?;
+        self.pop_scope(&mut f);
+
+        let mut body = f.close();
+        naga::proc::ensure_block_returns(&mut body);
+        f.func.body = body;
+        Ok(f.func)
+    }
+
+    fn push_scope(&mut self, f: &mut FnBuilder) {
+        self.ctx.scope.push();
+        f.locals.push();
+    }
+
+    fn pop_scope(&mut self, f: &mut FnBuilder) {
+        self.ctx.scope.pop();
+        f.locals.pop();
+    }
+
+    fn add_local(
+        &mut self,
+        f: &mut FnBuilder,
+        ident: &Ident,
+        local: Local,
+        ty: Type,
+    ) -> Result<(), E> {
+        let name = ident.to_string();
+        if f.locals.local_contains(&name) {
+            return Err(EvalError::DuplicateDecl(name).into());
+        }
+        self.ctx.scope.add(name.clone(), Instance::Deferred(ty));
+        f.locals.add(name, local);
+        Ok(())
+    }
+
+    // ---------------------------------------------------------------------------------
+    // statements
+
+    fn compound(&mut self, f: &mut FnBuilder, stmt: &CompoundStatement) -> Result<(), E> {
+        self.push_scope(f);
+        for stmt in &stmt.statements {
+            self.stmt(f, stmt)?;
+        }
+        self.pop_scope(f);
+        Ok(())
+    }
+
+    fn stmt(&mut self, f: &mut FnBuilder, stmt: &StatementNode) -> Result<(), E> {
+        self.stmt_inner(f, stmt)
+            .inspect_err(|_| self.set_err_span(stmt.span()))
+    }
+
+    fn stmt_inner(&mut self, f: &mut FnBuilder, stmt: &StatementNode) -> Result<(), E> {
+        let span = ToNaga(stmt.span()).to_naga();
+        match stmt.node() {
+            Statement::Void => (),
+            Statement::Compound(stmt) => {
+                f.open();
+                self.compound(f, stmt)?;
+                let block = f.close();
+                f.push(naga::Statement::Block(block), span);
+            }
+            Statement::Assignment(stmt) => self.assignment(f, stmt, span)?,
+            Statement::Increment(stmt) => {
+                self.increment(f, &stmt.expression, BinaryOperator::Addition, span)?
+            }
+            Statement::Decrement(stmt) => {
+                self.increment(f, &stmt.expression, BinaryOperator::Subtraction, span)?
+            }
+            Statement::If(stmt) => self.if_stmt(
+                f,
+                &stmt.if_clause.expression,
+                &stmt.if_clause.body,
+                &stmt.else_if_clauses,
+                stmt.else_clause.as_ref(),
+                span,
+            )?,
+            Statement::Switch(stmt) => self.switch(f, stmt, span)?,
+            Statement::Loop(stmt) => self.loop_stmt(f, stmt, span)?,
+            Statement::For(stmt) => {
+                self.push_scope(f);
+                if let Some(init) = &stmt.initializer {
+                    self.stmt(f, init)?;
+                }
+                f.open();
+                if let Some(cond) = &stmt.condition {
+                    self.break_unless(f, cond, span)?;
+                }
+                self.compound(f, &stmt.body)?;
+                let body = f.close();
+                f.open();
+                if let Some(update) = &stmt.update {
+                    self.stmt(f, update)?;
+                }
+                let continuing = f.close();
+                f.push(
+                    naga::Statement::Loop {
+                        body,
+                        continuing,
+                        break_if: None,
+                    },
+                    span,
+                );
+                self.pop_scope(f);
+            }
+            Statement::While(stmt) => {
+                f.open();
+                self.break_unless(f, &stmt.condition, span)?;
+                self.compound(f, &stmt.body)?;
+                let body = f.close();
+                f.push(
+                    naga::Statement::Loop {
+                        body,
+                        continuing: Block::new(),
+                        break_if: None,
+                    },
+                    span,
+                );
+            }
+            Statement::Break(_) => f.push(naga::Statement::Break, span),
+            Statement::Continue(_) => f.push(naga::Statement::Continue, span),
+            Statement::Return(stmt) => {
+                let value = match &stmt.expression {
+                    Some(expr) => {
+                        let ty = f.ret.clone();
+                        let value = self.expr(f, expr)?;
+                        Some(self.value(f, value, ty.as_ref(), span)?.0)
+                    }
+                    None => None,
+                };
+                f.push(naga::Statement::Return { value }, span);
+            }
+            Statement::Discard(_) => f.push(naga::Statement::Kill, span),
+            Statement::FunctionCall(stmt) => {
+                self.call(f, &stmt.call, span)?;
+            }
+            Statement::ConstAssert(stmt) => stmt.exec(&mut self.ctx).map(|_| ())?,
+            Statement::Declaration(decl) => self.declaration(f, decl, span)?,
+        }
+        Ok(())
+    }
+
+    fn declaration(
+        &mut self,
+        f: &mut FnBuilder,
+        decl: &Declaration,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        let name = decl.ident.to_string();
+        match decl.kind {
+            DeclarationKind::Const => {
+                if f.locals.local_contains(&name) {
+                    return Err(EvalError::DuplicateDecl(name).into());
+                }
+                decl.exec(&mut self.ctx)?;
+                f.locals.add(name, Local::Const);
+            }
+            DeclarationKind::Override => return Err(EvalError::OverrideInFn.into()),
+            DeclarationKind::Let => {
+                let ty = decl
+                    .ty
+                    .as_ref()
+                    .map(|ty| ty_eval_ty(ty, &mut self.ctx))
+                    .transpose()?;
+                let init = decl
+                    .initializer
+                    .as_ref()
+                    .ok_or_else(|| EvalError::UninitLet(name.clone()))?;
+                let value = self.expr(f, init)?;
+                let (handle, ty) = self.value(f, value, ty.as_ref(), span)?;
+                f.func.named_expressions.entry(handle).or_insert(name);
+                self.add_local(f, &decl.ident, Local::Let(handle, ty.clone()), ty)?;
+            }
+            DeclarationKind::Var(space) => {
+                if !matches!(space, None | Some(AddressSpace::Function)) {
+                    return Err(EvalError::ForbiddenDecl(decl.kind, self.ctx.kind).into());
+                }
+                let ty = decl
+                    .ty
+                    .as_ref()
+                    .map(|ty| ty_eval_ty(ty, &mut self.ctx))
+                    .transpose()?;
+                let init = match &decl.initializer {
+                    Some(init) => {
+                        let value = self.expr(f, init)?;
+                        Some(self.value(f, value, ty.as_ref(), span)?)
+                    }
+                    None => None,
+                };
+                let ty = match (ty, &init) {
+                    (Some(ty), _) => ty,
+                    (None, Some((_, ty))) => ty.clone(),
+                    (None, None) => return Err(EvalError::UntypedDecl.into()),
+                };
+                let var = naga::LocalVariable {
+                    name: Some(name),
+                    ty: self.ty(&ty)?,
+                    init: None,
+                };
+                let var = f.func.local_variables.append(var, span);
+                if let Some((value, _)) = init {
+                    let pointer = f.append(naga::Expression::LocalVariable(var), span);
+                    f.push(naga::Statement::Store { pointer, value }, span);
+                }
+                self.add_local(f, &decl.ident, Local::Var(var, ty.clone()), ty)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn assignment(
+        &mut self,
+        f: &mut FnBuilder,
+        stmt: &AssignmentStatement,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        // phony assignment: the right-hand side is evaluated for its side-effects.
+        if let Expression::TypeOrIdentifier(ty) = stmt.lhs.node() {
+            if *ty.ident.name() == "_" {
+                let value = self.expr(f, &stmt.rhs)?;
+                if !matches!(value, Value::Const(_)) {
+                    self.value(f, value, None, span)?;
+                }
+                return Ok(());
+            }
+        }
+
+        let lhs = self.expr(f, &stmt.lhs)?;
+        let Value::Ref(pointer, ty, _) = lhs else {
+            return Err(E::NotRef(lhs.ty()));
+        };
+        let rhs = self.expr(f, &stmt.rhs)?;
+
+        let value = match assignment_op(&stmt.operator) {
+            None => self.value(f, rhs, Some(&ty), span)?.0,
+            Some(op) => {
+                let load = f.append(naga::Expression::Load { pointer }, span);
+                let value = self.binary(f, op, Value::Expr(load, ty), rhs, span)?;
+                self.value(f, value, None, span)?.0
+            }
+        };
+        f.push(naga::Statement::Store { pointer, value }, span);
+        Ok(())
+    }
+
+    fn increment(
+        &mut self,
+        f: &mut FnBuilder,
+        expr: &ExpressionNode,
+        op: BinaryOperator,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        let value = self.expr(f, expr)?;
+        let Value::Ref(pointer, ty, _) = value else {
+            return Err(E::NotRef(value.ty()));
+        };
+        let load = f.append(naga::Expression::Load { pointer }, span);
+        let one = Value::Const(LiteralInstance::AbstractInt(1).into());
+        let value = self.binary(f, op, Value::Expr(load, ty), one, span)?;
+        let (value, _) = self.value(f, value, None, span)?;
+        f.push(naga::Statement::Store { pointer, value }, span);
+        Ok(())
+    }
+
+    /// `if !cond { break; }`
+    fn break_unless(
+        &mut self,
+        f: &mut FnBuilder,
+        cond: &ExpressionNode,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        let value = self.expr(f, cond)?;
+        let (condition, _) = self.value(f, value, Some(&Type::Bool), span)?;
+        let mut reject = Block::new();
+        reject.push(naga::Statement::Break, span);
+        f.push(
+            naga::Statement::If {
+                condition,
+                accept: Block::new(),
+                reject,
+            },
+            span,
+        );
+        Ok(())
+    }
+
+    /// else-if clauses are lowered to nested if statements.
+    fn if_stmt(
+        &mut self,
+        f: &mut FnBuilder,
+        cond: &ExpressionNode,
+        body: &CompoundStatement,
+        else_if_clauses: &[ElseIfClause],
+        else_clause: Option<&ElseClause>,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        let value = self.expr(f, cond)?;
+        let (condition, _) = self.value(f, value, Some(&Type::Bool), span)?;
+
+        f.open();
+        self.compound(f, body)?;
+        let accept = f.close();
+
+        f.open();
+        if let [clause, rest @ ..] = else_if_clauses {
+            self.if_stmt(f, &clause.expression, &clause.body, rest, else_clause, span)?;
+        } else if let Some(clause) = else_clause {
+            self.compound(f, &clause.body)?;
+        }
+        let reject = f.close();
+
+        f.push(
+            naga::Statement::If {
+                condition,
+                accept,
+                reject,
+            },
+            span,
+        );
+        Ok(())
+    }
+
+    /// clauses with several selectors are lowered to fall-through cases.
+    fn switch(
+        &mut self,
+        f: &mut FnBuilder,
+        stmt: &SwitchStatement,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        let value = self.expr(f, &stmt.expression)?;
+        let (selector, ty) = self.value(f, value, None, span)?;
+
+        let mut cases = Vec::new();
+        for clause in &stmt.clauses {
+            let values = clause
+                .case_selectors
+                .iter()
+                .map(|selector| match selector {
+                    CaseSelector::Default => Ok(naga::SwitchValue::Default),
+                    CaseSelector::Expression(expr) => {
+                        let inst = expr
+                            .eval_value(&mut self.ctx)
+                            .inspect_err(|_| self.set_err_span(expr.span()))?;
+                        let inst = inst
+                            .convert_to(&ty)
+                            .ok_or_else(|| EvalError::Conversion(inst.ty(), ty.clone()))?;
+                        match inst {
+                            Instance::Literal(LiteralInstance::I32(n)) => {
+                                Ok(naga::SwitchValue::I32(n))
+                            }
+                            Instance::Literal(LiteralInstance::U32(n)) => {
+                                Ok(naga::SwitchValue::U32(n))
+                            }
+                            _ => Err(E::Type(inst.ty())),
+                        }
+                    }
+                })
+                .collect::<Result<Vec<_>, E>>()?;
+
+            f.open();
+            self.compound(f, &clause.body)?;
+            let mut body = Some(f.close());
+
+            let n = values.len();
+            for (i, value) in values.into_iter().enumerate() {
+                let last = i + 1 == n;
+                cases.push(naga::SwitchCase {
+                    value,
+                    body: if last {
+                        body.take().unwrap_or_default()
+                    } else {
+                        Block::new()
+                    },
+                    fall_through: !last,
+                });
+            }
+        }
+
+        f.push(naga::Statement::Switch { selector, cases }, span);
+        Ok(())
+    }
+
+    /// the continuing statement is in the scope of the loop body.
+    fn loop_stmt(
+        &mut self,
+        f: &mut FnBuilder,
+        stmt: &LoopStatement,
+        span: naga::Span,
+    ) -> Result<(), E> {
+        self.push_scope(f);
+        f.open();
+        for stmt in &stmt.body.statements {
+            self.stmt(f, stmt)?;
+        }
+        let body = f.close();
+
+        f.open();
+        let mut break_if = None;
+        if let Some(continuing) = &stmt.continuing {
+            for stmt in &continuing.body.statements {
+                self.stmt(f, stmt)?;
+            }
+            if let Some(stmt) = &continuing.break_if {
+                let value = self.expr(f, &stmt.expression)?;
+                break_if = Some(self.value(f, value, Some(&Type::Bool), span)?.0);
+            }
+        }
+        let continuing = f.close();
+        self.pop_scope(f);
+
+        f.push(
+            naga::Statement::Loop {
+                body,
+                continuing,
+                break_if,
+            },
+            span,
+        );
+        Ok(())
+    }
+
+    // ---------------------------------------------------------------------------------
+    // expressions
+
+    /// lower a value to an expression. Const values are converted to `ty` if given,
+    /// concretized otherwise. References are loaded.
+    fn value(
+        &mut self,
+        f: &mut FnBuilder,
+        value: Value,
+        ty: Option<&Type>,
+        span: naga::Span,
+    ) -> Result<(Handle<naga::Expression>, Type), E> {
+        match value {
+            Value::Const(inst) => {
+                let inst = match ty {
+                    Some(ty) => inst
+                        .convert_to(ty)
+                        .ok_or_else(|| EvalError::Conversion(inst.ty(), ty.clone()))?,
+                    None => inst.concretize(),
+                };
+                let handle = self.inst(Some(f), &inst, span)?;
+                Ok((handle, inst.ty()))
+            }
+            Value::Expr(handle, ty) => Ok((handle, ty)),
+            Value::Ref(pointer, ty, _) => {
+                let handle = f.append(naga::Expression::Load { pointer }, span);
+                Ok((handle, ty))
+            }
+        }
+    }
+
+    /// lower a value with its inner type converted to `inner`.
+    fn value_as(
+        &mut self,
+        f: &mut FnBuilder,
+        value: Value,
+        inner: &Type,
+        span: naga::Span,
+    ) -> Result<Handle<naga::Expression>, E> {
+        match value {
+            Value::Const(inst) => {
+                let ty = inst
+                    .ty()
+                    .convert_inner_to(inner)
+                    .ok_or_else(|| EvalError::Conversion(inst.ty(), inner.clone()))?;
+                Ok(self.value(f, Value::Const(inst), Some(&ty), span)?.0)
+            }
+            value => {
+                let (handle, ty) = self.value(f, value, None, span)?;
+                if ty.inner_ty() == *inner {
+                    Ok(handle)
+                } else {
+                    let scalar = scalar(inner)?;
+                    let expr = naga::Expression::As {
+                        expr: handle,
+                        kind: scalar.kind,
+                        convert: Some(scalar.width),
+                    };
+                    Ok(f.append(expr, span))
+                }
+            }
+        }
+    }
+
+    /// lower a value, converting it to the inner type `inner` if it is a const value.
+    fn value_inner(
+        &mut self,
+        f: &mut FnBuilder,
+        value: Value,
+        inner: Option<&Type>,
+        span: naga::Span,
+    ) -> Result<(Handle<naga::Expression>, Type), E> {
+        let ty = match (&value, inner) {
+            (Value::Const(inst), Some(inner)) => inst.ty().convert_inner_to(inner),
+            _ => None,
+        };
+        self.value(f, value, ty.as_ref(), span)
+    }
+
+    /// lower an evaluated instance, in the function expression arena or in the module
+    /// global expressions arena.
+    fn inst(
+        &mut self,
+        mut f: Option<&mut FnBuilder>,
+        inst: &Instance,
+        span: naga::Span,
+    ) -> Result<Handle<naga::Expression>, E> {
+        let expr = match inst {
+            Instance::Literal(lit) => naga::Expression::Literal(ToNaga(lit).to_naga()?),
+            Instance::Struct(_) | Instance::Array(_) | Instance::Vec(_) | Instance::Mat(_) => {
+                let components: Vec<&Instance> = match inst {
+                    Instance::Struct(s) => s.iter_members().map(|(_, m)| m).collect(),
+                    Instance::Array(a) => a.iter().collect(),
+                    Instance::Vec(v) => v.iter().collect(),
+                    Instance::Mat(m) => m.iter_cols().collect(),
+                    _ => unreachable!(),
+                };
+                let components = components
+                    .into_iter()
+                    .map(|c| self.inst(f.as_deref_mut(), c, span))
+                    .collect::<Result<_, _>>()?;
+                naga::Expression::Compose {
+                    ty: self.ty(&inst.ty())?,
+                    components,
+                }
+            }
+            _ => return Err(EvalError::NotConstructible(inst.ty()).into()),
+        };
+        Ok(match f {
+            Some(f) => f.append(expr, span),
+            None => self.module.global_expressions.append(expr, span),
         })
     }
-}
 
-impl ToNaga<&UnaryExpression> {
-    pub fn to_naga(&self, arena: &mut ExprArena) -> Result<naga::Expression, E> {
-        let op = match self.operator {
+    fn expr(&mut self, f: &mut FnBuilder, expr: &ExpressionNode) -> Result<Value, E> {
+        self.expr_inner(f, expr)
+            .inspect_err(|_| self.set_err_span(expr.span()))
+    }
+
+    fn expr_inner(&mut self, f: &mut FnBuilder, expr: &ExpressionNode) -> Result<Value, E> {
+        let span = ToNaga(expr.span()).to_naga();
+
+        // references to module-scope constants are kept.
+        if let Expression::TypeOrIdentifier(ty) = expr.node() {
+            let name = ty.ident.to_string();
+            if f.locals.get(&name).is_none() {
+                if let Some(Global::Const(handle, ty)) = self.globals.get(&name) {
+                    let ty = ty.clone();
+                    let handle = f.append(naga::Expression::Constant(*handle), span);
+                    return Ok(Value::Expr(handle, ty));
+                }
+            }
+        }
+
+        // const-expressions are evaluated.
+        if let Ok(inst) = expr.eval_value(&mut self.ctx) {
+            return Ok(Value::Const(inst));
+        }
+
+        match expr.node() {
+            Expression::Literal(lit) => Ok(Value::Const(lit.eval_value(&mut self.ctx)?)),
+            Expression::Parenthesized(expr) => self.expr(f, &expr.expression),
+            Expression::NamedComponent(expr) => {
+                let base = self.expr(f, &expr.base)?;
+                self.component(f, base, &expr.component.name(), span)
+            }
+            Expression::Indexing(expr) => {
+                let base = deref(self.expr(f, &expr.base)?);
+                let index = self.expr(f, &expr.index)?;
+                self.index(f, base, index, span)
+            }
+            Expression::Unary(expr) => {
+                let operand = self.expr(f, &expr.operand)?;
+                self.unary(f, expr.operator, operand, span)
+            }
+            Expression::Binary(expr) => {
+                let lhs = self.expr(f, &expr.left)?;
+                let rhs = self.expr(f, &expr.right)?;
+                self.binary(f, expr.operator, lhs, rhs, span)
+            }
+            Expression::FunctionCall(call) => self
+                .call(f, call, span)?
+                .ok_or_else(|| EvalError::Void(call.ty.ident.to_string()).into()),
+            Expression::TypeOrIdentifier(ty) => self.ident(f, &ty.ident.to_string(), span),
+        }
+    }
+
+    fn ident(&mut self, f: &mut FnBuilder, name: &str, span: naga::Span) -> Result<Value, E> {
+        if let Some(local) = f.locals.get(name).cloned() {
+            return match local {
+                Local::Let(handle, ty) => Ok(Value::Expr(handle, ty)),
+                Local::Var(var, ty) => {
+                    let handle = f.append(naga::Expression::LocalVariable(var), span);
+                    Ok(Value::Ref(handle, ty, AddressSpace::Function))
+                }
+                // const-declarations are evaluated in `expr`.
+                Local::Const => Err(EvalError::UnknownDecl(name.to_string()).into()),
+            };
+        }
+
+        match self.globals.get(name).cloned() {
+            Some(Global::Const(handle, ty)) => {
+                let handle = f.append(naga::Expression::Constant(handle), span);
+                Ok(Value::Expr(handle, ty))
+            }
+            Some(Global::Override(handle, ty)) => {
+                let handle = f.append(naga::Expression::Override(handle), span);
+                Ok(Value::Expr(handle, ty))
+            }
+            // handle-space variables are values in naga.
+            Some(Global::Var(handle, ty, AddressSpace::Handle)) => {
+                let handle = f.append(naga::Expression::GlobalVariable(handle), span);
+                Ok(Value::Expr(handle, ty))
+            }
+            Some(Global::Var(handle, ty, space)) => {
+                let handle = f.append(naga::Expression::GlobalVariable(handle), span);
+                Ok(Value::Ref(handle, ty, space))
+            }
+            None => Err(EvalError::UnknownDecl(name.to_string()).into()),
+        }
+    }
+
+    fn access_index(
+        &mut self,
+        f: &mut FnBuilder,
+        base: Value,
+        index: u32,
+        ty: Type,
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        match base {
+            Value::Ref(base, _, space) => {
+                let handle = f.append(naga::Expression::AccessIndex { base, index }, span);
+                Ok(Value::Ref(handle, ty, space))
+            }
+            base => {
+                let (base, _) = self.value(f, base, None, span)?;
+                let handle = f.append(naga::Expression::AccessIndex { base, index }, span);
+                Ok(Value::Expr(handle, ty.concretize()))
+            }
+        }
+    }
+
+    fn component(
+        &mut self,
+        f: &mut FnBuilder,
+        base: Value,
+        component: &str,
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        let base = deref(base);
+        match base.ty() {
+            Type::Struct(name) => {
+                let members = self.struct_members(&name)?;
+                let (index, (_, ty)) = members
+                    .into_iter()
+                    .enumerate()
+                    .find(|(_, (name, _))| name == component)
+                    .ok_or_else(|| EvalError::Component(base.ty(), component.to_string()))?;
+                self.access_index(f, base, index as u32, ty, span)
+            }
+            Type::Vec(n, inner) => {
+                let pattern = swizzle(component, n)
+                    .ok_or_else(|| EvalError::Swizzle(component.to_string()))?;
+                if let [index] = pattern.as_slice() {
+                    self.access_index(f, base, *index as u32, *inner, span)
+                } else {
+                    let (vector, _) = self.value(f, base, None, span)?;
+                    let mut components = [naga::SwizzleComponent::X; 4];
+                    components[..pattern.len()].copy_from_slice(&pattern);
+                    let expr = naga::Expression::Swizzle {
+                        size: vec_size(pattern.len() as u8)?,
+                        vector,
+                        pattern: components,
+                    };
+                    let ty = Type::Vec(pattern.len() as u8, Box::new(inner.concretize()));
+                    Ok(Value::Expr(f.append(expr, span), ty))
+                }
+            }
+            ty => Err(EvalError::Component(ty, component.to_string()).into()),
+        }
+    }
+
+    fn index(
+        &mut self,
+        f: &mut FnBuilder,
+        base: Value,
+        index: Value,
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        let ty = match base.ty() {
+            Type::Array(_, ty) | Type::Vec(_, ty) => *ty,
+            Type::Mat(_, r, ty) => Type::Vec(r, ty),
+            ty => return Err(EvalError::NotIndexable(ty).into()),
+        };
+
+        if let Value::Const(inst) = &index {
+            let i = match inst {
+                Instance::Literal(LiteralInstance::AbstractInt(i)) => u32::try_from(*i).ok(),
+                Instance::Literal(LiteralInstance::I32(i)) => u32::try_from(*i).ok(),
+                Instance::Literal(LiteralInstance::U32(i)) => Some(*i),
+                _ => None,
+            };
+            let i = i.ok_or_else(|| EvalError::Index(inst.ty()))?;
+            return self.access_index(f, base, i, ty, span);
+        }
+
+        let (index, _) = self.value(f, index, None, span)?;
+        match base {
+            Value::Ref(base, _, space) => {
+                let handle = f.append(naga::Expression::Access { base, index }, span);
+                Ok(Value::Ref(handle, ty, space))
+            }
+            base => {
+                let (base, _) = self.value(f, base, None, span)?;
+                let handle = f.append(naga::Expression::Access { base, index }, span);
+                Ok(Value::Expr(handle, ty.concretize()))
+            }
+        }
+    }
+
+    fn unary(
+        &mut self,
+        f: &mut FnBuilder,
+        op: UnaryOperator,
+        operand: Value,
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        let op = match op {
+            UnaryOperator::AddressOf => {
+                return match operand {
+                    Value::Ref(handle, ty, space) => {
+                        Ok(Value::Expr(handle, Type::Ptr(space, Box::new(ty))))
+                    }
+                    operand => Err(E::NotRef(operand.ty())),
+                };
+            }
+            UnaryOperator::Indirection => {
+                return match operand {
+                    Value::Expr(handle, Type::Ptr(space, ty)) => Ok(Value::Ref(handle, *ty, space)),
+                    operand => Err(E::NotPtr(operand.ty())),
+                };
+            }
             UnaryOperator::LogicalNegation => naga::UnaryOperator::LogicalNot,
             UnaryOperator::Negation => naga::UnaryOperator::Negate,
             UnaryOperator::BitwiseComplement => naga::UnaryOperator::BitwiseNot,
-            UnaryOperator::AddressOf => todo!(),
-            UnaryOperator::Indirection => todo!(),
+        };
+        let (expr, ty) = self.value(f, operand, None, span)?;
+        let ty = unary_op_type(
+            match op {
+                naga::UnaryOperator::LogicalNot => UnaryOperator::LogicalNegation,
+                naga::UnaryOperator::Negate => UnaryOperator::Negation,
+                naga::UnaryOperator::BitwiseNot => UnaryOperator::BitwiseComplement,
+            },
+            ty,
+        )?;
+        let handle = f.append(naga::Expression::Unary { op, expr }, span);
+        Ok(Value::Expr(handle, ty))
+    }
+
+    /// const operands are converted to the inner type of the other operand.
+    fn binary(
+        &mut self,
+        f: &mut FnBuilder,
+        op: BinaryOperator,
+        lhs: Value,
+        rhs: Value,
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        let shift = matches!(op, BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight);
+        let (lty, rty) = (lhs.ty().inner_ty(), rhs.ty().inner_ty());
+        let inner = convert_ty(&lty, &rty).map(|ty| ty.concretize());
+        let (linner, rinner) = if shift {
+            (None, Some(&Type::U32))
+        } else {
+            (inner.as_ref(), inner.as_ref())
+        };
+
+        let (mut left, lty) = self.value_inner(f, lhs, linner, span)?;
+        let (mut right, rty) = self.value_inner(f, rhs, rinner, span)?;
+        let ty = binary_op_type(op, lty.clone(), rty.clone())?;
+
+        // naga requires both operands to be vectors for these operators.
+        if matches!(
+            op,
+            BinaryOperator::Addition
+                | BinaryOperator::Subtraction
+                | BinaryOperator::Division
+                | BinaryOperator::Remainder
+        ) {
+            match (&lty, &rty) {
+                (Type::Vec(n, _), ty) if ty.is_scalar() => {
+                    let size = vec_size(*n)?;
+                    right = f.append(naga::Expression::Splat { size, value: right }, span);
+                }
+                (ty, Type::Vec(n, _)) if ty.is_scalar() => {
+                    let size = vec_size(*n)?;
+                    left = f.append(naga::Expression::Splat { size, value: left }, span);
+                }
+                _ => (),
+            }
+        }
+
+        let expr = naga::Expression::Binary {
+            op: ToNaga(&op).to_naga(),
+            left,
+            right,
+        };
+        Ok(Value::Expr(f.append(expr, span), ty))
+    }
+
+    // ---------------------------------------------------------------------------------
+    // function calls
+
+    fn call(
+        &mut self,
+        f: &mut FnBuilder,
+        call: &FunctionCall,
+        span: naga::Span,
+    ) -> Result<Option<Value>, E> {
+        let source = self.ctx.source;
+        let ty = source.resolve_ty(&call.ty);
+        let name = ty.ident.to_string();
+
+        match source.decl(&name) {
+            Some(GlobalDeclaration::Struct(_)) => {
+                let args = self.args(f, &call.arguments)?;
+                self.construct(f, Type::Struct(name), args, span).map(Some)
+            }
+            Some(GlobalDeclaration::Function(decl))
+                if decl.body.attributes.contains(&ATTR_INTRINSIC) =>
+            {
+                self.builtin(f, &name, ty, &call.arguments, span)
+            }
+            Some(GlobalDeclaration::Function(decl)) => {
+                if call.arguments.len() != decl.parameters.len() {
+                    return Err(EvalError::ParamCount(
+                        name,
+                        decl.parameters.len(),
+                        call.arguments.len(),
+                    )
+                    .into());
+                }
+                let function = self.function(&name)?;
+                let mut arguments = Vec::with_capacity(call.arguments.len());
+                for (arg, param) in zip(&call.arguments, &decl.parameters) {
+                    let ty = ty_eval_ty(&param.ty, &mut self.ctx)?;
+                    let value = self.expr(f, arg)?;
+                    arguments.push(self.value(f, value, Some(&ty), span)?.0);
+                }
+                let ret = decl
+                    .return_type
+                    .as_ref()
+                    .map(|ty| ty_eval_ty(ty, &mut self.ctx))
+                    .transpose()?;
+                let result = ret
+                    .as_ref()
+                    .map(|_| f.append(naga::Expression::CallResult(function), span));
+                f.push(
+                    naga::Statement::Call {
+                        function,
+                        arguments,
+                        result,
+                    },
+                    span,
+                );
+                Ok(result.zip(ret).map(|(handle, ty)| Value::Expr(handle, ty)))
+            }
+            _ if is_constructor_fn(&name) => {
+                self.constructor(f, ty, &call.arguments, span).map(Some)
+            }
+            _ => Err(EvalError::UnknownFunction(name).into()),
+        }
+    }
+
+    fn args(&mut self, f: &mut FnBuilder, args: &[ExpressionNode]) -> Result<Vec<Value>, E> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.expr(f, arg)?);
+        }
+        Ok(values)
+    }
+
+    fn constructor(
+        &mut self,
+        f: &mut FnBuilder,
+        ty: &TypeExpression,
+        args: &[ExpressionNode],
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        let args = self.args(f, args)?;
+        let tys = args.iter().map(Value::ty).collect::<Vec<_>>();
+        let target = constructor_type(ty, &tys, &mut self.ctx)?.concretize();
+        self.construct(f, target, args, span)
+    }
+
+    fn construct(
+        &mut self,
+        f: &mut FnBuilder,
+        target: Type,
+        args: Vec<Value>,
+        span: naga::Span,
+    ) -> Result<Value, E> {
+        let naga_ty = self.ty(&target)?;
+
+        if args.is_empty() {
+            let handle = f.append(naga::Expression::ZeroValue(naga_ty), span);
+            return Ok(Value::Expr(handle, target));
+        }
+
+        let expr = match &target {
+            Type::Bool | Type::I32 | Type::U32 | Type::F32 | Type::F16 => {
+                let [arg] = <[Value; 1]>::try_from(args)
+                    .map_err(|_| EvalError::Builtin("expected a single argument"))?;
+                let handle = self.value_as(f, arg, &target, span)?;
+                return Ok(Value::Expr(handle, target));
+            }
+            Type::Vec(n, inner) => match <[Value; 1]>::try_from(args) {
+                Ok([arg]) if arg.ty().is_scalar() => naga::Expression::Splat {
+                    size: vec_size(*n)?,
+                    value: self.value_as(f, arg, inner, span)?,
+                },
+                Ok([arg]) => {
+                    let handle = self.value_as(f, arg, inner, span)?;
+                    return Ok(Value::Expr(handle, target));
+                }
+                Err(args) => naga::Expression::Compose {
+                    ty: naga_ty,
+                    components: args
+                        .into_iter()
+                        .map(|arg| self.value_as(f, arg, inner, span))
+                        .collect::<Result<_, _>>()?,
+                },
+            },
+            Type::Mat(c, r, inner) => {
+                if let [arg] = args.as_slice() {
+                    if arg.ty().is_mat() {
+                        let arg = arg.clone();
+                        let handle = self.value_as(f, arg, inner, span)?;
+                        return Ok(Value::Expr(handle, target));
+                    }
+                }
+                let components = if args.len() == *c as usize {
+                    args.into_iter()
+                        .map(|arg| self.value_as(f, arg, inner, span))
+                        .collect::<Result<_, _>>()?
+                } else {
+                    // column-major scalars are grouped in column vectors.
+                    let col_ty = self.ty(&Type::Vec(*r, inner.clone()))?;
+                    let scalars = args
+                        .into_iter()
+                        .map(|arg| self.value_as(f, arg, inner, span))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    scalars
+                        .chunks(*r as usize)
+                        .map(|col| {
+                            let expr = naga::Expression::Compose {
+                                ty: col_ty,
+                                components: col.to_vec(),
+                            };
+                            f.append(expr, span)
+                        })
+                        .collect()
+                };
+                naga::Expression::Compose {
+                    ty: naga_ty,
+                    components,
+                }
+            }
+            Type::Array(_, elem) => naga::Expression::Compose {
+                ty: naga_ty,
+                components: args
+                    .into_iter()
+                    .map(|arg| Ok(self.value(f, arg, Some(elem), span)?.0))
+                    .collect::<Result<_, E>>()?,
+            },
+            Type::Struct(name) => {
+                let members = self.struct_members(name)?;
+                naga::Expression::Compose {
+                    ty: naga_ty,
+                    components: zip(args, members)
+                        .map(|(arg, (_, ty))| Ok(self.value(f, arg, Some(&ty), span)?.0))
+                        .collect::<Result<_, E>>()?,
+                }
+            }
+            _ => return Err(EvalError::NotConstructible(target).into()),
+        };
+        Ok(Value::Expr(f.append(expr, span), target))
+    }
+
+    fn builtin(
+        &mut self,
+        f: &mut FnBuilder,
+        name: &str,
+        ty: &TypeExpression,
+        args: &[ExpressionNode],
+        span: naga::Span,
+    ) -> Result<Option<Value>, E> {
+        match name {
+            "workgroupBarrier" => {
+                f.push(naga::Statement::Barrier(naga::Barrier::WORK_GROUP), span);
+                return Ok(None);
+            }
+            "storageBarrier" => {
+                f.push(naga::Statement::Barrier(naga::Barrier::STORAGE), span);
+                return Ok(None);
+            }
+            "textureBarrier" => return Err(E::Unsupported("textureBarrier")),
+            _ => (),
+        }
+
+        let args = self.args(f, args)?;
+        let tys = args.iter().map(Value::ty).collect::<Vec<_>>();
+
+        // builtins that have no return value or a special return type.
+        match name {
+            "atomicStore" => {
+                let (pointer, value) = self.atomic_args(f, args, span)?;
+                f.push(naga::Statement::Store { pointer, value }, span);
+                return Ok(None);
+            }
+            "atomicCompareExchangeWeak" => {
+                let mut args = args.into_iter();
+                let ptr = next_arg(&mut args)?;
+                let ty = atomic_ty(&ptr.ty())?;
+                let (pointer, _) = self.value(f, ptr, None, span)?;
+                let compare = self.value_as(f, next_arg(&mut args)?, &ty, span)?;
+                let value = self.value_as(f, next_arg(&mut args)?, &ty, span)?;
+                let special = naga::PredeclaredType::AtomicCompareExchangeWeakResult(scalar(&ty)?);
+                let result_ty = self.module.generate_predeclared_type(special);
+                let result = f.append(
+                    naga::Expression::AtomicResult {
+                        ty: result_ty,
+                        comparison: true,
+                    },
+                    span,
+                );
+                let fun = naga::AtomicFunction::Exchange {
+                    compare: Some(compare),
+                };
+                f.push(
+                    naga::Statement::Atomic {
+                        pointer,
+                        fun,
+                        value,
+                        result: Some(result),
+                    },
+                    span,
+                );
+                let ty = Type::Struct(format!("{ATOMIC_RESULT}{ty}"));
+                return Ok(Some(Value::Expr(result, ty)));
+            }
+            "textureStore" => {
+                let mut args = args.into_iter();
+                let image = next_arg(&mut args)?;
+                let tex = texture_ty(&image.ty())?;
+                let (image, _) = self.value(f, image, None, span)?;
+                let (coordinate, _) = self.value(f, next_arg(&mut args)?, None, span)?;
+                let array_index = if tex.is_arrayed() {
+                    Some(self.value(f, next_arg(&mut args)?, None, span)?.0)
+                } else {
+                    None
+                };
+                let inner = match &tex {
+                    TextureType::Storage1D(format, _)
+                    | TextureType::Storage2D(format, _)
+                    | TextureType::Storage2DArray(format, _)
+                    | TextureType::Storage3D(format, _) => Type::from(format.channel_type()),
+                    _ => {
+                        return Err(EvalError::InvalidTexture(tex.clone(), name.to_string()).into())
+                    }
+                };
+                let value = self.value_as(f, next_arg(&mut args)?, &inner, span)?;
+                let stmt = naga::Statement::ImageStore {
+                    image,
+                    coordinate,
+                    array_index,
+                    value,
+                };
+                f.push(stmt, span);
+                return Ok(None);
+            }
+            _ => (),
+        }
+
+        let ret = builtin_fn_type(ty, &tys, &mut self.ctx)?.concretize();
+        self.ty(&ret)?;
+
+        let expr = match name {
+            "bitcast" => {
+                let (expr, _) = self.value(f, next_arg(&mut args.into_iter())?, None, span)?;
+                naga::Expression::As {
+                    expr,
+                    kind: scalar(&ret.inner_ty())?.kind,
+                    convert: None,
+                }
+            }
+            "all" | "any" => {
+                let (argument, ty) = self.value(f, next_arg(&mut args.into_iter())?, None, span)?;
+                // naga's relational functions only take vectors.
+                if ty.is_scalar() {
+                    return Ok(Some(Value::Expr(argument, ty)));
+                }
+                let fun = if name == "all" {
+                    naga::RelationalFunction::All
+                } else {
+                    naga::RelationalFunction::Any
+                };
+                naga::Expression::Relational { fun, argument }
+            }
+            "select" => {
+                let inner = ret.inner_ty();
+                let mut args = args.into_iter();
+                let reject = self
+                    .value_inner(f, next_arg(&mut args)?, Some(&inner), span)?
+                    .0;
+                let accept = self
+                    .value_inner(f, next_arg(&mut args)?, Some(&inner), span)?
+                    .0;
+                let condition = self.value(f, next_arg(&mut args)?, None, span)?.0;
+                naga::Expression::Select {
+                    condition,
+                    accept,
+                    reject,
+                }
+            }
+            "arrayLength" => {
+                let (pointer, _) = self.value(f, next_arg(&mut args.into_iter())?, None, span)?;
+                naga::Expression::ArrayLength(pointer)
+            }
+            "dpdx" | "dpdxCoarse" | "dpdxFine" | "dpdy" | "dpdyCoarse" | "dpdyFine" | "fwidth"
+            | "fwidthCoarse" | "fwidthFine" => {
+                let axis = match &name[..4] {
+                    "dpdx" => naga::DerivativeAxis::X,
+                    "dpdy" => naga::DerivativeAxis::Y,
+                    _ => naga::DerivativeAxis::Width,
+                };
+                let ctrl = if name.ends_with("Coarse") {
+                    naga::DerivativeControl::Coarse
+                } else if name.ends_with("Fine") {
+                    naga::DerivativeControl::Fine
+                } else {
+                    naga::DerivativeControl::None
+                };
+                let (expr, _) = self.value(f, next_arg(&mut args.into_iter())?, None, span)?;
+                naga::Expression::Derivative { axis, ctrl, expr }
+            }
+            "atomicLoad" => {
+                let (pointer, _) = self.value(f, next_arg(&mut args.into_iter())?, None, span)?;
+                naga::Expression::Load { pointer }
+            }
+            "atomicAdd" | "atomicSub" | "atomicMax" | "atomicMin" | "atomicAnd" | "atomicOr"
+            | "atomicXor" | "atomicExchange" => {
+                let fun = match name {
+                    "atomicAdd" => naga::AtomicFunction::Add,
+                    "atomicSub" => naga::AtomicFunction::Subtract,
+                    "atomicMax" => naga::AtomicFunction::Max,
+                    "atomicMin" => naga::AtomicFunction::Min,
+                    "atomicAnd" => naga::AtomicFunction::And,
+                    "atomicOr" => naga::AtomicFunction::InclusiveOr,
+                    "atomicXor" => naga::AtomicFunction::ExclusiveOr,
+                    _ => naga::AtomicFunction::Exchange { compare: None },
+                };
+                let (pointer, value) = self.atomic_args(f, args, span)?;
+                let result = f.append(
+                    naga::Expression::AtomicResult {
+                        ty: self.ty(&ret)?,
+                        comparison: false,
+                    },
+                    span,
+                );
+                f.push(
+                    naga::Statement::Atomic {
+                        pointer,
+                        fun,
+                        value,
+                        result: Some(result),
+                    },
+                    span,
+                );
+                return Ok(Some(Value::Expr(result, ret)));
+            }
+            "workgroupUniformLoad" => {
+                let (pointer, _) = self.value(f, next_arg(&mut args.into_iter())?, None, span)?;
+                let result = f.append(
+                    naga::Expression::WorkGroupUniformLoadResult { ty: self.ty(&ret)? },
+                    span,
+                );
+                f.push(
+                    naga::Statement::WorkGroupUniformLoad { pointer, result },
+                    span,
+                );
+                return Ok(Some(Value::Expr(result, ret)));
+            }
+            "textureDimensions" | "textureNumLayers" | "textureNumLevels" | "textureNumSamples" => {
+                let mut args = args.into_iter();
+                let (image, _) = self.value(f, next_arg(&mut args)?, None, span)?;
+                let query = match name {
+                    "textureDimensions" => naga::ImageQuery::Size {
+                        level: args
+                            .next()
+                            .map(|level| Ok::<_, E>(self.value(f, level, None, span)?.0))
+                            .transpose()?,
+                    },
+                    "textureNumLayers" => naga::ImageQuery::NumLayers,
+                    "textureNumLevels" => naga::ImageQuery::NumLevels,
+                    _ => naga::ImageQuery::NumSamples,
+                };
+                naga::Expression::ImageQuery { image, query }
+            }
+            "textureLoad" => {
+                let mut args = args.into_iter();
+                let image = next_arg(&mut args)?;
+                let tex = texture_ty(&image.ty())?;
+                let (image, _) = self.value(f, image, None, span)?;
+                let (coordinate, _) = self.value(f, next_arg(&mut args)?, None, span)?;
+                let mut next = |this: &mut Self, f: &mut FnBuilder| -> Result<_, E> {
+                    args.next()
+                        .map(|arg| Ok(this.value(f, arg, None, span)?.0))
+                        .transpose()
+                };
+                let array_index = if tex.is_arrayed() {
+                    next(self, f)?
+                } else {
+                    None
+                };
+                let (level, sample) = if tex.is_multisampled() {
+                    (None, next(self, f)?)
+                } else if tex.is_mipmapped() {
+                    (next(self, f)?, None)
+                } else {
+                    (None, None)
+                };
+                naga::Expression::ImageLoad {
+                    image,
+                    coordinate,
+                    array_index,
+                    sample,
+                    level,
+                }
+            }
+            "textureSample"
+            | "textureSampleBias"
+            | "textureSampleCompare"
+            | "textureSampleCompareLevel"
+            | "textureSampleGrad"
+            | "textureSampleLevel"
+            | "textureGather"
+            | "textureGatherCompare" => self.texture_sample(f, name, args, &tys, span)?,
+            "textureSampleBaseClampToEdge" => {
+                return Err(E::Unsupported("textureSampleBaseClampToEdge"))
+            }
+            _ => {
+                let fun = math_fn(name).ok_or(E::Unsupported("this builtin function"))?;
+                let args = match name {
+                    // the integer arguments are not converted to the common type.
+                    "ldexp" => args
+                        .into_iter()
+                        .map(|arg| Ok(self.value(f, arg, None, span)?.0))
+                        .collect::<Result<Vec<_>, E>>()?,
+                    "extractBits" | "insertBits" => {
+                        let n = args.len();
+                        let inner = tys.first().map(|ty| ty.inner_ty().concretize());
+                        args.into_iter()
+                            .enumerate()
+                            .map(|(i, arg)| {
+                                let inner = if i + 2 >= n {
+                                    Some(&Type::U32)
+                                } else {
+                                    inner.as_ref()
+                                };
+                                Ok(self.value_inner(f, arg, inner, span)?.0)
+                            })
+                            .collect::<Result<Vec<_>, E>>()?
+                    }
+                    _ => {
+                        let inner = tys
+                            .iter()
+                            .map(|ty| ty.inner_ty())
+                            .reduce(|a, b| convert_ty(&a, &b).cloned().unwrap_or(a))
+                            .map(|ty| ty.concretize());
+                        args.into_iter()
+                            .map(|arg| Ok(self.value_inner(f, arg, inner.as_ref(), span)?.0))
+                            .collect::<Result<Vec<_>, E>>()?
+                    }
+                };
+                let mut args = args.into_iter();
+                naga::Expression::Math {
+                    fun,
+                    arg: args.next().ok_or(EvalError::Builtin("missing argument"))?,
+                    arg1: args.next(),
+                    arg2: args.next(),
+                    arg3: args.next(),
+                }
+            }
+        };
+
+        Ok(Some(Value::Expr(f.append(expr, span), ret)))
+    }
+
+    /// the pointer and value arguments of an atomic function.
+    fn atomic_args(
+        &mut self,
+        f: &mut FnBuilder,
+        args: Vec<Value>,
+        span: naga::Span,
+    ) -> Result<(Handle<naga::Expression>, Handle<naga::Expression>), E> {
+        let mut args = args.into_iter();
+        let ptr = args.next().ok_or(EvalError::Builtin("missing argument"))?;
+        let ty = atomic_ty(&ptr.ty())?;
+        let (pointer, _) = self.value(f, ptr, None, span)?;
+        let value = args.next().ok_or(EvalError::Builtin("missing argument"))?;
+        let value = self.value_as(f, value, &ty, span)?;
+        Ok((pointer, value))
+    }
+
+    fn texture_sample(
+        &mut self,
+        f: &mut FnBuilder,
+        name: &str,
+        args: Vec<Value>,
+        tys: &[Type],
+        span: naga::Span,
+    ) -> Result<naga::Expression, E> {
+        let mut args = args.into_iter();
+
+        // the gather component is the first argument of textureGather on color textures.
+        let gather = match name {
+            "textureGather" if !tys.first().is_some_and(Type::is_texture) => {
+                let comp = next_arg(&mut args)?;
+                let Value::Const(Instance::Literal(comp)) = comp else {
+                    return Err(E::NotConst("gather component"));
+                };
+                let comp = match comp {
+                    LiteralInstance::AbstractInt(i) => u32::try_from(i).ok(),
+                    LiteralInstance::I32(i) => u32::try_from(i).ok(),
+                    LiteralInstance::U32(i) => Some(i),
+                    _ => None,
+                };
+                let comp = comp
+                    .filter(|c| *c < 4)
+                    .ok_or(EvalError::Builtin("invalid gather component"))?;
+                Some(naga::SwizzleComponent::XYZW[comp as usize])
+            }
+            "textureGather" | "textureGatherCompare" => Some(naga::SwizzleComponent::X),
+            _ => None,
+        };
+
+        let image = next_arg(&mut args)?;
+        let tex = texture_ty(&image.ty())?;
+        let (image, _) = self.value(f, image, None, span)?;
+        let (sampler, _) = self.value(f, next_arg(&mut args)?, None, span)?;
+        let coordinate = self.value_as(f, next_arg(&mut args)?, &Type::F32, span)?;
+        let array_index = if tex.is_arrayed() {
+            Some(self.value(f, next_arg(&mut args)?, None, span)?.0)
+        } else {
+            None
+        };
+
+        let mut depth_ref = None;
+        let level = match name {
+            "textureSample" => naga::SampleLevel::Auto,
+            "textureSampleBias" => {
+                let bias = self.value_as(f, next_arg(&mut args)?, &Type::F32, span)?;
+                naga::SampleLevel::Bias(bias)
+            }
+            "textureSampleCompare" | "textureSampleCompareLevel" | "textureGatherCompare" => {
+                depth_ref = Some(self.value_as(f, next_arg(&mut args)?, &Type::F32, span)?);
+                if name == "textureSampleCompare" {
+                    naga::SampleLevel::Auto
+                } else {
+                    naga::SampleLevel::Zero
+                }
+            }
+            "textureSampleGrad" => {
+                let x = self.value_as(f, next_arg(&mut args)?, &Type::F32, span)?;
+                let y = self.value_as(f, next_arg(&mut args)?, &Type::F32, span)?;
+                naga::SampleLevel::Gradient { x, y }
+            }
+            "textureSampleLevel" => {
+                // naga expects a float level, also for depth textures.
+                let level = self.value_as(f, next_arg(&mut args)?, &Type::F32, span)?;
+                naga::SampleLevel::Exact(level)
+            }
+            _ => naga::SampleLevel::Zero,
         };
 
-        let expr = ToNaga(&self.operand).to_naga(arena)?;
-        let expr = arena.append(expr, ToNaga(self.operand.span()).to_naga());
+        // the offset is a const-expression, naga stores it in the global expressions.
+        let offset = match args.next() {
+            Some(Value::Const(inst)) => {
+                let ty = inst
+                    .ty()
+                    .convert_inner_to(&Type::I32)
+                    .ok_or_else(|| EvalError::Conversion(inst.ty(), Type::I32))?;
+                let inst = inst
+                    .convert_to(&ty)
+                    .ok_or_else(|| EvalError::Conversion(inst.ty(), ty.clone()))?;
+                Some(self.inst(None, &inst, span)?)
+            }
+            Some(_) => return Err(E::NotConst("texture offset")),
+            None => None,
+        };
 
-        Ok(naga::Expression::Unary { op, expr })
+        Ok(naga::Expression::ImageSample {
+            image,
+            sampler,
+            gather,
+            coordinate,
+            array_index,
+            offset,
+            level,
+            depth_ref,
+        })
     }
 }
 
-impl ToNaga<&ExpressionNode> {
-    pub fn to_naga(&self, arena: &mut ExprArena) -> Result<naga::Expression, E> {
-        Ok(match self.node() {
-            Expression::Literal(expr) => naga::Expression::Literal(ToNaga(expr).to_naga()?),
-            Expression::Parenthesized(expr) => todo!(),
-            Expression::NamedComponent(expr) => todo!(),
-            Expression::Indexing(expr) => todo!(),
-            Expression::Unary(expr) => ToNaga(expr).to_naga(arena)?,
-            Expression::Binary(expr) => todo!(),
-            Expression::FunctionCall(expr) => todo!(),
-            Expression::TypeOrIdentifier(expr) => todo!(),
+// -------------------------------------------------------------------------------------
+// utilities
+
+fn is_entry_point(decl: &Function) -> bool {
+    decl.attributes.iter().any(|attr| {
+        matches!(
+            attr,
+            Attribute::Vertex | Attribute::Fragment | Attribute::Compute
+        )
+    })
+}
+
+/// pointers are implicitly dereferenced when accessing components.
+fn deref(value: Value) -> Value {
+    match value {
+        Value::Expr(handle, Type::Ptr(space, ty)) => Value::Ref(handle, *ty, space),
+        value => value,
+    }
+}
+
+fn swizzle(components: &str, n: u8) -> Option<Vec<naga::SwizzleComponent>> {
+    if components.len() > 4 {
+        return None;
+    }
+    components
+        .chars()
+        .map(|c| {
+            let i = match c {
+                'x' | 'r' => 0,
+                'y' | 'g' => 1,
+                'z' | 'b' => 2,
+                'w' | 'a' => 3,
+                _ => return None,
+            };
+            (i < n).then(|| naga::SwizzleComponent::XYZW[i as usize])
+        })
+        .collect()
+}
+
+fn scalar(ty: &Type) -> Result<naga::Scalar, E> {
+    match ty {
+        Type::Bool => Ok(naga::Scalar::BOOL),
+        Type::AbstractInt | Type::I32 => Ok(naga::Scalar::I32),
+        Type::U32 => Ok(naga::Scalar::U32),
+        Type::AbstractFloat | Type::F32 => Ok(naga::Scalar::F32),
+        Type::F16 => Err(E::F16NotSupported),
+        _ => Err(E::Type(ty.clone())),
+    }
+}
+
+fn vec_size(n: u8) -> Result<naga::VectorSize, E> {
+    match n {
+        2 => Ok(naga::VectorSize::Bi),
+        3 => Ok(naga::VectorSize::Tri),
+        4 => Ok(naga::VectorSize::Quad),
+        _ => Err(EvalError::Builtin("vector size must be 2, 3 or 4").into()),
+    }
+}
+
+/// The next argument of a builtin function call.
+fn next_arg(args: &mut impl Iterator<Item = Value>) -> Result<Value, E> {
+    args.next()
+        .ok_or_else(|| EvalError::Builtin("missing argument").into())
+}
+
+fn atomic_ty(ptr: &Type) -> Result<Type, E> {
+    match ptr {
+        Type::Ptr(_, ty) if ty.is_atomic() => Ok(ty.inner_ty()),
+        ty => Err(E::NotPtr(ty.clone())),
+    }
+}
+
+fn texture_ty(ty: &Type) -> Result<TextureType, E> {
+    match ty {
+        Type::Texture(tex) => Ok(tex.clone()),
+        ty => Err(E::Type(ty.clone())),
+    }
+}
+
+fn math_fn(name: &str) -> Option<naga::MathFunction> {
+    use naga::MathFunction as M;
+    Some(match name {
+        "abs" => M::Abs,
+        "min" => M::Min,
+        "max" => M::Max,
+        "clamp" => M::Clamp,
+        "saturate" => M::Saturate,
+        "cos" => M::Cos,
+        "cosh" => M::Cosh,
+        "sin" => M::Sin,
+        "sinh" => M::Sinh,
+        "tan" => M::Tan,
+        "tanh" => M::Tanh,
+        "acos" => M::Acos,
+        "asin" => M::Asin,
+        "atan" => M::Atan,
+        "atan2" => M::Atan2,
+        "asinh" => M::Asinh,
+        "acosh" => M::Acosh,
+        "atanh" => M::Atanh,
+        "radians" => M::Radians,
+        "degrees" => M::Degrees,
+        "ceil" => M::Ceil,
+        "floor" => M::Floor,
+        "round" => M::Round,
+        "fract" => M::Fract,
+        "trunc" => M::Trunc,
+        "modf" => M::Modf,
+        "frexp" => M::Frexp,
+        "ldexp" => M::Ldexp,
+        "exp" => M::Exp,
+        "exp2" => M::Exp2,
+        "log" => M::Log,
+        "log2" => M::Log2,
+        "pow" => M::Pow,
+        "dot" => M::Dot,
+        "cross" => M::Cross,
+        "distance" => M::Distance,
+        "length" => M::Length,
+        "normalize" => M::Normalize,
+        "faceForward" => M::FaceForward,
+        "reflect" => M::Reflect,
+        "refract" => M::Refract,
+        "sign" => M::Sign,
+        "fma" => M::Fma,
+        "mix" => M::Mix,
+        "step" => M::Step,
+        "smoothstep" => M::SmoothStep,
+        "sqrt" => M::Sqrt,
+        "inverseSqrt" => M::InverseSqrt,
+        "transpose" => M::Transpose,
+        "determinant" => M::Determinant,
+        "countTrailingZeros" => M::CountTrailingZeros,
+        "countLeadingZeros" => M::CountLeadingZeros,
+        "countOneBits" => M::CountOneBits,
+        "reverseBits" => M::ReverseBits,
+        "extractBits" => M::ExtractBits,
+        "insertBits" => M::InsertBits,
+        "firstTrailingBit" => M::FirstTrailingBit,
+        "firstLeadingBit" => M::FirstLeadingBit,
+        "pack4x8snorm" => M::Pack4x8snorm,
+        "pack4x8unorm" => M::Pack4x8unorm,
+        "pack2x16snorm" => M::Pack2x16snorm,
+        "pack2x16unorm" => M::Pack2x16unorm,
+        "pack2x16float" => M::Pack2x16float,
+        "pack4xI8" => M::Pack4xI8,
+        "pack4xU8" => M::Pack4xU8,
+        "unpack4x8snorm" => M::Unpack4x8snorm,
+        "unpack4x8unorm" => M::Unpack4x8unorm,
+        "unpack2x16snorm" => M::Unpack2x16snorm,
+        "unpack2x16unorm" => M::Unpack2x16unorm,
+        "unpack2x16float" => M::Unpack2x16float,
+        "unpack4xI8" => M::Unpack4xI8,
+        "unpack4xU8" => M::Unpack4xU8,
+        _ => return None,
+    })
+}
+
+// -------------------------------------------------------------------------------------
+// leaf conversions
+
+impl ToNaga<&LiteralInstance> {
+    fn to_naga(&self) -> Result<naga::Literal, E> {
+        match self.deref() {
+            LiteralInstance::Bool(b) => Ok(naga::Literal::Bool(*b)),
+            LiteralInstance::I32(i) => Ok(naga::Literal::I32(*i)),
+            LiteralInstance::U32(u) => Ok(naga::Literal::U32(*u)),
+            LiteralInstance::F32(f) => Ok(naga::Literal::F32(*f)),
+            LiteralInstance::F16(_) => Err(E::F16NotSupported),
+            lit => Err(E::Type(lit.ty())),
+        }
+    }
+}
+
+impl ToNaga<&TextureType> {
+    fn to_naga(&self) -> Result<naga::TypeInner, E> {
+        let dim = match self.dimension() {
+            TextureDimension::D1 => naga::ImageDimension::D1,
+            TextureDimension::D2 => naga::ImageDimension::D2,
+            TextureDimension::D3 => naga::ImageDimension::D3,
+            TextureDimension::Cube => naga::ImageDimension::Cube,
+        };
+        let kind = |ty: &SampledType| match ty {
+            SampledType::I32 => naga::ScalarKind::Sint,
+            SampledType::U32 => naga::ScalarKind::Uint,
+            SampledType::F32 => naga::ScalarKind::Float,
+        };
+        let class = match self.deref() {
+            TextureType::Sampled1D(ty)
+            | TextureType::Sampled2D(ty)
+            | TextureType::Sampled2DArray(ty)
+            | TextureType::Sampled3D(ty)
+            | TextureType::SampledCube(ty)
+            | TextureType::SampledCubeArray(ty) => naga::ImageClass::Sampled {
+                kind: kind(ty),
+                multi: false,
+            },
+            TextureType::Multisampled2D(ty) => naga::ImageClass::Sampled {
+                kind: kind(ty),
+                multi: true,
+            },
+            TextureType::DepthMultisampled2D => naga::ImageClass::Depth { multi: true },
+            TextureType::External => return Err(E::Unsupported("texture_external")),
+            TextureType::Storage1D(format, access)
+            | TextureType::Storage2D(format, access)
+            | TextureType::Storage2DArray(format, access)
+            | TextureType::Storage3D(format, access) => naga::ImageClass::Storage {
+                format: ToNaga(format).to_naga(),
+                access: ToNaga(access).to_naga(),
+            },
+            TextureType::Depth2D
+            | TextureType::Depth2DArray
+            | TextureType::DepthCube
+            | TextureType::DepthCubeArray => naga::ImageClass::Depth { multi: false },
+        };
+        Ok(naga::TypeInner::Image {
+            dim,
+            arrayed: self.is_arrayed(),
+            class,
         })
     }
 }
 
+impl ToNaga<&TexelFormat> {
+    fn to_naga(&self) -> naga::StorageFormat {
+        match self.deref() {
+            TexelFormat::Rgba8Unorm => naga::StorageFormat::Rgba8Unorm,
+            TexelFormat::Rgba8Snorm => naga::StorageFormat::Rgba8Snorm,
+            TexelFormat::Rgba8Uint => naga::StorageFormat::Rgba8Uint,
+            TexelFormat::Rgba8Sint => naga::StorageFormat::Rgba8Sint,
+            TexelFormat::Rgba16Uint => naga::StorageFormat::Rgba16Uint,
+            TexelFormat::Rgba16Sint => naga::StorageFormat::Rgba16Sint,
+            TexelFormat::Rgba16Float => naga::StorageFormat::Rgba16Float,
+            TexelFormat::R32Uint => naga::StorageFormat::R32Uint,
+            TexelFormat::R32Sint => naga::StorageFormat::R32Sint,
+            TexelFormat::R32Float => naga::StorageFormat::R32Float,
+            TexelFormat::Rg32Uint => naga::StorageFormat::Rg32Uint,
+            TexelFormat::Rg32Sint => naga::StorageFormat::Rg32Sint,
+            TexelFormat::Rg32Float => naga::StorageFormat::Rg32Float,
+            TexelFormat::Rgba32Uint => naga::StorageFormat::Rgba32Uint,
+            TexelFormat::Rgba32Sint => naga::StorageFormat::Rgba32Sint,
+            TexelFormat::Rgba32Float => naga::StorageFormat::Rgba32Float,
+            TexelFormat::Bgra8Unorm => naga::StorageFormat::Bgra8Unorm,
+        }
+    }
+}
+
+impl ToNaga<&BuiltinValue> {
+    fn to_naga(&self, invariant: bool) -> naga::BuiltIn {
+        match self.deref() {
+            BuiltinValue::VertexIndex => naga::BuiltIn::VertexIndex,
+            BuiltinValue::InstanceIndex => naga::BuiltIn::InstanceIndex,
+            BuiltinValue::Position => naga::BuiltIn::Position { invariant },
+            BuiltinValue::FrontFacing => naga::BuiltIn::FrontFacing,
+            BuiltinValue::FragDepth => naga::BuiltIn::FragDepth,
+            BuiltinValue::SampleIndex => naga::BuiltIn::SampleIndex,
+            BuiltinValue::SampleMask => naga::BuiltIn::SampleMask,
+            BuiltinValue::LocalInvocationId => naga::BuiltIn::LocalInvocationId,
+            BuiltinValue::LocalInvocationIndex => naga::BuiltIn::LocalInvocationIndex,
+            BuiltinValue::GlobalInvocationId => naga::BuiltIn::GlobalInvocationId,
+            BuiltinValue::WorkgroupId => naga::BuiltIn::WorkGroupId,
+            BuiltinValue::NumWorkgroups => naga::BuiltIn::NumWorkGroups,
+        }
+    }
+}
+
+impl ToNaga<&InterpolationType> {
+    fn to_naga(&self) -> naga::Interpolation {
+        match self.deref() {
+            InterpolationType::Perspective => naga::Interpolation::Perspective,
+            InterpolationType::Linear => naga::Interpolation::Linear,
+            InterpolationType::Flat => naga::Interpolation::Flat,
+        }
+    }
+}
+
+impl ToNaga<&InterpolationSampling> {
+    fn to_naga(&self) -> naga::Sampling {
+        match self.deref() {
+            InterpolationSampling::Center => naga::Sampling::Center,
+            InterpolationSampling::Centroid => naga::Sampling::Centroid,
+            InterpolationSampling::Sample => naga::Sampling::Sample,
+            InterpolationSampling::First => naga::Sampling::First,
+            InterpolationSampling::Either => naga::Sampling::Either,
+        }
+    }
+}
+
+impl ToNaga<&BinaryOperator> {
+    fn to_naga(&self) -> naga::BinaryOperator {
+        match self.deref() {
+            BinaryOperator::ShortCircuitOr => naga::BinaryOperator::LogicalOr,
+            BinaryOperator::ShortCircuitAnd => naga::BinaryOperator::LogicalAnd,
+            BinaryOperator::Addition => naga::BinaryOperator::Add,
+            BinaryOperator::Subtraction => naga::BinaryOperator::Subtract,
+            BinaryOperator::Multiplication => naga::BinaryOperator::Multiply,
+            BinaryOperator::Division => naga::BinaryOperator::Divide,
+            BinaryOperator::Remainder => naga::BinaryOperator::Modulo,
+            BinaryOperator::Equality => naga::BinaryOperator::Equal,
+            BinaryOperator::Inequality => naga::BinaryOperator::NotEqual,
+            BinaryOperator::LessThan => naga::BinaryOperator::Less,
+            BinaryOperator::LessThanEqual => naga::BinaryOperator::LessEqual,
+            BinaryOperator::GreaterThan => naga::BinaryOperator::Greater,
+            BinaryOperator::GreaterThanEqual => naga::BinaryOperator::GreaterEqual,
+            BinaryOperator::BitwiseOr => naga::BinaryOperator::InclusiveOr,
+            BinaryOperator::BitwiseAnd => naga::BinaryOperator::And,
+            BinaryOperator::BitwiseXor => naga::BinaryOperator::ExclusiveOr,
+            BinaryOperator::ShiftLeft => naga::BinaryOperator::ShiftLeft,
+            BinaryOperator::ShiftRight => naga::BinaryOperator::ShiftRight,
+        }
+    }
+}
+
 impl ToNaga<&Span> {
-    pub fn to_naga(&self) -> naga::Span {
+    fn to_naga(&self) -> naga::Span {
         naga::Span::new(self.start as u32, self.end as u32)
     }
 }
 
 impl ToNaga<&AccessMode> {
-    pub fn to_naga(&self) -> naga::StorageAccess {
+    fn to_naga(&self) -> naga::StorageAccess {
         match self.deref() {
             AccessMode::Read => naga::StorageAccess::LOAD,
             AccessMode::Write => naga::StorageAccess::STORE,
@@ -152,7 +2384,7 @@ impl ToNaga<&AccessMode> {
 }
 
 impl ToNaga<&AddressSpace> {
-    pub fn to_naga(&self) -> naga::AddressSpace {
+    fn to_naga(&self) -> naga::AddressSpace {
         match self.deref() {
             AddressSpace::Function => naga::AddressSpace::Function,
             AddressSpace::Private => naga::AddressSpace::Private,
@@ -166,117 +2398,136 @@ impl ToNaga<&AddressSpace> {
     }
 }
 
-impl ToNaga<&Struct> {
-    pub fn to_naga(&self, ctx: &mut Context, arena: &mut TypeArena) -> Result<naga::TypeInner, E> {
-        let mut offset = 0;
-        let members = self
-            .members
-            .iter()
-            .map(|m| {
-                let ty = self.ty.eval_ty(ctx)?;
-                let size = self.attr_size(ctx)?.or_else(|| ty.size_of(ctx));
-                let align = self.attr_align(ctx)?.or_else(|| ty.align_of(ctx));
-
-                let name = Some(m.ident.to_string());
-                let ty = arena.insert(ToNaga(&ty).to_naga(ctx, arena)?, naga::Span::UNDEFINED);
-                let binding = None; // XXX: can someone explain to me why this is on struct??
-                offset = round_up(align, offset);
-
-                let mem = naga::StructMember {
-                    name,
-                    ty,
-                    binding,
-                    offset,
-                };
-                offset += size;
-                Ok(mem)
-            })
-            .try_collect()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(naga::TypeInner::Struct {
-            members,
-            span: todo!(),
-        })
-    }
-}
+    #[test]
+    fn test_to_naga() {
+        let source = "const SCALE = 2.0;
+            const COUNT: u32 = 4u;
+            override gain: f32 = 1.5;
+            struct Uniforms {
+                transform: mat4x4f,
+                tint: vec3f,
+                time: f32,
+            }
+            struct VertexOut {
+                @builtin(position) position: vec4f,
+                @location(0) uv: vec2f,
+                @location(1) @interpolate(flat) index: u32,
+            }
+            @group(0) @binding(0) var<uniform> uniforms: Uniforms;
+            @group(0) @binding(1) var tex: texture_2d<f32>;
+            @group(0) @binding(2) var samp: sampler;
+            @group(1) @binding(0) var<storage, read_write> values: array<f32>;
+            @group(1) @binding(1) var<storage, read_write> counter: atomic<u32>;
+            var<workgroup> shared_values: array<f32, 64>;
+            var<private> seed: u32 = 7u;
 
-impl ToNaga<&TranslationUnit> {
-    pub fn to_naga(&self) -> Result<naga::Module, E> {
-        let mut types = UniqueArena::new();
-        let mut constants = Arena::new();
-        let mut overrides = Arena::new();
-        let mut global_variables = Arena::new();
-        let mut global_expressions = Arena::new();
-        let mut functions = Arena::new();
-        let mut entry_points = Vec::new();
-        let mut diagnostic_filters = Arena::new();
-
-        let mut ctx = Context::new(self);
-        self.exec(&mut ctx)?; // add const-decls to the scope and eval const_asserts
-
-        for decl in &self.global_declarations {
-            match decl {
-                GlobalDeclaration::Void => (),
-                GlobalDeclaration::Declaration(decl) => {
-                    let name = Some(decl.ident.to_string());
-                    let ty = ToNaga(decl.ty.as_ref().unwrap()).to_naga(&mut ctx)?;
-                    let ty = types.insert(ty, naga::Span::UNDEFINED);
-                    let init = decl
-                        .initializer
-                        .as_ref()
-                        .map(|init| {
-                            let span = ToNaga(init.span()).to_naga();
-                            let init = ToNaga(init).to_naga(&mut global_expressions)?;
-                            let init = global_expressions.append(init, span);
-                            Ok(init)
-                        })
-                        .transpose()?;
-                    match decl.kind {
-                        DeclarationKind::Const => {
-                            let init = init.ok_or(E::UninitConst)?;
-                            let constant = naga::Constant { name, ty, init };
-                            constants.append(constant, naga::Span::UNDEFINED);
-                        }
-                        DeclarationKind::Override => {
-                            let id = attr_id(&decl.attributes)?;
-                            let override_ = naga::Override { name, id, ty, init };
-                            overrides.append(override_, naga::Span::UNDEFINED);
-                        }
-                        DeclarationKind::Let => return Err(E::LetInMod),
-                        DeclarationKind::Var(space) => {
-                            let space = ToNaga(&space.unwrap_or(AddressSpace::Handle)).to_naga();
-                            let binding = attr_group_binding(&decl.attributes)?
-                                .map(|(group, binding)| naga::ResourceBinding { group, binding });
-
-                            let constant = naga::GlobalVariable {
-                                name,
-                                space,
-                                binding,
-                                ty,
-                                init,
-                            };
-                            global_variables.append(constant, naga::Span::UNDEFINED);
-                        }
-                    }
+            fn rand() -> u32 {
+                seed = seed * 747796405u + 2891336453u;
+                return seed >> 16u;
+            }
+            fn accumulate(acc: ptr<function, f32>, x: f32) {
+                *acc += x * SCALE;
+            }
+            @vertex
+            fn vs(@builtin(vertex_index) index: u32) -> VertexOut {
+                let uv = vec2f(f32(index & 1u), f32(index >> 1u));
+                var out: VertexOut;
+                out.position = uniforms.transform * vec4f(uv * SCALE - 1.0, 0.0, 1.0);
+                out.uv = uv.yx;
+                out.index = index % COUNT;
+                return out;
+            }
+            @fragment
+            fn fs(in: VertexOut) -> @location(0) vec4f {
+                var color = textureSample(tex, samp, in.uv).rgb * uniforms.tint;
+                var total = 0.0;
+                for (var i = 0u; i < COUNT; i++) {
+                    accumulate(&total, f32(i));
+                }
+                switch in.index {
+                    case 0u, 1u: { color.r = total; }
+                    default: { color *= gain; }
                 }
-                GlobalDeclaration::TypeAlias(_) => return Err(E::Unsupported("type aliases")),
-                GlobalDeclaration::Struct(decl) => {}
-                GlobalDeclaration::Function(decl) => todo!(),
-                GlobalDeclaration::ConstAssert(decl) => todo!(),
+                let parts = modf(uniforms.time);
+                if parts.fract > 0.5 {
+                    discard;
+                } else if all(color == vec3f()) {
+                    color = select(vec3(0.5), vec3(1.0), color.g > 0.1);
+                }
+                return vec4f(color, 1.0);
             }
-        }
+            @compute @workgroup_size(64)
+            fn cs(@builtin(local_invocation_index) local: u32, @builtin(global_invocation_id) id: vec3u) {
+                shared_values[local] = values[id.x];
+                workgroupBarrier();
+                var i = 32u;
+                loop {
+                    if local < i {
+                        shared_values[local] += shared_values[local + i];
+                    }
+                    workgroupBarrier();
+                    continuing {
+                        i /= 2u;
+                        break if i == 0u;
+                    }
+                }
+                if local == 0u {
+                    let n = atomicAdd(&counter, 1u);
+                    values[n % arrayLength(&values)] = shared_values[0] + f32(rand());
+                }
+            }";
+        let syntax = source.parse().unwrap();
+        let module = to_naga(&syntax).unwrap();
+        assert_eq!(module.entry_points.len(), 3);
+        assert_eq!(module.entry_points[2].workgroup_size, [64, 1, 1]);
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
 
-        Ok(naga::Module {
-            types,
-            special_types: SpecialTypes::default(),
-            constants,
-            overrides,
-            global_variables,
-            global_expressions,
-            functions,
-            entry_points,
-            diagnostic_filters,
-            diagnostic_filter_leaf: None,
-        })
+    #[test]
+    fn test_abstract_values() {
+        // const-evaluated values of abstract types are concretized.
+        let source = "@compute @workgroup_size(1) fn main() {
+                let fr = frexp(1.5);
+                let fr2 = frexp(vec2(1.5, 3.0));
+                let m = modf(2.5);
+                let e: i32 = fr.exp + fr2.exp.y;
+                let x = 1 + 2;
+            }";
+        let syntax = source.parse().unwrap();
+        let module = to_naga(&syntax).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn test_builtin_arity() {
+        // the arguments are not type-checked before the lowering.
+        for call in [
+            "textureStore(tex)",
+            "textureSample(tex, samp)",
+            "atomicCompareExchangeWeak(&counter, 1u)",
+        ] {
+            let source = format!(
+                "@group(0) @binding(0) var tex: texture_storage_2d<rgba8unorm, write>;
+                @group(0) @binding(1) var samp: sampler;
+                var<workgroup> counter: atomic<u32>;
+                @compute @workgroup_size(1) fn main() {{ _ = {call}; }}"
+            );
+            let syntax = source.parse().unwrap();
+            assert!(to_naga(&syntax).is_err(), "{call}");
+        }
     }
 }
@@ -1,4 +1,6 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+
+use itertools::Itertools;
 
 use wesl_macros::query;
 use wgsl_parse::syntax::{
@@ -8,6 +10,10 @@ use wgsl_parse::syntax::{
 use crate::visit::Visit;
 use crate::{Diagnostic, Error};
 
+#[cfg(feature = "eval")]
+pub(crate) mod types;
+pub(crate) mod uniformity;
+
 /// WESL or WGSL Validation error.
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum ValidateError {
@@ -19,6 +25,12 @@ pub enum ValidateError {
     NotCallable(String),
     #[error("duplicate declaration of `{0}`")]
     Duplicate(String),
+    #[error("calls to overloaded function `{0}` require the `eval` feature")]
+    Overload(String),
+    #[error("`{0}` must only be called from uniform control flow")]
+    NonUniformCall(String),
+    #[error("argument `{1}` of `{0}` must be uniform")]
+    NonUniformArgument(String, String),
 }
 
 type E = ValidateError;
@@ -611,15 +623,33 @@ fn check_function_calls(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>>
     Ok(())
 }
 
-fn check_no_duplicate_decl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
-    let mut unique = HashSet::new();
+/// When `overloads` is set, functions can share a name if their parameter types differ.
+fn check_no_duplicate_decl(
+    wesl: &TranslationUnit,
+    overloads: bool,
+) -> Result<(), Diagnostic<Error>> {
+    // the parameter types of each declaration, `None` for declarations that cannot be
+    // overloaded.
+    let mut unique: HashMap<String, Vec<Option<Vec<String>>>> = HashMap::new();
     for decl in &wesl.global_declarations {
         if let Some(id) = decl.ident() {
-            if !unique.insert(id.to_string()) {
+            let sig = match decl {
+                GlobalDeclaration::Function(decl) if overloads => Some(
+                    decl.parameters
+                        .iter()
+                        .map(|p| p.ty.to_string())
+                        .collect_vec(),
+                ),
+                _ => None,
+            };
+            let sigs = unique.entry(id.to_string()).or_default();
+            let is_overload = sig.is_some() && sigs.iter().all(|s| s.is_some() && *s != sig);
+            if !sigs.is_empty() && !is_overload {
                 return Err(
                     Diagnostic::from(E::Duplicate(id.to_string())).with_declaration(id.to_string())
                 );
             }
+            sigs.push(sig);
         }
     }
     Ok(())
@@ -630,14 +660,26 @@ fn check_no_duplicate_decl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Erro
 /// Used idents must have use_count > 1.
 pub(crate) fn validate_wesl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
     check_defined_symbols(wesl)?;
-    check_no_duplicate_decl(wesl)?;
+    check_no_duplicate_decl(wesl, true)?;
     Ok(())
 }
 
 /// Validate the final output (valid WGSL).
 pub fn validate_wgsl(wgsl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
     check_defined_symbols(wgsl)?;
-    check_no_duplicate_decl(wgsl)?;
+    check_no_duplicate_decl(wgsl, false)?;
     check_function_calls(wgsl)?;
+    #[cfg(feature = "eval")]
+    types::check_types(wgsl)?;
+    uniformity::check_uniformity(wgsl)?;
     Ok(())
 }
+
+/// declarations and statements with an unevaluated `@if` attribute are not checked,
+/// because they can conflict with their alternatives.
+#[cfg(feature = "condcomp")]
+fn is_conditional(attrs: &[wgsl_parse::syntax::Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|attr| matches!(attr, wgsl_parse::syntax::Attribute::If(_)))
+}
@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+#[cfg(feature = "condcomp")]
+use wgsl_parse::Decorated;
+use wgsl_parse::{span::Span, syntax::*};
+
+#[cfg(feature = "condcomp")]
+use super::is_conditional;
+use crate::{
+    eval::{
+        assignment_op, binary_op_type, convert_all_ty, is_assignable, is_constructor_fn,
+        ty_eval_ty, Context, Convert, EvalError, EvalTy, Exec, Instance, Scope, ScopeKind,
+        SyntaxUtil, Type,
+    },
+    Diagnostic, Error,
+};
+
+type E = EvalError;
+
+/// Check the types of module-scope declarations and function bodies.
+///
+/// This reports the first type error, with the declaration and the span of the
+/// expression or statement that caused it.
+#[allow(clippy::result_large_err)]
+pub(crate) fn check_types(wgsl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    select_overloads(wgsl, &HashMap::new()).map(|_| ())
+}
+
+/// Check the types, like [`check_types`], and return the overload selected by each call
+/// to an overloaded function. `calls` maps call sites to the function they call. See
+/// [`Context::overloads`].
+#[allow(clippy::result_large_err)]
+pub(crate) fn select_overloads(
+    wgsl: &TranslationUnit,
+    calls: &HashMap<String, String>,
+) -> Result<HashMap<String, usize>, Diagnostic<Error>> {
+    let mut checker = Checker::new(wgsl);
+    checker.calls = Some(calls);
+    match checker.check() {
+        Ok(()) => Ok(checker.overloads),
+        Err(e) => {
+            let mut err = Diagnostic::from(e);
+            err.declaration = checker.err_decl.take();
+            err.span = checker.err_span.take();
+            Err(err)
+        }
+    }
+}
+
+struct Checker<'s> {
+    source: &'s TranslationUnit,
+    /// the type of the declarations in scope. Const-declarations are evaluated, because
+    /// their value can appear in types, e.g. the size of arrays.
+    scope: Scope<Instance>,
+    /// whether the declarations in scope are references (`var` declarations).
+    refs: Scope<bool>,
+    kind: ScopeKind,
+    /// the name and return type of the function being checked.
+    func: Option<(String, Option<Type>)>,
+    calls: Option<&'s HashMap<String, String>>,
+    overloads: HashMap<String, usize>,
+    err_decl: Option<String>,
+    err_span: Option<Span>,
+}
+
+impl<'s> Checker<'s> {
+    fn new(source: &'s TranslationUnit) -> Self {
+        Self {
+            source,
+            scope: Scope::new(),
+            refs: Scope::new(),
+            kind: ScopeKind::Module,
+            func: None,
+            calls: None,
+            overloads: HashMap::new(),
+            err_decl: None,
+            err_span: None,
+        }
+    }
+
+    fn set_err_decl(&mut self, decl: String) {
+        if self.err_decl.is_none() {
+            self.err_decl = Some(decl)
+        }
+    }
+
+    fn set_err_span(&mut self, span: &Span) {
+        if self.err_span.is_none() {
+            self.err_span = Some(span.clone())
+        }
+    }
+
+    /// run `f` in an evaluation context with the current scope. On error, records the span
+    /// of the innermost failing expression.
+    fn eval<T>(&mut self, f: impl FnOnce(&mut Context) -> Result<T, E>) -> Result<T, E> {
+        let mut ctx = Context::new(self.source);
+        ctx.kind = self.kind;
+        ctx.calls = self.calls;
+        ctx.scope = std::mem::take(&mut self.scope);
+        let res = f(&mut ctx);
+        if res.is_err() {
+            if let (_, Some(span)) = ctx.err_ctx() {
+                self.set_err_span(&span);
+            }
+        }
+        self.scope = ctx.scope;
+        self.overloads.extend(ctx.overloads);
+        res
+    }
+
+    fn expr_ty(&mut self, expr: &ExpressionNode) -> Result<Type, E> {
+        self.eval(|ctx| expr.eval_ty(ctx))
+    }
+
+    fn ty(&mut self, ty: &TypeExpression) -> Result<Type, E> {
+        self.eval(|ctx| ty_eval_ty(ty, ctx))
+    }
+
+    fn push_scope(&mut self) {
+        self.scope.push();
+        self.refs.push();
+    }
+
+    fn pop_scope(&mut self) {
+        self.scope.pop();
+        self.refs.pop();
+    }
+
+    fn add_decl(&mut self, ident: &Ident, inst: Instance, is_ref: bool) -> Result<(), E> {
+        let name = ident.to_string();
+        if self.scope.local_contains(&name) {
+            return Err(E::DuplicateDecl(name));
+        }
+        self.scope.add(name.clone(), inst);
+        self.refs.add(name, is_ref);
+        Ok(())
+    }
+
+    fn check(&mut self) -> Result<(), E> {
+        self.module_decls()?;
+
+        for decl in &self.source.global_declarations {
+            #[cfg(feature = "condcomp")]
+            if is_conditional(decl.attributes()) {
+                continue;
+            }
+            self.kind = ScopeKind::Module;
+            let res = match decl {
+                GlobalDeclaration::TypeAlias(decl) => self.ty(&decl.ty).map(|_| ()),
+                GlobalDeclaration::Struct(decl) => decl
+                    .members
+                    .iter()
+                    .try_for_each(|m| self.ty(&m.ty).map(|_| ())),
+                GlobalDeclaration::Function(decl) => self.function(decl),
+                GlobalDeclaration::ConstAssert(decl) => self.condition(&decl.expression),
+                GlobalDeclaration::Declaration(_) | GlobalDeclaration::Void => Ok(()),
+            };
+            if let (Err(_), Some(ident)) = (&res, decl.ident()) {
+                self.set_err_decl(ident.to_string());
+            }
+            res?;
+        }
+        Ok(())
+    }
+
+    /// module-scope declarations can refer to declarations declared after them, so they
+    /// are added to the scope in dependency order.
+    fn module_decls(&mut self) -> Result<(), E> {
+        let mut pending = self
+            .source
+            .global_declarations
+            .iter()
+            .filter_map(|decl| match decl {
+                GlobalDeclaration::Declaration(decl) => Some(decl),
+                _ => None,
+            })
+            .collect_vec();
+        #[cfg(feature = "condcomp")]
+        pending.retain(|decl| !is_conditional(&decl.attributes));
+
+        while !pending.is_empty() {
+            let mut deferred = Vec::new();
+            for decl in &pending {
+                match self.module_decl(decl) {
+                    Ok(()) => (),
+                    Err(E::UnknownDecl(name))
+                        if pending.iter().any(|decl| *decl.ident.name() == name) =>
+                    {
+                        self.err_span = None;
+                        deferred.push(*decl);
+                    }
+                    Err(e) => {
+                        self.set_err_decl(decl.ident.to_string());
+                        return Err(e);
+                    }
+                }
+            }
+            // no progress: the declarations depend on each other.
+            if deferred.len() == pending.len() {
+                let decl = deferred[0];
+                self.set_err_decl(decl.ident.to_string());
+                return self.module_decl(decl);
+            }
+            pending = deferred;
+        }
+        Ok(())
+    }
+
+    fn module_decl(&mut self, decl: &Declaration) -> Result<(), E> {
+        self.kind = ScopeKind::Module;
+        if decl.kind.is_const() {
+            return self.const_decl(decl);
+        }
+        if let (Some(ty), Some(init)) = (&decl.ty, &decl.initializer) {
+            let ty = self.ty(ty)?;
+            self.check_init(init, &ty)?;
+        }
+        // var and override declarations are added to the scope with their type only.
+        self.eval(|ctx| decl.exec(ctx))?;
+        self.refs.add(decl.ident.to_string(), decl.kind.is_var());
+        Ok(())
+    }
+
+    fn const_decl(&mut self, decl: &Declaration) -> Result<(), E> {
+        match self.eval(|ctx| decl.exec(ctx)) {
+            Ok(_) => {
+                self.refs.add(decl.ident.to_string(), false);
+                Ok(())
+            }
+            // not all built-in functions can be evaluated yet, fall back to the type.
+            Err(E::Todo(_)) => {
+                self.err_span = None;
+                let ty = match (&decl.ty, &decl.initializer) {
+                    (Some(ty), _) => self.ty(ty)?,
+                    (None, Some(init)) => self.expr_ty(init)?,
+                    (None, None) => return Err(E::UninitConst(decl.ident.to_string())),
+                };
+                self.add_decl(&decl.ident, Instance::Deferred(ty), false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_init(&mut self, init: &ExpressionNode, ty: &Type) -> Result<(), E> {
+        let init_ty = self.expr_ty(init)?;
+        if is_assignable(&init_ty, ty) {
+            Ok(())
+        } else {
+            self.set_err_span(init.span());
+            Err(E::Conversion(init_ty, ty.clone()))
+        }
+    }
+
+    /// check that an expression is a `bool`.
+    fn condition(&mut self, expr: &ExpressionNode) -> Result<(), E> {
+        let ty = self.expr_ty(expr)?;
+        if ty.is_bool() {
+            Ok(())
+        } else {
+            self.set_err_span(expr.span());
+            Err(E::Type(Type::Bool, ty))
+        }
+    }
+
+    // ---------------------------------------------------------------------------------
+    // functions
+
+    fn function(&mut self, decl: &Function) -> Result<(), E> {
+        self.kind = ScopeKind::Function;
+        self.push_scope();
+        let res = self.function_inner(decl);
+        self.pop_scope();
+        self.func = None;
+        res
+    }
+
+    fn function_inner(&mut self, decl: &Function) -> Result<(), E> {
+        for param in &decl.parameters {
+            let ty = self.ty(&param.ty)?;
+            self.add_decl(&param.ident, Instance::Deferred(ty), false)?;
+        }
+        let ret = decl
+            .return_type
+            .as_ref()
+            .map(|ty| self.ty(ty))
+            .transpose()?;
+        self.func = Some((decl.ident.to_string(), ret));
+
+        // the parameters are in the same scope as the function body.
+        self.statements(&decl.body.statements)
+    }
+
+    fn compound(&mut self, stmt: &CompoundStatement) -> Result<(), E> {
+        self.push_scope();
+        let res = self.statements(&stmt.statements);
+        self.pop_scope();
+        res
+    }
+
+    fn statements(&mut self, stmts: &[StatementNode]) -> Result<(), E> {
+        stmts.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn stmt(&mut self, stmt: &StatementNode) -> Result<(), E> {
+        #[cfg(feature = "condcomp")]
+        if is_conditional(stmt.attributes()) {
+            return Ok(());
+        }
+        self.stmt_inner(stmt)
+            .inspect_err(|_| self.set_err_span(stmt.span()))
+    }
+
+    fn stmt_inner(&mut self, stmt: &StatementNode) -> Result<(), E> {
+        match stmt.node() {
+            Statement::Void | Statement::Break(_) | Statement::Continue(_) => Ok(()),
+            Statement::Discard(_) => Ok(()),
+            Statement::Compound(stmt) => self.compound(stmt),
+            Statement::Assignment(stmt) => self.assignment(stmt),
+            Statement::Increment(stmt) => self.increment(&stmt.expression, true),
+            Statement::Decrement(stmt) => self.increment(&stmt.expression, false),
+            Statement::If(stmt) => {
+                self.condition(&stmt.if_clause.expression)?;
+                self.compound(&stmt.if_clause.body)?;
+                for clause in &stmt.else_if_clauses {
+                    self.condition(&clause.expression)?;
+                    self.compound(&clause.body)?;
+                }
+                if let Some(clause) = &stmt.else_clause {
+                    self.compound(&clause.body)?;
+                }
+                Ok(())
+            }
+            Statement::Switch(stmt) => self.switch(stmt),
+            Statement::Loop(stmt) => {
+                // the continuing statement is in the same scope as the loop body.
+                self.push_scope();
+                let res = self.statements(&stmt.body.statements).and_then(|()| {
+                    if let Some(continuing) = &stmt.continuing {
+                        self.statements(&continuing.body.statements)?;
+                        if let Some(break_if) = &continuing.break_if {
+                            self.condition(&break_if.expression)?;
+                        }
+                    }
+                    Ok(())
+                });
+                self.pop_scope();
+                res
+            }
+            Statement::For(stmt) => {
+                // the initializer is in the same scope as the body.
+                self.push_scope();
+                let res = (|| {
+                    if let Some(init) = &stmt.initializer {
+                        self.stmt(init)?;
+                    }
+                    if let Some(cond) = &stmt.condition {
+                        self.condition(cond)?;
+                    }
+                    if let Some(update) = &stmt.update {
+                        self.stmt(update)?;
+                    }
+                    self.statements(&stmt.body.statements)
+                })();
+                self.pop_scope();
+                res
+            }
+            Statement::While(stmt) => {
+                self.condition(&stmt.condition)?;
+                self.compound(&stmt.body)
+            }
+            Statement::Return(stmt) => self.return_stmt(stmt),
+            Statement::FunctionCall(stmt) => self.call_stmt(&stmt.call),
+            Statement::ConstAssert(stmt) => self.condition(&stmt.expression),
+            Statement::Declaration(decl) => self.declaration(decl),
+        }
+    }
+
+    fn declaration(&mut self, decl: &Declaration) -> Result<(), E> {
+        let name = decl.ident.to_string();
+        let ty = decl.ty.as_ref().map(|ty| self.ty(ty)).transpose()?;
+
+        let is_ref = match decl.kind {
+            DeclarationKind::Const => return self.const_decl(decl),
+            DeclarationKind::Override => return Err(E::OverrideInFn),
+            DeclarationKind::Let => {
+                if decl.initializer.is_none() {
+                    return Err(E::UninitLet(name));
+                }
+                false
+            }
+            DeclarationKind::Var(space) => {
+                if !matches!(space, None | Some(AddressSpace::Function)) {
+                    return Err(E::ForbiddenDecl(decl.kind, self.kind));
+                }
+                true
+            }
+        };
+
+        let ty = match (ty, &decl.initializer) {
+            (Some(ty), Some(init)) => {
+                self.check_init(init, &ty)?;
+                ty
+            }
+            (Some(ty), None) => ty,
+            (None, Some(init)) => self.expr_ty(init)?.concretize(),
+            (None, None) => return Err(E::UntypedDecl),
+        };
+        self.add_decl(&decl.ident, Instance::Deferred(ty), is_ref)
+    }
+
+    fn assignment(&mut self, stmt: &AssignmentStatement) -> Result<(), E> {
+        // phony assignment: the right-hand side is evaluated for its side-effects.
+        if let Expression::TypeOrIdentifier(ty) = stmt.lhs.node() {
+            if *ty.ident.name() == "_" {
+                return self.expr_ty(&stmt.rhs).map(|_| ());
+            }
+        }
+
+        if !self.is_ref(&stmt.lhs)? {
+            self.set_err_span(stmt.lhs.span());
+            return Err(E::NotAssignable(stmt.lhs.clone()));
+        }
+        let lhs = self.expr_ty(&stmt.lhs)?;
+        let rhs = self.expr_ty(&stmt.rhs)?;
+        let ty = match assignment_op(&stmt.operator) {
+            None => rhs,
+            Some(op) => binary_op_type(op, lhs.clone(), rhs)?,
+        };
+        if is_assignable(&ty, &lhs) {
+            Ok(())
+        } else {
+            Err(E::AssignType(ty, lhs))
+        }
+    }
+
+    fn increment(&mut self, expr: &ExpressionNode, incr: bool) -> Result<(), E> {
+        if !self.is_ref(expr)? {
+            self.set_err_span(expr.span());
+            return Err(E::NotAssignable(expr.clone()));
+        }
+        let ty = self.expr_ty(expr)?;
+        if ty.is_i_32() || ty.is_u_32() {
+            Ok(())
+        } else if incr {
+            Err(E::IncrType(ty))
+        } else {
+            Err(E::DecrType(ty))
+        }
+    }
+
+    /// whether an expression is a memory view, which can be assigned to.
+    fn is_ref(&mut self, expr: &ExpressionNode) -> Result<bool, E> {
+        Ok(match expr.node() {
+            Expression::TypeOrIdentifier(ty) => {
+                self.refs.get(&ty.ident.name()).copied().unwrap_or(false)
+            }
+            Expression::Parenthesized(e) => self.is_ref(&e.expression)?,
+            Expression::NamedComponent(e) => match self.expr_ty(&e.base)? {
+                // pointers are implicitly dereferenced.
+                Type::Ptr(_, _) => true,
+                // a swizzle with several components is a value.
+                Type::Vec(_, _) if e.component.name().len() > 1 => false,
+                _ => self.is_ref(&e.base)?,
+            },
+            Expression::Indexing(e) => match self.expr_ty(&e.base)? {
+                Type::Ptr(_, _) => true,
+                _ => self.is_ref(&e.base)?,
+            },
+            Expression::Unary(e) => e.operator.is_indirection(),
+            Expression::Literal(_) | Expression::Binary(_) | Expression::FunctionCall(_) => false,
+        })
+    }
+
+    /// the selector and the case selectors must convert to the same integer type.
+    fn switch(&mut self, stmt: &SwitchStatement) -> Result<(), E> {
+        let mut tys = vec![self.expr_ty(&stmt.expression)?];
+        for clause in &stmt.clauses {
+            for selector in &clause.case_selectors {
+                if let CaseSelector::Expression(expr) = selector {
+                    let ty = self.expr_ty(expr)?;
+                    if convert_all_ty([&tys[0], &ty]).is_none() {
+                        self.set_err_span(expr.span());
+                        return Err(E::Conversion(ty, tys[0].clone()));
+                    }
+                    tys.push(ty);
+                }
+            }
+        }
+        match convert_all_ty(&tys).map(|ty| ty.concretize()) {
+            Some(Type::I32 | Type::U32) => (),
+            _ => {
+                self.set_err_span(stmt.expression.span());
+                return Err(E::Type(Type::I32, tys[0].clone()));
+            }
+        }
+        for clause in &stmt.clauses {
+            self.compound(&clause.body)?;
+        }
+        Ok(())
+    }
+
+    fn return_stmt(&mut self, stmt: &ReturnStatement) -> Result<(), E> {
+        let (name, ret) = self
+            .func
+            .clone()
+            .expect("return statement outside of a function");
+        match (&stmt.expression, ret) {
+            (Some(expr), Some(ret)) => {
+                let ty = self.expr_ty(expr)?;
+                if is_assignable(&ty, &ret) {
+                    Ok(())
+                } else {
+                    self.set_err_span(expr.span());
+                    Err(E::ReturnType(ty, name, ret))
+                }
+            }
+            (Some(expr), None) => {
+                let ty = self.expr_ty(expr)?;
+                Err(E::UnexpectedReturn(ty, name))
+            }
+            (None, _) => Ok(()),
+        }
+    }
+
+    fn call_stmt(&mut self, call: &FunctionCall) -> Result<(), E> {
+        let ty = self.source.resolve_ty(&call.ty);
+        let call_name = ty.ident.to_string();
+        let res = self.eval(|ctx| call.eval_ty(ctx));
+        let name = match self.calls.and_then(|calls| calls.get(&call_name)) {
+            Some(name) => name.clone(),
+            None => call_name.clone(),
+        };
+        let decl = match self.overloads.get(&call_name) {
+            Some(idx) => self.source.global_declarations.get(*idx),
+            None => self.source.decl(&name),
+        };
+        let is_must_use = match decl {
+            Some(GlobalDeclaration::Function(decl)) => {
+                decl.attributes.contains(&Attribute::MustUse)
+            }
+            Some(GlobalDeclaration::Struct(_)) => true,
+            Some(_) => return Err(E::NotCallable(name)),
+            None if is_constructor_fn(&name) => true,
+            None => return Err(E::UnknownFunction(name)),
+        };
+        if is_must_use {
+            self.err_span = None;
+            return Err(E::MustUse(name));
+        }
+        match res {
+            Ok(_) | Err(E::Void(_)) => {
+                self.err_span = None;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate_wgsl, SyntaxUtil};
+
+    #[test]
+    fn test_type_check() {
+        let check = |source: &str| {
+            let mut wgsl: TranslationUnit = source.parse().unwrap();
+            wgsl.retarget_idents();
+            validate_wgsl(&wgsl).map_err(|d| d.error.to_string())
+        };
+        let ok = "struct S { a: f32, b: vec2u }
+            var<private> p: f32 = 1.0;
+            fn f(x: f32, y: ptr<function, i32>) -> f32 { *y += 1; return x * p; }
+            fn main() { var i = 0; let s = S(f(1.0, &i), vec2(1u)); p = f32(s.b.yx.x) + s.a; i++; }";
+        assert_eq!(check(ok), Ok(()));
+
+        let errors = [
+            (
+                "fn f(x: f32) {} fn g() { f(true); }",
+                "invalid parameter type",
+            ),
+            (
+                "struct S { a: f32 } fn g() { let s = S(1u); }",
+                "invalid parameter type",
+            ),
+            ("fn g() { var x = 1.0; x = 1u; }", "cannot assign a `u32`"),
+            ("fn g() -> f32 { return 1u; }", "returned `u32`"),
+            (
+                "fn g() { let v = vec2f(); let w = v.xyz; }",
+                "swizzle `xyz`",
+            ),
+            ("fn g() { let x = 1; x = 2; }", "not a reference"),
+            ("fn g() { if 1 {} }", "expected type `bool`"),
+        ];
+        for (source, err) in errors {
+            let res = check(source);
+            assert!(
+                res.as_ref().is_err_and(|e| e.contains(err)),
+                "{source}: {res:?}"
+            );
+        }
+    }
+}
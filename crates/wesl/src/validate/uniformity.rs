@@ -0,0 +1,998 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[cfg(feature = "condcomp")]
+use wgsl_parse::Decorated;
+use wgsl_parse::{span::Span, syntax::*};
+
+#[cfg(feature = "condcomp")]
+use super::is_conditional;
+use super::ValidateError;
+use crate::{Diagnostic, Error, SyntaxUtil};
+
+type E = ValidateError;
+
+/// The name of the WGSL diagnostic rule for derivatives.
+pub(crate) const DERIVATIVE_UNIFORMITY: &str = "derivative_uniformity";
+
+/// Built-in functions that must be called from uniform control flow, under the
+/// `derivative_uniformity` rule.
+const DERIVATIVE_FUNCTIONS: &[&str] = &[
+    "dpdx",
+    "dpdxCoarse",
+    "dpdxFine",
+    "dpdy",
+    "dpdyCoarse",
+    "dpdyFine",
+    "fwidth",
+    "fwidthCoarse",
+    "fwidthFine",
+    "textureSample",
+    "textureSampleBias",
+    "textureSampleCompare",
+];
+
+/// Built-in functions that must be called from uniform control flow. Those are always
+/// errors.
+const BARRIER_FUNCTIONS: &[&str] = &[
+    "storageBarrier",
+    "textureBarrier",
+    "workgroupBarrier",
+    "workgroupUniformLoad",
+];
+
+/// A violation of the uniformity rules, found by [`analyze`].
+pub(crate) struct Violation {
+    /// The severity of the violation. Never [`DiagnosticSeverity::Off`].
+    pub severity: DiagnosticSeverity,
+    /// Whether the violation is caused by a derivative, under the
+    /// `derivative_uniformity` rule. Otherwise, it is caused by a barrier.
+    pub derivative: bool,
+    /// The error, with the mangled declaration name and the span.
+    pub diagnostic: Diagnostic<Error>,
+}
+
+/// Check that barriers and derivatives are called from uniform control flow.
+///
+/// This reports the first violation with the `error` severity. Violations with a lower
+/// severity are not reported, see [`crate::Wesl::lint`].
+#[allow(clippy::result_large_err)]
+pub(crate) fn check_uniformity(wgsl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    match analyze(wgsl, DiagnosticSeverity::Error)
+        .into_iter()
+        .find(|violation| violation.severity == DiagnosticSeverity::Error)
+    {
+        Some(violation) => Err(violation.diagnostic),
+        None => Ok(()),
+    }
+}
+
+/// Run the WGSL uniformity analysis on a module where identifiers point at their
+/// declaration.
+///
+/// `severity` is the severity of the `derivative_uniformity` rule when it is not set by a
+/// `diagnostic` directive or attribute.
+///
+/// The analysis follows the spec, with a few simplifications. Each function is
+/// summarized by the uniformity it requires of the call site and of its arguments, and
+/// by the uniformity of its return value and of the contents of its pointer parameters.
+/// Values and control flow are tracked by their sources: non-uniform values, the call
+/// site control flow, and parameters.
+///
+/// Spec: <https://www.w3.org/TR/WGSL/#uniformity>
+pub(crate) fn analyze(wgsl: &TranslationUnit, severity: DiagnosticSeverity) -> Vec<Violation> {
+    let mut analyzer = Analyzer::new(wgsl, severity);
+    for decl in &wgsl.global_declarations {
+        if let GlobalDeclaration::Function(decl) = decl {
+            #[cfg(feature = "condcomp")]
+            if is_conditional(&decl.attributes) {
+                continue;
+            }
+            analyzer.info(&decl.ident);
+        }
+    }
+    let mut seen = HashSet::new();
+    analyzer
+        .violations
+        .into_iter()
+        .filter(|violation| {
+            seen.insert((
+                violation.diagnostic.span.as_ref().map(|span| span.range()),
+                violation.diagnostic.error.to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    /// A value that may be non-uniform, e.g. the invocation id.
+    NonUniform,
+    /// The control flow at the call site of the function.
+    CallSite,
+    /// The value of a parameter, or the contents of a pointer parameter.
+    Param(usize),
+}
+
+/// The sources a value or the control flow depends on. It is uniform when empty.
+type Deps = BTreeSet<Source>;
+
+fn strictness(severity: &DiagnosticSeverity) -> u8 {
+    match severity {
+        DiagnosticSeverity::Error => 3,
+        DiagnosticSeverity::Warning => 2,
+        DiagnosticSeverity::Info => 1,
+        DiagnosticSeverity::Off => 0,
+    }
+}
+
+/// A requirement of uniformity, from barriers and derivatives.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Requirement {
+    barrier: bool,
+    /// The strictest severity of the `derivative_uniformity` rule at the derivative calls.
+    derivative: Option<DiagnosticSeverity>,
+}
+
+impl Requirement {
+    fn is_none(&self) -> bool {
+        !self.barrier && self.derivative.is_none()
+    }
+
+    fn merge(&mut self, other: &Requirement) {
+        self.barrier |= other.barrier;
+        if let Some(severity) = &other.derivative {
+            if self
+                .derivative
+                .as_ref()
+                .is_none_or(|s| strictness(severity) > strictness(s))
+            {
+                self.derivative = Some(severity.clone());
+            }
+        }
+    }
+}
+
+/// The uniformity summary of a function.
+#[derive(Clone, Debug, Default)]
+struct FnInfo {
+    /// Uniformity required of the control flow at the call site.
+    call_site: Requirement,
+    /// Uniformity required of each argument.
+    params: Vec<Requirement>,
+    /// The sources of the return value.
+    ret: Deps,
+    /// The sources of the contents of pointer parameters when the function returns.
+    ptr_params: Vec<Option<Deps>>,
+}
+
+/// A function-scope declaration.
+#[derive(Clone, Debug, PartialEq)]
+enum Local {
+    /// The sources of the value, or of the contents of a variable.
+    Value(Deps),
+    /// A pointer to a variable.
+    Ptr(Key),
+}
+
+/// Identifies a declaration by the address shared by its identifier and the references
+/// to it, like the equality of [`Ident`]. Unlike `Ident`, it has no interior mutability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Key(*const String);
+
+impl From<&Ident> for Key {
+    fn from(ident: &Ident) -> Self {
+        Key(&*ident.name())
+    }
+}
+
+type Locals = HashMap<Key, Local>;
+
+/// How a statement can transfer control, besides continuing to the next statement.
+#[derive(Clone, Copy, Debug, Default)]
+struct Exits {
+    ret: bool,
+    brk: bool,
+    cont: bool,
+}
+
+impl Exits {
+    fn any(&self) -> bool {
+        self.ret || self.brk || self.cont
+    }
+
+    fn union(self, other: Exits) -> Exits {
+        Exits {
+            ret: self.ret || other.ret,
+            brk: self.brk || other.brk,
+            cont: self.cont || other.cont,
+        }
+    }
+}
+
+/// The state of the function being analyzed.
+#[derive(Default)]
+struct FnState {
+    name: String,
+    info: FnInfo,
+    /// Parameters of pointer type.
+    ptr_params: Vec<Option<Key>>,
+    locals: Locals,
+    /// The sources of the current control flow.
+    cf: Deps,
+}
+
+struct Analyzer<'a> {
+    functions: HashMap<Key, &'a Function>,
+    structs: HashMap<Key, &'a Struct>,
+    entry_points: HashSet<Key>,
+    /// Module-scope variables holding non-uniform values.
+    non_uniform_globals: HashSet<Key>,
+    infos: HashMap<Key, FnInfo>,
+    /// Functions being analyzed, to guard against recursion.
+    pending: HashSet<Key>,
+    /// Severity of the `derivative_uniformity` rule set by the `diagnostic` directives, or
+    /// the default severity.
+    module_severity: DiagnosticSeverity,
+    /// Severities set by `@diagnostic` attributes, innermost last.
+    overrides: Vec<DiagnosticSeverity>,
+    state: FnState,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(wgsl: &'a TranslationUnit, severity: DiagnosticSeverity) -> Self {
+        let mut functions = HashMap::new();
+        let mut structs = HashMap::new();
+        let mut non_uniform_globals = HashSet::new();
+        for decl in &wgsl.global_declarations {
+            match decl {
+                GlobalDeclaration::Function(decl) => {
+                    functions.insert(Key::from(&decl.ident), decl);
+                }
+                GlobalDeclaration::Struct(decl) => {
+                    structs.insert(Key::from(&decl.ident), decl);
+                }
+                GlobalDeclaration::Declaration(decl) => {
+                    // read-only variables are uniform, all invocations see the same value.
+                    let uniform = match decl.kind {
+                        DeclarationKind::Var(space) => matches!(
+                            space,
+                            None | Some(AddressSpace::Uniform)
+                                | Some(AddressSpace::Handle)
+                                | Some(AddressSpace::Storage(None))
+                                | Some(AddressSpace::Storage(Some(AccessMode::Read)))
+                        ),
+                        _ => true,
+                    };
+                    if !uniform {
+                        non_uniform_globals.insert(Key::from(&decl.ident));
+                    }
+                }
+                _ => (),
+            }
+        }
+        let module_severity = wgsl
+            .global_directives
+            .iter()
+            .filter_map(|directive| match directive {
+                GlobalDirective::Diagnostic(directive)
+                    if directive.rule_name == DERIVATIVE_UNIFORMITY =>
+                {
+                    Some(directive.severity.clone())
+                }
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or(severity);
+        Self {
+            functions,
+            structs,
+            entry_points: wgsl.entry_points().map(Key::from).collect(),
+            non_uniform_globals,
+            infos: HashMap::new(),
+            pending: HashSet::new(),
+            module_severity,
+            overrides: Vec::new(),
+            state: FnState::default(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// The summary of a function, `None` if it is not a user-defined function.
+    fn info(&mut self, ident: &Ident) -> Option<FnInfo> {
+        let key = Key::from(ident);
+        if let Some(info) = self.infos.get(&key) {
+            return Some(info.clone());
+        }
+        let decl = *self.functions.get(&key)?;
+        if !self.pending.insert(key) {
+            // recursion is forbidden in WGSL, this is reported elsewhere.
+            return Some(FnInfo::default());
+        }
+        let state = std::mem::take(&mut self.state);
+        let overrides = std::mem::take(&mut self.overrides);
+        self.function(decl);
+        let info = std::mem::replace(&mut self.state, state).info;
+        self.overrides = overrides;
+        self.pending.remove(&key);
+        self.infos.insert(key, info.clone());
+        Some(info)
+    }
+
+    fn severity(&self) -> DiagnosticSeverity {
+        self.overrides
+            .last()
+            .unwrap_or(&self.module_severity)
+            .clone()
+    }
+
+    /// Push the severities of `@diagnostic` attributes. Returns the number of pushed
+    /// overrides.
+    fn push_attributes(&mut self, attrs: &[Attribute]) -> usize {
+        let len = self.overrides.len();
+        for attr in attrs {
+            if let Attribute::Diagnostic(attr) = attr {
+                if attr.rule == DERIVATIVE_UNIFORMITY {
+                    self.overrides.push(attr.severity.clone());
+                }
+            }
+        }
+        self.overrides.len() - len
+    }
+
+    fn pop_attributes(&mut self, count: usize) {
+        self.overrides.truncate(self.overrides.len() - count);
+    }
+
+    /// Whether a parameter of an entry point is uniform. Only some built-in values are.
+    fn is_uniform_input(&self, attrs: &[Attribute], ty: &TypeExpression) -> bool {
+        let uniform_builtin = |attrs: &[Attribute]| {
+            attrs.iter().any(|attr| {
+                matches!(
+                    attr,
+                    Attribute::Builtin(BuiltinValue::WorkgroupId | BuiltinValue::NumWorkgroups)
+                )
+            })
+        };
+        match self.structs.get(&Key::from(&ty.ident)) {
+            Some(decl) => decl
+                .members
+                .iter()
+                .all(|member| uniform_builtin(&member.attributes)),
+            None => uniform_builtin(attrs),
+        }
+    }
+
+    fn function(&mut self, decl: &Function) {
+        let entry_point = self.entry_points.contains(&Key::from(&decl.ident));
+        self.state.name = decl.ident.to_string();
+        self.state.info.params = vec![Requirement::default(); decl.parameters.len()];
+        self.state.info.ptr_params = vec![None; decl.parameters.len()];
+        for (i, param) in decl.parameters.iter().enumerate() {
+            let deps = if entry_point {
+                if self.is_uniform_input(&param.attributes, &param.ty) {
+                    Deps::new()
+                } else {
+                    Deps::from([Source::NonUniform])
+                }
+            } else {
+                Deps::from([Source::Param(i)])
+            };
+            let is_ptr = *param.ty.ident.name() == "ptr";
+            self.state
+                .ptr_params
+                .push(is_ptr.then(|| Key::from(&param.ident)));
+            self.state
+                .locals
+                .insert(Key::from(&param.ident), Local::Value(deps));
+        }
+        if !entry_point {
+            self.state.cf = Deps::from([Source::CallSite]);
+        }
+
+        let count = self.push_attributes(&decl.attributes);
+        self.compound(&decl.body);
+        self.pop_attributes(count);
+        self.return_ptr_params();
+    }
+
+    /// Record the contents of pointer parameters when the function returns.
+    fn return_ptr_params(&mut self) {
+        for i in 0..self.state.ptr_params.len() {
+            if let Some(param) = self.state.ptr_params[i] {
+                let deps = self.load(param);
+                self.state.info.ptr_params[i]
+                    .get_or_insert_with(Deps::new)
+                    .extend(deps);
+            }
+        }
+    }
+
+    /// Check a requirement of uniformity on a value or control flow.
+    fn require(&mut self, req: &Requirement, deps: &Deps, err: impl FnOnce() -> E, span: &Span) {
+        if req.is_none() {
+            return;
+        }
+        for source in deps {
+            match source {
+                Source::NonUniform => {
+                    let severity = if req.barrier {
+                        DiagnosticSeverity::Error
+                    } else {
+                        req.derivative.clone().unwrap()
+                    };
+                    let mut diagnostic = Diagnostic::from(err());
+                    diagnostic.declaration = Some(self.state.name.clone());
+                    diagnostic.span = Some(span.clone());
+                    self.violations.push(Violation {
+                        severity,
+                        derivative: !req.barrier,
+                        diagnostic,
+                    });
+                    return;
+                }
+                Source::CallSite => self.state.info.call_site.merge(req),
+                Source::Param(i) => self.state.info.params[*i].merge(req),
+            }
+        }
+    }
+
+    /// Merge the states of branches that join.
+    fn join(&mut self, states: Vec<Locals>) {
+        let mut locals = Locals::new();
+        for state in states {
+            for (ident, local) in state {
+                match (locals.get_mut(&ident), local) {
+                    (Some(Local::Value(deps)), Local::Value(other)) => deps.extend(other),
+                    (None, local) => {
+                        locals.insert(ident, local);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.state.locals = locals;
+    }
+
+    fn compound(&mut self, stmt: &CompoundStatement) -> Exits {
+        let count = self.push_attributes(&stmt.attributes);
+        let mut exits = Exits::default();
+        for stmt in &stmt.statements {
+            #[cfg(feature = "condcomp")]
+            if is_conditional(stmt.attributes()) {
+                continue;
+            }
+            exits = exits.union(self.stmt(stmt));
+        }
+        self.pop_attributes(count);
+        exits
+    }
+
+    /// Analyze branches executed with a control flow that depends on `cond`. The control
+    /// flow reconverges after the branches if none of them exits.
+    fn branches<'b>(
+        &mut self,
+        cond: Deps,
+        branches: impl IntoIterator<Item = &'b CompoundStatement>,
+        fallthrough: bool,
+    ) -> Exits {
+        let cf = self.state.cf.clone();
+        let before = self.state.locals.clone();
+        let mut states = Vec::new();
+        let mut end_cf = cf.clone();
+        let mut exits = Exits::default();
+        for body in branches {
+            self.state.locals = before.clone();
+            self.state.cf = cf.union(&cond).cloned().collect();
+            exits = exits.union(self.compound(body));
+            end_cf.extend(self.state.cf.iter().cloned());
+            states.push(std::mem::take(&mut self.state.locals));
+        }
+        if fallthrough {
+            states.push(before);
+        }
+        self.join(states);
+        self.state.cf = if exits.any() { end_cf } else { cf };
+        exits
+    }
+
+    /// Analyze a loop until the state of declarations and the control flow are stable.
+    /// `body` is called for each iteration.
+    fn repeat(&mut self, mut body: impl FnMut(&mut Self) -> Exits) -> Exits {
+        let cf = self.state.cf.clone();
+        let mut exits;
+        loop {
+            let start = (self.state.cf.clone(), self.state.locals.clone());
+            exits = body(self);
+            let end = std::mem::take(&mut self.state.locals);
+            self.join(vec![start.1.clone(), end]);
+            let mut next_cf = start.0.clone();
+            next_cf.extend(self.state.cf.iter().cloned());
+            self.state.cf = next_cf;
+            if self.state.cf == start.0 && self.state.locals == start.1 {
+                break;
+            }
+        }
+        // the loop absorbs `break` and `continue`.
+        if !exits.ret {
+            self.state.cf = cf;
+        }
+        Exits {
+            ret: exits.ret,
+            ..Default::default()
+        }
+    }
+
+    fn stmt(&mut self, node: &StatementNode) -> Exits {
+        match node.node() {
+            Statement::Void => Exits::default(),
+            Statement::Compound(stmt) => self.compound(stmt),
+            Statement::Assignment(stmt) => {
+                self.assignment(&stmt.lhs, &stmt.rhs, &stmt.operator);
+                Exits::default()
+            }
+            Statement::Increment(stmt) => {
+                self.increment(&stmt.expression);
+                Exits::default()
+            }
+            Statement::Decrement(stmt) => {
+                self.increment(&stmt.expression);
+                Exits::default()
+            }
+            Statement::If(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                let mut cond = self.expr(&stmt.if_clause.expression);
+                let mut branches = vec![&stmt.if_clause.body];
+                // the conditions of `else if` clauses are evaluated in the control flow of
+                // the previous conditions.
+                let cf = self.state.cf.clone();
+                for clause in &stmt.else_if_clauses {
+                    self.state.cf.extend(cond.iter().cloned());
+                    cond.extend(self.expr(&clause.expression));
+                    branches.push(&clause.body);
+                }
+                self.state.cf = cf;
+                if let Some(clause) = &stmt.else_clause {
+                    branches.push(&clause.body);
+                }
+                let exits = self.branches(cond, branches, stmt.else_clause.is_none());
+                self.pop_attributes(count);
+                exits
+            }
+            Statement::Switch(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                let cond = self.expr(&stmt.expression);
+                let body_count = self.push_attributes(&stmt.body_attributes);
+                let has_default = stmt.clauses.iter().any(|clause| {
+                    clause
+                        .case_selectors
+                        .iter()
+                        .any(|sel| matches!(sel, CaseSelector::Default))
+                });
+                let cf = self.state.cf.clone();
+                let exits = self.branches(
+                    cond.clone(),
+                    stmt.clauses.iter().map(|clause| &clause.body),
+                    !has_default,
+                );
+                // `break` exits the switch.
+                if !exits.ret && !exits.cont {
+                    self.state.cf = cf;
+                } else {
+                    self.state.cf.extend(cond);
+                }
+                self.pop_attributes(body_count);
+                self.pop_attributes(count);
+                Exits {
+                    brk: false,
+                    ..exits
+                }
+            }
+            Statement::Loop(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                let exits = self.repeat(|this| {
+                    let mut exits = this.compound(&stmt.body);
+                    if let Some(cont) = &stmt.continuing {
+                        exits = exits.union(this.compound(&cont.body));
+                        if let Some(break_if) = &cont.break_if {
+                            let cond = this.expr(&break_if.expression);
+                            this.state.cf.extend(cond);
+                        }
+                    }
+                    exits
+                });
+                self.pop_attributes(count);
+                exits
+            }
+            Statement::For(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                if let Some(init) = &stmt.initializer {
+                    self.stmt(init);
+                }
+                let exits = self.repeat(|this| {
+                    if let Some(cond) = &stmt.condition {
+                        let cond = this.expr(cond);
+                        this.state.cf.extend(cond);
+                    }
+                    let exits = this.compound(&stmt.body);
+                    if let Some(update) = &stmt.update {
+                        this.stmt(update);
+                    }
+                    exits
+                });
+                self.pop_attributes(count);
+                exits
+            }
+            Statement::While(stmt) => {
+                let count = self.push_attributes(&stmt.attributes);
+                let exits = self.repeat(|this| {
+                    let cond = this.expr(&stmt.condition);
+                    this.state.cf.extend(cond);
+                    this.compound(&stmt.body)
+                });
+                self.pop_attributes(count);
+                exits
+            }
+            Statement::Break(_) => Exits {
+                brk: true,
+                ..Default::default()
+            },
+            Statement::Continue(_) => Exits {
+                cont: true,
+                ..Default::default()
+            },
+            Statement::Return(stmt) => {
+                if let Some(expr) = &stmt.expression {
+                    let mut deps = self.expr(expr);
+                    deps.extend(self.state.cf.iter().cloned());
+                    self.state.info.ret.extend(deps);
+                }
+                self.return_ptr_params();
+                Exits {
+                    ret: true,
+                    ..Default::default()
+                }
+            }
+            // `discard` demotes the invocation to a helper invocation, which keeps
+            // executing.
+            Statement::Discard(_) => Exits::default(),
+            Statement::FunctionCall(stmt) => {
+                self.call(&stmt.call, node.span());
+                Exits::default()
+            }
+            Statement::ConstAssert(_) => Exits::default(),
+            Statement::Declaration(decl) => {
+                self.declaration(decl);
+                Exits::default()
+            }
+        }
+    }
+
+    fn declaration(&mut self, decl: &Declaration) {
+        let local = match (&decl.kind, &decl.initializer) {
+            (DeclarationKind::Const, _) => Local::Value(Deps::new()),
+            (DeclarationKind::Let, Some(init)) if self.is_ptr(init) => {
+                self.expr(init);
+                match self.root(init) {
+                    Some(root) => Local::Ptr(root),
+                    None => Local::Value(Deps::new()),
+                }
+            }
+            (_, Some(init)) => {
+                let mut deps = self.expr(init);
+                deps.extend(self.state.cf.iter().cloned());
+                Local::Value(deps)
+            }
+            (_, None) => Local::Value(self.state.cf.clone()),
+        };
+        self.state.locals.insert(Key::from(&decl.ident), local);
+    }
+
+    fn assignment(&mut self, lhs: &ExpressionNode, rhs: &ExpressionNode, op: &AssignmentOperator) {
+        let mut deps = self.expr(rhs);
+        deps.extend(self.state.cf.iter().cloned());
+        let Some(root) = self.root(lhs) else {
+            // phony assignment, e.g. `_ = f();`
+            return;
+        };
+        deps.extend(self.place(lhs));
+        let whole = matches!(lhs.node(), Expression::TypeOrIdentifier(_))
+            && *op == AssignmentOperator::Equal;
+        self.store(root, deps, whole);
+    }
+
+    fn increment(&mut self, expr: &ExpressionNode) {
+        let mut deps = self.place(expr);
+        deps.extend(self.state.cf.iter().cloned());
+        if let Some(root) = self.root(expr) {
+            self.store(root, deps, false);
+        }
+    }
+
+    /// Whether an expression is a pointer.
+    fn is_ptr(&self, expr: &ExpressionNode) -> bool {
+        match expr.node() {
+            Expression::Parenthesized(expr) => self.is_ptr(&expr.expression),
+            Expression::Unary(expr) => expr.operator == UnaryOperator::AddressOf,
+            Expression::TypeOrIdentifier(ty) => {
+                let key = Key::from(&ty.ident);
+                matches!(self.state.locals.get(&key), Some(Local::Ptr(_)))
+                    || self.state.ptr_params.contains(&Some(key))
+            }
+            _ => false,
+        }
+    }
+
+    /// The variable a reference or pointer expression refers to.
+    fn root(&self, expr: &ExpressionNode) -> Option<Key> {
+        match expr.node() {
+            Expression::Parenthesized(expr) => self.root(&expr.expression),
+            Expression::NamedComponent(expr) => self.root(&expr.base),
+            Expression::Indexing(expr) => self.root(&expr.base),
+            Expression::Unary(expr) => self.root(&expr.operand),
+            Expression::TypeOrIdentifier(ty) => {
+                let key = Key::from(&ty.ident);
+                match self.state.locals.get(&key) {
+                    Some(Local::Ptr(root)) => Some(*root),
+                    _ => Some(key),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The sources of the contents of a variable.
+    fn load(&self, root: Key) -> Deps {
+        match self.state.locals.get(&root) {
+            Some(Local::Value(deps)) => deps.clone(),
+            _ if self.non_uniform_globals.contains(&root) => Deps::from([Source::NonUniform]),
+            _ => Deps::new(),
+        }
+    }
+
+    /// Store a value in a variable. If `whole` is false, only a part of the variable is
+    /// written, e.g. a vector component.
+    fn store(&mut self, root: Key, deps: Deps, whole: bool) {
+        if let Some(Local::Value(old)) = self.state.locals.get_mut(&root) {
+            if whole {
+                *old = deps;
+            } else {
+                old.extend(deps);
+            }
+        }
+    }
+
+    /// The sources of a reference expression: the indices and the pointers it goes
+    /// through.
+    fn place(&mut self, expr: &ExpressionNode) -> Deps {
+        match expr.node() {
+            Expression::Parenthesized(expr) => self.place(&expr.expression),
+            Expression::NamedComponent(expr) => self.place(&expr.base),
+            Expression::Indexing(expr) => {
+                let mut deps = self.place(&expr.base);
+                deps.extend(self.expr(&expr.index));
+                deps
+            }
+            Expression::Unary(expr) => match expr.operator {
+                UnaryOperator::AddressOf => self.place(&expr.operand),
+                _ => self.expr(&expr.operand),
+            },
+            _ => self.state.cf.clone(),
+        }
+    }
+
+    /// The sources of an expression value.
+    fn expr(&mut self, expr: &ExpressionNode) -> Deps {
+        match expr.node() {
+            Expression::Literal(_) => self.state.cf.clone(),
+            Expression::Parenthesized(expr) => self.expr(&expr.expression),
+            Expression::NamedComponent(_) | Expression::Indexing(_) => self.access(expr),
+            Expression::Unary(unary) => match unary.operator {
+                UnaryOperator::AddressOf => self.place(&unary.operand),
+                UnaryOperator::Indirection => self.access(expr),
+                _ => self.expr(&unary.operand),
+            },
+            Expression::Binary(binary) => {
+                let mut deps = self.expr(&binary.left);
+                if matches!(
+                    binary.operator,
+                    BinaryOperator::ShortCircuitAnd | BinaryOperator::ShortCircuitOr
+                ) {
+                    // the right operand is evaluated conditionally.
+                    let cf = self.state.cf.clone();
+                    self.state.cf.extend(deps.iter().cloned());
+                    deps.extend(self.expr(&binary.right));
+                    self.state.cf = cf;
+                } else {
+                    deps.extend(self.expr(&binary.right));
+                }
+                deps
+            }
+            Expression::FunctionCall(call) => self.call(call, expr.span()),
+            Expression::TypeOrIdentifier(ty) => {
+                if self.is_ptr(expr) {
+                    self.state.cf.clone()
+                } else {
+                    self.load(Key::from(&ty.ident))
+                }
+            }
+        }
+    }
+
+    /// The sources of a component, an element or the contents of a pointer.
+    fn access(&mut self, expr: &ExpressionNode) -> Deps {
+        if let Some(root) = self.root(expr) {
+            let mut deps = self.place(expr);
+            deps.extend(self.load(root));
+            return deps;
+        }
+        // access to a value that is not in a variable, e.g. a function call result.
+        match expr.node() {
+            Expression::NamedComponent(expr) => self.expr(&expr.base),
+            Expression::Indexing(expr) => {
+                let mut deps = self.expr(&expr.base);
+                deps.extend(self.expr(&expr.index));
+                deps
+            }
+            Expression::Unary(expr) => self.expr(&expr.operand),
+            _ => self.expr(expr),
+        }
+    }
+
+    /// The sources of the value of a function call.
+    fn call(&mut self, call: &FunctionCall, span: &Span) -> Deps {
+        let name = call.ty.ident.name().to_string();
+        let Some(info) = self.info(&call.ty.ident) else {
+            return self.builtin_call(&name, call, span);
+        };
+        let decl = self.functions[&Key::from(&call.ty.ident)];
+
+        let cf = self.state.cf.clone();
+        self.require(
+            &info.call_site,
+            &cf,
+            || E::NonUniformCall(name.clone()),
+            span,
+        );
+
+        // the sources of each argument, or of the contents of pointer arguments.
+        let mut args = Vec::new();
+        for (i, arg) in call.arguments.iter().enumerate() {
+            let mut deps = self.expr(arg);
+            if info.ptr_params.get(i).is_some_and(|p| p.is_some()) {
+                if let Some(root) = self.root(arg) {
+                    deps.extend(self.load(root));
+                }
+            }
+            if let (Some(req), Some(param)) = (info.params.get(i), decl.parameters.get(i)) {
+                let param = param.ident.to_string();
+                self.require(
+                    req,
+                    &deps,
+                    || E::NonUniformArgument(name.clone(), param),
+                    arg.span(),
+                );
+            }
+            args.push(deps);
+        }
+
+        let substitute = |deps: &Deps| -> Deps {
+            let mut res = cf.clone();
+            for source in deps {
+                match source {
+                    Source::NonUniform => {
+                        res.insert(Source::NonUniform);
+                    }
+                    Source::CallSite => (),
+                    Source::Param(i) => res.extend(args.get(*i).into_iter().flatten().cloned()),
+                }
+            }
+            res
+        };
+
+        for (arg, deps) in call.arguments.iter().zip(&info.ptr_params) {
+            if let (Some(root), Some(deps)) = (self.root(arg), deps) {
+                let deps = substitute(deps);
+                self.store(root, deps, false);
+            }
+        }
+        substitute(&info.ret)
+    }
+
+    fn builtin_call(&mut self, name: &str, call: &FunctionCall, span: &Span) -> Deps {
+        let mut deps = self.state.cf.clone();
+        for arg in &call.arguments {
+            deps.extend(self.expr(arg));
+        }
+        let cf = self.state.cf.clone();
+        if DERIVATIVE_FUNCTIONS.contains(&name) {
+            let severity = self.severity();
+            if severity != DiagnosticSeverity::Off {
+                let req = Requirement {
+                    barrier: false,
+                    derivative: Some(severity),
+                };
+                self.require(&req, &cf, || E::NonUniformCall(name.to_string()), span);
+            }
+        } else if BARRIER_FUNCTIONS.contains(&name) {
+            let req = Requirement {
+                barrier: true,
+                derivative: None,
+            };
+            self.require(&req, &cf, || E::NonUniformCall(name.to_string()), span);
+            // `workgroupUniformLoad` returns a uniform value.
+            deps = cf;
+        } else if name.starts_with("atomic")
+            || name.starts_with("subgroup")
+            || name.starts_with("quad")
+        {
+            deps.insert(Source::NonUniform);
+        }
+        deps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, LintConfig, LintRule, VirtualResolver, Wesl};
+
+    #[cfg(feature = "imports")]
+    #[test]
+    fn test_uniformity() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "import package::util::{shade, sync};
+            @fragment fn fs(@location(0) uv: vec2f) -> @location(0) vec4f {
+                if uv.x > 0.5 { return shade(uv); }
+                return vec4f();
+            }
+            @compute @workgroup_size(64) fn cs(@builtin(workgroup_id) wid: vec3u) {
+                if wid.x == 0u { sync(); }
+            }"
+            .into(),
+        );
+        resolver.add_module(
+            "util",
+            "@group(0) @binding(0) var t: texture_2d<f32>;
+            @group(0) @binding(1) var s: sampler;
+            fn shade(uv: vec2f) -> vec4f { return textureSample(t, s, uv); }
+            fn sync() { workgroupBarrier(); }"
+                .into(),
+        );
+        resolver.add_module(
+            "barrier",
+            "@compute @workgroup_size(64) fn cs(@builtin(local_invocation_index) lid: u32) {
+                if lid == 0u { return; }
+                workgroupBarrier();
+            }"
+            .into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions::default());
+        let compiler = compiler.set_custom_resolver(resolver);
+
+        // derivatives in non-uniform control flow are errors by default.
+        let Err(err) = compiler.compile("main") else {
+            panic!("expected an error");
+        };
+        assert!(err
+            .to_string()
+            .contains("must only be called from uniform control flow"));
+        let Err(err) = compiler.compile("barrier") else {
+            panic!("expected an error");
+        };
+        assert!(err
+            .to_string()
+            .contains("`workgroupBarrier` must only be called from uniform control flow"));
+
+        let lints = compiler.lint("main", &LintConfig::new()).unwrap();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule, LintRule::DerivativeUniformity);
+        assert_eq!(lints[0].diagnostic.declaration.as_deref(), Some("fs"));
+        // the span refers to the source of the `main` module.
+        let source = lints[0].diagnostic.source.as_deref().unwrap();
+        let span = lints[0].diagnostic.span.clone().unwrap();
+        assert_eq!(&source[span.range()], "shade(uv)");
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::ModulePath;
+
+use crate::{deps::json_string, CompileResult, CompileSession, Error, Resolver};
+
+/// A set of enabled and disabled conditional compilation features.
+pub type Features = BTreeMap<String, bool>;
+
+/// All combinations of a set of feature flags. There are `2^n` combinations of `n` flags.
+///
+/// ```
+/// # use wesl::permutations;
+/// let variants = permutations(["a", "b"]);
+/// assert_eq!(variants.len(), 4);
+/// assert_eq!(variants[1].get("a"), Some(&true));
+/// assert_eq!(variants[1].get("b"), Some(&false));
+/// ```
+pub fn permutations(flags: impl IntoIterator<Item = impl Into<String>>) -> Vec<Features> {
+    let flags = flags.into_iter().map(Into::into).collect_vec();
+    (0..1usize << flags.len())
+        .map(|bits| {
+            flags
+                .iter()
+                .enumerate()
+                .map(|(i, flag)| (flag.clone(), bits & (1 << i) != 0))
+                .collect()
+        })
+        .collect()
+}
+
+/// A feature permutation of a shader. See [`Variants`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant {
+    /// The features of this variant, on top of the compiler's features.
+    pub features: Features,
+    /// Index of the compiled output in [`Variants::outputs`].
+    pub output: usize,
+}
+
+/// The result of [`CompileSession::compile_variants`].
+///
+/// Variants that compile to the same WGSL share the same output.
+#[derive(Clone)]
+pub struct Variants {
+    pub variants: Vec<Variant>,
+    pub outputs: Vec<CompileResult>,
+}
+
+impl Variants {
+    /// Get the output of a variant.
+    pub fn get(&self, features: &Features) -> Option<&CompileResult> {
+        self.variants
+            .iter()
+            .find(|variant| variant.features == *features)
+            .map(|variant| &self.outputs[variant.output])
+    }
+
+    /// Format as a JSON manifest that maps each variant's features to its output file.
+    /// `file_name` gives the file name of an output from its index in [`Self::outputs`].
+    ///
+    /// ```json
+    /// {"variants":[{"features":{"a":true,"b":false},"output":"main.0.wgsl"}]}
+    /// ```
+    pub fn manifest(&self, file_name: impl Fn(usize) -> String) -> String {
+        let variants = self
+            .variants
+            .iter()
+            .map(|variant| {
+                let features = variant
+                    .features
+                    .iter()
+                    .map(|(name, val)| format!("{}:{val}", json_string(name)))
+                    .format(",");
+                let output = json_string(&file_name(variant.output));
+                format!(r#"{{"features":{{{features}}},"output":{output}}}"#)
+            })
+            .format(",");
+        format!(r#"{{"variants":[{variants}]}}"#)
+    }
+}
+
+/// Error produced by [`CompileSession::compile_variants`] when a variant fails to compile.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("in variant `{}`: {error}", fmt_features(features))]
+pub struct VariantError {
+    pub features: Features,
+    pub error: Error,
+}
+
+fn fmt_features(features: &Features) -> String {
+    features
+        .iter()
+        .map(|(name, val)| format!("{name}={val}"))
+        .join(", ")
+}
+
+impl<R: Resolver> CompileSession<R> {
+    /// Compile several feature permutations of a root module. Modules are imported and
+    /// parsed once for all variants.
+    ///
+    /// Use [`permutations`] to compile all combinations of a set of feature flags.
+    #[allow(clippy::result_large_err)]
+    pub fn compile_variants(
+        &mut self,
+        root: impl Into<ModulePath>,
+        variants: impl IntoIterator<Item = Features>,
+    ) -> Result<Variants, VariantError> {
+        let root = root.into();
+        let mut res = Variants {
+            variants: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let mut outputs = HashMap::new();
+
+        for features in variants {
+            let map = features.clone().into_iter().collect();
+            let comp = self
+                .compile_variant(root.clone(), &map)
+                .map_err(|error| VariantError {
+                    features: features.clone(),
+                    error,
+                })?;
+            let output = *outputs.entry(comp.to_string()).or_insert_with(|| {
+                res.outputs.push(comp);
+                res.outputs.len() - 1
+            });
+            res.variants.push(Variant { features, output });
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    #[test]
+    fn test_compile_variants() {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "main",
+            "@compute @workgroup_size(1) fn main() { @if(a) let x = 1u; @if(b) let y = 2u; @if(!b) let y = 2u; }".into(),
+        );
+        let mut compiler = Wesl::new_barebones();
+        compiler.set_options(CompileOptions {
+            condcomp: true,
+            ..Default::default()
+        });
+        let mut session = CompileSession::new(compiler.set_custom_resolver(resolver));
+        let variants = session
+            .compile_variants("main", permutations(["a", "b"]))
+            .unwrap();
+        // `b` has no effect on the output.
+        assert_eq!(variants.variants.len(), 4);
+        assert_eq!(variants.outputs.len(), 2);
+        let features = Features::from_iter([("a".to_string(), true), ("b".to_string(), true)]);
+        assert!(variants.get(&features).unwrap().to_string().contains("1u"));
+    }
+}
@@ -404,6 +404,8 @@ impl_visit! { TranslationUnit => Attributes,
             GlobalDirective::Diagnostic.attributes,
             GlobalDirective::Enable.attributes,
             GlobalDirective::Requires.attributes,
+            #[cfg(feature = "condcomp")]
+            GlobalDirective::Feature.attributes,
         },
         global_declarations.[].{
             GlobalDeclaration::Declaration.attributes,
@@ -0,0 +1,685 @@
+//! A lossless concrete syntax tree (CST), for tools that need to preserve the source
+//! layout: formatters, codemods, documentation generators...
+//!
+//! Contrary to the [`syntax`][crate::syntax] tree, the CST keeps every byte of the source:
+//! whitespace and comments are kept as [`Trivia`] attached to the tokens. A token owns the
+//! trivia that follows it on the same line (trailing trivia) and the trivia on the lines
+//! before it (leading trivia). Comments on the lines before a declaration or a statement
+//! are therefore the leading trivia of its first token.
+//!
+//! The tree is shallow: it only delimits imports, directives, declarations, struct
+//! members, attributes, blocks and statements. Expressions are flat lists of tokens.
+//! The nodes of the [`TranslationUnit`][NodeKind::TranslationUnit] appear in the same
+//! order as the `imports`, `global_directives` and `global_declarations` of
+//! [`TranslationUnit`][crate::syntax::TranslationUnit].
+//!
+//! Building the CST does not check the syntax, only the tokens. Use
+//! [`parse_str`][crate::parse_str] for that.
+//!
+//! ```rust
+//! # use wgsl_parse::cst::{Cst, NodeKind};
+//! let source = "// the light intensity\nconst I = 1.0; // lux\n\nfn f() { /* nothing */ }\n";
+//! let cst = Cst::parse(source).unwrap();
+//! assert_eq!(cst.to_string(), source);
+//!
+//! let decl = cst.declarations().next().unwrap();
+//! assert_eq!(decl.kind, NodeKind::Declaration);
+//! assert_eq!(cst.leading_comments(decl).collect::<Vec<_>>(), ["// the light intensity"]);
+//! assert_eq!(cst.trailing_comments(decl).collect::<Vec<_>>(), ["// lux"]);
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    error::{Error, ErrorKind},
+    lexer::{Lexer, Token},
+    span::Span,
+};
+
+/// Kinds of source text skipped by the lexer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    /// `// ...`, not including the line break.
+    LineComment,
+    /// `/* ... */`, possibly nested.
+    BlockComment,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+impl Trivia {
+    pub fn is_comment(&self) -> bool {
+        self.kind != TriviaKind::Whitespace
+    }
+}
+
+/// A token with the trivia surrounding it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstToken {
+    pub token: Token,
+    pub span: Span,
+    /// Trivia on the lines before the token.
+    pub leading: Vec<Trivia>,
+    /// Trivia after the token on the same line, excluding the line break.
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The root node.
+    TranslationUnit,
+    /// An `import` statement.
+    Import,
+    /// `enable`, `requires` or `diagnostic` directive.
+    Directive,
+    /// Any global declaration, including functions, structs and lone semicolons.
+    Declaration,
+    /// A struct member.
+    Member,
+    /// An attribute, e.g. `@location(0)`.
+    Attribute,
+    /// A brace-delimited list of statements or struct members.
+    Block,
+    /// A statement. Compound statements contain their blocks.
+    Statement,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Element {
+    Node(Node),
+    Token(CstToken),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Self {
+        Self {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    /// All tokens in the node, in source order.
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &CstToken> + '_> {
+        Box::new(self.children.iter().flat_map(|child| match child {
+            Element::Token(tok) => Box::new(std::iter::once(tok)),
+            Element::Node(node) => node.tokens(),
+        }))
+    }
+
+    /// The direct children nodes.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    pub fn first_token(&self) -> Option<&CstToken> {
+        self.tokens().next()
+    }
+
+    pub fn last_token(&self) -> Option<&CstToken> {
+        self.tokens().last()
+    }
+
+    /// The span of the node tokens, excluding the leading and trailing trivia.
+    pub fn span(&self) -> Span {
+        match (self.first_token(), self.last_token()) {
+            (Some(first), Some(last)) => first.span.extend(&last.span),
+            _ => Span::default(),
+        }
+    }
+
+    /// Find the first node of a kind starting at a source offset. The spans of
+    /// [`StatementNode`][crate::syntax::StatementNode]s can be used to find the
+    /// corresponding statement.
+    pub fn find(&self, kind: NodeKind, start: usize) -> Option<&Node> {
+        if self.kind == kind && self.span().start == start {
+            return Some(self);
+        }
+        self.nodes()
+            .filter(|node| {
+                let span = node.span();
+                span.start <= start && start < span.end
+            })
+            .find_map(|node| node.find(kind, start))
+    }
+}
+
+/// A lossless syntax tree. See the [module-level documentation][self].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cst {
+    source: String,
+    root: Node,
+    /// Trivia after the last token.
+    eof: Vec<Trivia>,
+}
+
+impl Cst {
+    /// Build the CST of a source. Fails only if the source contains invalid tokens.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let (tokens, eof) = tokenize(source)?;
+        let mut builder = Builder {
+            tokens: tokens.into_iter().peekable(),
+        };
+        let root = builder.translation_unit();
+        Ok(Self {
+            source: source.to_string(),
+            root,
+            eof,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Trivia after the last token.
+    pub fn eof_trivia(&self) -> &[Trivia] {
+        &self.eof
+    }
+
+    /// The source text of a token, trivia or node span.
+    pub fn text(&self, span: &Span) -> &str {
+        &self.source[span.range()]
+    }
+
+    fn kind(&self, kind: NodeKind) -> impl Iterator<Item = &Node> {
+        self.root.nodes().filter(move |node| node.kind == kind)
+    }
+
+    /// The import statements, in the order of `TranslationUnit::imports`.
+    pub fn imports(&self) -> impl Iterator<Item = &Node> {
+        self.kind(NodeKind::Import)
+    }
+
+    /// The global directives, in the order of `TranslationUnit::global_directives`.
+    pub fn directives(&self) -> impl Iterator<Item = &Node> {
+        self.kind(NodeKind::Directive)
+    }
+
+    /// The global declarations, in the order of `TranslationUnit::global_declarations`.
+    pub fn declarations(&self) -> impl Iterator<Item = &Node> {
+        self.kind(NodeKind::Declaration)
+    }
+
+    fn comments<'a>(&'a self, trivia: &'a [Trivia]) -> impl Iterator<Item = &'a str> {
+        trivia
+            .iter()
+            .filter(|t| t.is_comment())
+            .map(|t| self.text(&t.span))
+    }
+
+    /// The comments on the lines before the node.
+    pub fn leading_comments<'a>(&'a self, node: &'a Node) -> impl Iterator<Item = &'a str> {
+        let trivia = node.first_token().map(|tok| tok.leading.as_slice());
+        self.comments(trivia.unwrap_or_default())
+    }
+
+    /// The comments after the node, on the same line as its last token.
+    pub fn trailing_comments<'a>(&'a self, node: &'a Node) -> impl Iterator<Item = &'a str> {
+        let trivia = node.last_token().map(|tok| tok.trailing.as_slice());
+        self.comments(trivia.unwrap_or_default())
+    }
+}
+
+impl FromStr for Cst {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+/// Prints the source unchanged, byte for byte.
+impl Display for Cst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spans = self.root.tokens().flat_map(|tok| {
+            let leading = tok.leading.iter().map(|t| &t.span);
+            let trailing = tok.trailing.iter().map(|t| &t.span);
+            leading.chain(std::iter::once(&tok.span)).chain(trailing)
+        });
+        let eof = self.eof.iter().map(|t| &t.span);
+        spans
+            .chain(eof)
+            .try_for_each(|span| f.write_str(self.text(span)))
+    }
+}
+
+// see line breaks: https://www.w3.org/TR/WGSL/#line-break
+fn is_line_break(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\u{000B}' | '\u{000C}' | '\r' | '\u{0085}' | '\u{2028}' | '\u{2029}'
+    )
+}
+
+// see blankspace: https://www.w3.org/TR/WGSL/#blankspace
+fn is_blankspace(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '\u{200E}' | '\u{200F}')
+}
+
+/// Split the source text between two tokens into trivia.
+fn scan_trivia(source: &str, mut pos: usize, end: usize, out: &mut Vec<Trivia>) {
+    while pos < end {
+        let rest = &source[pos..end];
+        let (kind, len) = if rest.starts_with("//") {
+            let len = rest.find(is_line_break).unwrap_or(rest.len());
+            (TriviaKind::LineComment, len)
+        } else if rest.starts_with("/*") {
+            let mut depth = 0;
+            let mut len = 0;
+            while len < rest.len() {
+                if rest[len..].starts_with("/*") {
+                    depth += 1;
+                    len += 2;
+                } else if rest[len..].starts_with("*/") {
+                    depth -= 1;
+                    len += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    len += rest[len..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+            (TriviaKind::BlockComment, len)
+        } else {
+            let len = rest.find(|c| !is_blankspace(c)).unwrap_or(rest.len());
+            // the lexer only skips trivia, so this does not happen. But make progress anyway.
+            let len = if len == 0 { rest.len() } else { len };
+            (TriviaKind::Whitespace, len)
+        };
+        out.push(Trivia {
+            kind,
+            span: Span::new(pos..pos + len),
+        });
+        pos += len;
+    }
+}
+
+/// Split the trivia after a token into its trailing trivia, up to the first line break,
+/// and the leading trivia of the next token.
+fn split_line(source: &str, trivia: Vec<Trivia>) -> (Vec<Trivia>, Vec<Trivia>) {
+    let mut trailing = Vec::new();
+    let mut rest = trivia.into_iter();
+    while let Some(t) = rest.next() {
+        let text = &source[t.span.range()];
+        match text.find(is_line_break) {
+            Some(i) if t.kind == TriviaKind::Whitespace => {
+                let (start, end) = (t.span.start, t.span.end);
+                if i > 0 {
+                    trailing.push(Trivia {
+                        kind: TriviaKind::Whitespace,
+                        span: Span::new(start..start + i),
+                    });
+                }
+                let line = Trivia {
+                    kind: TriviaKind::Whitespace,
+                    span: Span::new(start + i..end),
+                };
+                let leading = std::iter::once(line).chain(rest).collect();
+                return (trailing, leading);
+            }
+            _ => trailing.push(t),
+        }
+    }
+    (trailing, Vec::new())
+}
+
+/// Lex the source and attach the trivia to the tokens. Returns the tokens and the
+/// trivia after the last token.
+fn tokenize(source: &str) -> Result<(Vec<CstToken>, Vec<Trivia>), Error> {
+    let mut tokens: Vec<CstToken> = Vec::new();
+    let mut pos = 0;
+
+    let attach = |tokens: &mut Vec<CstToken>, start: usize, end: usize| {
+        let mut trivia = Vec::new();
+        scan_trivia(source, start, end, &mut trivia);
+        match tokens.last_mut() {
+            Some(prev) => {
+                let (trailing, leading) = split_line(source, trivia);
+                prev.trailing = trailing;
+                leading
+            }
+            None => trivia,
+        }
+    };
+
+    for tok in Lexer::new(source) {
+        let (l, token, r) = tok.map_err(|(l, _, r)| Error {
+            error: ErrorKind::InvalidToken,
+            span: Span::new(l..r),
+        })?;
+        let leading = attach(&mut tokens, pos, l);
+        tokens.push(CstToken {
+            token,
+            span: Span::new(l..r),
+            leading,
+            trailing: Vec::new(),
+        });
+        pos = r;
+    }
+
+    let eof = attach(&mut tokens, pos, source.len());
+    Ok((tokens, eof))
+}
+
+struct Builder {
+    tokens: std::iter::Peekable<std::vec::IntoIter<CstToken>>,
+}
+
+impl Builder {
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek().map(|tok| &tok.token)
+    }
+
+    fn bump(&mut self, node: &mut Node) {
+        if let Some(tok) = self.tokens.next() {
+            node.children.push(Element::Token(tok));
+        }
+    }
+
+    /// Bump a token and update the nesting depth of parentheses, brackets and templates.
+    fn bump_nested(&mut self, node: &mut Node, depth: &mut u32) {
+        match self.peek() {
+            Some(Token::SymParenLeft | Token::SymBracketLeft | Token::TemplateArgsStart) => {
+                *depth += 1
+            }
+            Some(Token::SymParenRight | Token::SymBracketRight | Token::TemplateArgsEnd) => {
+                *depth = depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+        self.bump(node);
+    }
+
+    fn translation_unit(&mut self) -> Node {
+        let mut node = Node::new(NodeKind::TranslationUnit);
+        while self.peek().is_some() {
+            let item = self.global_item();
+            node.children.push(Element::Node(item));
+        }
+        node
+    }
+
+    fn attributes(&mut self, node: &mut Node) {
+        while self.peek() == Some(&Token::SymAttr) {
+            let mut attr = Node::new(NodeKind::Attribute);
+            self.bump(&mut attr); // @
+            self.bump(&mut attr); // name
+            if self.peek() == Some(&Token::SymParenLeft) {
+                let mut depth = 0;
+                loop {
+                    self.bump_nested(&mut attr, &mut depth);
+                    if depth == 0 || self.peek().is_none() {
+                        break;
+                    }
+                }
+            }
+            node.children.push(Element::Node(attr));
+        }
+    }
+
+    fn global_item(&mut self) -> Node {
+        let mut node = Node::new(NodeKind::Declaration);
+        self.attributes(&mut node);
+        match self.peek() {
+            #[cfg(feature = "imports")]
+            Some(Token::KwImport) => node.kind = NodeKind::Import,
+            Some(Token::KwEnable | Token::KwRequires | Token::KwDiagnostic) => {
+                node.kind = NodeKind::Directive
+            }
+            #[cfg(feature = "condcomp")]
+            Some(Token::Ident(name)) if name == "feature" => node.kind = NodeKind::Directive,
+            _ => {}
+        }
+        let is_struct = self.peek() == Some(&Token::KwStruct);
+
+        let mut depth = 0u32;
+        while let Some(tok) = self.peek() {
+            match tok {
+                Token::SymSemicolon if depth == 0 => {
+                    self.bump(&mut node);
+                    break;
+                }
+                // import collections are the only braces not delimiting a block.
+                Token::SymBraceLeft if node.kind == NodeKind::Declaration && depth == 0 => {
+                    let block = if is_struct {
+                        self.block(Self::member)
+                    } else {
+                        self.block(Self::statement)
+                    };
+                    node.children.push(Element::Node(block));
+                    break;
+                }
+                Token::SymBraceLeft => {
+                    depth += 1;
+                    self.bump(&mut node);
+                }
+                Token::SymBraceRight => {
+                    depth = depth.saturating_sub(1);
+                    self.bump(&mut node);
+                }
+                _ => self.bump_nested(&mut node, &mut depth),
+            }
+        }
+        node
+    }
+
+    /// A block: `{`, items, `}`.
+    fn block(&mut self, item: fn(&mut Self) -> Node) -> Node {
+        let mut node = Node::new(NodeKind::Block);
+        self.bump(&mut node); // {
+        while let Some(tok) = self.peek() {
+            if *tok == Token::SymBraceRight {
+                self.bump(&mut node);
+                break;
+            }
+            let item = item(self);
+            node.children.push(Element::Node(item));
+        }
+        node
+    }
+
+    fn member(&mut self) -> Node {
+        let mut node = Node::new(NodeKind::Member);
+        self.attributes(&mut node);
+        let mut depth = 0;
+        while let Some(tok) = self.peek() {
+            match tok {
+                Token::SymComma if depth == 0 => {
+                    self.bump(&mut node);
+                    break;
+                }
+                Token::SymBraceRight if depth == 0 => break,
+                _ => self.bump_nested(&mut node, &mut depth),
+            }
+        }
+        // a stray closing brace in a member list.
+        if node.children.is_empty() {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    /// A statement ends with a semicolon or a block. Blocks may be followed by `else`.
+    fn statement(&mut self) -> Node {
+        let mut node = Node::new(NodeKind::Statement);
+        self.attributes(&mut node);
+        let mut depth = 0;
+        while let Some(tok) = self.peek() {
+            match tok {
+                Token::SymSemicolon if depth == 0 => {
+                    self.bump(&mut node);
+                    break;
+                }
+                Token::SymBraceRight if depth == 0 => break,
+                Token::SymBraceLeft if depth == 0 => {
+                    let block = self.block(Self::statement);
+                    node.children.push(Element::Node(block));
+                    if self.peek() != Some(&Token::KwElse) {
+                        break;
+                    }
+                }
+                _ => self.bump_nested(&mut node, &mut depth),
+            }
+        }
+        if node.children.is_empty() {
+            self.bump(&mut node);
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+
+    /// The sources of the sample shaders of the workspace.
+    ///
+    /// The samples are not packaged with the crate: the directories that do not exist,
+    /// e.g. when testing the crate out of the workspace, are skipped.
+    pub(crate) fn sample_shaders() -> Vec<(String, String)> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let dirs = [
+            "crates/wesl-test/webgpu-samples",
+            "crates/wesl-test/shaders/circular_import",
+            "samples",
+        ];
+        let mut res = Vec::new();
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(root.join(dir)) else {
+                eprintln!("skipping the missing sample directory `{dir}`");
+                continue;
+            };
+            let mut paths = entries
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == "wgsl" || ext == "wesl")
+                })
+                .collect::<Vec<_>>();
+            paths.sort();
+            for path in paths {
+                let source = fs::read_to_string(&path).unwrap();
+                res.push((path.display().to_string(), source));
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn test_roundtrip_samples() {
+        for (path, source) in sample_shaders() {
+            let cst = Cst::parse(&source).unwrap();
+            assert_eq!(cst.to_string(), source, "{path}");
+            // some samples use syntax extensions not enabled in this build.
+            if let Ok(wgsl) = crate::parse_str(&source) {
+                let decls = cst.declarations().count();
+                assert_eq!(decls, wgsl.global_declarations.len(), "{path}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "// header
+import package::a::{
+    b, // b
+    /* c */ c,
+};
+
+struct S {
+    // leading
+    x: f32, // trailing
+    /* inline */ y: f32,
+}
+
+fn f() {
+    // empty
+}
+
+fn g() {
+    let x = 1; // after
+    return; // last
+    // end of block
+}
+// end of file
+";
+        let cst = Cst::parse(source).unwrap();
+        assert_eq!(cst.to_string(), source);
+
+        let import = cst.imports().next().unwrap();
+        assert_eq!(
+            cst.leading_comments(import).collect::<Vec<_>>(),
+            ["// header"]
+        );
+        let import = cst.text(&import.span());
+        assert!(import.contains("// b") && import.contains("/* c */"));
+
+        let [s, f, g] = cst.declarations().collect::<Vec<_>>()[..] else {
+            panic!("expected 3 declarations")
+        };
+        let block = s.nodes().find(|node| node.kind == NodeKind::Block).unwrap();
+        let members = block.nodes().collect::<Vec<_>>();
+        assert_eq!(members.len(), 2);
+        assert_eq!(
+            cst.leading_comments(members[0]).collect::<Vec<_>>(),
+            ["// leading"]
+        );
+        assert_eq!(
+            cst.trailing_comments(members[0]).collect::<Vec<_>>(),
+            ["// trailing"]
+        );
+        assert_eq!(
+            cst.leading_comments(members[1]).collect::<Vec<_>>(),
+            ["/* inline */"]
+        );
+
+        // comments in an empty block are the leading trivia of the closing brace.
+        let block = f.nodes().find(|node| node.kind == NodeKind::Block).unwrap();
+        let close = block.last_token().unwrap();
+        assert_eq!(
+            cst.comments(&close.leading).collect::<Vec<_>>(),
+            ["// empty"]
+        );
+
+        let block = g.nodes().find(|node| node.kind == NodeKind::Block).unwrap();
+        let stmts = block.nodes().collect::<Vec<_>>();
+        assert_eq!(
+            cst.trailing_comments(stmts[0]).collect::<Vec<_>>(),
+            ["// after"]
+        );
+        assert_eq!(
+            cst.trailing_comments(stmts[1]).collect::<Vec<_>>(),
+            ["// last"]
+        );
+        let close = block.last_token().unwrap();
+        assert_eq!(
+            cst.comments(&close.leading).collect::<Vec<_>>(),
+            ["// end of block"]
+        );
+        assert_eq!(
+            cst.comments(cst.eof_trivia()).collect::<Vec<_>>(),
+            ["// end of file"]
+        );
+    }
+}
@@ -28,15 +28,21 @@ pub enum ErrorKind {
     Attribute(&'static str, &'static str),
     #[error("invalid `var` template arguments, {0}")]
     VarTemplate(&'static str),
+    #[error("unknown directive `{0}`")]
+    Directive(String),
+    #[error("cannot format the source, its syntax trees do not match")]
+    Format,
 }
 
+#[doc(hidden)]
 #[derive(Default, Clone, Debug, PartialEq)]
-pub(crate) enum CustomLalrError {
+pub enum CustomLalrError {
     #[default]
     LexerError,
     DiagnosticSeverity,
     Attribute(&'static str, &'static str),
     VarTemplate(&'static str),
+    Directive(String),
 }
 
 type LalrError = lalrpop_util::ParseError<usize, Token, (usize, CustomLalrError, usize)>;
@@ -106,6 +112,7 @@ impl From<LalrError> for Error {
                         ErrorKind::Attribute(attr, expected)
                     }
                     CustomLalrError::VarTemplate(reason) => ErrorKind::VarTemplate(reason),
+                    CustomLalrError::Directive(name) => ErrorKind::Directive(name),
                 };
                 Self { span, error }
             }
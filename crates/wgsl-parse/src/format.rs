@@ -0,0 +1,1222 @@
+//! A source code formatter for WGSL and WESL.
+//!
+//! The formatter prints the [syntax tree][crate::syntax] in a canonical style and
+//! re-inserts the comments found in the [lossless syntax tree][crate::cst]. Comments
+//! before, after or at the end of imports, directives, declarations, struct members and
+//! statements are moved with them. A node that has comments elsewhere, e.g. in the
+//! middle of an expression, is printed as it was written.
+//!
+//! ```rust
+//! # use wgsl_parse::format::{format, FormatOptions};
+//! let source = "// entry point\n@fragment fn main()->@location(0) vec4f{return vec4f(1.0);}";
+//! let formatted = format(source, &FormatOptions::default()).unwrap();
+//! assert_eq!(
+//!     formatted,
+//!     "// entry point\n@fragment\nfn main() -> @location(0) vec4f {\n    return vec4f(1.0);\n}\n"
+//! );
+//! ```
+
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use crate::{
+    cst::{Cst, CstToken, Node, NodeKind, Trivia, TriviaKind},
+    error::{Error, ErrorKind},
+    lexer::Token,
+    span::Span,
+    syntax::*,
+};
+
+/// Where to place the attributes of global declarations.
+///
+/// Attributes of struct members, function parameters, return types and statements are
+/// always placed on the same line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AttributePlacement {
+    /// Attributes of functions and structs on their own line, others on the same line.
+    #[default]
+    Auto,
+    /// All attributes on the same line as the declaration.
+    SameLine,
+    /// All attributes on their own line before the declaration.
+    OwnLine,
+}
+
+/// Formatting style.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Maximum width of a line. Lines are broken at argument lists, parameter lists,
+    /// import collections and binary operators to fit. Default: 100.
+    pub max_width: usize,
+    /// Number of spaces per indentation level. Default: 4.
+    pub tab_spaces: usize,
+    /// Indent with tabs instead of spaces. Default: false.
+    pub hard_tabs: bool,
+    /// Placement of the attributes of global declarations. Default: `Auto`.
+    pub attributes: AttributePlacement,
+    /// Group imports by origin (external packages, `package::`, then `super::`) and sort
+    /// them alphabetically. Default: true.
+    pub reorder_imports: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            tab_spaces: 4,
+            hard_tabs: false,
+            attributes: AttributePlacement::Auto,
+            reorder_imports: true,
+        }
+    }
+}
+
+/// Format a WGSL or WESL source.
+///
+/// Fails if the source is not syntactically valid, or if the syntax tree does not match
+/// the lossless syntax tree. The latter does not happen with valid sources.
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, Error> {
+    let tu = TranslationUnit::from_str(source)?;
+    let cst = Cst::parse(source)?;
+    let fmt = Formatter { cst: &cst, options };
+    let doc = fmt.translation_unit(&tu).ok_or_else(|| Error {
+        error: ErrorKind::Format,
+        span: Span::new(0..source.len()),
+    })?;
+    let mut printer = Printer::new(options);
+    printer.print(&doc);
+    Ok(printer.finish())
+}
+
+// ----------------------------------------------------------------------------------
+// documents
+
+/// A document describes the layout alternatives of a piece of code, see Wadler's
+/// "A prettier printer".
+#[derive(Clone, Debug)]
+enum Doc {
+    Text(String),
+    /// A space, or a line break if the enclosing group breaks.
+    Line,
+    /// Nothing, or a line break if the enclosing group breaks.
+    SoftLine,
+    /// Always a line break. Breaks all enclosing groups.
+    HardLine,
+    /// Text printed only if the enclosing group breaks.
+    IfBreak(&'static str),
+    /// Indent the line breaks by one level.
+    Nest(Vec<Doc>),
+    /// Print on one line if it fits, otherwise break all its lines.
+    Group(Vec<Doc>),
+    Concat(Vec<Doc>),
+}
+
+fn text(s: impl ToString) -> Doc {
+    Doc::Text(s.to_string())
+}
+
+impl Doc {
+    fn has_hardline(&self) -> bool {
+        match self {
+            Doc::HardLine => true,
+            Doc::Nest(docs) | Doc::Group(docs) | Doc::Concat(docs) => {
+                docs.iter().any(Doc::has_hardline)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `open`, the items separated by commas, `close`. Breaks one item per line with a
+/// trailing comma.
+fn list(open: &str, items: Vec<Doc>, close: &str, padding: bool) -> Doc {
+    if items.is_empty() {
+        return text(format!("{open}{close}"));
+    }
+    let line = if padding { Doc::Line } else { Doc::SoftLine };
+    let items = Itertools::intersperse(items.into_iter(), Doc::Concat(vec![text(","), Doc::Line]));
+    let mut inner = vec![line.clone()];
+    inner.extend(items);
+    inner.push(Doc::IfBreak(","));
+    Doc::Group(vec![text(open), Doc::Nest(inner), line, text(close)])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    out: String,
+    col: usize,
+    /// the indentation is written with the first text of a line, to avoid indenting
+    /// empty lines.
+    pending_indent: Option<usize>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(options: &'a FormatOptions) -> Self {
+        Self {
+            options,
+            out: String::new(),
+            col: 0,
+            pending_indent: Some(0),
+        }
+    }
+
+    fn write(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if let Some(level) = self.pending_indent.take() {
+            if self.options.hard_tabs {
+                self.out.extend(std::iter::repeat_n('\t', level));
+            } else {
+                self.out
+                    .extend(std::iter::repeat_n(' ', level * self.options.tab_spaces));
+            }
+            self.col = level * self.options.tab_spaces;
+        }
+        self.out.push_str(s);
+        self.col += s.chars().count();
+    }
+
+    fn newline(&mut self, level: usize) {
+        let len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(len);
+        self.out.push('\n');
+        self.col = 0;
+        self.pending_indent = Some(level);
+    }
+
+    fn print(&mut self, doc: &Doc) {
+        let mut stack = vec![(0, Mode::Break, doc)];
+        while let Some((level, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(s) => self.write(s),
+                Doc::Line if mode == Mode::Flat => self.write(" "),
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(level),
+                Doc::IfBreak(s) => {
+                    if mode == Mode::Break {
+                        self.write(s)
+                    }
+                }
+                Doc::Nest(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (level + 1, mode, doc)))
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+                Doc::Group(docs) => {
+                    let indent = self.pending_indent.unwrap_or(0) * self.options.tab_spaces;
+                    let width = self.options.max_width as isize - (self.col + indent) as isize;
+                    let mode = if mode == Mode::Flat
+                        || (!doc.has_hardline() && fits(width, docs, &stack))
+                    {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.extend(docs.iter().rev().map(|doc| (level, mode, doc)));
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        let len = self.out.trim_end().len();
+        self.out.truncate(len);
+        self.out.push('\n');
+        self.out
+    }
+}
+
+/// Whether the group fits on the remaining width, up to the next line break.
+fn fits(mut width: isize, group: &[Doc], rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = group
+        .iter()
+        .rev()
+        .map(|doc| (Mode::Flat, doc))
+        .collect::<Vec<_>>();
+    let mut rest = rest.iter().rev().map(|(_, mode, doc)| (*mode, *doc));
+    while width >= 0 {
+        let Some((mode, doc)) = stack.pop().or_else(|| rest.next()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(s) => width -= s.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => width -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::IfBreak(s) => {
+                if mode == Mode::Break {
+                    width -= s.len() as isize
+                }
+            }
+            Doc::Nest(docs) | Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)))
+            }
+            Doc::Group(docs) => {
+                let mode = if doc.has_hardline() {
+                    Mode::Break
+                } else {
+                    Mode::Flat
+                };
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)))
+            }
+        }
+    }
+    false
+}
+
+// ----------------------------------------------------------------------------------
+// comments
+
+fn is_line_break(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\u{000B}' | '\u{000C}' | '\r' | '\u{0085}' | '\u{2028}' | '\u{2029}'
+    )
+}
+
+fn count_lines(s: &str) -> usize {
+    s.replace("\r\n", "\n")
+        .chars()
+        .filter(|c| is_line_break(*c))
+        .count()
+}
+
+/// A formatted node with its comments.
+struct Item {
+    /// Whether there is an empty line before the node in the source.
+    blank_before: bool,
+    doc: Doc,
+}
+
+/// Attributes of nodes which only have attributes with the `attributes` feature.
+macro_rules! ext_attrs {
+    ($node:expr) => {{
+        #[cfg(feature = "attributes")]
+        let attrs: &[Attribute] = &$node.attributes;
+        #[cfg(not(feature = "attributes"))]
+        let attrs: &[Attribute] = {
+            let _ = &$node;
+            &[]
+        };
+        attrs
+    }};
+}
+
+/// Formats the last statement of a compound statement, given its syntax tree node.
+type LastStatement<'a> = dyn Fn(Option<&Node>) -> Option<Doc> + 'a;
+
+struct Formatter<'a> {
+    cst: &'a Cst,
+    options: &'a FormatOptions,
+}
+
+impl Formatter<'_> {
+    /// Split a multi-line text. `start` is the source offset of the text: the following
+    /// lines are un-indented by the column of `start`.
+    fn lines(&self, s: &str, start: usize) -> Doc {
+        let source = self.cst.source();
+        let line_start = source[..start]
+            .rfind(is_line_break)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let column = source[line_start..start].chars().count();
+        let s = s.replace("\r\n", "\n");
+        let lines = s.split(is_line_break).enumerate().map(|(i, line)| {
+            if i == 0 {
+                text(line)
+            } else {
+                let skip = line
+                    .char_indices()
+                    .take(column)
+                    .take_while(|(_, c)| c.is_whitespace())
+                    .last()
+                    .map(|(i, c)| i + c.len_utf8())
+                    .unwrap_or(0);
+                text(&line[skip..])
+            }
+        });
+        Doc::Concat(Itertools::intersperse(lines, Doc::HardLine).collect())
+    }
+
+    fn comment(&self, trivia: &Trivia) -> Doc {
+        self.lines(self.cst.text(&trivia.span), trivia.span.start)
+    }
+
+    /// Comments before a node, each followed by a separator.
+    fn leading(&self, trivia: &[Trivia]) -> (bool, Vec<Doc>) {
+        let mut blank_before = false;
+        let mut docs = Vec::new();
+        let mut after_comment = false;
+        for t in trivia {
+            if t.kind == TriviaKind::Whitespace {
+                let lines = count_lines(self.cst.text(&t.span));
+                if after_comment {
+                    docs.push(match lines {
+                        0 => text(" "),
+                        1 => Doc::HardLine,
+                        _ => Doc::Concat(vec![Doc::HardLine, Doc::HardLine]),
+                    });
+                    after_comment = false;
+                } else if docs.is_empty() {
+                    blank_before = lines >= 2;
+                }
+            } else {
+                if after_comment {
+                    docs.push(text(" "));
+                }
+                docs.push(self.comment(t));
+                after_comment = true;
+            }
+        }
+        if after_comment {
+            docs.push(text(" "));
+        }
+        (blank_before, docs)
+    }
+
+    /// Comments after a node, on the same line.
+    fn trailing(&self, tok: Option<&CstToken>) -> Vec<Doc> {
+        let trivia = tok.map(|tok| tok.trailing.as_slice()).unwrap_or_default();
+        trivia
+            .iter()
+            .filter(|t| t.is_comment())
+            .flat_map(|t| [text(" "), self.comment(t)])
+            .collect()
+    }
+
+    /// Format a node with its comments. Nodes with comments in places other than their
+    /// boundaries are printed verbatim.
+    fn item(&self, node: Option<&Node>, doc: impl FnOnce() -> Option<Doc>) -> Option<Item> {
+        let Some(node) = node else {
+            return Some(Item {
+                blank_before: false,
+                doc: doc()?,
+            });
+        };
+        let first = node.first_token()?;
+        let (blank_before, mut docs) = self.leading(&first.leading);
+        if has_inner_comments(node) {
+            let span = node.span();
+            docs.push(self.lines(self.cst.text(&span), span.start));
+        } else {
+            docs.push(doc()?);
+        }
+        docs.extend(self.trailing(node.last_token()));
+        Some(Item {
+            blank_before,
+            doc: Doc::Concat(docs),
+        })
+    }
+
+    /// Items separated by line breaks, keeping at most one empty line between them.
+    fn items(items: Vec<Item>) -> Doc {
+        let mut docs = Vec::new();
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                docs.push(Doc::HardLine);
+                if item.blank_before {
+                    docs.push(Doc::HardLine);
+                }
+            }
+            docs.push(item.doc);
+        }
+        Doc::Concat(docs)
+    }
+
+    /// A block: the opening brace, the items and the closing brace, with the comments
+    /// after the opening brace and before the closing brace.
+    fn block(&self, node: Option<&Node>, items: Vec<Item>) -> Doc {
+        let open = node.and_then(|node| node.first_token());
+        let close = node
+            .and_then(|node| node.last_token())
+            .filter(|tok| tok.token == Token::SymBraceRight);
+        let mut inner = self.trailing(open);
+        let (_, mut end_comments) = close
+            .map(|tok| self.leading(&tok.leading))
+            .unwrap_or_default();
+        if items.is_empty() && end_comments.is_empty() {
+            let inline = open.is_none_or(|tok| {
+                tok.trailing
+                    .iter()
+                    .all(|t| t.kind != TriviaKind::LineComment)
+            });
+            if inline {
+                inner.push(text(if inner.is_empty() { "}" } else { " }" }));
+                return Doc::Concat(std::iter::once(text("{")).chain(inner).collect());
+            }
+        }
+        if !items.is_empty() {
+            inner.push(Doc::HardLine);
+            inner.push(Self::items(items));
+        }
+        if !end_comments.is_empty() {
+            end_comments.pop(); // the separator
+            inner.push(Doc::HardLine);
+            inner.extend(end_comments);
+        }
+        Doc::Concat(vec![text("{"), Doc::Nest(inner), Doc::HardLine, text("}")])
+    }
+
+    fn translation_unit(&self, tu: &TranslationUnit) -> Option<Doc> {
+        let mut sections = Vec::new();
+
+        #[cfg(feature = "imports")]
+        {
+            let mut imports = tu
+                .imports
+                .iter()
+                .zip_eq(self.cst.imports())
+                .map(|(import, node)| {
+                    let key = (import_group(&import.path), import_key(import));
+                    let item = self.item(Some(node), || Some(self.import(import)))?;
+                    Some((key, item))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            if self.options.reorder_imports {
+                imports.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let groups = imports.into_iter().chunk_by(|((group, _), _)| *group);
+                for (_, group) in &groups {
+                    let items = group
+                        .map(|(_, item)| Item {
+                            blank_before: false,
+                            ..item
+                        })
+                        .collect();
+                    sections.push(Self::items(items));
+                }
+            } else if !imports.is_empty() {
+                sections.push(Self::items(
+                    imports.into_iter().map(|(_, item)| item).collect(),
+                ));
+            }
+        }
+
+        let directives = tu
+            .global_directives
+            .iter()
+            .zip_eq(self.cst.directives())
+            .map(|(directive, node)| self.item(Some(node), || Some(self.directive(directive))))
+            .collect::<Option<Vec<_>>>()?;
+        if !directives.is_empty() {
+            sections.push(Self::items(directives));
+        }
+
+        let mut decls = Vec::new();
+        for (decl, node) in tu
+            .global_declarations
+            .iter()
+            .zip_eq(self.cst.declarations())
+        {
+            let is_void = matches!(decl, GlobalDeclaration::Void);
+            if is_void && !node.tokens().any(has_comments) {
+                continue;
+            }
+            let mut item = self.item(Some(node), || self.global_declaration(decl, node))?;
+            // functions and structs are always surrounded with empty lines.
+            let is_block = matches!(
+                decl,
+                GlobalDeclaration::Function(_) | GlobalDeclaration::Struct(_)
+            );
+            if let Some((prev_block, _)) = decls.last() {
+                item.blank_before |= is_block || *prev_block;
+            }
+            decls.push((is_block, item));
+        }
+        if !decls.is_empty() {
+            sections.push(Self::items(
+                decls.into_iter().map(|(_, item)| item).collect(),
+            ));
+        }
+
+        let (_, mut end_comments) = self.leading(self.cst.eof_trivia());
+        if !end_comments.is_empty() {
+            end_comments.pop();
+            sections.push(Doc::Concat(end_comments));
+        }
+
+        let sections = Itertools::intersperse(
+            sections.into_iter(),
+            Doc::Concat(vec![Doc::HardLine, Doc::HardLine]),
+        );
+        Some(Doc::Concat(sections.collect()))
+    }
+
+    #[cfg(feature = "imports")]
+    fn import(&self, import: &ImportStatement) -> Doc {
+        Doc::Concat(vec![
+            self.inline_attrs(ext_attrs!(import)),
+            text("import "),
+            text(module_path(&import.path)),
+            self.import_content(&import.content),
+            text(";"),
+        ])
+    }
+
+    #[cfg(feature = "imports")]
+    fn import_content(&self, content: &ImportContent) -> Doc {
+        match content {
+            ImportContent::Item(item) => match &item.rename {
+                Some(rename) => text(format!("{} as {rename}", item.ident)),
+                None => text(&item.ident),
+            },
+            ImportContent::Collection(coll) => {
+                let mut coll = coll.iter().collect_vec();
+                if self.options.reorder_imports {
+                    coll.sort_by_cached_key(|import| import.to_string());
+                }
+                let items = coll
+                    .into_iter()
+                    .map(|import| {
+                        let path = import.path.iter().map(|c| format!("{c}::")).join("");
+                        Doc::Concat(vec![text(path), self.import_content(&import.content)])
+                    })
+                    .collect();
+                list("{", items, "}", true)
+            }
+        }
+    }
+
+    fn directive(&self, directive: &GlobalDirective) -> Doc {
+        let (attrs, directive) = match directive {
+            GlobalDirective::Diagnostic(d) => (
+                ext_attrs!(d),
+                format!("diagnostic({}, {});", d.severity, d.rule_name),
+            ),
+            GlobalDirective::Enable(d) => (
+                ext_attrs!(d),
+                format!("enable {};", d.extensions.iter().format(", ")),
+            ),
+            GlobalDirective::Requires(d) => (
+                ext_attrs!(d),
+                format!("requires {};", d.extensions.iter().format(", ")),
+            ),
+            #[cfg(feature = "condcomp")]
+            GlobalDirective::Feature(d) => (
+                ext_attrs!(d),
+                format!("feature {} = {};", d.name, d.default),
+            ),
+        };
+        Doc::Concat(vec![self.inline_attrs(attrs), text(directive)])
+    }
+
+    /// Attributes of global declarations.
+    fn decl_attrs(&self, attrs: &[Attribute], own_line: bool) -> Doc {
+        let own_line = match self.options.attributes {
+            AttributePlacement::Auto => own_line,
+            AttributePlacement::SameLine => false,
+            AttributePlacement::OwnLine => true,
+        };
+        if own_line && !attrs.is_empty() {
+            let attrs = attrs.iter().map(|attr| self.attribute(attr));
+            let mut docs = Itertools::intersperse(attrs, text(" ")).collect_vec();
+            docs.push(Doc::HardLine);
+            Doc::Concat(docs)
+        } else {
+            self.inline_attrs(attrs)
+        }
+    }
+
+    fn inline_attrs(&self, attrs: &[Attribute]) -> Doc {
+        Doc::Concat(
+            attrs
+                .iter()
+                .flat_map(|attr| [self.attribute(attr), text(" ")])
+                .collect(),
+        )
+    }
+
+    fn global_declaration(&self, decl: &GlobalDeclaration, node: &Node) -> Option<Doc> {
+        let doc = match decl {
+            GlobalDeclaration::Void => text(";"),
+            GlobalDeclaration::Declaration(decl) => Doc::Concat(vec![
+                self.decl_attrs(&decl.attributes, false),
+                self.declaration(decl),
+                text(";"),
+            ]),
+            GlobalDeclaration::TypeAlias(alias) => Doc::Concat(vec![
+                self.decl_attrs(ext_attrs!(alias), false),
+                text(format!("alias {} = ", alias.ident)),
+                self.ty(&alias.ty),
+                text(";"),
+            ]),
+            GlobalDeclaration::Struct(s) => {
+                let block = node.nodes().find(|node| node.kind == NodeKind::Block);
+                let mut members = block.map(|block| block.nodes());
+                let members = s
+                    .members
+                    .iter()
+                    .map(|member| {
+                        let node = members.as_mut().and_then(|nodes| nodes.next());
+                        self.item(node, || {
+                            Some(Doc::Concat(vec![
+                                self.inline_attrs(&member.attributes),
+                                text(format!("{}: ", member.ident)),
+                                self.ty(&member.ty),
+                                text(","),
+                            ]))
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Doc::Concat(vec![
+                    self.decl_attrs(ext_attrs!(s), true),
+                    text(format!("struct {} ", s.ident)),
+                    self.block(block, members),
+                ])
+            }
+            GlobalDeclaration::Function(f) => {
+                let params = f
+                    .parameters
+                    .iter()
+                    .map(|p| {
+                        Doc::Concat(vec![
+                            self.inline_attrs(&p.attributes),
+                            text(format!("{}: ", p.ident)),
+                            self.ty(&p.ty),
+                        ])
+                    })
+                    .collect();
+                let mut docs = vec![
+                    self.decl_attrs(&f.attributes, true),
+                    text(format!("fn {}", f.ident)),
+                    list("(", params, ")", false),
+                ];
+                if let Some(ty) = &f.return_type {
+                    docs.push(text(" -> "));
+                    docs.push(self.inline_attrs(&f.return_attributes));
+                    docs.push(self.ty(ty));
+                }
+                docs.push(text(" "));
+                let block = node.nodes().find(|node| node.kind == NodeKind::Block);
+                docs.push(self.compound(&f.body, block, None)?);
+                Doc::Concat(docs)
+            }
+            GlobalDeclaration::ConstAssert(assert) => Doc::Concat(vec![
+                self.decl_attrs(ext_attrs!(assert), false),
+                text("const_assert "),
+                self.expr(&assert.expression),
+                text(";"),
+            ]),
+        };
+        Some(doc)
+    }
+
+    /// A declaration, without attributes and semicolon.
+    fn declaration(&self, decl: &Declaration) -> Doc {
+        let mut docs = vec![text(format!("{} {}", decl.kind, decl.ident))];
+        if let Some(ty) = &decl.ty {
+            docs.push(text(": "));
+            docs.push(self.ty(ty));
+        }
+        if let Some(init) = &decl.initializer {
+            docs.push(text(" = "));
+            docs.push(self.expr(init));
+        }
+        Doc::Concat(docs)
+    }
+
+    /// A compound statement. The `last` statement is for the continuing statement of
+    /// loops and the break-if statement of continuing statements.
+    fn compound(
+        &self,
+        compound: &CompoundStatement,
+        node: Option<&Node>,
+        last: Option<&LastStatement>,
+    ) -> Option<Doc> {
+        let mut nodes = node.map(|node| node.nodes());
+        let mut items = Vec::new();
+        for stmt in &compound.statements {
+            let node = nodes.as_mut().and_then(|nodes| nodes.next());
+            let is_void = matches!(stmt.node(), Statement::Void);
+            if is_void && !node.is_some_and(|node| node.tokens().any(has_comments)) {
+                continue;
+            }
+            items.push(self.item(node, || self.statement(stmt, node))?);
+        }
+        if let Some(last) = last {
+            let node = nodes.as_mut().and_then(|nodes| nodes.next());
+            items.push(self.item(node, || last(node))?);
+        }
+        Some(Doc::Concat(vec![
+            self.inline_attrs(&compound.attributes),
+            self.block(node, items),
+        ]))
+    }
+
+    fn statement(&self, stmt: &Statement, node: Option<&Node>) -> Option<Doc> {
+        let mut blocks = node
+            .into_iter()
+            .flat_map(|node| node.nodes())
+            .filter(|node| node.kind == NodeKind::Block);
+        let doc = match stmt {
+            Statement::Void => text(";"),
+            Statement::Compound(compound) => self.compound(compound, blocks.next(), None)?,
+            Statement::If(stmt) => {
+                let mut docs = vec![
+                    self.inline_attrs(&stmt.attributes),
+                    text("if "),
+                    self.expr(&stmt.if_clause.expression),
+                    text(" "),
+                    self.compound(&stmt.if_clause.body, blocks.next(), None)?,
+                ];
+                for clause in &stmt.else_if_clauses {
+                    docs.push(text(" "));
+                    docs.push(self.inline_attrs(ext_attrs!(clause)));
+                    docs.push(text("else if "));
+                    docs.push(self.expr(&clause.expression));
+                    docs.push(text(" "));
+                    docs.push(self.compound(&clause.body, blocks.next(), None)?);
+                }
+                if let Some(clause) = &stmt.else_clause {
+                    docs.push(text(" "));
+                    docs.push(self.inline_attrs(ext_attrs!(clause)));
+                    docs.push(text("else "));
+                    docs.push(self.compound(&clause.body, blocks.next(), None)?);
+                }
+                Doc::Concat(docs)
+            }
+            Statement::Switch(stmt) => {
+                let block = blocks.next();
+                let mut nodes = block.map(|block| block.nodes());
+                let clauses = stmt
+                    .clauses
+                    .iter()
+                    .map(|clause| {
+                        let node = nodes.as_mut().and_then(|nodes| nodes.next());
+                        self.item(node, || {
+                            let body = node
+                                .into_iter()
+                                .flat_map(|node| node.nodes())
+                                .find(|node| node.kind == NodeKind::Block);
+                            let selectors = clause.case_selectors.iter().map(|sel| match sel {
+                                CaseSelector::Default => text("default"),
+                                CaseSelector::Expression(expr) => self.expr(expr),
+                            });
+                            let mut docs = vec![self.inline_attrs(ext_attrs!(clause))];
+                            if let [CaseSelector::Default] = clause.case_selectors.as_slice() {
+                                docs.push(text("default: "));
+                            } else {
+                                docs.push(text("case "));
+                                docs.extend(Itertools::intersperse(selectors, text(", ")));
+                                docs.push(text(": "));
+                            }
+                            docs.push(self.compound(&clause.body, body, None)?);
+                            Some(Doc::Concat(docs))
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Doc::Concat(vec![
+                    self.inline_attrs(&stmt.attributes),
+                    text("switch "),
+                    self.expr(&stmt.expression),
+                    text(" "),
+                    self.inline_attrs(&stmt.body_attributes),
+                    self.block(block, clauses),
+                ])
+            }
+            Statement::Loop(stmt) => {
+                let continuing = |node: Option<&Node>| {
+                    let cont = stmt.continuing.as_ref()?;
+                    let block = node
+                        .into_iter()
+                        .flat_map(|node| node.nodes())
+                        .find(|node| node.kind == NodeKind::Block);
+                    let break_if = |_: Option<&Node>| {
+                        let stmt = cont.break_if.as_ref()?;
+                        Some(Doc::Concat(vec![
+                            self.inline_attrs(ext_attrs!(stmt)),
+                            text("break if "),
+                            self.expr(&stmt.expression),
+                            text(";"),
+                        ]))
+                    };
+                    Some(Doc::Concat(vec![
+                        self.inline_attrs(ext_attrs!(cont)),
+                        text("continuing "),
+                        self.compound(
+                            &cont.body,
+                            block,
+                            cont.break_if.as_ref().map(|_| &break_if as _),
+                        )?,
+                    ]))
+                };
+                Doc::Concat(vec![
+                    self.inline_attrs(&stmt.attributes),
+                    text("loop "),
+                    self.compound(
+                        &stmt.body,
+                        blocks.next(),
+                        stmt.continuing.as_ref().map(|_| &continuing as _),
+                    )?,
+                ])
+            }
+            Statement::For(stmt) => {
+                let mut docs = vec![self.inline_attrs(&stmt.attributes), text("for (")];
+                if let Some(init) = &stmt.initializer {
+                    docs.push(self.simple_statement(init)?);
+                }
+                docs.push(text(";"));
+                if let Some(cond) = &stmt.condition {
+                    docs.push(text(" "));
+                    docs.push(self.expr(cond));
+                }
+                docs.push(text(";"));
+                if let Some(update) = &stmt.update {
+                    docs.push(text(" "));
+                    docs.push(self.simple_statement(update)?);
+                }
+                docs.push(text(") "));
+                docs.push(self.compound(&stmt.body, blocks.next(), None)?);
+                Doc::Concat(docs)
+            }
+            Statement::While(stmt) => Doc::Concat(vec![
+                self.inline_attrs(&stmt.attributes),
+                text("while "),
+                self.expr(&stmt.condition),
+                text(" "),
+                self.compound(&stmt.body, blocks.next(), None)?,
+            ]),
+            Statement::Break(stmt) => {
+                Doc::Concat(vec![self.inline_attrs(ext_attrs!(stmt)), text("break;")])
+            }
+            Statement::Continue(stmt) => {
+                Doc::Concat(vec![self.inline_attrs(ext_attrs!(stmt)), text("continue;")])
+            }
+            Statement::Discard(stmt) => {
+                Doc::Concat(vec![self.inline_attrs(ext_attrs!(stmt)), text("discard;")])
+            }
+            Statement::Return(stmt) => {
+                let mut docs = vec![self.inline_attrs(ext_attrs!(stmt)), text("return")];
+                if let Some(expr) = &stmt.expression {
+                    docs.push(text(" "));
+                    docs.push(self.expr(expr));
+                }
+                docs.push(text(";"));
+                Doc::Concat(docs)
+            }
+            Statement::ConstAssert(stmt) => Doc::Concat(vec![
+                self.inline_attrs(ext_attrs!(stmt)),
+                text("const_assert "),
+                self.expr(&stmt.expression),
+                text(";"),
+            ]),
+            Statement::Assignment(_)
+            | Statement::Increment(_)
+            | Statement::Decrement(_)
+            | Statement::FunctionCall(_)
+            | Statement::Declaration(_) => {
+                Doc::Concat(vec![self.simple_statement(stmt)?, text(";")])
+            }
+        };
+        Some(doc)
+    }
+
+    /// Statements allowed in for loop headers, without the semicolon.
+    fn simple_statement(&self, stmt: &Statement) -> Option<Doc> {
+        let doc = match stmt {
+            Statement::Assignment(stmt) => Doc::Concat(vec![
+                self.inline_attrs(ext_attrs!(stmt)),
+                self.expr(&stmt.lhs),
+                text(format!(" {} ", stmt.operator)),
+                self.expr(&stmt.rhs),
+            ]),
+            Statement::Increment(stmt) => Doc::Concat(vec![
+                self.inline_attrs(ext_attrs!(stmt)),
+                self.expr(&stmt.expression),
+                text("++"),
+            ]),
+            Statement::Decrement(stmt) => Doc::Concat(vec![
+                self.inline_attrs(ext_attrs!(stmt)),
+                self.expr(&stmt.expression),
+                text("--"),
+            ]),
+            Statement::FunctionCall(stmt) => Doc::Concat(vec![
+                self.inline_attrs(ext_attrs!(stmt)),
+                self.call(&stmt.call),
+            ]),
+            Statement::Declaration(decl) => Doc::Concat(vec![
+                self.inline_attrs(&decl.attributes),
+                self.declaration(decl),
+            ]),
+            _ => return None,
+        };
+        Some(doc)
+    }
+
+    fn attribute(&self, attr: &Attribute) -> Doc {
+        let (name, args) = match attr {
+            Attribute::Align(e) => ("align", vec![e]),
+            Attribute::Binding(e) => ("binding", vec![e]),
+            Attribute::BlendSrc(e) => ("blend_src", vec![e]),
+            Attribute::Group(e) => ("group", vec![e]),
+            Attribute::Id(e) => ("id", vec![e]),
+            Attribute::Location(e) => ("location", vec![e]),
+            Attribute::Size(e) => ("size", vec![e]),
+            Attribute::WorkgroupSize(WorkgroupSizeAttribute { x, y, z }) => (
+                "workgroup_size",
+                std::iter::once(x).chain(y).chain(z).collect(),
+            ),
+            #[cfg(feature = "condcomp")]
+            Attribute::If(e) => ("if", vec![e]),
+            #[cfg(feature = "condcomp")]
+            Attribute::Elif(e) => ("elif", vec![e]),
+            Attribute::Custom(CustomAttribute {
+                name,
+                arguments: Some(args),
+            }) => (name.as_str(), args.iter().collect()),
+            _ => return text(attr),
+        };
+        let args = args.into_iter().map(|e| self.expr(e)).collect();
+        Doc::Concat(vec![text(format!("@{name}")), list("(", args, ")", false)])
+    }
+
+    fn ty(&self, ty: &TypeExpression) -> Doc {
+        let mut docs = Vec::new();
+        #[cfg(feature = "imports")]
+        if let Some(path) = &ty.path {
+            docs.push(text(module_path(path)));
+        }
+        docs.push(text(&ty.ident));
+        if let Some(args) = &ty.template_args {
+            let args = args.iter().map(|arg| self.expr(&arg.expression));
+            docs.push(text("<"));
+            docs.extend(Itertools::intersperse(args, text(", ")));
+            docs.push(text(">"));
+        }
+        Doc::Concat(docs)
+    }
+
+    fn call(&self, call: &FunctionCall) -> Doc {
+        let args = call.arguments.iter().map(|arg| self.expr(arg)).collect();
+        Doc::Concat(vec![self.ty(&call.ty), list("(", args, ")", false)])
+    }
+
+    fn expr(&self, expr: &ExpressionNode) -> Doc {
+        match expr.node() {
+            Expression::Literal(lit) => {
+                // keep the literal as written, e.g. hexadecimal numbers
+                let span = expr.span();
+                if span.is_empty() {
+                    text(lit)
+                } else {
+                    text(self.cst.text(span))
+                }
+            }
+            Expression::Parenthesized(e) => Doc::Group(vec![
+                text("("),
+                Doc::Nest(vec![Doc::SoftLine, self.expr(&e.expression)]),
+                Doc::SoftLine,
+                text(")"),
+            ]),
+            Expression::NamedComponent(e) => {
+                Doc::Concat(vec![self.expr(&e.base), text(format!(".{}", e.component))])
+            }
+            Expression::Indexing(e) => Doc::Concat(vec![
+                self.expr(&e.base),
+                text("["),
+                self.expr(&e.index),
+                text("]"),
+            ]),
+            Expression::Unary(e) => Doc::Concat(vec![text(e.operator), self.expr(&e.operand)]),
+            Expression::Binary(e) => {
+                // chains of operators with the same precedence break together.
+                let prec = precedence(e.operator);
+                let mut ops = Vec::new(); // from right to left
+                let mut left = expr;
+                while let Expression::Binary(e) = left.node() {
+                    if precedence(e.operator) != prec {
+                        break;
+                    }
+                    ops.push((e.operator, &e.right));
+                    left = &e.left;
+                }
+                let rest = ops
+                    .iter()
+                    .rev()
+                    .flat_map(|(op, operand)| {
+                        [Doc::Line, text(format!("{op} ")), self.expr(operand)]
+                    })
+                    .collect();
+                Doc::Group(vec![self.expr(left), Doc::Nest(rest)])
+            }
+            Expression::FunctionCall(call) => self.call(call),
+            Expression::TypeOrIdentifier(ty) => self.ty(ty),
+        }
+    }
+}
+
+/// Operator precedence levels, see <https://www.w3.org/TR/WGSL/#operator-precedence-associativity>.
+fn precedence(op: BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::ShortCircuitOr => 0,
+        BinaryOperator::ShortCircuitAnd => 1,
+        BinaryOperator::BitwiseOr => 2,
+        BinaryOperator::BitwiseXor => 3,
+        BinaryOperator::BitwiseAnd => 4,
+        BinaryOperator::Equality
+        | BinaryOperator::Inequality
+        | BinaryOperator::LessThan
+        | BinaryOperator::LessThanEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::GreaterThanEqual => 5,
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 6,
+        BinaryOperator::Addition | BinaryOperator::Subtraction => 7,
+        BinaryOperator::Multiplication | BinaryOperator::Division | BinaryOperator::Remainder => 8,
+    }
+}
+
+#[cfg(feature = "imports")]
+fn module_path(path: &ModulePath) -> String {
+    let origin = match path.origin {
+        PathOrigin::Absolute => "package::".to_string(),
+        PathOrigin::Relative(0) => "self::".to_string(),
+        PathOrigin::Relative(n) => "super::".repeat(n),
+        PathOrigin::Package => String::new(),
+    };
+    let components = path.components.iter().map(|c| format!("{c}::")).join("");
+    format!("{origin}{components}")
+}
+
+/// Imports are grouped: external packages, then `package::`, then relative imports.
+#[cfg(feature = "imports")]
+fn import_group(path: &ModulePath) -> u8 {
+    match path.origin {
+        PathOrigin::Package => 0,
+        PathOrigin::Absolute => 1,
+        PathOrigin::Relative(_) => 2,
+    }
+}
+
+#[cfg(feature = "imports")]
+fn import_key(import: &ImportStatement) -> String {
+    let content = match &import.content {
+        ImportContent::Item(item) => item.ident.to_string(),
+        ImportContent::Collection(_) => String::new(),
+    };
+    format!("{}{content}", module_path(&import.path))
+}
+
+fn has_comments(tok: &CstToken) -> bool {
+    tok.leading
+        .iter()
+        .chain(&tok.trailing)
+        .any(Trivia::is_comment)
+}
+
+/// Whether a node has comments which are not before or after it, or after the opening
+/// brace or before the closing brace of its blocks. Statements and members in blocks
+/// are not included, they are formatted separately.
+fn has_inner_comments(node: &Node) -> bool {
+    fn walk<'a>(node: &'a Node, tokens: &mut Vec<(&'a CstToken, bool)>) {
+        use crate::cst::Element;
+        let is_block = node.kind == NodeKind::Block;
+        let len = node.children.len();
+        for (i, child) in node.children.iter().enumerate() {
+            match child {
+                Element::Token(tok) => {
+                    let is_brace = is_block && (i == 0 || i + 1 == len);
+                    tokens.push((tok, is_brace));
+                }
+                // statements and members are formatted separately.
+                Element::Node(_) if is_block => {}
+                Element::Node(child) => walk(child, tokens),
+            }
+        }
+    }
+    let mut tokens = Vec::new();
+    walk(node, &mut tokens);
+    let last = tokens.len().saturating_sub(1);
+    tokens.iter().enumerate().any(|(i, (tok, is_brace))| {
+        let leading = i != 0 && !(*is_brace && tok.token == Token::SymBraceRight);
+        let trailing = i != last && !(*is_brace && tok.token == Token::SymBraceLeft);
+        (leading && tok.leading.iter().any(Trivia::is_comment))
+            || (trailing && tok.trailing.iter().any(Trivia::is_comment))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cst::tests::sample_shaders;
+
+    #[test]
+    fn test_idempotent_samples() {
+        let opts = FormatOptions::default();
+        for (path, source) in sample_shaders() {
+            // some samples use syntax extensions not enabled in this build.
+            let Ok(wgsl) = crate::parse_str(&source) else {
+                continue;
+            };
+            let formatted = format(&source, &opts).unwrap();
+            let reparsed = crate::parse_str(&formatted).unwrap();
+            assert_eq!(reparsed.to_string(), wgsl.to_string(), "{path}");
+            assert_eq!(format(&formatted, &opts).unwrap(), formatted, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        #[cfg(feature = "imports")]
+        let imports = "// header\nimport package::a::{\n    b, // b\n    /* c */ c,\n};\n";
+        #[cfg(not(feature = "imports"))]
+        let imports = "";
+        let source = format!(
+            "{imports}
+struct S {{
+  // leading
+  x: f32, // trailing
+  /* inline */ y: f32,
+}}
+fn f() {{ // open
+  // empty
+}}
+fn g() {{
+  let x = 1; // after
+  return; // last
+  // end of block
+}}
+// end of file
+"
+        );
+        let formatted = format(&source, &FormatOptions::default()).unwrap();
+        let comments = [
+            "// leading",
+            "// trailing",
+            "/* inline */",
+            "// open",
+            "// empty",
+            "// after",
+            "// last",
+            "// end of block",
+            "// end of file",
+        ];
+        for comment in comments {
+            assert_eq!(
+                formatted.matches(comment).count(),
+                1,
+                "{comment}\n{formatted}"
+            );
+        }
+        #[cfg(feature = "imports")]
+        for comment in ["// header", "// b", "/* c */"] {
+            assert_eq!(
+                formatted.matches(comment).count(),
+                1,
+                "{comment}\n{formatted}"
+            );
+        }
+        assert!(formatted.contains("x: f32, // trailing\n"), "{formatted}");
+        assert!(formatted.contains("let x = 1; // after\n"), "{formatted}");
+        assert!(formatted.ends_with("}\n\n// end of file\n"), "{formatted}");
+        assert_eq!(
+            format(&formatted, &FormatOptions::default()).unwrap(),
+            formatted
+        );
+    }
+}
@@ -1,8 +1,11 @@
 //! Prefer using [`Parser::parse_str`]. You shouldn't need to manipulate the lexer.
+//!
+//! This module is public with the `lexer` feature, for tools that need the tokens of
+//! invalid code. The tokens follow the parser's grammar and may change in any release.
 
 use crate::error::CustomLalrError;
 use logos::{Logos, SpannedIter};
-use std::{fmt::Display, num::NonZeroU8, sync::LazyLock};
+use std::{cell::RefCell, fmt::Display, num::NonZeroU8, rc::Rc, sync::LazyLock};
 
 type Span = std::ops::Range<usize>;
 
@@ -172,24 +175,34 @@ fn parse_block_comment(lex: &mut logos::Lexer<Token>) -> logos::Skip {
     logos::Skip
 }
 
+fn parse_line_comment(lex: &mut logos::Lexer<Token>) -> logos::Skip {
+    let comment = lex.slice();
+    if comment.starts_with("///") && !comment.starts_with("////") {
+        lex.extras.doc_comments.borrow_mut().push(lex.span());
+    }
+    logos::Skip
+}
+
+#[doc(hidden)]
 #[derive(Default, Clone, Debug, PartialEq)]
-pub(crate) struct LexerState {
+pub struct LexerState {
     depth: i32,
     template_depths: Vec<i32>,
     lookahead: Option<Token>,
+    doc_comments: Rc<RefCell<Vec<Span>>>,
 }
 
 // follwing the spec at this date: https://www.w3.org/TR/2024/WD-WGSL-20240731/
 #[derive(Logos, Clone, Debug, PartialEq)]
 #[logos(
     skip r"[\s\u0085\u200e\u200f\u2028\u2029]+",
-    // see line breaks: https://www.w3.org/TR/WGSL/#line-break
-    skip r"//[^\n\v\f\r\u0085\u2028\u2029]*", // line comment
     extras = LexerState,
     error = CustomLalrError)]
-pub(crate) enum Token {
+pub enum Token {
     // comments. This variant is never produced.
     #[token("/*", parse_block_comment, priority = 2)]
+    // see line breaks: https://www.w3.org/TR/WGSL/#line-break
+    #[regex(r"//[^\n\v\f\r\u0085\u2028\u2029]*", parse_line_comment)]
     Ignored,
     // syntactic tokens
     // https://www.w3.org/TR/WGSL/#syntactic-tokens
@@ -606,7 +619,7 @@ pub type Spanned<Tok, Loc, ParseError> = Result<(Loc, Tok, Loc), (Loc, ParseErro
 type NextToken = Option<(Result<Token, CustomLalrError>, Span)>;
 
 #[derive(Clone)]
-pub(crate) struct Lexer<'s> {
+pub struct Lexer<'s> {
     source: &'s str,
     token_stream: SpannedIter<'s, Token>,
     next_token: NextToken,
@@ -627,6 +640,12 @@ impl<'s> Lexer<'s> {
         }
     }
 
+    /// The spans of the `///` doc comments, filled as the lexer advances. Doc comments
+    /// inside block comments are not included.
+    pub(crate) fn doc_comments(&self) -> Rc<RefCell<Vec<Span>>> {
+        self.token_stream.extras.doc_comments.clone()
+    }
+
     fn take_two_tokens(&mut self) -> (NextToken, NextToken) {
         let mut tok1 = self.next_token.take();
 
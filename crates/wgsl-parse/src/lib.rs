@@ -1,10 +1,15 @@
 #![doc = include_str!("../README.md")]
 
+pub mod cst;
 pub mod error;
+pub mod format;
+#[cfg(feature = "lexer")]
+pub mod lexer;
+#[cfg(not(feature = "lexer"))]
+mod lexer;
 pub mod span;
 pub mod syntax;
 
-mod lexer;
 mod parser;
 mod parser_support;
 mod syntax_display;
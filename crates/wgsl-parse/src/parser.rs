@@ -22,8 +22,9 @@ lalrpop_mod!(
 /// Identical to [`TranslationUnit::from_str`].
 pub fn parse_str(source: &str) -> Result<TranslationUnit, Error> {
     let lexer = Lexer::new(source);
+    let docs = lexer.doc_comments();
     let parser = wgsl::TranslationUnitParser::new();
-    parser.parse(lexer).map_err(Into::into)
+    parser.parse(source, &docs, lexer).map_err(Into::into)
 }
 
 /// Test whether a string represent a valid WGSL module ([`TranslationUnit`]).
@@ -45,8 +46,9 @@ impl FromStr for TranslationUnit {
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
+        let docs = lexer.doc_comments();
         let parser = wgsl::TranslationUnitParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(source, &docs, lexer).map_err(Into::into)
     }
 }
 impl FromStr for GlobalDirective {
@@ -54,8 +56,9 @@ impl FromStr for GlobalDirective {
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
+        let docs = lexer.doc_comments();
         let parser = wgsl::GlobalDirectiveParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(source, &docs, lexer).map_err(Into::into)
     }
 }
 impl FromStr for GlobalDeclaration {
@@ -63,8 +66,9 @@ impl FromStr for GlobalDeclaration {
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
+        let docs = lexer.doc_comments();
         let parser = wgsl::GlobalDeclParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(source, &docs, lexer).map_err(Into::into)
     }
 }
 impl FromStr for Statement {
@@ -72,8 +76,9 @@ impl FromStr for Statement {
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
+        let docs = lexer.doc_comments();
         let parser = wgsl::StatementParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(source, &docs, lexer).map_err(Into::into)
     }
 }
 impl FromStr for Expression {
@@ -81,8 +86,9 @@ impl FromStr for Expression {
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
+        let docs = lexer.doc_comments();
         let parser = wgsl::ExpressionParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(source, &docs, lexer).map_err(Into::into)
     }
 }
 #[cfg(feature = "imports")]
@@ -91,7 +97,102 @@ impl FromStr for crate::syntax::ImportStatement {
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
+        let docs = lexer.doc_comments();
         let parser = wgsl::ImportStatementParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(source, &docs, lexer).map_err(Into::into)
     }
 }
+
+#[test]
+fn doc_comments() {
+    let source = "/// A light.\n///\n/// Lit.\nstruct Light {\n    /// Color.\n    @size(16) color: vec3f,\n    intensity: f32,\n}\n//// not a doc\nconst A = 1; fn f() {}\n";
+    let wesl = parse_str(source).unwrap();
+    let GlobalDeclaration::Struct(light) = &wesl.global_declarations[0] else {
+        panic!("expected a struct")
+    };
+    assert_eq!(light.doc.as_deref(), Some("A light.\n\nLit."));
+    assert_eq!(light.members[0].doc.as_deref(), Some("Color."));
+    assert_eq!(light.members[1].doc, None);
+    let GlobalDeclaration::Declaration(a) = &wesl.global_declarations[1] else {
+        panic!("expected a declaration")
+    };
+    assert_eq!(a.doc, None);
+    // doc comments are not part of the compiled output.
+    assert!(wesl
+        .to_string()
+        .starts_with("struct Light {\n    @size(16)\n    color"));
+
+    let source = "/* block\n/// not a doc\n*/\nconst A = 1;\nconst B = 2; /// not a doc\nconst C = 3;\n/// C.\n\nfn f() {}\n/// D.  \n/// E.\nfn g() {}\n";
+    let wesl = parse_str(source).unwrap();
+    let docs = wesl
+        .global_declarations
+        .iter()
+        .map(|decl| match decl {
+            GlobalDeclaration::Declaration(decl) => decl.doc.clone(),
+            GlobalDeclaration::Function(decl) => decl.doc.clone(),
+            _ => panic!("unexpected declaration"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(docs, [None, None, None, None, Some("D.\nE.".to_string())]);
+}
+
+#[cfg(feature = "condcomp")]
+#[test]
+fn feature_directives() {
+    let source = "/// Fast path.\nfeature fast = true;\nfeature debug = false;\nfn feature() {}\n";
+    let wesl = parse_str(source).unwrap();
+    let GlobalDirective::Feature(fast) = &wesl.global_directives[0] else {
+        panic!("expected a feature directive")
+    };
+    assert_eq!(fast.name, "fast");
+    assert!(fast.default);
+    assert_eq!(fast.doc.as_deref(), Some("Fast path."));
+    assert!(wesl
+        .to_string()
+        .starts_with("feature fast = true;\nfeature debug = false;\n"));
+    assert!(parse_str("enabled fast = true;").is_err());
+}
+
+#[test]
+fn doc_comment_spans() {
+    use crate::{cst::Cst, format};
+
+    let source =
+        "/// Ünïcode ✓\r\nconst A = 1 + 2;\r\n\r\n/// Fn.\r\nfn f() -> i32 { return A * 3; }\r\n";
+    let wesl = parse_str(source).unwrap();
+    let GlobalDeclaration::Declaration(a) = &wesl.global_declarations[0] else {
+        panic!("expected a declaration")
+    };
+    assert_eq!(a.doc.as_deref(), Some("Ünïcode ✓"));
+    let init = a.initializer.as_ref().unwrap();
+    assert_eq!(&source[init.span().range()], "1 + 2");
+    let GlobalDeclaration::Function(f) = &wesl.global_declarations[1] else {
+        panic!("expected a function")
+    };
+    assert_eq!(f.doc.as_deref(), Some("Fn."));
+    let stmt = &f.body.statements[0];
+    assert_eq!(&source[stmt.span().range()], "return A * 3;");
+
+    // the CST and the formatter see doc comments as regular comments.
+    let cst = Cst::parse(source).unwrap();
+    assert_eq!(cst.to_string(), source);
+    let decls = cst.declarations().collect::<Vec<_>>();
+    assert_eq!(cst.text(&decls[0].span()), "const A = 1 + 2;");
+    assert_eq!(
+        cst.leading_comments(decls[0]).collect::<Vec<_>>(),
+        ["/// Ünïcode ✓"]
+    );
+    assert_eq!(
+        cst.leading_comments(decls[1]).collect::<Vec<_>>(),
+        ["/// Fn."]
+    );
+    let formatted = format::format(source, &Default::default()).unwrap();
+    assert_eq!(
+        formatted,
+        "/// Ünïcode ✓\nconst A = 1 + 2;\n\n/// Fn.\nfn f() -> i32 {\n    return A * 3;\n}\n"
+    );
+
+    let source = "/// Ünïcode ✓\nconst A = ;";
+    let err = parse_str(source).unwrap_err();
+    assert_eq!(&source[err.span.range()], ";");
+}
@@ -1,6 +1,6 @@
 //! support functions to be injected in the lalrpop parser.
 
-use std::str::FromStr;
+use std::{ops::Range, str::FromStr};
 
 use itertools::Itertools;
 
@@ -309,6 +309,16 @@ pub(crate) fn parse_attribute(
             Some(expr) => Ok(Attribute::If(expr)),
             None => Err(E::Attribute("if", "expected 1 argument")),
         },
+        #[cfg(feature = "condcomp")]
+        "elif" => match one_arg(args) {
+            Some(expr) => Ok(Attribute::Elif(expr)),
+            None => Err(E::Attribute("elif", "expected 1 argument")),
+        },
+        #[cfg(feature = "condcomp")]
+        "else" => match zero_args(args) {
+            true => Ok(Attribute::Else),
+            false => Err(E::Attribute("else", "expected 0 arguments")),
+        },
         #[cfg(feature = "generics")]
         "type" => parse_attr_type(args).map(Attribute::Type),
         _ => Ok(Attribute::Custom(CustomAttribute {
@@ -385,3 +395,40 @@ pub(crate) fn parse_var_template(template_args: TemplateArgs) -> Result<Option<A
         None => Ok(None),
     }
 }
+
+/// The doc comment of the item starting at byte `start`: the `///` lines immediately
+/// above it, without the comment markers.
+///
+/// `docs` are the spans of the doc comments found by the lexer, see
+/// [`crate::lexer::Lexer::doc_comments`]. The lexer produced no token between them, so a
+/// blank gap with a single line break separates two consecutive lines.
+pub(crate) fn doc_comment(source: &str, docs: &[Range<usize>], start: usize) -> Option<String> {
+    let is_line_break = |gap: &str| gap.trim().is_empty() && gap.matches('\n').count() == 1;
+    let starts_line = |pos: usize| {
+        let line = source[..pos].rsplit('\n').next().unwrap_or_default();
+        line.trim().is_empty()
+    };
+    let end = docs.partition_point(|doc| doc.end <= start);
+    let mut next = start;
+    let mut lines = Vec::new();
+    for doc in docs[..end].iter().rev() {
+        if !is_line_break(&source[doc.end..next]) || !starts_line(doc.start) {
+            break;
+        }
+        let line = source[doc.start + 3..doc.end].trim_end();
+        lines.push(line.strip_prefix(' ').unwrap_or(line));
+        next = doc.start;
+    }
+    (!lines.is_empty()).then(|| lines.into_iter().rev().join("\n"))
+}
+
+pub(crate) fn with_doc(mut decl: GlobalDeclaration, doc: Option<String>) -> GlobalDeclaration {
+    match &mut decl {
+        GlobalDeclaration::Declaration(decl) => decl.doc = doc,
+        GlobalDeclaration::TypeAlias(decl) => decl.doc = doc,
+        GlobalDeclaration::Struct(decl) => decl.doc = doc,
+        GlobalDeclaration::Function(decl) => decl.doc = doc,
+        GlobalDeclaration::Void | GlobalDeclaration::ConstAssert(_) => (),
+    }
+    decl
+}
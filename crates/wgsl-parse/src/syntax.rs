@@ -142,6 +142,8 @@ pub enum GlobalDirective {
     Diagnostic(DiagnosticDirective),
     Enable(EnableDirective),
     Requires(RequiresDirective),
+    #[cfg(feature = "condcomp")]
+    Feature(FeatureDirective),
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -178,6 +180,20 @@ pub struct RequiresDirective {
     pub extensions: Vec<String>,
 }
 
+/// Declaration of a conditional compilation feature flag: `feature name = default;`.
+///
+/// The default value is used when the feature is not set by the host.
+#[cfg(feature = "condcomp")]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureDirective {
+    /// The doc comment (`///`) above the directive, the description of the feature.
+    pub doc: Option<String>,
+    pub attributes: Attributes,
+    pub name: String,
+    pub default: bool,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, From, IsVariant, Unwrap)]
 pub enum GlobalDeclaration {
@@ -192,6 +208,8 @@ pub enum GlobalDeclaration {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Declaration {
+    /// Documentation of the declaration, from the `///` comments that precede it.
+    pub doc: Option<String>,
     pub attributes: Attributes,
     pub kind: DeclarationKind,
     pub ident: Ident,
@@ -230,6 +248,8 @@ pub enum AccessMode {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeAlias {
+    /// Documentation of the declaration, from the `///` comments that precede it.
+    pub doc: Option<String>,
     #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub ident: Ident,
@@ -239,6 +259,8 @@ pub struct TypeAlias {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Struct {
+    /// Documentation of the declaration, from the `///` comments that precede it.
+    pub doc: Option<String>,
     #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub ident: Ident,
@@ -248,6 +270,8 @@ pub struct Struct {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct StructMember {
+    /// Documentation of the declaration, from the `///` comments that precede it.
+    pub doc: Option<String>,
     pub attributes: Attributes,
     pub ident: Ident,
     pub ty: TypeExpression,
@@ -256,6 +280,8 @@ pub struct StructMember {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Function {
+    /// Documentation of the declaration, from the `///` comments that precede it.
+    pub doc: Option<String>,
     pub attributes: Attributes,
     pub ident: Ident,
     pub parameters: Vec<FormalParameter>,
@@ -281,7 +307,7 @@ pub struct ConstAssert {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, IsVariant)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, IsVariant)]
 pub enum BuiltinValue {
     VertexIndex,
     InstanceIndex,
@@ -366,6 +392,10 @@ pub enum Attribute {
     Compute,
     #[cfg(feature = "condcomp")]
     If(ExpressionNode),
+    #[cfg(feature = "condcomp")]
+    Elif(ExpressionNode),
+    #[cfg(feature = "condcomp")]
+    Else,
     #[cfg(feature = "generics")]
     Type(TypeConstraint),
     Custom(CustomAttribute),
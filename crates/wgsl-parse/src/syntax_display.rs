@@ -118,6 +118,8 @@ impl Display for GlobalDirective {
             GlobalDirective::Diagnostic(print) => write!(f, "{}", print),
             GlobalDirective::Enable(print) => write!(f, "{}", print),
             GlobalDirective::Requires(print) => write!(f, "{}", print),
+            #[cfg(feature = "condcomp")]
+            GlobalDirective::Feature(print) => write!(f, "{}", print),
         }
     }
 }
@@ -161,6 +163,16 @@ impl Display for RequiresDirective {
     }
 }
 
+#[cfg(feature = "condcomp")]
+impl Display for FeatureDirective {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", fmt_attrs(&self.attributes, false))?;
+        let name = &self.name;
+        let default = self.default;
+        write!(f, "feature {name} = {default};")
+    }
+}
+
 impl Display for GlobalDeclaration {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -370,6 +382,10 @@ impl Display for Attribute {
             Attribute::Compute => write!(f, "@compute"),
             #[cfg(feature = "condcomp")]
             Attribute::If(e1) => write!(f, "@if({e1})"),
+            #[cfg(feature = "condcomp")]
+            Attribute::Elif(e1) => write!(f, "@elif({e1})"),
+            #[cfg(feature = "condcomp")]
+            Attribute::Else => write!(f, "@else"),
             #[cfg(feature = "generics")]
             Attribute::Type(e1) => write!(f, "@type({e1})"),
             Attribute::Custom(custom) => {
@@ -307,6 +307,8 @@ impl Decorated for GlobalDirective {
             GlobalDirective::Diagnostic(directive) => &directive.attributes,
             GlobalDirective::Enable(directive) => &directive.attributes,
             GlobalDirective::Requires(directive) => &directive.attributes,
+            #[cfg(feature = "condcomp")]
+            GlobalDirective::Feature(directive) => &directive.attributes,
         }
     }
 
@@ -315,6 +317,8 @@ impl Decorated for GlobalDirective {
             GlobalDirective::Diagnostic(directive) => &mut directive.attributes,
             GlobalDirective::Enable(directive) => &mut directive.attributes,
             GlobalDirective::Requires(directive) => &mut directive.attributes,
+            #[cfg(feature = "condcomp")]
+            GlobalDirective::Feature(directive) => &mut directive.attributes,
         }
     }
 }
@@ -328,6 +332,9 @@ impl_decorated_struct!(EnableDirective);
 #[cfg(feature = "attributes")]
 impl_decorated_struct!(RequiresDirective);
 
+#[cfg(feature = "condcomp")]
+impl_decorated_struct!(FeatureDirective);
+
 #[cfg(feature = "attributes")]
 impl Decorated for GlobalDeclaration {
     fn attributes(&self) -> &[Attribute] {
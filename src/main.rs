@@ -2,21 +2,39 @@
 
 use clap::{command, Args, Parser, Subcommand, ValueEnum};
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     error::Error,
+    ffi::OsStr,
     fs::{self, File},
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    rc::Rc,
     str::FromStr,
+    thread,
+    time::{Duration, SystemTime},
 };
 use wesl::{
-    eval::{ty_eval_ty, Eval, EvalAttrs, HostShareable, Instance, RefInstance, Ty},
-    syntax::{self, AccessMode, AddressSpace},
-    CompileOptions, CompileResult, Diagnostic, FileResolver, ManglerKind, PkgBuilder, Router,
-    SyntaxUtil, VirtualResolver, Wesl,
+    eval::{
+        ty_eval_ty, AddressMode, CompareFunction, Eval, EvalAttrs, FilterMode, HostShareable,
+        Instance, RefInstance, SampledType, SamplerInstance, SamplerType, TexelFormat,
+        TextureInstance, TextureType, Ty, Type,
+    },
+    permutations,
+    syntax::{self, AccessMode, AddressSpace, DiagnosticSeverity, ModulePath},
+    AttributePlacement, BindgenOptions, CompileOptions, CompileResult, CompileSession,
+    Dependencies, Diagnostic, DocFormat, Features, FileResolver, FormatOptions, LintConfig,
+    LintRule, ManglerKind, NoResolver, PkgBuilder, ResolveError, Resolver, Router, SyntaxUtil,
+    VirtualResolver, Wesl,
 };
 use wgsl_parse::syntax::TranslationUnit;
 
+#[cfg(feature = "naga")]
+use naga::valid::{Capabilities, ValidationFlags};
+#[cfg(feature = "naga")]
+use wesl::NagaError;
+
 #[derive(Parser)]
 #[command(version, author, about)]
 #[command(propagate_version = true)]
@@ -42,6 +60,20 @@ enum Command {
     Exec(ExecArgs),
     /// Generate a publishable Cargo package from WESL source code
     Package(PkgArgs),
+    /// Generate Rust structs for the types of the uniform and storage bindings
+    Bindgen(BindgenArgs),
+    /// Format WESL and WGSL source files
+    Fmt(FmtArgs),
+    /// Generate the documentation of a WESL package
+    Doc(DocArgs),
+    /// Compile a WESL file and recompile it each time one of the files it loads changes
+    Watch(WatchArgs),
+    /// Compile feature permutations (shader variants) of a WESL file
+    Variants(VariantsArgs),
+    /// List the conditional compilation features used by a WESL file and its imports
+    Features(FeaturesArgs),
+    /// Run the lint rules on a WESL file and its imports
+    Lint(LintArgs),
 }
 
 #[derive(Default, Clone, Copy, Debug, ValueEnum)]
@@ -146,10 +178,112 @@ impl From<&CompOptsArgs> for CompileOptions {
 struct CompileArgs {
     #[command(flatten)]
     options: CompOptsArgs,
+    /// Output language
+    #[arg(long, default_value = "wgsl")]
+    target: CompileTarget,
+    /// Entry point to compile. Compiles all entry points by default. Only used by the
+    /// `spirv` and `glsl` targets
+    #[cfg(feature = "naga")]
+    #[arg(long)]
+    entrypoint: Option<String>,
+    /// Pipeline-overridable constant values, for targets that do not support them.
+    /// Syntax: name=number or id=number
+    #[cfg(feature = "naga")]
+    #[arg(long = "override", value_parser = parse_key_val::<String, f64>)]
+    overrides: Vec<(String, f64)>,
+    /// Write the output to a file instead of stdout. With the `glsl` target, one file is
+    /// written per entry point: `<output>.<entrypoint>.<vert|frag|comp>`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Write the list of files read during compilation to a dependency file. The file
+    /// is in the Makefile format understood by Make and Ninja (requires `--output`),
+    /// or JSON if the file extension is `.json`
+    #[arg(long)]
+    depfile: Option<PathBuf>,
+    /// Write a source map v3 to a JSON file, mapping the WGSL output to the WESL
+    /// sources (incompatible with `--no-sourcemap`)
+    #[arg(long)]
+    sourcemap: Option<PathBuf>,
     /// WESL file entry point
     file: Option<PathBuf>,
 }
 
+#[derive(Args, Clone, Debug)]
+struct WatchArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// Write the output to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Interval between two checks for file changes, in milliseconds
+    #[arg(long, default_value_t = 200)]
+    interval: u64,
+    /// WESL file entry point
+    file: PathBuf,
+}
+
+#[derive(Args, Clone, Debug)]
+struct VariantsArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// Conditional compilation feature to permute. Can be repeated. All combinations of
+    /// the flags are compiled, unless `--variant` is given
+    #[arg(long = "flag")]
+    flags: Vec<String>,
+    /// Variant to compile, as a comma-separated list of the enabled flags. The other
+    /// flags are disabled. Can be repeated
+    #[arg(long = "variant")]
+    variants: Vec<String>,
+    /// Output directory. Contains the compiled variants `<name>.<n>.wgsl` and the manifest
+    /// `<name>.json` that maps each variant's features to its output file
+    #[arg(short, long)]
+    output: PathBuf,
+    /// WESL file entry point
+    file: PathBuf,
+}
+
+#[derive(Args, Clone, Debug)]
+struct FeaturesArgs {
+    /// Root folder for `package::` imports. Defaults to the parent directory of the root module.
+    #[arg(long)]
+    base: Option<PathBuf>,
+    /// WESL file entry point
+    file: PathBuf,
+}
+
+#[derive(Args, Clone, Debug)]
+struct LintArgs {
+    /// Root folder for `package::` imports. Defaults to the parent directory of the root module.
+    #[arg(long)]
+    base: Option<PathBuf>,
+    /// Set the severity of a lint rule: `error`, `warning`, `info` or `off`. Can be repeated.
+    /// Rules: unused_import, unused_declaration, shadowing, constant_condition,
+    /// unreachable_code, derivative_uniformity
+    #[arg(long = "lint", value_name = "RULE=SEVERITY", value_parser = parse_lint, verbatim_doc_comment)]
+    lints: Vec<(LintRule, DiagnosticSeverity)>,
+    /// WESL file entry point
+    file: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum CompileTarget {
+    /// WGSL source code
+    #[default]
+    Wgsl,
+    /// SPIR-V binary
+    #[cfg(feature = "naga")]
+    Spirv,
+    /// Metal Shading Language 2.4
+    #[cfg(feature = "naga")]
+    Msl,
+    /// HLSL (shader model 6.0)
+    #[cfg(feature = "naga")]
+    Hlsl,
+    /// GLSL 4.50 core, one shader per entry point
+    #[cfg(feature = "naga")]
+    Glsl,
+}
+
 #[derive(Args, Clone, Debug)]
 struct CheckArgs {
     /// Input file type (wgsl or wesl)
@@ -186,6 +320,32 @@ where
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
 
+fn parse_lint(s: &str) -> Result<(LintRule, DiagnosticSeverity), String> {
+    let (rule, severity) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid RULE=SEVERITY: no `=` found in `{s}`"))?;
+    let rule = rule
+        .parse()
+        .map_err(|()| format!("unknown lint rule `{rule}`"))?;
+    let severity = severity
+        .parse()
+        .map_err(|()| format!("invalid severity `{severity}`"))?;
+    Ok((rule, severity))
+}
+
+fn parse_workgroups(s: &str) -> Result<[u32; 3], Box<dyn Error + Send + Sync + 'static>> {
+    let dims = s
+        .split(',')
+        .map(|d| d.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    match dims.as_slice() {
+        [x] => Ok([*x, 1, 1]),
+        [x, y] => Ok([*x, *y, 1]),
+        [x, y, z] => Ok([*x, *y, *z]),
+        _ => Err(format!("invalid workgroup count `{s}`: expected 1 to 3 dimensions").into()),
+    }
+}
+
 /// reference: <https://gpuweb.github.io/gpuweb/#binding-type>
 #[derive(Clone, Copy, Debug)]
 enum BindingType {
@@ -229,12 +389,77 @@ impl FromStr for BindingType {
     }
 }
 
+impl BindingType {
+    fn is_buffer(&self) -> bool {
+        matches!(self, Self::Uniform | Self::Storage | Self::ReadOnlyStorage)
+    }
+    fn is_sampler(&self) -> bool {
+        matches!(
+            self,
+            Self::Filtering | Self::NonFiltering | Self::Comparison
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+enum BindingData {
+    /// contents of the buffer
+    Buffer(Box<[u8]>),
+    /// contents of the texture, size and texel format
+    Texture(Box<[u8]>, [u32; 3], Option<TexelFormat>),
+    Sampler(SamplerInstance),
+}
+
 #[derive(Clone, Debug)]
 struct Binding {
     group: u32,
     binding: u32,
     kind: BindingType,
-    data: Box<[u8]>,
+    data: BindingData,
+}
+
+fn read_binding_file(path: &str) -> Box<[u8]> {
+    let mut file = File::open(PathBuf::from(path)).expect("failed to open binding file");
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)
+        .expect("failed to read binding file");
+    buf.into_boxed_slice()
+}
+
+fn parse_texture_size(s: &str) -> Result<[u32; 3], String> {
+    let dims = s
+        .split('x')
+        .map(|n| n.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid texture size `{s}`: {e}"))?;
+    match dims.as_slice() {
+        [w] => Ok([*w, 1, 1]),
+        [w, h] => Ok([*w, *h, 1]),
+        [w, h, d] => Ok([*w, *h, *d]),
+        _ => Err(format!(
+            "invalid texture size `{s}`: expected 1 to 3 dimensions"
+        )),
+    }
+}
+
+fn parse_sampler(kind: BindingType, options: Option<&str>) -> Result<SamplerInstance, String> {
+    let ty = match kind {
+        BindingType::Comparison => SamplerType::SamplerComparison,
+        _ => SamplerType::Sampler,
+    };
+    let mut sampler = SamplerInstance::new(ty);
+    for opt in options.into_iter().flat_map(|o| o.split(',')) {
+        if let Ok(filter) = opt.parse::<FilterMode>() {
+            sampler = sampler.with_filter(filter);
+        } else if let Ok(mode) = opt.parse::<AddressMode>() {
+            sampler = sampler.with_address_mode(mode);
+        } else if let Ok(compare) = opt.parse::<CompareFunction>() {
+            sampler.compare = compare;
+        } else {
+            return Err(format!("invalid sampler option `{opt}`"));
+        }
+    }
+    Ok(sampler)
 }
 
 impl FromStr for Binding {
@@ -243,30 +468,45 @@ impl FromStr for Binding {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut it = s.split(':');
         let binding = (|| {
-            Ok(Binding {
-                group: it
-                    .next()
-                    .ok_or("missing @group number")?
-                    .parse()
-                    .map_err(|e| format!("failed to parse group: {e}"))?,
-                binding: it
-                    .next()
-                    .ok_or("missing @binding number")?
-                    .parse()
-                    .map_err(|e| format!("failed to parse binding: {e}"))?,
-                kind: it
+            let group = it
+                .next()
+                .ok_or("missing @group number")?
+                .parse()
+                .map_err(|e| format!("failed to parse group: {e}"))?;
+            let binding = it
+                .next()
+                .ok_or("missing @binding number")?
+                .parse()
+                .map_err(|e| format!("failed to parse binding: {e}"))?;
+            let kind: BindingType = it
+                .next()
+                .ok_or("missing resource binding type")?
+                .parse()
+                .map_err(|()| "invalid resource binding type".to_string())?;
+            let data = if kind.is_buffer() {
+                BindingData::Buffer(read_binding_file(it.next().ok_or("missing data")?))
+            } else if kind.is_sampler() {
+                BindingData::Sampler(parse_sampler(kind, it.next())?)
+            } else {
+                let data = read_binding_file(it.next().ok_or("missing data")?);
+                let size = parse_texture_size(it.next().ok_or("missing texture size")?)?;
+                let format = it
                     .next()
-                    .ok_or("missing resource binding type")?
-                    .parse()
-                    .map_err(|()| "invalid resource binding type".to_string())?,
-                data: {
-                    let path = PathBuf::from(it.next().ok_or("missing data")?);
-                    let mut file = File::open(&path).expect("failed to open binding file");
-                    let mut buf = Vec::new();
-                    file.read_to_end(&mut buf)
-                        .expect("failed to read binding file");
-                    buf.into_boxed_slice()
-                },
+                    .map(|f| {
+                        f.parse()
+                            .map_err(|()| format!("invalid texel format `{f}`"))
+                    })
+                    .transpose()?;
+                BindingData::Texture(data, size, format)
+            };
+            if it.next().is_some() {
+                return Err("too many arguments".to_string());
+            }
+            Ok(Binding {
+                group,
+                binding,
+                kind,
+                data,
             })
         })();
         binding.map_err(|e: String| format!("failed to parse binding: {e}").into())
@@ -293,12 +533,25 @@ struct ExecArgs {
     /// Context to evaluate the expression into
     #[command(flatten)]
     options: CompOptsArgs,
-    /// Bound resources. Only buffer bindings are supported at the moment.
-    /// Syntax: colon-separated group,binding,binding_type,path
-    /// Example: 0:0:storage:./my_buffer.bin
+    /// Bound resources.
+    /// Syntax: colon-separated group:binding:binding_type[:args]
     ///  - group and binding are @group and @binding numbers
-    ///  - binding_type is the `GPUBufferBindingType` (uniform, storage, read-only-storage)
-    ///  - path is a path to a binary file of the buffer contents
+    ///  - binding_type is the buffer, texture, storage texture or sampler binding type:
+    ///    * buffers (uniform, storage, read-only-storage): group:binding:binding_type:path
+    ///      - path is a path to a binary file of the buffer contents
+    ///      - example: 0:0:storage:./my_buffer.bin
+    ///    * textures (float, unfilterable-float, sint, uint, depth) and storage textures
+    ///      (write-only, read-write, read-only): group:binding:binding_type:path:size[:format]
+    ///      - path is a path to a binary file of the texels of the first mip level
+    ///      - size is the width, height and depth or layers, e.g. 256x256 or 16x16x6
+    ///      - format is the texel format, it defaults to the storage texture format, or
+    ///        rgba32float, rgba32sint, rgba32uint or r32float (depth)
+    ///      - example: 0:1:float:./my_image.bin:256x256:rgba8unorm
+    ///    * samplers (filtering, non-filtering, comparison): group:binding:binding_type[:options]
+    ///      - options is a comma-separated list of filter mode (nearest, linear),
+    ///        address mode (clamp-to-edge, repeat, mirror-repeat) and compare function
+    ///        (never, less, equal, less-equal, greater, not-equal, greater-equal, always)
+    ///      - example: 0:2:filtering:linear,repeat
     #[arg(long = "resource", value_parser = Binding::from_str, verbatim_doc_comment)]
     resources: Vec<Binding>,
     /// Pipeline-overridable constants.
@@ -311,6 +564,12 @@ struct ExecArgs {
     /// Function name to execute
     #[arg(long, default_value = "main")]
     entrypoint: String,
+    /// Dispatch the compute entrypoint with this number of workgroups, instead of
+    /// calling it once.
+    /// Syntax: comma-separated x,y,z (missing dimensions default to 1)
+    /// Example: --workgroups 4,4
+    #[arg(long, value_parser = parse_workgroups, verbatim_doc_comment)]
+    workgroups: Option<[u32; 3]>,
     /// WESL entrypoint module to evaluate the expression into
     file: Option<PathBuf>,
 }
@@ -323,6 +582,112 @@ struct PkgArgs {
     dir: PathBuf,
 }
 
+#[derive(Args, Clone, Debug)]
+struct BindgenArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// Derive `bytemuck::Pod` and `bytemuck::Zeroable` on the generated structs
+    #[arg(long)]
+    bytemuck: bool,
+    /// Generate `wgpu` entry point names, bind group layouts and vertex buffer layouts
+    #[arg(long)]
+    wgpu: bool,
+    /// Write the output to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// WESL file entry point
+    file: Option<PathBuf>,
+}
+
+#[derive(Default, Clone, Copy, Debug, ValueEnum)]
+enum ClapDocFormat {
+    /// One HTML page per module
+    #[default]
+    Html,
+    /// One Markdown page per module
+    Markdown,
+}
+
+impl From<ClapDocFormat> for DocFormat {
+    fn from(value: ClapDocFormat) -> Self {
+        match value {
+            ClapDocFormat::Html => DocFormat::Html,
+            ClapDocFormat::Markdown => DocFormat::Markdown,
+        }
+    }
+}
+
+#[derive(Args, Clone, Debug)]
+struct DocArgs {
+    /// name of the package
+    name: String,
+    /// directory containing the .wesl shader files
+    dir: PathBuf,
+    /// Output format
+    #[arg(long, default_value = "html")]
+    format: ClapDocFormat,
+    /// Directory to write the pages to
+    #[arg(short, long, default_value = "doc")]
+    output: PathBuf,
+}
+
+#[derive(Default, Clone, Copy, Debug, ValueEnum)]
+enum ClapAttributePlacement {
+    /// Attributes of functions and structs on their own line, others on the same line
+    #[default]
+    Auto,
+    /// All attributes on the same line as the declaration
+    SameLine,
+    /// All attributes on their own line before the declaration
+    OwnLine,
+}
+
+impl From<ClapAttributePlacement> for AttributePlacement {
+    fn from(value: ClapAttributePlacement) -> Self {
+        match value {
+            ClapAttributePlacement::Auto => AttributePlacement::Auto,
+            ClapAttributePlacement::SameLine => AttributePlacement::SameLine,
+            ClapAttributePlacement::OwnLine => AttributePlacement::OwnLine,
+        }
+    }
+}
+
+#[derive(Args, Clone, Debug)]
+struct FmtArgs {
+    /// Do not write the files, fail if some files are not formatted
+    #[arg(long)]
+    check: bool,
+    /// Maximum width of a line
+    #[arg(long, default_value_t = 100)]
+    max_width: usize,
+    /// Number of spaces per indentation level
+    #[arg(long, default_value_t = 4)]
+    tab_spaces: usize,
+    /// Indent with tabs instead of spaces
+    #[arg(long)]
+    hard_tabs: bool,
+    /// Placement of the attributes of global declarations
+    #[arg(long, default_value = "auto")]
+    attributes: ClapAttributePlacement,
+    /// Keep the imports in their original order
+    #[arg(long)]
+    no_reorder_imports: bool,
+    /// Files or directories to format in place. Formats stdin to stdout if omitted
+    files: Vec<PathBuf>,
+}
+
+impl From<&FmtArgs> for FormatOptions {
+    fn from(args: &FmtArgs) -> Self {
+        Self {
+            max_width: args.max_width,
+            tab_spaces: args.tab_spaces,
+            hard_tabs: args.hard_tabs,
+            attributes: args.attributes.into(),
+            reorder_imports: !args.no_reorder_imports,
+        }
+    }
+}
+
 #[derive(Clone, Debug, thiserror::Error)]
 enum CliError {
     #[error("input file not found")]
@@ -333,6 +698,8 @@ enum CliError {
         "resource `@group({0}) @binding({1})` ({2} bytes) incompatible with type `{3}` ({4} bytes)"
     )]
     ResourceIncompatible(u32, u32, u32, wesl::eval::Type, u32),
+    #[error("resource `@group({0}) @binding({1})` binding type is incompatible with type `{2}`")]
+    BindingTypeIncompatible(u32, u32, wesl::eval::Type),
     #[error("Could not convert instance to buffer (type `{0}` is not storable)")]
     NotStorable(wesl::eval::Type),
     #[error("{0}")]
@@ -342,6 +709,45 @@ enum CliError {
     #[cfg(feature = "naga")]
     #[error("naga error: {}", .0.emit_to_string(.1))]
     Naga(naga::front::wgsl::ParseError, String),
+    #[cfg(feature = "naga")]
+    #[error("naga validation error: {}", error_chain(.0))]
+    NagaValidation(Box<naga::valid::ValidationError>),
+    #[cfg(feature = "naga")]
+    #[error("{0} backend error: {1}")]
+    NagaBackend(&'static str, String),
+    #[cfg(feature = "naga")]
+    #[error("entry point `{0}` not found")]
+    EntrypointNotFound(String),
+    #[error("writing multiple outputs requires `--output` or `--entrypoint`")]
+    MultipleOutputs,
+    #[error("writing a Makefile depfile requires `--output`")]
+    DepfileTarget,
+    #[error("writing a source map requires the sourcemap, remove `--no-sourcemap`")]
+    NoSourcemap,
+    #[error("linting failed with {0} error(s)")]
+    LintErrors(usize),
+    #[error("variant enables `{0}`, which is not a `--flag`")]
+    UnknownFlag(String),
+    #[error("{0}")]
+    Variant(#[from] wesl::VariantError),
+    #[error("could not write output file `{}`", .0.display())]
+    OutputFile(PathBuf),
+    #[error("{}: {1}", .0.display())]
+    FileDiagnostic(PathBuf, Box<wesl::Diagnostic<wesl::Error>>),
+    #[error("files are not formatted:\n{}", .0.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join("\n"))]
+    Unformatted(Vec<PathBuf>),
+}
+
+/// naga validation errors carry the details in their sources.
+#[cfg(feature = "naga")]
+fn error_chain(e: &dyn Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        msg.push_str(&format!(": {e}"));
+        source = e.source();
+    }
+    msg
 }
 
 enum FileOrSource {
@@ -349,30 +755,37 @@ enum FileOrSource {
     Source(String),
 }
 
-fn run_compile(
-    options: &CompOptsArgs,
-    file_or_source: FileOrSource,
-) -> Result<CompileResult, CliError> {
-    let compile_options = CompileOptions::from(options);
-
+fn new_compiler(options: &CompOptsArgs) -> Wesl<NoResolver> {
     let mut compiler = Wesl::new_barebones();
     compiler
-        .set_options(compile_options)
+        .set_options(CompileOptions::from(options))
         .use_sourcemap(!options.no_sourcemap)
         .set_mangler(options.mangler.into());
+    compiler
+}
 
-    match file_or_source {
+/// Split a root module file into the base directory and the module name.
+fn split_root<'a>(
+    base: Option<&'a Path>,
+    path: &'a Path,
+) -> Result<(&'a Path, &'a OsStr), CliError> {
+    let base = base.or(path.parent()).ok_or(CliError::FileNotFound)?;
+    let name = path.file_name().ok_or(CliError::FileNotFound)?;
+    Ok((base, name))
+}
+
+fn run_compile(
+    options: &CompOptsArgs,
+    file_or_source: FileOrSource,
+) -> Result<CompileResult, CliError> {
+    let compiler = new_compiler(options);
+
+    let comp = match file_or_source {
         FileOrSource::File(path) => {
-            let base = options
-                .base
-                .as_deref()
-                .or(path.parent())
-                .ok_or(CliError::FileNotFound)?;
-            let name = path.file_name().ok_or(CliError::FileNotFound)?;
+            let (base, name) = split_root(options.base.as_deref(), &path)?;
             let resolver = FileResolver::new(base);
 
-            let res = compiler.set_custom_resolver(resolver).compile(name)?;
-            Ok(res)
+            compiler.set_custom_resolver(resolver).compile(name)?
         }
         FileOrSource::Source(source) => {
             let base = std::env::current_dir().unwrap();
@@ -383,8 +796,368 @@ fn run_compile(
             router.mount_resolver(name, resolver);
             router.mount_fallback_resolver(FileResolver::new(base));
 
-            let res = compiler.set_custom_resolver(router).compile(name)?;
-            Ok(res)
+            compiler.set_custom_resolver(router).compile(name)?
+        }
+    };
+    for warning in &comp.warnings {
+        eprintln!("{}", warning.display_warning());
+    }
+    Ok(comp)
+}
+
+fn write_output(path: Option<&Path>, bytes: &[u8]) -> Result<(), CliError> {
+    match path {
+        Some(path) => fs::write(path, bytes).map_err(|_| CliError::OutputFile(path.to_path_buf())),
+        None => {
+            std::io::stdout().write_all(bytes).unwrap();
+            Ok(())
+        }
+    }
+}
+
+fn write_depfile(path: &Path, output: Option<&Path>, deps: &Dependencies) -> Result<(), CliError> {
+    let contents = if path.extension().is_some_and(|ext| ext == "json") {
+        deps.to_json()
+    } else {
+        deps.to_makefile(output.ok_or(CliError::DepfileTarget)?)
+    };
+    fs::write(path, contents).map_err(|_| CliError::OutputFile(path.to_path_buf()))
+}
+
+fn write_sourcemap(
+    path: &Path,
+    output: Option<&Path>,
+    comp: &CompileResult,
+) -> Result<(), CliError> {
+    let (Some(sourcemap), Some(span_map)) = (&comp.sourcemap, comp.span_map()) else {
+        return Err(CliError::NoSourcemap);
+    };
+    let file = output
+        .and_then(|output| output.file_name())
+        .map(|name| name.to_string_lossy());
+    let contents = span_map.to_json(sourcemap, file.as_deref());
+    fs::write(path, contents).map_err(|_| CliError::OutputFile(path.to_path_buf()))
+}
+
+/// Lower the compiled module to naga. Constructs that the lowering does not support go
+/// through naga's WGSL front-end instead, spans then point to the compiled WGSL.
+#[cfg(feature = "naga")]
+fn naga_module(comp: &CompileResult) -> Result<naga::Module, CliError> {
+    match comp.to_naga() {
+        Err(wesl::Error::Error(Diagnostic { error, .. }))
+            if matches!(*error, wesl::Error::NagaError(NagaError::Unsupported(_))) =>
+        {
+            let source = comp.to_string();
+            naga::front::wgsl::parse_str(&source).map_err(|e| CliError::Naga(e, source))
+        }
+        res => Ok(res?),
+    }
+}
+
+/// Check that naga accepts the compiled module, see [`naga_module`].
+#[cfg(feature = "naga")]
+fn naga_validate(comp: &CompileResult) -> Result<(), CliError> {
+    let module = naga_module(comp)?;
+    naga::valid::Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| CliError::NagaValidation(Box::new(e.into_inner())))?;
+    Ok(())
+}
+
+/// A [`FileResolver`] that records the files it reads, and the files it looked for
+/// but did not find.
+struct WatchResolver {
+    files: FileResolver,
+    loaded: Rc<RefCell<HashSet<PathBuf>>>,
+}
+
+impl Resolver for WatchResolver {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        let res = self.files.resolve_source(path);
+        let mut loaded = self.loaded.borrow_mut();
+        match &res {
+            Ok(_) => loaded.extend(self.files.display_name(path).map(PathBuf::from)),
+            Err(ResolveError::FileNotFound(file, _)) => {
+                // the module may be created later with either extension.
+                loaded.insert(file.with_extension("wesl"));
+                loaded.insert(file.with_extension("wgsl"));
+            }
+            Err(_) => (),
+        }
+        res
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.files.display_name(path)
+    }
+}
+
+fn modified_times(files: &HashSet<PathBuf>) -> HashMap<PathBuf, Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            let time = fs::metadata(file).and_then(|m| m.modified()).ok();
+            (file.clone(), time)
+        })
+        .collect()
+}
+
+fn run_watch(args: &WatchArgs) -> Result<(), CliError> {
+    let options = &args.options;
+    let (base, name) = split_root(options.base.as_deref(), &args.file)?;
+    let loaded = Rc::new(RefCell::new(HashSet::new()));
+    let resolver = WatchResolver {
+        files: FileResolver::new(base),
+        loaded: loaded.clone(),
+    };
+
+    // the session rereads the sources on each compilation, but only reparses the
+    // modules that changed.
+    let compiler = new_compiler(options).set_custom_resolver(resolver);
+    let mut session = CompileSession::new(compiler);
+
+    loop {
+        loaded.borrow_mut().clear();
+        let res = session
+            .compile(name)
+            .map_err(CliError::from)
+            .and_then(|comp| {
+                #[cfg(feature = "naga")]
+                if !options.no_naga {
+                    naga_validate(&comp)?;
+                }
+                write_output(args.output.as_deref(), format!("{comp}\n").as_bytes())
+            });
+
+        let mut files = loaded.borrow().clone();
+        files.insert(args.file.clone());
+        match res {
+            Ok(()) => eprintln!(
+                "compiled `{}` ({} files watched)",
+                args.file.display(),
+                files.len()
+            ),
+            Err(e) => eprintln!("{e}"),
+        }
+
+        let times = modified_times(&files);
+        while modified_times(&files) == times {
+            thread::sleep(Duration::from_millis(args.interval));
+        }
+    }
+}
+
+fn run_variants(args: &VariantsArgs) -> Result<(), CliError> {
+    let options = &args.options;
+    let (base, name) = split_root(options.base.as_deref(), &args.file)?;
+    let compiler = new_compiler(options).set_custom_resolver(FileResolver::new(base));
+    let mut session = CompileSession::new(compiler);
+
+    let variants = if args.variants.is_empty() {
+        permutations(&args.flags)
+    } else {
+        args.variants
+            .iter()
+            .map(|variant| {
+                let enabled = variant.split(',').filter(|flag| !flag.is_empty());
+                let mut features = args
+                    .flags
+                    .iter()
+                    .map(|flag| (flag.clone(), false))
+                    .collect::<Features>();
+                for flag in enabled {
+                    let val = features
+                        .get_mut(flag)
+                        .ok_or_else(|| CliError::UnknownFlag(flag.to_string()))?;
+                    *val = true;
+                }
+                Ok(features)
+            })
+            .collect::<Result<Vec<_>, CliError>>()?
+    };
+
+    let comp = session.compile_variants(name, variants)?;
+
+    let stem = args.file.file_stem().ok_or(CliError::FileNotFound)?;
+    let stem = stem.to_string_lossy();
+    let file_name = |i: usize| format!("{stem}.{i}.wgsl");
+    fs::create_dir_all(&args.output).map_err(|_| CliError::OutputFile(args.output.clone()))?;
+    for (i, output) in comp.outputs.iter().enumerate() {
+        #[cfg(feature = "naga")]
+        if !options.no_naga {
+            naga_validate(output)?;
+        }
+        let path = args.output.join(file_name(i));
+        fs::write(&path, format!("{output}\n")).map_err(|_| CliError::OutputFile(path))?;
+    }
+    let path = args.output.join(format!("{stem}.json"));
+    fs::write(&path, comp.manifest(file_name)).map_err(|_| CliError::OutputFile(path))?;
+    eprintln!(
+        "compiled {} variants to {} outputs",
+        comp.variants.len(),
+        comp.outputs.len()
+    );
+    Ok(())
+}
+
+fn run_features(args: &FeaturesArgs) -> Result<(), CliError> {
+    let (base, name) = split_root(args.base.as_deref(), &args.file)?;
+    let compiler = Wesl::new_barebones().set_custom_resolver(FileResolver::new(base));
+    for feature in compiler.features(name)? {
+        match feature.default {
+            Some(default) => println!("{} = {default}", feature.name),
+            None => println!("{} (undeclared)", feature.name),
+        }
+        for line in feature.description.iter().flat_map(|desc| desc.lines()) {
+            println!("    {line}");
+        }
+        for path in &feature.used_in {
+            if !feature.declared_in.contains(path) {
+                eprintln!(
+                    "warning: feature `{}` is used but not declared in `{path}`",
+                    feature.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_lint(args: &LintArgs) -> Result<(), CliError> {
+    let (base, name) = split_root(args.base.as_deref(), &args.file)?;
+    let compiler = Wesl::new_barebones().set_custom_resolver(FileResolver::new(base));
+    let mut config = LintConfig::new();
+    for (rule, severity) in &args.lints {
+        config.set(*rule, severity.clone());
+    }
+    let lints = compiler.lint(name, &config)?;
+    for lint in &lints {
+        eprintln!("{lint}");
+    }
+    let errors = lints
+        .iter()
+        .filter(|lint| lint.severity == DiagnosticSeverity::Error)
+        .count();
+    if errors > 0 {
+        return Err(CliError::LintErrors(errors));
+    }
+    Ok(())
+}
+
+/// Run one of naga's backends on the validated module and write the result.
+#[cfg(feature = "naga")]
+fn run_backend(
+    args: &CompileArgs,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Result<(), CliError> {
+    use naga::back;
+
+    let output = args.output.as_deref();
+    let entry_points = module
+        .entry_points
+        .iter()
+        .filter(|ep| args.entrypoint.as_ref().is_none_or(|name| *name == ep.name))
+        .collect::<Vec<_>>();
+    if let Some(name) = &args.entrypoint {
+        if entry_points.is_empty() {
+            return Err(CliError::EntrypointNotFound(name.clone()));
+        }
+    }
+
+    let err = |target| move |e: &dyn Error| CliError::NagaBackend(target, error_chain(e));
+
+    // GLSL, MSL and HLSL do not support pipeline-overridable constants, they are replaced by
+    // their value given on the command-line or their default value.
+    let constants = args.overrides.iter().cloned().collect();
+    let (module, info) = back::pipeline_constants::process_overrides(module, info, &constants)
+        .map_err(|e| err("naga")(&e))?;
+
+    match args.target {
+        CompileTarget::Wgsl => unreachable!("WGSL output does not go through naga"),
+        CompileTarget::Spirv => {
+            let pipeline_options = args
+                .entrypoint
+                .as_ref()
+                .map(|_| back::spv::PipelineOptions {
+                    shader_stage: entry_points[0].stage,
+                    entry_point: entry_points[0].name.clone(),
+                });
+            let words = back::spv::write_vec(
+                &module,
+                &info,
+                &back::spv::Options::default(),
+                pipeline_options.as_ref(),
+            )
+            .map_err(|e| err("SPIR-V")(&e))?;
+            let bytes = words
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect::<Vec<_>>();
+            write_output(output, &bytes)
+        }
+        CompileTarget::Msl => {
+            let (source, _) = back::msl::write_string(
+                &module,
+                &info,
+                &back::msl::Options {
+                    lang_version: (2, 4),
+                    ..Default::default()
+                },
+                &back::msl::PipelineOptions::default(),
+            )
+            .map_err(|e| err("MSL")(&e))?;
+            write_output(output, source.as_bytes())
+        }
+        CompileTarget::Hlsl => {
+            let mut source = String::new();
+            let options = back::hlsl::Options {
+                shader_model: back::hlsl::ShaderModel::V6_0,
+                ..Default::default()
+            };
+            back::hlsl::Writer::new(&mut source, &options)
+                .write(&module, &info, None)
+                .map_err(|e| err("HLSL")(&e))?;
+            write_output(output, source.as_bytes())
+        }
+        CompileTarget::Glsl => {
+            if output.is_none() && entry_points.len() > 1 {
+                return Err(CliError::MultipleOutputs);
+            }
+            for ep in entry_points {
+                let mut source = String::new();
+                let options = back::glsl::Options {
+                    version: back::glsl::Version::Desktop(450),
+                    ..Default::default()
+                };
+                let pipeline_options = back::glsl::PipelineOptions {
+                    shader_stage: ep.stage,
+                    entry_point: ep.name.clone(),
+                    multiview: None,
+                };
+                back::glsl::Writer::new(
+                    &mut source,
+                    &module,
+                    &info,
+                    &options,
+                    &pipeline_options,
+                    naga::proc::BoundsCheckPolicies::default(),
+                )
+                .and_then(|mut w| w.write())
+                .map_err(|e| err("GLSL")(&e))?;
+
+                let path = output.map(|path| {
+                    let ext = match ep.stage {
+                        naga::ShaderStage::Vertex => "vert",
+                        naga::ShaderStage::Fragment => "frag",
+                        naga::ShaderStage::Compute => "comp",
+                    };
+                    let mut name = path.as_os_str().to_owned();
+                    name.push(format!(".{}.{ext}", ep.name));
+                    PathBuf::from(name)
+                });
+                write_output(path.as_deref(), source.as_bytes())?;
+            }
+            Ok(())
         }
     }
 }
@@ -416,43 +1189,99 @@ fn parse_binding(
             .with_ctx(&ctx)
             .with_source(ty_expr.to_string())
     })?;
-    let (storage, access) = match b.kind {
-        BindingType::Uniform => (AddressSpace::Uniform, AccessMode::Read),
-        BindingType::Storage => (
-            AddressSpace::Storage(Some(AccessMode::ReadWrite)),
-            AccessMode::ReadWrite,
-        ),
-        BindingType::ReadOnlyStorage => (
-            AddressSpace::Storage(Some(AccessMode::Read)),
-            AccessMode::Read,
-        ),
-        BindingType::Filtering => todo!(),
-        BindingType::NonFiltering => todo!(),
-        BindingType::Comparison => todo!(),
-        BindingType::Float => todo!(),
-        BindingType::UnfilterableFloat => todo!(),
-        BindingType::Sint => todo!(),
-        BindingType::Uint => todo!(),
-        BindingType::Depth => todo!(),
-        BindingType::WriteOnly => todo!(),
-        BindingType::ReadWrite => todo!(),
-        BindingType::ReadOnly => todo!(),
+    let incompatible = || CliError::BindingTypeIncompatible(b.group, b.binding, ty.clone());
+    let (inst, storage, access) = match (&b.data, &ty) {
+        (BindingData::Buffer(data), _) => {
+            let (storage, access) = match b.kind {
+                BindingType::Uniform => (AddressSpace::Uniform, AccessMode::Read),
+                BindingType::Storage => (
+                    AddressSpace::Storage(Some(AccessMode::ReadWrite)),
+                    AccessMode::ReadWrite,
+                ),
+                _ => (
+                    AddressSpace::Storage(Some(AccessMode::Read)),
+                    AccessMode::Read,
+                ),
+            };
+            let inst = Instance::from_buffer(data, &ty, &mut ctx).ok_or_else(|| {
+                CliError::ResourceIncompatible(
+                    b.group,
+                    b.binding,
+                    data.len() as u32,
+                    ty.clone(),
+                    ty.size_of(&mut ctx).unwrap_or_default(),
+                )
+            })?;
+            (inst, storage, access)
+        }
+        (BindingData::Texture(data, size, format), Type::Texture(tex_ty)) => {
+            let compatible = match b.kind {
+                BindingType::Float | BindingType::UnfilterableFloat => {
+                    !tex_ty.is_storage()
+                        && !tex_ty.is_depth()
+                        && tex_ty.sampled_type() == SampledType::F32
+                }
+                BindingType::Sint => {
+                    !tex_ty.is_storage() && tex_ty.sampled_type() == SampledType::I32
+                }
+                BindingType::Uint => {
+                    !tex_ty.is_storage() && tex_ty.sampled_type() == SampledType::U32
+                }
+                BindingType::Depth => tex_ty.is_depth(),
+                BindingType::WriteOnly => storage_access(tex_ty) == Some(AccessMode::Write),
+                BindingType::ReadWrite => storage_access(tex_ty) == Some(AccessMode::ReadWrite),
+                BindingType::ReadOnly => storage_access(tex_ty) == Some(AccessMode::Read),
+                _ => false,
+            };
+            if !compatible {
+                return Err(incompatible());
+            }
+            let format = format.unwrap_or_else(|| default_texel_format(tex_ty));
+            let tex = TextureInstance::from_bytes(tex_ty.clone(), format, *size, data)
+                .map_err(|e| Diagnostic::from(e).with_ctx(&ctx))?;
+            (tex.into(), AddressSpace::Handle, AccessMode::Read)
+        }
+        (BindingData::Sampler(sampler), Type::Sampler(sampler_ty)) if sampler.ty == *sampler_ty => {
+            (
+                sampler.clone().into(),
+                AddressSpace::Handle,
+                AccessMode::Read,
+            )
+        }
+        _ => return Err(incompatible()),
     };
-    let inst = Instance::from_buffer(&b.data, &ty, &mut ctx).ok_or_else(|| {
-        CliError::ResourceIncompatible(
-            b.group,
-            b.binding,
-            b.data.len() as u32,
-            ty.clone(),
-            ty.size_of(&mut ctx).unwrap_or_default(),
-        )
-    })?;
     Ok((
         (b.group, b.binding),
         RefInstance::new(inst, storage, access),
     ))
 }
 
+fn storage_access(ty: &TextureType) -> Option<AccessMode> {
+    match ty {
+        TextureType::Storage1D(_, access)
+        | TextureType::Storage2D(_, access)
+        | TextureType::Storage2DArray(_, access)
+        | TextureType::Storage3D(_, access) => Some(*access),
+        _ => None,
+    }
+}
+
+/// The texel format of texture binding files, if not specified.
+fn default_texel_format(ty: &TextureType) -> TexelFormat {
+    match ty {
+        TextureType::Storage1D(format, _)
+        | TextureType::Storage2D(format, _)
+        | TextureType::Storage2DArray(format, _)
+        | TextureType::Storage3D(format, _) => *format,
+        ty if ty.is_depth() => TexelFormat::R32Float,
+        ty => match ty.sampled_type() {
+            SampledType::I32 => TexelFormat::Rgba32Sint,
+            SampledType::U32 => TexelFormat::Rgba32Uint,
+            SampledType::F32 => TexelFormat::Rgba32Float,
+        },
+    }
+}
+
 fn parse_override(src: &str, wgsl: &TranslationUnit) -> Result<Instance, CliError> {
     let mut ctx = wesl::eval::Context::new(wgsl);
     let expr = src
@@ -473,7 +1302,10 @@ fn main() {
             std::process::exit(1)
         })
         .unwrap();
-    run(cli).inspect_err(|e| eprintln!("{e}")).ok();
+    if let Err(e) = run(cli) {
+        eprintln!("{e}");
+        std::process::exit(1)
+    }
 }
 
 fn file_or_source(path: Option<PathBuf>) -> Option<FileOrSource> {
@@ -486,6 +1318,74 @@ fn file_or_source(path: Option<PathBuf>) -> Option<FileOrSource> {
     })
 }
 
+/// The WESL and WGSL files in a directory and its subdirectories, in alphabetical order.
+fn shader_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), CliError> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)
+            .map_err(|_| CliError::FileNotFound)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CliError::FileNotFound)?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir()
+                || entry
+                    .extension()
+                    .is_some_and(|ext| ext == "wesl" || ext == "wgsl")
+            {
+                shader_files(&entry, files)?;
+            }
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn run_fmt(args: &FmtArgs) -> Result<(), CliError> {
+    let options = FormatOptions::from(args);
+
+    if args.files.is_empty() {
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|_| CliError::FileNotFound)?;
+        let formatted = wesl::format(&source, &options)
+            .map_err(|e| Diagnostic::from(e).with_source(source.clone()))?;
+        if args.check && formatted != source {
+            return Err(CliError::Unformatted(vec![PathBuf::from("<stdin>")]));
+        } else if !args.check {
+            print!("{formatted}");
+        }
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    for path in &args.files {
+        shader_files(path, &mut files)?;
+    }
+    let mut unformatted = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file).map_err(|_| CliError::FileNotFound)?;
+        let formatted = wesl::format(&source, &options).map_err(|e| {
+            let diag = Diagnostic::from(e).with_source(source.clone());
+            CliError::FileDiagnostic(file.clone(), Box::new(diag))
+        })?;
+        if formatted != source {
+            if args.check {
+                unformatted.push(file);
+            } else {
+                fs::write(&file, formatted).map_err(|_| CliError::OutputFile(file))?;
+            }
+        }
+    }
+    if unformatted.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Unformatted(unformatted))
+    }
+}
+
 fn run(cli: Cli) -> Result<(), CliError> {
     match cli.command {
         Command::Check(args) => {
@@ -511,8 +1411,11 @@ fn run(cli: Cli) -> Result<(), CliError> {
                 }
                 CheckKind::Wesl => {
                     let mut wesl = TranslationUnit::from_str(&source)
-                        .map_err(|e| Diagnostic::from(e).with_source(source))?;
+                        .map_err(|e| Diagnostic::from(e).with_source(source.clone()))?;
                     wesl.retarget_idents();
+                    for warning in wesl::undeclared_features(&wesl) {
+                        eprintln!("{}", warning.with_source(source.clone()).display_warning());
+                    }
                     todo!("validating WESL needs more work")
                     // wesl::validate_wesl(&wesl)?;
                     // let wgsl_source = wesl.to_string();
@@ -526,21 +1429,43 @@ fn run(cli: Cli) -> Result<(), CliError> {
             println!("OK");
         }
         Command::Compile(args) => {
-            let comp = file_or_source(args.file)
+            let comp = file_or_source(args.file.clone())
                 .map(|input| run_compile(&args.options, input))
                 .unwrap_or_else(|| {
                     Ok(CompileResult {
                         syntax: TranslationUnit::default(),
                         sourcemap: None,
+                        deps: Default::default(),
+                        warnings: Vec::new(),
                     })
                 })?;
+            if let Some(depfile) = &args.depfile {
+                write_depfile(depfile, args.output.as_deref(), &comp.deps)?;
+            }
+            if let Some(sourcemap) = &args.sourcemap {
+                write_sourcemap(sourcemap, args.output.as_deref(), &comp)?;
+            }
             #[cfg(feature = "naga")]
-            if !args.options.no_naga {
-                let source = comp.to_string();
-                naga::front::wgsl::parse_str(&source).map_err(|e| CliError::Naga(e, source))?;
+            if args.target != CompileTarget::Wgsl {
+                let module = naga_module(&comp)?;
+                let flags = if args.options.no_naga {
+                    ValidationFlags::empty()
+                } else {
+                    ValidationFlags::all()
+                };
+                let info = naga::valid::Validator::new(flags, Capabilities::all())
+                    .validate(&module)
+                    .map_err(|e| CliError::NagaValidation(Box::new(e.into_inner())))?;
+                return run_backend(&args, &module, &info);
+            } else if !args.options.no_naga {
+                naga_validate(&comp)?;
             }
-            println!("{}", comp);
+            write_output(args.output.as_deref(), format!("{comp}\n").as_bytes())?;
         }
+        Command::Watch(args) => run_watch(&args)?,
+        Command::Variants(args) => run_variants(&args)?,
+        Command::Features(args) => run_features(&args)?,
+        Command::Lint(args) => run_lint(&args)?,
         Command::Eval(args) => {
             let comp = file_or_source(args.file)
                 .map(|input| run_compile(&args.options, input))
@@ -548,6 +1473,8 @@ fn run(cli: Cli) -> Result<(), CliError> {
                     Ok(CompileResult {
                         syntax: TranslationUnit::default(),
                         sourcemap: None,
+                        deps: Default::default(),
+                        warnings: Vec::new(),
                     })
                 })?;
             let mut eval = comp.eval(&args.expr)?;
@@ -568,6 +1495,8 @@ fn run(cli: Cli) -> Result<(), CliError> {
                     Ok(CompileResult {
                         syntax: TranslationUnit::default(),
                         sourcemap: None,
+                        deps: Default::default(),
+                        warnings: Vec::new(),
                     })
                 })?;
 
@@ -585,7 +1514,12 @@ fn run(cli: Cli) -> Result<(), CliError> {
                 })
                 .collect::<Result<_, _>>()?;
 
-            let mut exec = comp.exec(&args.entrypoint, resources, overrides)?;
+            let mut exec = match args.workgroups {
+                Some(workgroups) => {
+                    comp.dispatch(&args.entrypoint, workgroups, resources, overrides)?
+                }
+                None => comp.exec(&args.entrypoint, resources, overrides)?,
+            };
 
             if let Some(inst) = &exec.inst {
                 if args.binary {
@@ -596,7 +1530,7 @@ fn run(cli: Cli) -> Result<(), CliError> {
                 } else {
                     println!("return: {}", inst)
                 }
-            } else if !args.binary {
+            } else if !args.binary && args.workgroups.is_none() {
                 println!("return: void")
             }
 
@@ -612,9 +1546,26 @@ fn run(cli: Cli) -> Result<(), CliError> {
 
             for (group, binding, inst) in resources {
                 if args.binary {
-                    let buf = inst
-                        .to_buffer(&mut exec.ctx)
-                        .ok_or_else(|| CliError::NotStorable(inst.ty()))?;
+                    let buf = match &inst {
+                        Instance::Texture(tex) => {
+                            let format = args
+                                .resources
+                                .iter()
+                                .find_map(|r| match r.data {
+                                    BindingData::Texture(_, _, format)
+                                        if r.group == group && r.binding == binding =>
+                                    {
+                                        format
+                                    }
+                                    _ => None,
+                                })
+                                .unwrap_or_else(|| default_texel_format(tex.texture_type()));
+                            tex.to_bytes(format, 0)
+                        }
+                        Instance::Sampler(_) => continue,
+                        _ => inst.to_buffer(&mut exec.ctx),
+                    }
+                    .ok_or_else(|| CliError::NotStorable(inst.ty()))?;
                     std::io::stdout().write_all(buf.as_slice()).unwrap();
                 } else {
                     println!("resource: group={group} binding={binding} value={inst}")
@@ -635,6 +1586,225 @@ fn run(cli: Cli) -> Result<(), CliError> {
                 .expect("failed to build package");
             println!("{code}");
         }
+        Command::Bindgen(args) => {
+            let comp = file_or_source(args.file.clone())
+                .map(|input| run_compile(&args.options, input))
+                .unwrap_or_else(|| {
+                    Ok(CompileResult {
+                        syntax: TranslationUnit::default(),
+                        sourcemap: None,
+                        deps: Default::default(),
+                        warnings: Vec::new(),
+                    })
+                })?;
+            let options = BindgenOptions {
+                bytemuck: args.bytemuck,
+                wgpu: args.wgpu,
+            };
+            let code = comp.bindgen(&options)?;
+            let file = syn::parse_file(&code).expect("bindgen generates valid Rust items");
+            let code = prettyplease::unparse(&file);
+            write_output(args.output.as_deref(), code.as_bytes())?;
+        }
+        Command::Fmt(args) => run_fmt(&args)?,
+        Command::Doc(args) => {
+            let pages = PkgBuilder::new(&args.name)
+                .scan_directory(&args.dir)
+                .map_err(|_| CliError::FileNotFound)?
+                .doc(args.format.into())?;
+            fs::create_dir_all(&args.output)
+                .map_err(|_| CliError::OutputFile(args.output.clone()))?;
+            for page in pages {
+                let path = args.output.join(&page.file_name);
+                fs::write(&path, page.content).map_err(|_| CliError::OutputFile(path))?;
+            }
+        }
     };
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: &str = "import package::util::scale;
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+        override gain: f32 = 1.0;
+        @vertex fn vs() -> @builtin(position) vec4f { return vec4f(scale(1.0)); }
+        @compute @workgroup_size(1) fn cs() { data[0] = scale(gain); }";
+    const UTIL: &str = "fn scale(x: f32) -> f32 { return 2.0 * x; }";
+
+    /// A fresh temporary directory containing the given files.
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wesl-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            fs::write(dir.join(file), source).unwrap();
+        }
+        dir
+    }
+
+    fn wesl(args: &[&str]) -> Result<(), CliError> {
+        let cli = Cli::try_parse_from(["wesl"].iter().chain(args)).unwrap();
+        run(cli)
+    }
+
+    fn path(dir: &Path, file: &str) -> String {
+        dir.join(file).display().to_string()
+    }
+
+    #[cfg(feature = "naga")]
+    #[test]
+    fn test_compile_targets() {
+        let dir = temp_dir("targets", &[("main.wesl", MAIN), ("util.wesl", UTIL)]);
+        let main = &path(&dir, "main.wesl");
+
+        let out = &path(&dir, "out.wgsl");
+        wesl(&["compile", "-o", out, main]).unwrap();
+        assert!(fs::read_to_string(out).unwrap().contains("fn cs()"));
+
+        let out = &path(&dir, "out.spv");
+        wesl(&["compile", "--target", "spirv", "-o", out, main]).unwrap();
+        let spirv = fs::read(out).unwrap();
+        assert_eq!(spirv[..4], 0x07230203u32.to_le_bytes());
+
+        // one GLSL file per entry point.
+        let out = &path(&dir, "out");
+        wesl(&["compile", "--target", "glsl", "-o", out, main]).unwrap();
+        assert!(dir.join("out.vs.vert").exists());
+        assert!(dir.join("out.cs.comp").exists());
+        let res = wesl(&["compile", "--target", "glsl", main]);
+        assert!(matches!(res, Err(CliError::MultipleOutputs)));
+
+        let out = &path(&dir, "cs.comp");
+        let args = [
+            "compile",
+            "--target",
+            "glsl",
+            "--entrypoint",
+            "cs",
+            "-o",
+            out,
+            main,
+        ];
+        wesl(&args).unwrap();
+        assert!(dir.join("cs.comp.cs.comp").exists());
+        assert!(!dir.join("cs.comp.vs.vert").exists());
+        let res = wesl(&["compile", "--target", "spirv", "--entrypoint", "fs", main]);
+        assert!(matches!(res, Err(CliError::EntrypointNotFound(name)) if name == "fs"));
+
+        // overrides are replaced by their value in HLSL.
+        let out = &path(&dir, "out.hlsl");
+        let args = [
+            "compile",
+            "--target",
+            "hlsl",
+            "--override",
+            "gain=2.5",
+            "-o",
+            out,
+            main,
+        ];
+        wesl(&args).unwrap();
+        assert!(fs::read_to_string(out).unwrap().contains("2.5"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "naga")]
+    #[test]
+    fn test_naga_validate() {
+        // neither the lowering nor naga's front-end support `texture_external`.
+        let main = "@group(0) @binding(0) var t: texture_external;
+            @fragment fn fs() -> @location(0) vec4f { return textureLoad(t, vec2u(0u)); }";
+        let dir = temp_dir("naga", &[("main.wesl", main)]);
+        let main = &path(&dir, "main.wesl");
+        let res = wesl(&["compile", main]);
+        assert!(matches!(res, Err(CliError::Naga(..))));
+        wesl(&["compile", "--no-naga", main]).unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_depfile() {
+        let dir = temp_dir("depfile", &[("main.wesl", MAIN), ("util.wesl", UTIL)]);
+        let main = &path(&dir, "main.wesl");
+        let out = &path(&dir, "out.wgsl");
+
+        let depfile = &path(&dir, "out.d");
+        wesl(&["compile", "--depfile", depfile, "-o", out, main]).unwrap();
+        let expected = format!("{out}: {main} {}\n", path(&dir, "util.wesl"));
+        assert_eq!(fs::read_to_string(depfile).unwrap(), expected);
+
+        let depfile = &path(&dir, "deps.json");
+        wesl(&["compile", "--depfile", depfile, main]).unwrap();
+        let json = fs::read_to_string(depfile).unwrap();
+        assert!(json.starts_with(r#"{"modules":["package::main","package::util"],"files":["#));
+
+        // the Makefile rule needs a target.
+        let depfile = &path(&dir, "stdout.d");
+        let res = wesl(&["compile", "--depfile", depfile, main]);
+        assert!(matches!(res, Err(CliError::DepfileTarget)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_bindgen() {
+        let main = "struct Light { color: vec3f, intensity: f32 }
+            @group(0) @binding(0) var<uniform> light: Light;
+            @compute @workgroup_size(1) fn cs() { let c = light.color; }";
+        let dir = temp_dir("bindgen", &[("main.wesl", main)]);
+        let out = &path(&dir, "bindings.rs");
+        wesl(&["bindgen", "-o", out, &path(&dir, "main.wesl")]).unwrap();
+        let code = fs::read_to_string(out).unwrap();
+        assert!(code.contains("#[repr(C)]\n"));
+        assert!(code.contains("pub struct Light {\n    pub color: [f32; 3],\n"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_watch_resolver() {
+        let main = "import package::util::scale;
+            import package::missing::f;
+            @compute @workgroup_size(1) fn cs() { let x = scale(f()); }";
+        let dir = temp_dir("watch", &[("main.wesl", main), ("util.wesl", UTIL)]);
+        let loaded = Rc::new(RefCell::new(HashSet::new()));
+        let resolver = WatchResolver {
+            files: FileResolver::new(&dir),
+            loaded: loaded.clone(),
+        };
+        let res = Wesl::new(&dir)
+            .set_custom_resolver(resolver)
+            .compile("main");
+        assert!(res.is_err());
+
+        // the missing module is watched with both extensions, it may be created later.
+        let mut expected = ["main.wesl", "util.wesl", "missing.wesl", "missing.wgsl"]
+            .map(|file| dir.join(file))
+            .to_vec();
+        let mut loaded = loaded.take().into_iter().collect::<Vec<_>>();
+        expected.sort();
+        loaded.sort();
+        assert_eq!(loaded, expected);
+
+        // the watch loop recompiles when a watched file is modified or created.
+        let files = loaded.into_iter().collect::<HashSet<_>>();
+        let times = modified_times(&files);
+        let file = File::options()
+            .write(true)
+            .open(dir.join("util.wesl"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_ne!(modified_times(&files), times);
+        let times = modified_times(&files);
+        fs::write(dir.join("missing.wgsl"), "fn f() -> f32 { return 1.0; }").unwrap();
+        assert_ne!(modified_times(&files), times);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}